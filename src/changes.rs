@@ -0,0 +1,321 @@
+//! Classifies what a generated migration does to existing data, so
+//! `migrate generate` can summarize the changes and refuse destructive ones
+//! that were not asked for.
+
+use crate::diff::{SchemaDiff, SchemaOperation};
+use crate::ir::SchemaIR;
+use serde::Serialize;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeRisk {
+    /// Cannot lose data or fail on existing rows
+    Safe,
+    /// Keeps data but may fail on existing rows or break running code
+    NeedsReview,
+    /// Loses data
+    Destructive,
+}
+
+impl ChangeRisk {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChangeRisk::Safe => "safe",
+            ChangeRisk::NeedsReview => "needs-review",
+            ChangeRisk::Destructive => "destructive",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaChange {
+    pub risk: ChangeRisk,
+    pub description: String,
+    /// The schema asked for this change explicitly (e.g. `dropped = true`)
+    pub declared: bool,
+}
+
+impl SchemaChange {
+    fn new(risk: ChangeRisk, description: String) -> Self {
+        SchemaChange { risk, description, declared: false }
+    }
+}
+
+/// Type family and size used to tell widening from narrowing type changes.
+fn type_shape(db_type: &str) -> (String, Vec<i64>) {
+    let upper = db_type.to_uppercase();
+    let end = [" PRIMARY KEY", " UNIQUE", " REFERENCES", " NOT NULL", " DEFAULT"]
+        .iter()
+        .filter_map(|kw| upper.find(kw))
+        .min()
+        .unwrap_or(upper.len());
+    let base = upper[..end].trim();
+    let (name, args) = match base.find('(') {
+        Some(pos) => (
+            base[..pos].trim(),
+            base[pos + 1..]
+                .trim_end_matches(')')
+                .split(',')
+                .filter_map(|a| a.trim().parse().ok())
+                .collect(),
+        ),
+        None => (base, Vec::new()),
+    };
+    let name = match name {
+        "INT2" => "SMALLINT",
+        "INT" | "INT4" => "INTEGER",
+        "INT8" => "BIGINT",
+        "CHARACTER VARYING" => "VARCHAR",
+        "NUMERIC" => "DECIMAL",
+        "FLOAT4" => "REAL",
+        "FLOAT8" | "DOUBLE" => "DOUBLE PRECISION",
+        other => other,
+    };
+    (name.to_string(), args)
+}
+
+fn type_change_risk(from: &str, to: &str) -> ChangeRisk {
+    let (from_name, from_args) = type_shape(from);
+    let (to_name, to_args) = type_shape(to);
+    let rank = |name: &str, order: &[&str]| order.iter().position(|t| *t == name);
+    let ints = ["SMALLINT", "INTEGER", "BIGINT"];
+    let floats = ["REAL", "DOUBLE PRECISION"];
+    let widening = if from_name == to_name {
+        match (from_name.as_str(), from_args.as_slice(), to_args.as_slice()) {
+            (_, [], []) => true,
+            // VARCHAR(n) without a length is unbounded
+            (_, [_], []) => true,
+            ("DECIMAL", [p1, s1], [p2, s2]) => s2 >= s1 && p2 - s2 >= p1 - s1,
+            (_, [n1], [n2]) => n2 >= n1,
+            _ => false,
+        }
+    } else if let (Some(a), Some(b)) = (rank(&from_name, &ints), rank(&to_name, &ints)) {
+        b >= a
+    } else if let (Some(a), Some(b)) = (rank(&from_name, &floats), rank(&to_name, &floats)) {
+        b >= a
+    } else if to_name == "TEXT" {
+        return ChangeRisk::NeedsReview;
+    } else {
+        from_name == "CHAR" && to_name == "VARCHAR" && (to_args.is_empty() || to_args >= from_args)
+    };
+    if widening {
+        ChangeRisk::Safe
+    } else {
+        ChangeRisk::Destructive
+    }
+}
+
+/// Every change `diff` makes, classified by what it can do to existing
+/// data. Drops of models and fields marked `dropped = true` are flagged as
+/// declared. Indexes, constraints and foreign keys of tables created or
+/// dropped in the same diff count as part of creating or dropping the table.
+pub fn classify_changes(diff: &SchemaDiff) -> Vec<SchemaChange> {
+    let whole_tables: HashSet<&str> = diff
+        .operations
+        .iter()
+        .filter_map(|op| match op {
+            SchemaOperation::CreateTable { table, .. }
+            | SchemaOperation::CreateJoinTable { table, .. }
+            | SchemaOperation::DropTable { table, .. }
+            | SchemaOperation::DropJoinTable { table, .. } => Some(table.as_str()),
+            _ => None,
+        })
+        .collect();
+    let mut out = Vec::new();
+    for op in &diff.operations {
+        op_changes(diff, &whole_tables, &diff.operations, op, &mut out);
+    }
+    out
+}
+
+/// Declared name of an enum value, whose database label is lowercase.
+fn variant_name(ir: Option<&SchemaIR>, name: &str, label: &str) -> String {
+    ir.and_then(|ir| ir.enums.get(name))
+        .and_then(|en| en.variants.iter().find(|v| v.name.to_lowercase() == label))
+        .map(|v| v.name.clone())
+        .unwrap_or_else(|| label.to_string())
+}
+
+fn op_changes(
+    diff: &SchemaDiff,
+    whole_tables: &HashSet<&str>,
+    siblings: &[SchemaOperation],
+    op: &SchemaOperation,
+    out: &mut Vec<SchemaChange>,
+) {
+    use ChangeRisk::*;
+    use SchemaOperation::*;
+    let dropped = |key: String, description: String| SchemaChange {
+        risk: Destructive,
+        description,
+        declared: diff.declared_drops.contains(&key),
+    };
+    let change = match op {
+        RenameTable { from, to } => SchemaChange::new(NeedsReview, format!("rename table {} to {}", from, to)),
+        RenameColumn { table, from, to } => {
+            SchemaChange::new(NeedsReview, format!("rename column {}.{} to {}", table, from, to))
+        }
+        // Follow from the table and column renames
+        RenameIndex { .. } | RenameConstraint { .. } | RenameSequence { .. } => return,
+        RenameEnum { from, to } => SchemaChange::new(NeedsReview, format!("rename enum {} to {}", from, to)),
+        CreateEnum { name, .. } => SchemaChange::new(Safe, format!("create enum {}", name)),
+        DropEnum { name, .. } => SchemaChange::new(NeedsReview, format!("drop enum {}", name)),
+        AddEnumVariant { name, variant } => SchemaChange::new(
+            Safe,
+            format!("add enum value {}.{}", name, variant_name(Some(&diff.new), name, variant)),
+        ),
+        DropEnumVariant { name, variant } => SchemaChange::new(
+            Destructive,
+            format!("remove enum value {}.{}", name, variant_name(diff.old.as_ref(), name, variant)),
+        ),
+        RenameEnumVariant { name, from, to } => SchemaChange::new(
+            NeedsReview,
+            format!(
+                "rename enum value {}.{} to {}",
+                name,
+                variant_name(diff.old.as_ref(), name, from),
+                variant_name(Some(&diff.new), name, to)
+            ),
+        ),
+        ReorderEnumVariants { name, .. } => {
+            SchemaChange::new(NeedsReview, format!("reorder enum values of {}", name))
+        }
+        CreateTable { table, .. } => SchemaChange::new(Safe, format!("create table {}", table)),
+        CreateJoinTable { table, .. } => SchemaChange::new(Safe, format!("create join table {}", table)),
+        DropTable { table, .. } => dropped(table.clone(), format!("drop table {}", table)),
+        DropJoinTable { table, .. } => dropped(table.clone(), format!("drop join table {}", table)),
+        RebuildTable { changes, .. } => {
+            for op in changes {
+                op_changes(diff, whole_tables, changes, op, out);
+            }
+            return;
+        }
+        AddColumn { table, column, field } if field.nullable || field.default.is_some() => {
+            SchemaChange::new(Safe, format!("add column {}.{}", table, column))
+        }
+        AddColumn { table, column, .. } => SchemaChange::new(
+            NeedsReview,
+            format!("add NOT NULL column {}.{} without a default (fails if the table has rows)", table, column),
+        ),
+        DropColumn { table, column, .. } => {
+            dropped(format!("{}.{}", table, column), format!("drop column {}.{}", table, column))
+        }
+        AlterColumnType { table, column, from, to } => {
+            let risk = type_change_risk(from, to);
+            let kind = if risk == Destructive { "narrow" } else { "change" };
+            SchemaChange::new(risk, format!("{} type of {}.{} from {} to {}", kind, table, column, from, to))
+        }
+        AlterColumnDefault { table, column, .. } => {
+            SchemaChange::new(Safe, format!("change default of {}.{}", table, column))
+        }
+        AlterColumnNullability { table, column, nullable: false } => SchemaChange::new(
+            NeedsReview,
+            format!("make {}.{} NOT NULL (fails if any row is NULL)", table, column),
+        ),
+        AlterColumnNullability { table, column, nullable: true } => {
+            SchemaChange::new(Safe, format!("make {}.{} nullable", table, column))
+        }
+        // A drop and an add move the key; report the pair once, at the add
+        DropPrimaryKey { table, .. } => {
+            if siblings.iter().any(|o| matches!(o, AddPrimaryKey { table: t, .. } if t == table)) {
+                return;
+            }
+            SchemaChange::new(NeedsReview, format!("drop primary key of {}", table))
+        }
+        AddPrimaryKey { table, .. } => {
+            let moved = siblings.iter().any(|o| matches!(o, DropPrimaryKey { table: t, .. } if t == table));
+            let verb = if moved { "change" } else { "add" };
+            SchemaChange::new(NeedsReview, format!("{} primary key of {}", verb, table))
+        }
+        ChangeKeyGeneration { table, .. } => {
+            SchemaChange::new(NeedsReview, format!("change key generation of {}", table))
+        }
+        AddIndex { table, name, .. }
+        | DropIndex { table, name, .. }
+        | AddUniqueConstraint { table, name, .. }
+        | DropUniqueConstraint { table, name, .. }
+        | AddCheckConstraint { table, name, .. }
+        | DropCheckConstraint { table, name, .. }
+        | AddExclusionConstraint { table, name, .. }
+        | DropExclusionConstraint { table, name, .. }
+        | AddRelation { table, name, .. }
+        | DropRelation { table, name, .. } => {
+            if whole_tables.contains(table.as_str()) {
+                return;
+            }
+            // A drop and an add of the same name replace the object; report
+            // the pair once, at the add
+            let replaced = siblings.iter().filter(|o| o.target() == Some((table, name))).count() > 1;
+            let adding = matches!(
+                op,
+                AddIndex { .. }
+                    | AddUniqueConstraint { .. }
+                    | AddCheckConstraint { .. }
+                    | AddExclusionConstraint { .. }
+                    | AddRelation { .. }
+            );
+            if replaced && !adding {
+                return;
+            }
+            let kind = match op {
+                AddIndex { .. } if replaced => {
+                    return out.push(SchemaChange::new(NeedsReview, format!("recreate index {}", name)));
+                }
+                AddIndex { index, .. } if index.unique => {
+                    return out.push(SchemaChange::new(
+                        NeedsReview,
+                        format!("create unique index {} (fails on duplicate rows)", name),
+                    ));
+                }
+                AddIndex { .. } => return out.push(SchemaChange::new(Safe, format!("create index {}", name))),
+                DropIndex { .. } => {
+                    return out.push(SchemaChange::new(NeedsReview, format!("drop index {}", name)));
+                }
+                AddUniqueConstraint { .. } | DropUniqueConstraint { .. } => "unique constraint",
+                AddCheckConstraint { .. } | DropCheckConstraint { .. } => "check constraint",
+                AddExclusionConstraint { .. } | DropExclusionConstraint { .. } => "exclusion constraint",
+                _ => "foreign key",
+            };
+            let action = match (replaced, adding) {
+                (true, _) => "change",
+                (false, true) => "add",
+                (false, false) => "drop",
+            };
+            SchemaChange::new(NeedsReview, format!("{} {} {} on {}", action, kind, name, table))
+        }
+    };
+    out.push(change);
+}
+
+/// Human-readable summary, one change per line, most dangerous first.
+pub fn change_summary(changes: &[SchemaChange]) -> String {
+    let mut sorted: Vec<&SchemaChange> = changes.iter().collect();
+    sorted.sort_by_key(|c| std::cmp::Reverse(c.risk));
+    let mut out = String::new();
+    for change in sorted {
+        out.push_str(&format!("  {:<13}{}", change.risk.label(), change.description));
+        if change.declared {
+            out.push_str(" (declared)");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Fail unless every destructive change is declared in the schema or
+/// `allow_destructive` is set.
+pub fn guard_destructive(changes: &[SchemaChange], allow_destructive: bool) -> anyhow::Result<()> {
+    let undeclared: Vec<&str> = changes
+        .iter()
+        .filter(|c| c.risk == ChangeRisk::Destructive && !c.declared)
+        .map(|c| c.description.as_str())
+        .collect();
+    if undeclared.is_empty() || allow_destructive {
+        return Ok(());
+    }
+    anyhow::bail!(
+        "refusing to generate a destructive migration:\n  {}\nMark dropped models or fields with `dropped = true`, or pass --allow-destructive",
+        undeclared.join("\n  ")
+    )
+}
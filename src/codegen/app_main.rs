@@ -1,5 +1,3 @@
-use crate::ir;
-
 pub fn generate_app_main(_ir: &crate::ir::SchemaIR) -> String {
     let mut out = String::new();
     out.push_str("pub mod generated;\n\n");
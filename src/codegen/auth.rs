@@ -45,12 +45,17 @@ pub fn generate_auth_module(ir: &ir::SchemaIR) -> String {
     out.push_str("    let route: Option<&Route> = match path {\n");
     
     // Generate route mappings for all defined routes
-    let mut routes: Vec<_> = ir.routes.iter().collect();
-    routes.sort_by(|a, b| a.0.cmp(b.0));
+    let routes: Vec<_> = ir.routes.iter().collect();
     
     for (route_name, route_def) in &routes {
         let route_path = &route_def.path;
-        let route_path_with_id = format!("{}/:id", route_path);
+        // Routes named after a model use that model's key segments
+        let key_path = ir
+            .models
+            .get(*route_name)
+            .map(|model| super::primary_key_path(&super::primary_key_params(model)))
+            .unwrap_or_else(|| "/:id".to_string());
+        let route_path_with_id = format!("{}{}", route_path, key_path);
         out.push_str(&format!("        \"{}\" | \"{}\" => Some(&routes::{}),\n", 
             route_path, route_path_with_id, route_name.to_uppercase()));
     }
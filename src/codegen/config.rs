@@ -14,6 +14,21 @@ pub fn generate_config_struct(_ir: &ir::SchemaIR) -> String {
     out.push_str("#[derive(Debug, Deserialize, Clone)]\n");
     out.push_str("pub struct DatabaseConfig {\n");
     out.push_str("    pub url: String,\n");
+    out.push_str("    pub migrations: MigrationMode,\n");
+    out.push_str("    /// How long `apply` waits for another process holding the migration lock\n");
+    out.push_str("    pub migration_lock_timeout: std::time::Duration,\n");
+    out.push_str("}\n\n");
+
+    out.push_str("/// What the server does with the embedded migrations at startup.\n");
+    out.push_str("#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]\n");
+    out.push_str("#[serde(rename_all = \"lowercase\")]\n");
+    out.push_str("pub enum MigrationMode {\n");
+    out.push_str("    /// Apply pending migrations\n");
+    out.push_str("    Apply,\n");
+    out.push_str("    /// Refuse to start unless every migration is applied unchanged\n");
+    out.push_str("    Verify,\n");
+    out.push_str("    /// Leave the database alone\n");
+    out.push_str("    Off,\n");
     out.push_str("}\n\n");
     
     out.push_str("#[derive(Debug, Deserialize, Clone)]\n");
@@ -28,11 +43,22 @@ pub fn generate_config_struct(_ir: &ir::SchemaIR) -> String {
     out.push_str("        dotenvy::dotenv().ok();\n\n");
     out.push_str("        let database_url = std::env::var(\"DATABASE_URL\")\n");
     out.push_str("            .map_err(|_| anyhow::anyhow!(\"DATABASE_URL must be set\"))?;\n\n");
+    out.push_str("        let migrations = match std::env::var(\"MIGRATIONS\").as_deref() {\n");
+    out.push_str("            Ok(\"apply\") => MigrationMode::Apply,\n");
+    out.push_str("            Ok(\"verify\") | Err(_) => MigrationMode::Verify,\n");
+    out.push_str("            Ok(\"off\") => MigrationMode::Off,\n");
+    out.push_str("            Ok(other) => anyhow::bail!(\"MIGRATIONS must be apply, verify or off, not {}\", other),\n");
+    out.push_str("        };\n");
+    out.push_str("        let migration_lock_timeout = std::env::var(\"MIGRATION_LOCK_TIMEOUT\")\n");
+    out.push_str("            .unwrap_or_else(|_| \"60\".to_string())\n");
+    out.push_str("            .parse::<u64>()\n");
+    out.push_str("            .map(std::time::Duration::from_secs)\n");
+    out.push_str("            .map_err(|_| anyhow::anyhow!(\"MIGRATION_LOCK_TIMEOUT must be a number of seconds\"))?;\n\n");
     out.push_str("        let port = std::env::var(\"PORT\").unwrap_or_else(|_| \"3000\".to_string()).parse::<u16>()?;\n\n");
     out.push_str("        let jwt_secret = std::env::var(\"JWT_SECRET\")\n");
     out.push_str("            .map_err(|_| anyhow::anyhow!(\"JWT_SECRET must be set\"))?;\n\n");
     out.push_str("        Ok(Self {\n");
-    out.push_str("            database: DatabaseConfig { url: database_url },\n");
+    out.push_str("            database: DatabaseConfig { url: database_url, migrations, migration_lock_timeout },\n");
     out.push_str("            server: ServerConfig { port, jwt_secret },\n");
     out.push_str("        })\n");
     out.push_str("    }\n");
@@ -8,10 +8,7 @@ pub fn generate_crud_impls(ir: &ir::SchemaIR) -> String {
     // Add imports at the top
     out.push_str("use crate::generated::*;\n\n");
 
-    let mut models: Vec<_> = ir.models.iter().collect();
-    models.sort_by(|a, b| a.0.cmp(b.0));
-
-    for (model_name, model) in models {
+    for (model_name, model) in &ir.models {
         out.push_str(&generate_model_crud(
             model_name,
             model,
@@ -54,19 +51,16 @@ fn generate_model_crud(
     create_sig.push_str(&format!(") -> Result<{}, {}CreateError> {{\n", model_name, model_name));
     out.push_str(&create_sig);
 
-    let mut uc_names: Vec<_> = model.unique_constraints.keys().collect();
-    uc_names.sort();
-    let mut rel_names: Vec<_> = model.relations.iter().filter(|(_, rel)| rel.on != "id").map(|(k, _)| k.clone()).collect();
-    rel_names.sort();
+    let uc_names: Vec<_> = model.unique_constraints.keys().collect();
+    let rel_names: Vec<_> = model.relations.iter().filter(|(_, rel)| rel.on != "id").map(|(k, _)| k.clone()).collect();
 
-    let mut field_names: Vec<String> = model
+    let field_names: Vec<String> = model
         .fields
         .iter()
         .filter(|(name, f)| f.default.is_none() && *name != "deleted_at")
         .map(|(n, _)| n.to_string())
         .collect();
     
-    field_names.sort();
 
     match ir.meta.db_backend {
         ir::DatabaseBackend::Postgres | ir::DatabaseBackend::Sqlite => {
@@ -189,7 +183,7 @@ fn generate_model_crud(
             ));
         }
     }
-    out.push_str("\n");
+    out.push('\n');
 
     // find
     if tracing_enabled {
@@ -249,8 +243,7 @@ fn generate_model_crud(
         model_name.to_lowercase()
     ));
     out.push_str("        let mut has_updates = false;\n");
-    let mut fields_vec: Vec<_> = model.fields.iter().collect();
-    fields_vec.sort_by(|a, b| a.0.cmp(b.0));
+    let fields_vec: Vec<_> = model.fields.iter().collect();
     out.push_str("        let mut separated = qb.separated(\", \");\n");
     for (field_name, field) in fields_vec {
         if field.default.is_none() {
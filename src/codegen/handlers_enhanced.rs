@@ -1,5 +1,4 @@
 use crate::ir;
-use std::collections::HashMap;
 
 pub fn generate_enhanced_crud_impls(ir: &ir::SchemaIR) -> String {
     let mut out = String::new();
@@ -11,10 +10,7 @@ pub fn generate_enhanced_crud_impls(ir: &ir::SchemaIR) -> String {
     out.push_str("use crate::generated::executor::*;\n");
     out.push_str("use std::collections::HashMap;\n\n");
 
-    let mut models: Vec<_> = ir.models.iter().collect();
-    models.sort_by(|a, b| a.0.cmp(b.0));
-
-    for (model_name, model) in models {
+    for (model_name, model) in &ir.models {
         out.push_str(&generate_enhanced_model_crud(
             model_name,
             model,
@@ -52,6 +48,9 @@ fn generate_enhanced_model_crud(
     // Generate relational helpers
     out.push_str(&generate_relational_helpers(model_name, model, ir, tracing_enabled, executor_trait));
     
+    // Generate many-to-many helpers
+    out.push_str(&generate_many_to_many_helpers(model_name, model, ir, tracing_enabled, executor_trait, placeholder_fn));
+
     // Generate eager loading helpers
     out.push_str(&generate_eager_loading_helpers(model_name, model, ir, tracing_enabled, executor_trait));
 
@@ -90,19 +89,24 @@ fn generate_enhanced_create(
     out.push_str(&create_sig);
 
     // Get field constraints and relations
-    let mut uc_names: Vec<_> = model.unique_constraints.keys().collect();
-    uc_names.sort();
-    let mut rel_names: Vec<_> = model.relations.iter().filter(|(_, rel)| rel.on != "id").map(|(k, _)| k.clone()).collect();
-    rel_names.sort();
+    let uc_names: Vec<_> = model.unique_constraints.keys().collect();
+    let rel_names: Vec<_> = model.relations.iter().filter(|(_, rel)| rel.on != "id").map(|(k, _)| k.clone()).collect();
 
-    let mut field_names: Vec<String> = model
+    let pk_fields = model.primary_key_fields();
+    let identity = model.key_generation() == ir::KeyGeneration::Identity;
+    let field_names: Vec<String> = model
         .fields
         .iter()
         .filter(|(name, f)| f.default.is_none() && *name != "deleted_at")
+        .filter(|(name, _)| !(identity && pk_fields.contains(*name)))
         .map(|(n, _)| n.to_string())
         .collect();
-    
-    field_names.sort();
+    let table = ir.qualify(&ir.table_name(model_name));
+    let column_list = field_names
+        .iter()
+        .map(|f| ir.column_name(model_name, f))
+        .collect::<Vec<_>>()
+        .join(", ");
 
     match ir.meta.db_backend {
         ir::DatabaseBackend::Postgres | ir::DatabaseBackend::Sqlite => {
@@ -110,16 +114,16 @@ fn generate_enhanced_create(
                 out.push_str(&format!(
                     "        let res = sqlx::query_as::<_, {}>(\"INSERT INTO {} DEFAULT VALUES RETURNING *\")\n            .fetch_one(executor)\n            .await;\n",
                     model_name,
-                    model_name.to_lowercase()
+                    table
                 ));
             } else {
-                let cols = field_names.join(", ");
+                let cols = &column_list;
                 let binds: Vec<String> = (1..=field_names.len()).map(placeholder_fn).collect();
                 let placeholders = binds.join(", ");
                 out.push_str(&format!(
                     "        let res = sqlx::query_as::<_, {}>(\"INSERT INTO {} ({}) VALUES ({}) RETURNING *\")\n",
                     model_name,
-                    model_name.to_lowercase(),
+                    table,
                     cols,
                     placeholders
                 ));
@@ -182,15 +186,15 @@ fn generate_enhanced_create(
             if field_names.is_empty() {
                 out.push_str(&format!(
                     "        sqlx::query(\"INSERT INTO {} DEFAULT VALUES\")\n            .execute(executor)\n            .await?;\n",
-                    model_name.to_lowercase()
+                    table
                 ));
             } else {
-                let cols = field_names.join(", ");
+                let cols = &column_list;
                 let binds: Vec<String> = (1..=field_names.len()).map(placeholder_fn).collect();
                 let placeholders = binds.join(", ");
                 out.push_str(&format!(
                     "        sqlx::query(\"INSERT INTO {} ({}) VALUES ({})\")\n",
-                    model_name.to_lowercase(),
+                    table,
                     cols,
                     placeholders
                 ));
@@ -225,11 +229,31 @@ fn generate_enhanced_create(
                 }
                 out.push_str("            .execute(executor)\n            .await?;\n");
             }
-            out.push_str(&format!(
-                "        let last_id: u64 = sqlx::query_scalar(\"SELECT LAST_INSERT_ID()\")\n            .fetch_one(executor)\n            .await?;\n        let res = sqlx::query_as::<_, {}>(\"SELECT * FROM {} WHERE id = ?\")\n            .bind(last_id)\n            .fetch_one(executor)\n            .await;\n",
-                model_name,
-                model_name.to_lowercase()
-            ));
+            // Natural keys are known up front; anything else comes back from LAST_INSERT_ID()
+            let key_params = super::primary_key_params(model);
+            let key_columns = key_column_names(ir, model_name, &key_params);
+            let supplied = !identity && key_params.iter().all(|(name, _)| {
+                field_names.iter().any(|f| escape_rust_keyword(f) == *name)
+            });
+            if supplied {
+                out.push_str(&format!(
+                    "        let res = sqlx::query_as::<_, {}>(\"SELECT * FROM {} WHERE {}\")\n",
+                    model_name,
+                    table,
+                    key_where(&key_columns, placeholder_fn, 1)
+                ));
+                for (name, _) in &key_params {
+                    out.push_str(&format!("            .bind(&item.{})\n", name));
+                }
+                out.push_str("            .fetch_one(executor)\n            .await;\n");
+            } else {
+                out.push_str(&format!(
+                    "        let last_id: u64 = sqlx::query_scalar(\"SELECT LAST_INSERT_ID()\")\n            .fetch_one(executor)\n            .await?;\n        let res = sqlx::query_as::<_, {}>(\"SELECT * FROM {} WHERE {} = ?\")\n            .bind(last_id)\n            .fetch_one(executor)\n            .await;\n",
+                    model_name,
+                    table,
+                    key_columns[0]
+                ));
+            }
             
             // Error handling for MySQL
             out.push_str("        match res {\n            Ok(v) => Ok(v),\n            Err(e) => {\n                if let sqlx::Error::Database(db_err) = &e {\n                    if let Some(c) = db_err.constraint() {\n");
@@ -267,6 +291,12 @@ fn generate_enhanced_find(
     placeholder_fn: fn(usize) -> String,
 ) -> String {
     let mut out = String::new();
+    let key_params = super::primary_key_params(model);
+    let key_sig = super::primary_key_signature(&key_params);
+    let key_columns = key_column_names(ir, model_name, &key_params);
+    let table = ir.qualify(&ir.table_name(model_name));
+    let owner_column = ir.column_name(model_name, "user_id");
+    let deleted_at = ir.column_name(model_name, "deleted_at");
 
     if tracing_enabled {
         out.push_str("    #[tracing::instrument]\n");
@@ -275,13 +305,13 @@ fn generate_enhanced_find(
     // Generate find function signature with executor trait
     if model.owned_by.is_some() {
         out.push_str(&format!(
-            "    pub async fn find<'c, E>(executor: E, id: uuid::Uuid, user_id: uuid::Uuid) -> Result<{}, sqlx::Error>\n",
-            model_name
+            "    pub async fn find<'c, E>(executor: E, {}, user_id: uuid::Uuid) -> Result<{}, sqlx::Error>\n",
+            key_sig, model_name
         ));
     } else {
         out.push_str(&format!(
-            "    pub async fn find<'c, E>(executor: E, id: uuid::Uuid) -> Result<{}, sqlx::Error>\n",
-            model_name
+            "    pub async fn find<'c, E>(executor: E, {}) -> Result<{}, sqlx::Error>\n",
+            key_sig, model_name
         ));
     }
     
@@ -291,23 +321,23 @@ fn generate_enhanced_find(
     
     // Generate query based on ownership
     if model.owned_by.is_some() {
-        let mut find_query = format!("SELECT * FROM {} WHERE id = {} AND user_id = {}", 
-            model_name.to_lowercase(), placeholder_fn(1), placeholder_fn(2));
+        let mut find_query = format!("SELECT * FROM {} WHERE {} AND {} = {}",
+            table, key_where(&key_columns, placeholder_fn, 1), owner_column, placeholder_fn(key_params.len() + 1));
         if model.options.soft_delete {
-            find_query.push_str(" AND deleted_at IS NULL");
+            find_query.push_str(&format!(" AND {} IS NULL", deleted_at));
         }
         out.push_str(&format!(
-            "        sqlx::query_as::<_, {}>(\"{}\")\n            .bind(id)\n            .bind(user_id)\n            .fetch_one(executor)\n            .await\n    }}\n\n",
-            model_name, find_query
+            "        sqlx::query_as::<_, {}>(\"{}\")\n{}            .bind(user_id)\n            .fetch_one(executor)\n            .await\n    }}\n\n",
+            model_name, find_query, key_binds(&key_params)
         ));
     } else {
-        let mut find_query = format!("SELECT * FROM {} WHERE id = {}", model_name.to_lowercase(), placeholder_fn(1));
+        let mut find_query = format!("SELECT * FROM {} WHERE {}", table, key_where(&key_columns, placeholder_fn, 1));
         if model.options.soft_delete {
-            find_query.push_str(" AND deleted_at IS NULL");
+            find_query.push_str(&format!(" AND {} IS NULL", deleted_at));
         }
         out.push_str(&format!(
-            "        sqlx::query_as::<_, {}>(\"{}\")\n            .bind(id)\n            .fetch_one(executor)\n            .await\n    }}\n\n",
-            model_name, find_query
+            "        sqlx::query_as::<_, {}>(\"{}\")\n{}            .fetch_one(executor)\n            .await\n    }}\n\n",
+            model_name, find_query, key_binds(&key_params)
         ));
     }
 
@@ -324,6 +354,13 @@ fn generate_enhanced_update(
     placeholder_fn: fn(usize) -> String,
 ) -> String {
     let mut out = String::new();
+    let key_params = super::primary_key_params(model);
+    let key_sig = super::primary_key_signature(&key_params);
+    let pk_fields = model.primary_key_fields();
+    let key_columns = key_column_names(ir, model_name, &key_params);
+    let table = ir.qualify(&ir.table_name(model_name));
+    let owner_column = ir.column_name(model_name, "user_id");
+    let deleted_at = ir.column_name(model_name, "deleted_at");
 
     if tracing_enabled {
         out.push_str("    #[tracing::instrument]\n");
@@ -332,13 +369,13 @@ fn generate_enhanced_update(
     // Generate update function signature with executor trait
     if model.owned_by.is_some() {
         out.push_str(&format!(
-            "    pub async fn update<'c, E>(executor: E, id: uuid::Uuid, user_id: uuid::Uuid, item: &{}Update) -> Result<{}, sqlx::Error>\n",
-            model_name, model_name
+            "    pub async fn update<'c, E>(executor: E, {}, user_id: uuid::Uuid, item: &{}Update) -> Result<{}, sqlx::Error>\n",
+            key_sig, model_name, model_name
         ));
     } else {
         out.push_str(&format!(
-            "    pub async fn update<'c, E>(executor: E, id: uuid::Uuid, item: &{}Update) -> Result<{}, sqlx::Error>\n",
-            model_name, model_name
+            "    pub async fn update<'c, E>(executor: E, {}, item: &{}Update) -> Result<{}, sqlx::Error>\n",
+            key_sig, model_name, model_name
         ));
     }
     
@@ -348,17 +385,16 @@ fn generate_enhanced_update(
     
     out.push_str(&format!(
         "        let mut qb = sqlx::QueryBuilder::<{}>::new(\"UPDATE {} SET \" );\n",
-        qb_type,
-        model_name.to_lowercase()
+        qb_type, table
     ));
     out.push_str("        let mut has_updates = false;\n");
-    let mut fields_vec: Vec<_> = model.fields.iter().collect();
-    fields_vec.sort_by(|a, b| a.0.cmp(b.0));
+    let fields_vec: Vec<_> = model.fields.iter().collect();
     out.push_str("        let mut separated = qb.separated(\", \");\n");
     
     for (field_name, field) in fields_vec {
-        if field.default.is_none() {
+        if field.default.is_none() && !pk_fields.contains(field_name) {
             let field_name_escaped = escape_rust_keyword(field_name);
+            let column = ir.column_name(model_name, field_name);
             
             // Handle password fields specially - hash the password
             if field.tags.contains(&"password".to_string()) {
@@ -373,19 +409,19 @@ fn generate_enhanced_update(
                     out.push_str("                .to_string();\n");
                     out.push_str(&format!(
                         "            separated.push(\"{} = \").push_bind(hashed); has_updates = true;\n",
-                        field_name
+                        column
                     ));
                     out.push_str("        }\n");
                 } else {
                     out.push_str(&format!(
                         "        if let Some(value) = &item.{} {{ separated.push(\"{} = \").push_bind(value); has_updates = true; }}\n",
-                        field_name_escaped, field_name
+                        field_name_escaped, column
                     ));
                 }
             } else {
                 out.push_str(&format!(
                     "        if let Some(value) = &item.{} {{ separated.push(\"{} = \").push_bind(value); has_updates = true; }}\n",
-                    field_name_escaped, field_name
+                    field_name_escaped, column
                 ));
             }
         }
@@ -394,19 +430,19 @@ fn generate_enhanced_update(
     out.push_str("        if !has_updates {\n");
     if model.owned_by.is_some() {
         out.push_str("            // Can't call Self::find with a generic executor easily, so we query directly\n");
-        out.push_str(&format!("            return sqlx::query_as(\"SELECT * FROM {} WHERE id = {} AND user_id = {}", 
-            model_name.to_lowercase(), placeholder_fn(1), placeholder_fn(2)));
+        out.push_str(&format!("            return sqlx::query_as(\"SELECT * FROM {} WHERE {} AND {} = {}",
+            table, key_where(&key_columns, placeholder_fn, 1), owner_column, placeholder_fn(key_params.len() + 1)));
         if model.options.soft_delete {
-            out.push_str(" AND deleted_at IS NULL");
+            out.push_str(&format!(" AND {} IS NULL", deleted_at));
         }
-        out.push_str("\")\n                .bind(id).bind(user_id).fetch_one(executor).await;\n");
+        out.push_str(&format!("\")\n                {}.bind(user_id).fetch_one(executor).await;\n", key_binds_inline(&key_params)));
     } else {
-        out.push_str(&format!("            return sqlx::query_as(\"SELECT * FROM {} WHERE id = {}", 
-            model_name.to_lowercase(), placeholder_fn(1)));
+        out.push_str(&format!("            return sqlx::query_as(\"SELECT * FROM {} WHERE {}",
+            table, key_where(&key_columns, placeholder_fn, 1)));
         if model.options.soft_delete {
-            out.push_str(" AND deleted_at IS NULL");
+            out.push_str(&format!(" AND {} IS NULL", deleted_at));
         }
-        out.push_str("\")\n                .bind(id).fetch_one(executor).await;\n");
+        out.push_str(&format!("\")\n                {}.fetch_one(executor).await;\n", key_binds_inline(&key_params)));
     }
     out.push_str("        }\n");
     
@@ -414,12 +450,15 @@ fn generate_enhanced_update(
         ir::DatabaseBackend::Postgres | ir::DatabaseBackend::Sqlite => {
             if model.owned_by.is_some() {
                 out.push_str(&format!(
-                    "        qb.push(\" WHERE id = \" ).push_bind(id).push(\" AND user_id = \").push_bind(user_id).push(\" RETURNING *\");\n        let query = qb.build_query_as::<{}>();\n        query.fetch_one(executor).await\n    }}\n\n",
+                    "        qb.push(\" WHERE \"){}.push(\" AND {} = \").push_bind(user_id).push(\" RETURNING *\");\n        let query = qb.build_query_as::<{}>();\n        query.fetch_one(executor).await\n    }}\n\n",
+                    key_push_binds(&key_params, &key_columns),
+                    owner_column,
                     model_name,
                 ));
             } else {
                 out.push_str(&format!(
-                    "        qb.push(\" WHERE id = \" ).push_bind(id).push(\" RETURNING *\");\n        let query = qb.build_query_as::<{}>();\n        query.fetch_one(executor).await\n    }}\n\n",
+                    "        qb.push(\" WHERE \"){}.push(\" RETURNING *\");\n        let query = qb.build_query_as::<{}>();\n        query.fetch_one(executor).await\n    }}\n\n",
+                    key_push_binds(&key_params, &key_columns),
                     model_name,
                 ));
             }
@@ -428,15 +467,23 @@ fn generate_enhanced_update(
             // MySQL implementation (similar pattern)
             if model.owned_by.is_some() {
                 out.push_str(&format!(
-                    "        qb.push(\" WHERE id = \" ).push_bind(id).push(\" AND user_id = \").push_bind(user_id);\n        let query = qb.build();\n        query.execute(executor).await?;\n        sqlx::query_as::<_, {}>(\"SELECT * FROM {} WHERE id = ? AND user_id = ?\")\n            .bind(id)\n            .bind(user_id)\n            .fetch_one(executor)\n            .await\n    }}\n\n",
+                    "        qb.push(\" WHERE \"){}.push(\" AND {} = \").push_bind(user_id);\n        let query = qb.build();\n        query.execute(executor).await?;\n        sqlx::query_as::<_, {}>(\"SELECT * FROM {} WHERE {} AND {} = ?\")\n{}            .bind(user_id)\n            .fetch_one(executor)\n            .await\n    }}\n\n",
+                    key_push_binds(&key_params, &key_columns),
+                    owner_column,
                     model_name,
-                    model_name.to_lowercase(),
+                    table,
+                    key_where(&key_columns, placeholder_fn, 1),
+                    owner_column,
+                    key_binds(&key_params),
                 ));
             } else {
                 out.push_str(&format!(
-                    "        qb.push(\" WHERE id = \" ).push_bind(id);\n        let query = qb.build();\n        query.execute(executor).await?;\n        sqlx::query_as::<_, {}>(\"SELECT * FROM {} WHERE id = ?\")\n            .bind(id)\n            .fetch_one(executor)\n            .await\n    }}\n\n",
+                    "        qb.push(\" WHERE \"){};\n        let query = qb.build();\n        query.execute(executor).await?;\n        sqlx::query_as::<_, {}>(\"SELECT * FROM {} WHERE {}\")\n{}            .fetch_one(executor)\n            .await\n    }}\n\n",
+                    key_push_binds(&key_params, &key_columns),
                     model_name,
-                    model_name.to_lowercase(),
+                    table,
+                    key_where(&key_columns, placeholder_fn, 1),
+                    key_binds(&key_params),
                 ));
             }
         }
@@ -454,6 +501,12 @@ fn generate_enhanced_delete(
     placeholder_fn: fn(usize) -> String,
 ) -> String {
     let mut out = String::new();
+    let key_params = super::primary_key_params(model);
+    let key_sig = super::primary_key_signature(&key_params);
+    let key_columns = key_column_names(ir, model_name, &key_params);
+    let key_clause = key_where(&key_columns, placeholder_fn, 1);
+    let table = ir.qualify(&ir.table_name(model_name));
+    let owner_placeholder = placeholder_fn(key_params.len() + 1);
 
     if tracing_enabled {
         out.push_str("    #[tracing::instrument]\n");
@@ -462,11 +515,13 @@ fn generate_enhanced_delete(
     // Generate delete function signature with executor trait
     if model.owned_by.is_some() {
         out.push_str(&format!(
-            "    pub async fn delete<'c, E>(executor: E, id: uuid::Uuid, user_id: uuid::Uuid) -> Result<u64, sqlx::Error>\n"
+            "    pub async fn delete<'c, E>(executor: E, {}, user_id: uuid::Uuid) -> Result<u64, {}DeleteError>\n",
+            key_sig, model_name
         ));
     } else {
         out.push_str(&format!(
-            "    pub async fn delete<'c, E>(executor: E, id: uuid::Uuid) -> Result<u64, sqlx::Error>\n"
+            "    pub async fn delete<'c, E>(executor: E, {}) -> Result<u64, {}DeleteError>\n",
+            key_sig, model_name
         ));
     }
     
@@ -474,31 +529,47 @@ fn generate_enhanced_delete(
     out.push_str(&format!("        E: {},\n", executor_trait));
     out.push_str("    {\n");
     
+    let statement = if model.options.soft_delete {
+        format!(
+            "UPDATE {} SET {} = now() WHERE {}",
+            table,
+            ir.column_name(model_name, "deleted_at"),
+            key_clause
+        )
+    } else {
+        format!("DELETE FROM {} WHERE {}", table, key_clause)
+    };
     if model.owned_by.is_some() {
-        if model.options.soft_delete {
-            out.push_str(&format!(
-                "        let res = sqlx::query(\"UPDATE {} SET deleted_at = now() WHERE id = {} AND user_id = {}\")\n            .bind(id)\n            .bind(user_id)\n            .execute(executor)\n            .await?;\n        Ok(res.rows_affected())\n    }}\n\n",
-                model_name.to_lowercase(), placeholder_fn(1), placeholder_fn(2)
-            ));
-        } else {
-            out.push_str(&format!(
-                "        let res = sqlx::query(\"DELETE FROM {} WHERE id = {} AND user_id = {}\")\n            .bind(id)\n            .bind(user_id)\n            .execute(executor)\n            .await?;\n        Ok(res.rows_affected())\n    }}\n\n",
-                model_name.to_lowercase(), placeholder_fn(1), placeholder_fn(2)
-            ));
-        }
+        out.push_str(&format!(
+            "        let res = sqlx::query(\"{} AND {} = {}\")\n{}            .bind(user_id)\n            .execute(executor)\n            .await;\n",
+            statement, ir.column_name(model_name, "user_id"), owner_placeholder, key_binds(&key_params)
+        ));
     } else {
-        if model.options.soft_delete {
-            out.push_str(&format!(
-                "        let res = sqlx::query(\"UPDATE {} SET deleted_at = now() WHERE id = {}\")\n            .bind(id)\n            .execute(executor)\n            .await?;\n        Ok(res.rows_affected())\n    }}\n\n",
-                model_name.to_lowercase(), placeholder_fn(1)
-            ));
-        } else {
+        out.push_str(&format!(
+            "        let res = sqlx::query(\"{}\")\n{}            .execute(executor)\n            .await;\n",
+            statement, key_binds(&key_params)
+        ));
+    }
+
+    // Map foreign keys that block the delete to typed errors
+    out.push_str("        match res {\n            Ok(r) => Ok(r.rows_affected()),\n            Err(e) => {\n");
+    let refs = super::restricting_references(ir, model_name);
+    if !refs.is_empty() {
+        out.push_str("                if let sqlx::Error::Database(db_err) = &e {\n                    if let Some(c) = db_err.constraint() {\n");
+        for (other_model, rel_name) in refs {
             out.push_str(&format!(
-                "        let res = sqlx::query(\"DELETE FROM {} WHERE id = {}\")\n            .bind(id)\n            .execute(executor)\n            .await?;\n        Ok(res.rows_affected())\n    }}\n\n",
-                model_name.to_lowercase(), placeholder_fn(1)
+                "                        if c == \"{}\" {{ return Err({}DeleteError::{}); }}\n",
+                rel_name,
+                model_name,
+                super::referenced_by_variant(other_model, rel_name)
             ));
         }
+        out.push_str("                    }\n                }\n");
     }
+    out.push_str(&format!(
+        "                Err({}DeleteError::Database(e))\n            }}\n        }}\n    }}\n\n",
+        model_name
+    ));
 
     out
 }
@@ -512,6 +583,8 @@ fn generate_enhanced_list(
     qb_type: &str,
 ) -> String {
     let mut out = String::new();
+    let owner_column = ir.column_name(model_name, "user_id");
+    let deleted_at = ir.column_name(model_name, "deleted_at");
 
     if tracing_enabled {
         out.push_str("    #[tracing::instrument]\n");
@@ -537,18 +610,21 @@ fn generate_enhanced_list(
     out.push_str(&format!(
         "        let mut qb = sqlx::QueryBuilder::<{}>::new(\"SELECT * FROM {}\");\n",
         qb_type,
-        model_name.to_lowercase()
+        ir.qualify(&ir.table_name(model_name))
     ));
     
     if model.owned_by.is_some() {
         if model.options.soft_delete {
-            out.push_str("        qb.push(\" WHERE deleted_at IS NULL AND user_id = \").push_bind(user_id);\n");
+            out.push_str(&format!(
+                "        qb.push(\" WHERE {} IS NULL AND {} = \").push_bind(user_id);\n",
+                deleted_at, owner_column
+            ));
         } else {
-            out.push_str("        qb.push(\" WHERE user_id = \").push_bind(user_id);\n");
+            out.push_str(&format!("        qb.push(\" WHERE {} = \").push_bind(user_id);\n", owner_column));
         }
     } else {
         if model.options.soft_delete {
-            out.push_str("        qb.push(\" WHERE deleted_at IS NULL\");\n");
+            out.push_str(&format!("        qb.push(\" WHERE {} IS NULL\");\n", deleted_at));
         }
     }
     
@@ -579,8 +655,8 @@ fn generate_relational_helpers(
             let target_model = field_name.strip_suffix("_id").unwrap();
             let target_model_pascal = pascal_case(target_model);
             
-            // Check if this target model actually exists in the schema
-            if ir.models.contains_key(&target_model_pascal) {
+            // Check if this target model actually exists and has a single-column key
+            if ir.models.get(&target_model_pascal).is_some_and(|t| super::primary_key_params(t).len() == 1) {
                 out.push_str("    // --- Relational Helper: belongs to ---\n");
                 if tracing_enabled {
                     out.push_str("    #[tracing::instrument]\n");
@@ -601,7 +677,13 @@ fn generate_relational_helpers(
         }
     }
 
-    // Generate "has many" relationships (other models that reference this model)
+    // Generate "has many" relationships (other models that reference this model);
+    // the foreign key column can only hold a single-column key
+    let key_params = super::primary_key_params(model);
+    if key_params.len() != 1 {
+        return out;
+    }
+    let self_key = format!("self.{}", key_params[0].0);
     for (other_model_name, other_model) in &ir.models {
         if other_model_name == model_name {
             continue; // Skip self
@@ -636,14 +718,14 @@ fn generate_relational_helpers(
                 
                 out.push_str(&format!(
                     "        let mut qb = sqlx::QueryBuilder::<{}>::new(\"SELECT * FROM {}\");\n",
-                    qb_type, other_model_name.to_lowercase()
+                    qb_type, ir.qualify(&ir.table_name(other_model_name))
                 ));
                 
                 let mut where_conditions = Vec::new();
                 if other_model.options.soft_delete {
-                    where_conditions.push("deleted_at IS NULL".to_string());
+                    where_conditions.push(format!("{} IS NULL", ir.column_name(other_model_name, "deleted_at")));
                 }
-                where_conditions.push(format!("{} = ", expected_fk));
+                where_conditions.push(format!("{} = ", ir.column_name(other_model_name, &expected_fk)));
                 
                 if !where_conditions.is_empty() {
                     out.push_str("        qb.push(\" WHERE \");\n");
@@ -652,7 +734,7 @@ fn generate_relational_helpers(
                             out.push_str("        qb.push(\" AND \");\n");
                         }
                         if condition.ends_with(" = ") {
-                            out.push_str(&format!("        qb.push(\"{}\").push_bind(self.id);\n", condition));
+                            out.push_str(&format!("        qb.push(\"{}\").push_bind({});\n", condition, self_key));
                         } else {
                             out.push_str(&format!("        qb.push(\"{}\");\n", condition));
                         }
@@ -677,14 +759,14 @@ fn generate_relational_helpers(
                 
                 out.push_str(&format!(
                     "        let mut qb = sqlx::QueryBuilder::<{}>::new(\"SELECT * FROM {}\");\n",
-                    qb_type, other_model_name.to_lowercase()
+                    qb_type, ir.qualify(&ir.table_name(other_model_name))
                 ));
                 
                 let mut where_conditions = Vec::new();
                 if other_model.options.soft_delete {
-                    where_conditions.push("deleted_at IS NULL".to_string());
+                    where_conditions.push(format!("{} IS NULL", ir.column_name(other_model_name, "deleted_at")));
                 }
-                where_conditions.push(format!("{} = ", expected_fk));
+                where_conditions.push(format!("{} = ", ir.column_name(other_model_name, &expected_fk)));
                 
                 if !where_conditions.is_empty() {
                     out.push_str("        qb.push(\" WHERE \");\n");
@@ -693,7 +775,7 @@ fn generate_relational_helpers(
                             out.push_str("        qb.push(\" AND \");\n");
                         }
                         if condition.ends_with(" = ") {
-                            out.push_str(&format!("        qb.push(\"{}\").push_bind(self.id);\n", condition));
+                            out.push_str(&format!("        qb.push(\"{}\").push_bind({});\n", condition, self_key));
                         } else {
                             out.push_str(&format!("        qb.push(\"{}\");\n", condition));
                         }
@@ -717,6 +799,132 @@ fn generate_relational_helpers(
     out
 }
 
+/// `attach_*`, `detach_*` and `find_*` for each many-to-many relation. The join
+/// table columns hold single-column keys, so composite keys on either side are skipped.
+fn generate_many_to_many_helpers(
+    model_name: &str,
+    model: &ir::ModelDef,
+    ir: &ir::SchemaIR,
+    tracing_enabled: bool,
+    executor_trait: &str,
+    placeholder_fn: fn(usize) -> String,
+) -> String {
+    let mut out = String::new();
+
+    let key_params = super::primary_key_params(model);
+    if key_params.len() != 1 {
+        return out;
+    }
+    let self_key = format!("&self.{}", key_params[0].0);
+    for rel_name in model.many_to_many.keys() {
+        let Some(mut jt) = ir.join_table(model_name, rel_name) else {
+            continue;
+        };
+        jt.table = ir.qualify(&jt.table);
+        let Some(target) = ir.models.get(&jt.target_model) else {
+            continue;
+        };
+        let target_params = super::primary_key_params(target);
+        if target_params.len() != 1 {
+            continue;
+        }
+        let target_key_column = ir.column_name(&jt.target_model, target_params[0].0.trim_start_matches("r#"));
+        let target_arg = escape_rust_keyword(&jt.target_column);
+
+        out.push_str(&format!("    // --- Many-to-many Helper: {} ---\n", rel_name));
+
+        // Attach; linking an already linked row is a no-op
+        let insert = match ir.meta.db_backend {
+            ir::DatabaseBackend::Postgres => format!(
+                "INSERT INTO {} ({}, {}) VALUES ({}, {}) ON CONFLICT DO NOTHING",
+                jt.table, jt.source_column, jt.target_column, placeholder_fn(1), placeholder_fn(2)
+            ),
+            ir::DatabaseBackend::Mysql => format!(
+                "INSERT IGNORE INTO {} ({}, {}) VALUES (?, ?)",
+                jt.table, jt.source_column, jt.target_column
+            ),
+            ir::DatabaseBackend::Sqlite => format!(
+                "INSERT OR IGNORE INTO {} ({}, {}) VALUES (?, ?)",
+                jt.table, jt.source_column, jt.target_column
+            ),
+        };
+        if tracing_enabled {
+            out.push_str("    #[tracing::instrument]\n");
+        }
+        out.push_str(&format!(
+            "    pub async fn attach_{}<'c, E>(&self, executor: E, {}: {}Key) -> Result<(), sqlx::Error>\n",
+            rel_name, target_arg, jt.target_model
+        ));
+        out.push_str("    where\n");
+        out.push_str(&format!("        E: {},\n", executor_trait));
+        out.push_str("    {\n");
+        out.push_str(&format!(
+            "        sqlx::query(\"{}\")\n            .bind({})\n            .bind({})\n            .execute(executor)\n            .await?;\n        Ok(())\n",
+            insert, self_key, target_arg
+        ));
+        out.push_str("    }\n\n");
+
+        // Detach
+        if tracing_enabled {
+            out.push_str("    #[tracing::instrument]\n");
+        }
+        out.push_str(&format!(
+            "    pub async fn detach_{}<'c, E>(&self, executor: E, {}: {}Key) -> Result<u64, sqlx::Error>\n",
+            rel_name, target_arg, jt.target_model
+        ));
+        out.push_str("    where\n");
+        out.push_str(&format!("        E: {},\n", executor_trait));
+        out.push_str("    {\n");
+        out.push_str(&format!(
+            "        let result = sqlx::query(\"DELETE FROM {} WHERE {} = {} AND {} = {}\")\n            .bind({})\n            .bind({})\n            .execute(executor)\n            .await?;\n        Ok(result.rows_affected())\n",
+            jt.table, jt.source_column, placeholder_fn(1), jt.target_column, placeholder_fn(2), self_key, target_arg
+        ));
+        out.push_str("    }\n\n");
+
+        // Find linked rows
+        let qb_type = match ir.meta.db_backend {
+            ir::DatabaseBackend::Postgres => "sqlx::Postgres",
+            ir::DatabaseBackend::Mysql => "sqlx::MySql",
+            ir::DatabaseBackend::Sqlite => "sqlx::Sqlite",
+        };
+        if tracing_enabled {
+            out.push_str("    #[tracing::instrument]\n");
+        }
+        out.push_str(&format!(
+            "    pub async fn find_{}<'c, E>(&self, executor: E, pagination: Option<Pagination>) -> Result<Vec<{}>, sqlx::Error>\n",
+            rel_name, jt.target_model
+        ));
+        out.push_str("    where\n");
+        out.push_str(&format!("        E: {},\n", executor_trait));
+        out.push_str("    {\n");
+        out.push_str(&format!(
+            "        let mut qb = sqlx::QueryBuilder::<{}>::new(\"SELECT t.* FROM {} t JOIN {} j ON j.{} = t.{}\");\n",
+            qb_type, ir.qualify(&ir.table_name(&jt.target_model)), jt.table, jt.target_column, target_key_column
+        ));
+        out.push_str(&format!(
+            "        qb.push(\" WHERE j.{} = \").push_bind({});\n",
+            jt.source_column, self_key
+        ));
+        if target.options.soft_delete {
+            out.push_str(&format!(
+                "        qb.push(\" AND t.{} IS NULL\");\n",
+                ir.column_name(&jt.target_model, "deleted_at")
+            ));
+        }
+        out.push_str("        if let Some(p) = pagination {\n");
+        out.push_str("            qb.push(\" LIMIT \" ).push_bind(p.limit);\n");
+        out.push_str("            qb.push(\" OFFSET \" ).push_bind(p.offset);\n");
+        out.push_str("        }\n");
+        out.push_str(&format!(
+            "        qb.build_query_as::<{}>().fetch_all(executor).await\n",
+            jt.target_model
+        ));
+        out.push_str("    }\n\n");
+    }
+
+    out
+}
+
 fn generate_eager_loading_helpers(
     model_name: &str,
     model: &ir::ModelDef,
@@ -732,22 +940,61 @@ fn generate_eager_loading_helpers(
         out.push_str("    #[tracing::instrument]\n");
     }
     out.push_str(&format!(
-        "    pub async fn find_by_ids<'c, E>(executor: E, ids: &[uuid::Uuid]) -> Result<Vec<{}>, sqlx::Error>\n",
-        model_name
+        "    pub async fn find_by_ids<'c, E>(executor: E, ids: &[{}Key]) -> Result<Vec<{}>, sqlx::Error>\n",
+        model_name, model_name
     ));
     out.push_str("    where\n");
     out.push_str(&format!("        E: {},\n", executor_trait));
     out.push_str("    {\n");
-    
-    let mut query = format!("SELECT * FROM {} WHERE id = ANY($1)", model_name.to_lowercase());
-    if model.options.soft_delete {
-        query.push_str(" AND deleted_at IS NULL");
+
+    let key_params = super::primary_key_params(model);
+    let key_columns = key_column_names(ir, model_name, &key_params);
+    let table = ir.qualify(&ir.table_name(model_name));
+    let deleted_at = ir.column_name(model_name, "deleted_at");
+    if key_params.len() == 1 && matches!(ir.meta.db_backend, ir::DatabaseBackend::Postgres) {
+        let mut query = format!("SELECT * FROM {} WHERE {} = ANY($1)", table, key_columns[0]);
+        if model.options.soft_delete {
+            query.push_str(&format!(" AND {} IS NULL", deleted_at));
+        }
+
+        out.push_str(&format!(
+            "        sqlx::query_as(\"{}\")\n            .bind(ids)\n            .fetch_all(executor)\n            .await\n    }}\n\n",
+            query
+        ));
+    } else {
+        // Composite keys (and backends without arrays) use a row-value IN list
+        let qb_type = match ir.meta.db_backend {
+            ir::DatabaseBackend::Postgres => "sqlx::Postgres",
+            ir::DatabaseBackend::Mysql => "sqlx::MySql",
+            ir::DatabaseBackend::Sqlite => "sqlx::Sqlite",
+        };
+        out.push_str("        if ids.is_empty() {\n            return Ok(Vec::new());\n        }\n");
+        let mut prefix = format!("SELECT * FROM {} WHERE ", table);
+        if model.options.soft_delete {
+            prefix.push_str(&format!("{} IS NULL AND ", deleted_at));
+        }
+        if key_columns.len() == 1 {
+            prefix.push_str(&format!("{} IN ", key_columns[0]));
+        } else {
+            prefix.push_str(&format!("({}) IN ", key_columns.join(", ")));
+        }
+        out.push_str(&format!(
+            "        let mut qb = sqlx::QueryBuilder::<{}>::new(\"{}\");\n",
+            qb_type, prefix
+        ));
+        if key_params.len() == 1 {
+            out.push_str("        qb.push_tuples(ids, |mut b, key| { b.push_bind(key.clone()); });\n");
+        } else {
+            let binds: String = (0..key_params.len())
+                .map(|i| format!(" b.push_bind(key.{}.clone());", i))
+                .collect();
+            out.push_str(&format!("        qb.push_tuples(ids, |mut b, key| {{{} }});\n", binds));
+        }
+        out.push_str(&format!(
+            "        qb.build_query_as::<{}>().fetch_all(executor).await\n    }}\n\n",
+            model_name
+        ));
     }
-    
-    out.push_str(&format!(
-        "        sqlx::query_as(\"{}\")\n            .bind(ids)\n            .fetch_all(executor)\n            .await\n    }}\n\n",
-        query
-    ));
 
     // Generate eager loading helpers for related models
     for (field_name, field) in &model.fields {
@@ -755,14 +1002,14 @@ fn generate_eager_loading_helpers(
             let target_model = field_name.strip_suffix("_id").unwrap();
             let target_model_pascal = pascal_case(target_model);
             
-            // Check if this target model actually exists in the schema
-            if ir.models.contains_key(&target_model_pascal) {
+            // Check if this target model actually exists and has a single-column key
+            if ir.models.get(&target_model_pascal).is_some_and(|t| super::primary_key_params(t).len() == 1) {
                 if tracing_enabled {
                     out.push_str("    #[tracing::instrument]\n");
                 }
                 out.push_str(&format!(
-                    "    pub async fn eager_load_{}<'c, E>(executor: E, items: &[{}]) -> Result<HashMap<uuid::Uuid, {}>, sqlx::Error>\n",
-                    target_model_pascal.to_lowercase(), model_name, target_model_pascal
+                    "    pub async fn eager_load_{}<'c, E>(executor: E, items: &[{}]) -> Result<HashMap<{}Key, {}>, sqlx::Error>\n",
+                    target_model_pascal.to_lowercase(), model_name, target_model_pascal, target_model_pascal
                 ));
                 out.push_str("    where\n");
                 out.push_str(&format!("        E: {},\n", executor_trait));
@@ -775,7 +1022,7 @@ fn generate_eager_loading_helpers(
                     "        let results = {}::find_by_ids(executor, &ids).await?;\n",
                     target_model_pascal
                 ));
-                out.push_str("        Ok(results.into_iter().map(|item| (item.id, item)).collect())\n");
+                out.push_str("        Ok(results.into_iter().map(|item| (item.key(), item)).collect())\n");
                 out.push_str("    }\n\n");
             }
         }
@@ -784,6 +1031,52 @@ fn generate_eager_loading_helpers(
     out
 }
 
+/// Database columns behind the key parameters of `model_name`.
+fn key_column_names(ir: &ir::SchemaIR, model_name: &str, params: &[(String, String)]) -> Vec<String> {
+    params
+        .iter()
+        .map(|(name, _)| ir.column_name(model_name, name.trim_start_matches("r#")))
+        .collect()
+}
+
+/// `a = $1 AND b = $2` for the key columns, numbering placeholders from `start`.
+fn key_where(columns: &[String], placeholder_fn: fn(usize) -> String, start: usize) -> String {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| format!("{} = {}", column, placeholder_fn(start + i)))
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+/// One `.bind(key)` line per key column.
+fn key_binds(params: &[(String, String)]) -> String {
+    params
+        .iter()
+        .map(|(name, _)| format!("            .bind({})\n", name))
+        .collect()
+}
+
+fn key_binds_inline(params: &[(String, String)]) -> String {
+    params.iter().map(|(name, _)| format!(".bind({})", name)).collect()
+}
+
+/// QueryBuilder chain binding each key column, joined with `AND`.
+fn key_push_binds(params: &[(String, String)], columns: &[String]) -> String {
+    params
+        .iter()
+        .zip(columns)
+        .enumerate()
+        .map(|(i, ((name, _), column))| {
+            if i == 0 {
+                format!(".push(\"{} = \").push_bind({})", column, name)
+            } else {
+                format!(".push(\" AND {} = \").push_bind({})", column, name)
+            }
+        })
+        .collect()
+}
+
 fn get_db_config(backend: &ir::DatabaseBackend) -> (&str, &str, fn(usize) -> String) {
     match backend {
         ir::DatabaseBackend::Postgres => ("PgExecutor<'c>", "sqlx::Postgres", |i| format!("${}", i)),
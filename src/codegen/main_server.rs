@@ -28,7 +28,7 @@ pub fn generate_main_server(ir: &ir::SchemaIR) -> String {
     }
     
     // Database connection
-    out.push_str(&format!("    let database_url = std::env::var(\"DATABASE_URL\").expect(\"DATABASE_URL must be set\");\n"));
+    out.push_str("    let database_url = std::env::var(\"DATABASE_URL\").expect(\"DATABASE_URL must be set\");\n");
     out.push_str(&format!("    let pool = {}::connect(&database_url).await?;\n\n", get_pool_connect(&ir.meta.db_backend)));
     
     // JWT secret for authentication
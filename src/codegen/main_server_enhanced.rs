@@ -14,7 +14,8 @@ pub fn generate_enhanced_main_server(ir: &ir::SchemaIR) -> String {
     out.push_str("use tower_http::cors::{Any, CorsLayer};\n");
     out.push_str("use crate::generated::router::create_router;\n");
     out.push_str("use crate::generated::auth::auth_middleware;\n");
-    out.push_str("use crate::generated::config::Config;\n\n");
+    out.push_str("use crate::generated::config::Config;\n");
+    out.push_str("use crate::generated::migrations::run_migrations;\n\n");
     
     out.push_str("pub struct AppState {\n");
     out.push_str(&format!("    pub pool: {},\n", get_pool_type(&ir.meta.db_backend)));
@@ -33,6 +34,7 @@ pub fn generate_enhanced_main_server(ir: &ir::SchemaIR) -> String {
     
     // Database connection
     out.push_str(&format!("    let pool = {}::connect(&config.database.url).await?;\n\n", get_pool_connect(&ir.meta.db_backend)));
+    out.push_str("    run_migrations(&pool, config.database.migrations, config.database.migration_lock_timeout).await?;\n\n");
     
     // CORS setup
     out.push_str("    let cors = CorsLayer::new()\n");
@@ -0,0 +1,388 @@
+use crate::ir;
+use crate::migrations::{
+    ADVISORY_LOCK_KEY, LOCK_NAME, file_hash, file_name, is_no_transaction, migration_files,
+    migration_statements, squashed_names, squashes,
+};
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// The generated `migrations` module: every migration in `migrations_dir`,
+/// split into statements and hashed the way the CLI hashes them, and the code
+/// that applies or checks them at startup against `__rustdbgen_migrations`.
+/// Files a squashed migration replaces are left out; a database part way
+/// through them is caught up by `migrate apply`.
+pub fn generate_migrations_module(ir: &ir::SchemaIR, migrations_dir: Option<&Path>) -> Result<String> {
+    let backend = &ir.meta.db_backend;
+    let mut out = String::new();
+
+    out.push_str("//! Migrations embedded by `rustdbgen generate`, applied or checked at\n");
+    out.push_str("//! startup against the `__rustdbgen_migrations` history the CLI keeps.\n\n");
+    out.push_str("use crate::generated::config::MigrationMode;\n");
+    out.push_str("use sqlx::{Connection, Executor};\n");
+    out.push_str("use std::time::Instant;\n\n");
+
+    out.push_str(&format!("type Conn = {};\n\n", connection_type(backend)));
+
+    out.push_str("pub struct EmbeddedMigration {\n");
+    out.push_str("    /// File name, as recorded in the history\n");
+    out.push_str("    pub name: &'static str,\n");
+    out.push_str("    /// SHA-256 of the file\n");
+    out.push_str("    pub hash: &'static str,\n");
+    out.push_str("    pub no_transaction: bool,\n");
+    out.push_str("    /// Migrations a squashed migration takes the place of\n");
+    out.push_str("    pub replaces: &'static [&'static str],\n");
+    out.push_str("    pub statements: &'static [&'static str],\n");
+    out.push_str("}\n\n");
+
+    out.push_str("pub const MIGRATIONS: &[EmbeddedMigration] = &[\n");
+    if let Some(dir) = migrations_dir.filter(|d| d.is_dir()) {
+        let dir = dir.to_str().context("migrations directory is not valid UTF-8")?;
+        let replaced: Vec<String> = squashes(dir)?.into_iter().flat_map(|(_, replaces)| replaces).collect();
+        for path in migration_files(dir)? {
+            let name = file_name(&path).context("invalid migration filename")?;
+            if replaced.iter().any(|r| r == name) {
+                continue;
+            }
+            let sql = fs::read_to_string(&path)?;
+            let statements =
+                migration_statements(backend, &sql).with_context(|| format!("cannot parse {}", name))?;
+            out.push_str("    EmbeddedMigration {\n");
+            out.push_str(&format!("        name: {:?},\n", name));
+            out.push_str(&format!("        hash: {:?},\n", file_hash(&sql)));
+            out.push_str(&format!("        no_transaction: {},\n", is_no_transaction(&sql)));
+            out.push_str(&format!("        replaces: &{:?},\n", squashed_names(&sql)));
+            out.push_str("        statements: &[\n");
+            for stmt in &statements {
+                out.push_str(&format!("            {:?},\n", stmt.sql));
+            }
+            out.push_str("        ],\n");
+            out.push_str("    },\n");
+        }
+    }
+    out.push_str("];\n\n");
+
+    out.push_str("struct HistoryRow {\n");
+    out.push_str("    name: String,\n");
+    out.push_str("    hash: String,\n");
+    out.push_str("    failed: bool,\n");
+    out.push_str("}\n\n");
+
+    out.push_str("/// Applies the pending migrations, or refuses to start unless every\n");
+    out.push_str("/// migration is applied unchanged, going by `mode`. Applying waits up to\n");
+    out.push_str("/// `lock_timeout` for another process holding the migration lock.\n");
+    out.push_str(&format!(
+        "pub async fn run_migrations(\n    pool: &{},\n    mode: MigrationMode,\n    lock_timeout: std::time::Duration,\n) -> anyhow::Result<()> {{\n",
+        pool_type(backend)
+    ));
+    out.push_str("    let mut conn = pool.acquire().await?;\n");
+    out.push_str("    match mode {\n");
+    out.push_str("        MigrationMode::Off => Ok(()),\n");
+    out.push_str("        MigrationMode::Verify => verify(&mut conn).await,\n");
+    out.push_str("        MigrationMode::Apply => {\n");
+    out.push_str("            lock(&mut conn, lock_timeout).await?;\n");
+    out.push_str("            let result = apply_pending(&mut conn).await;\n");
+    out.push_str("            let unlocked = unlock(&mut conn).await;\n");
+    out.push_str("            result?;\n");
+    out.push_str("            unlocked\n");
+    out.push_str("        }\n");
+    out.push_str("    }\n");
+    out.push_str("}\n\n");
+
+    out.push_str("async fn verify(conn: &mut Conn) -> anyhow::Result<()> {\n");
+    out.push_str("    ensure_history_table(conn).await?;\n");
+    out.push_str("    let recorded = history(conn).await?;\n");
+    out.push_str("    let applied = |name: &str| recorded.iter().find(|r| r.name == name && !r.failed);\n");
+    out.push_str("    let mut problems = Vec::new();\n");
+    out.push_str("    for migration in MIGRATIONS {\n");
+    out.push_str("        match applied(migration.name) {\n");
+    out.push_str("            Some(row) if row.hash == migration.hash => {}\n");
+    out.push_str("            Some(_) => problems.push(format!(\"{} has changed after being applied\", migration.name)),\n");
+    out.push_str("            None if !migration.replaces.is_empty()\n");
+    out.push_str("                && migration.replaces.iter().all(|r| applied(*r).is_some()) => {}\n");
+    out.push_str("            None => problems.push(format!(\"{} is not applied\", migration.name)),\n");
+    out.push_str("        }\n");
+    out.push_str("    }\n");
+    out.push_str("    if !problems.is_empty() {\n");
+    out.push_str("        anyhow::bail!(\n");
+    out.push_str("            \"the database does not match the embedded migrations:\\n  {}\\n\\\n");
+    out.push_str("             run `rustdbgen migrate apply` or start with MIGRATIONS=apply\",\n");
+    out.push_str("            problems.join(\"\\n  \")\n");
+    out.push_str("        );\n");
+    out.push_str("    }\n");
+    out.push_str("    Ok(())\n");
+    out.push_str("}\n\n");
+
+    out.push_str("async fn apply_pending(conn: &mut Conn) -> anyhow::Result<()> {\n");
+    out.push_str("    let recorded = history(conn).await?;\n");
+    out.push_str("    for migration in MIGRATIONS {\n");
+    out.push_str("        if let Some(row) = recorded.iter().find(|r| r.name == migration.name) {\n");
+    out.push_str("            if row.failed {\n");
+    out.push_str("                if migration.no_transaction {\n");
+    out.push_str("                    anyhow::bail!(\n");
+    out.push_str("                        \"the last attempt of {} failed outside a transaction; fix the database \\\n");
+    out.push_str("                         by hand, then run `rustdbgen migrate repair`\",\n");
+    out.push_str("                        migration.name\n");
+    out.push_str("                    );\n");
+    out.push_str("                }\n");
+    out.push_str("            } else if row.hash != migration.hash {\n");
+    out.push_str("                anyhow::bail!(\"migration {} has changed after being applied\", migration.name);\n");
+    out.push_str("            } else {\n");
+    out.push_str("                continue;\n");
+    out.push_str("            }\n");
+    out.push_str("        }\n");
+    out.push_str("        let replaced: Vec<&str> = migration\n");
+    out.push_str("            .replaces\n");
+    out.push_str("            .iter()\n");
+    out.push_str("            .copied()\n");
+    out.push_str("            .filter(|r| recorded.iter().any(|row| row.name == *r && !row.failed))\n");
+    out.push_str("            .collect();\n");
+    out.push_str("        if !migration.replaces.is_empty() && replaced.len() == migration.replaces.len() {\n");
+    out.push_str("            // a squash of migrations this database already ran\n");
+    out.push_str("            let start = Instant::now();\n");
+    out.push_str("            let mut tx = conn.begin().await?;\n");
+    out.push_str("            for name in migration.replaces {\n");
+    out.push_str("                forget(&mut tx, name).await?;\n");
+    out.push_str("            }\n");
+    out.push_str("            record(&mut tx, migration, start, None).await?;\n");
+    out.push_str("            tx.commit().await?;\n");
+    out.push_str("            continue;\n");
+    out.push_str("        }\n");
+    out.push_str("        if !replaced.is_empty() {\n");
+    out.push_str("            anyhow::bail!(\n");
+    out.push_str("                \"{} squashes migrations this database applied only some of ({}); \\\n");
+    out.push_str("                 run `rustdbgen migrate apply` to catch up from their files\",\n");
+    out.push_str("                migration.name,\n");
+    out.push_str("                replaced.join(\", \")\n");
+    out.push_str("            );\n");
+    out.push_str("        }\n");
+    out.push_str("        apply(conn, migration).await?;\n");
+    out.push_str("    }\n");
+    out.push_str("    Ok(())\n");
+    out.push_str("}\n\n");
+
+    out.push_str("/// Runs `migration` in a transaction and records it in the same one. A\n");
+    out.push_str("/// failure is recorded too, with the failing statement.\n");
+    out.push_str("async fn apply(conn: &mut Conn, migration: &EmbeddedMigration) -> anyhow::Result<()> {\n");
+    out.push_str("    let start = Instant::now();\n");
+    out.push_str("    if migration.no_transaction {\n");
+    out.push_str("        if let Err((statement, err)) = execute(conn, migration).await {\n");
+    out.push_str("            // leave a transaction the file opened, or the failure cannot be recorded\n");
+    out.push_str("            let _ = (&mut *conn).execute(\"ROLLBACK\").await;\n");
+    out.push_str("            record(conn, migration, start, Some((&err, statement))).await?;\n");
+    out.push_str("            return Err(err);\n");
+    out.push_str("        }\n");
+    out.push_str("        return record(conn, migration, start, None).await;\n");
+    out.push_str("    }\n");
+    out.push_str("    let mut tx = conn.begin().await?;\n");
+    out.push_str("    if let Err((statement, err)) = execute(&mut tx, migration).await {\n");
+    out.push_str("        tx.rollback().await?;\n");
+    out.push_str("        record(conn, migration, start, Some((&err, statement))).await?;\n");
+    out.push_str("        return Err(err);\n");
+    out.push_str("    }\n");
+    out.push_str("    record(&mut tx, migration, start, None).await?;\n");
+    out.push_str("    tx.commit().await?;\n");
+    out.push_str("    Ok(())\n");
+    out.push_str("}\n\n");
+
+    out.push_str("async fn execute(\n");
+    out.push_str("    conn: &mut Conn,\n");
+    out.push_str("    migration: &EmbeddedMigration,\n");
+    out.push_str(") -> Result<(), (&'static str, anyhow::Error)> {\n");
+    out.push_str("    for (index, statement) in migration.statements.iter().enumerate() {\n");
+    out.push_str("        if let Err(e) = (&mut *conn).execute(*statement).await {\n");
+    out.push_str("            let err = anyhow::Error::new(e)\n");
+    out.push_str("                .context(format!(\"{}: statement {} failed\", migration.name, index + 1));\n");
+    out.push_str("            return Err((*statement, err));\n");
+    out.push_str("        }\n");
+    out.push_str("    }\n");
+    out.push_str("    Ok(())\n");
+    out.push_str("}\n\n");
+
+    out.push_str("async fn record(\n");
+    out.push_str("    conn: &mut Conn,\n");
+    out.push_str("    migration: &EmbeddedMigration,\n");
+    out.push_str("    start: Instant,\n");
+    out.push_str("    failure: Option<(&anyhow::Error, &str)>,\n");
+    out.push_str(") -> anyhow::Result<()> {\n");
+    out.push_str("    forget(conn, migration.name).await?;\n");
+    out.push_str(&format!(
+        "    sqlx::query(\"INSERT INTO __rustdbgen_migrations (name, hash, applied_at, execution_time_ms, success, error, failed_statement, replaces) VALUES ({})\")\n",
+        (1..=8).map(|i| placeholder(backend, i)).collect::<Vec<_>>().join(", ")
+    ));
+    out.push_str("        .bind(migration.name)\n");
+    out.push_str("        .bind(migration.hash)\n");
+    out.push_str("        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true))\n");
+    out.push_str("        .bind(start.elapsed().as_millis() as i64)\n");
+    out.push_str("        .bind(failure.is_none())\n");
+    out.push_str("        .bind(failure.map(|(err, _)| format!(\"{:#}\", err)))\n");
+    out.push_str("        .bind(failure.map(|(_, statement)| statement.to_string()))\n");
+    out.push_str("        .bind(Some(migration.replaces.join(\", \")).filter(|r| !r.is_empty()))\n");
+    out.push_str("        .execute(&mut *conn)\n");
+    out.push_str("        .await?;\n");
+    out.push_str("    Ok(())\n");
+    out.push_str("}\n\n");
+
+    out.push_str("async fn forget(conn: &mut Conn, name: &str) -> anyhow::Result<()> {\n");
+    out.push_str(&format!(
+        "    sqlx::query(\"DELETE FROM __rustdbgen_migrations WHERE name = {}\")\n",
+        placeholder(backend, 1)
+    ));
+    out.push_str("        .bind(name)\n");
+    out.push_str("        .execute(&mut *conn)\n");
+    out.push_str("        .await?;\n");
+    out.push_str("    Ok(())\n");
+    out.push_str("}\n\n");
+
+    out.push_str("async fn history(conn: &mut Conn) -> anyhow::Result<Vec<HistoryRow>> {\n");
+    out.push_str("    let rows: Vec<(String, String, Option<String>)> = sqlx::query_as(\n");
+    out.push_str("        \"SELECT name, hash, error FROM __rustdbgen_migrations ORDER BY applied_at, name\",\n");
+    out.push_str("    )\n");
+    out.push_str("    .fetch_all(&mut *conn)\n");
+    out.push_str("    .await?;\n");
+    out.push_str("    Ok(rows\n");
+    out.push_str("        .into_iter()\n");
+    out.push_str("        .map(|(name, hash, error)| HistoryRow { name, hash, failed: error.is_some() })\n");
+    out.push_str("        .collect())\n");
+    out.push_str("}\n\n");
+
+    out.push_str("async fn ensure_history_table(conn: &mut Conn) -> anyhow::Result<()> {\n");
+    out.push_str("    (&mut *conn)\n");
+    out.push_str("        .execute(\n");
+    out.push_str("            \"CREATE TABLE IF NOT EXISTS __rustdbgen_migrations (\n");
+    out.push_str("                name VARCHAR(255) PRIMARY KEY,\n");
+    out.push_str("                hash TEXT NOT NULL,\n");
+    out.push_str("                applied_at TEXT NOT NULL,\n");
+    out.push_str("                execution_time_ms INTEGER,\n");
+    out.push_str("                success BOOLEAN NOT NULL,\n");
+    out.push_str("                error TEXT,\n");
+    out.push_str("                failed_statement TEXT,\n");
+    out.push_str("                replaces TEXT\n");
+    out.push_str("            )\",\n");
+    out.push_str("        )\n");
+    out.push_str("        .await?;\n");
+    out.push_str("    // histories created by older versions of the CLI\n");
+    out.push_str("    for column in [\"error\", \"failed_statement\", \"replaces\"] {\n");
+    out.push_str("        let probe = format!(\"SELECT {} FROM __rustdbgen_migrations WHERE 1 = 0\", column);\n");
+    out.push_str("        if (&mut *conn).execute(probe.as_str()).await.is_err() {\n");
+    out.push_str("            let add = format!(\"ALTER TABLE __rustdbgen_migrations ADD COLUMN {} TEXT\", column);\n");
+    out.push_str("            (&mut *conn).execute(add.as_str()).await?;\n");
+    out.push_str("        }\n");
+    out.push_str("    }\n");
+    out.push_str("    Ok(())\n");
+    out.push_str("}\n\n");
+
+    out.push_str(&generate_lock(backend));
+
+    Ok(out)
+}
+
+/// `lock` and `unlock`, taking the same lock as `rustdbgen migrate apply` so
+/// replicas starting together and the CLI apply each migration once. Like the
+/// CLI, `lock` gives up after `timeout` rather than hang the startup.
+fn generate_lock(backend: &ir::DatabaseBackend) -> String {
+    let mut out = String::new();
+    out.push_str("const LOCK_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);\n\n");
+    out.push_str("async fn lock(conn: &mut Conn, timeout: std::time::Duration) -> anyhow::Result<()> {\n");
+    match backend {
+        ir::DatabaseBackend::Postgres => {
+            out.push_str("    let deadline = Instant::now() + timeout;\n");
+            out.push_str("    let acquired = loop {\n");
+            out.push_str("        let (locked,): (bool,) = sqlx::query_as(\"SELECT pg_try_advisory_lock($1)\")\n");
+            out.push_str(&format!("            .bind({}_i64)\n", ADVISORY_LOCK_KEY));
+            out.push_str("            .fetch_one(&mut *conn)\n");
+            out.push_str("            .await?;\n");
+            out.push_str("        if locked || Instant::now() >= deadline {\n");
+            out.push_str("            break locked;\n");
+            out.push_str("        }\n");
+            out.push_str("        tokio::time::sleep(LOCK_POLL_INTERVAL).await;\n");
+            out.push_str("    };\n");
+        }
+        ir::DatabaseBackend::Mysql => {
+            out.push_str(&format!(
+                "    let (locked,): (Option<i64>,) = sqlx::query_as(\"SELECT GET_LOCK('{}', ?)\")\n",
+                LOCK_NAME
+            ));
+            out.push_str("        .bind(timeout.as_secs_f64())\n");
+            out.push_str("        .fetch_one(&mut *conn)\n");
+            out.push_str("        .await?;\n");
+            out.push_str("    let acquired = locked == Some(1);\n");
+        }
+        ir::DatabaseBackend::Sqlite => {
+            out.push_str("    (&mut *conn)\n");
+            out.push_str("        .execute(\n");
+            out.push_str("            \"CREATE TABLE IF NOT EXISTS __rustdbgen_migrations_lock (\n");
+            out.push_str("                id INTEGER PRIMARY KEY,\n");
+            out.push_str("                locked_at TEXT NOT NULL\n");
+            out.push_str("            )\",\n");
+            out.push_str("        )\n");
+            out.push_str("        .await?;\n");
+            out.push_str("    let deadline = Instant::now() + timeout;\n");
+            out.push_str("    let acquired = loop {\n");
+            out.push_str("        let inserted = sqlx::query(\n");
+            out.push_str("            \"INSERT OR IGNORE INTO __rustdbgen_migrations_lock (id, locked_at) VALUES (1, ?)\",\n");
+            out.push_str("        )\n");
+            out.push_str("        .bind(chrono::Utc::now().to_rfc3339())\n");
+            out.push_str("        .execute(&mut *conn)\n");
+            out.push_str("        .await?\n");
+            out.push_str("        .rows_affected()\n");
+            out.push_str("            == 1;\n");
+            out.push_str("        if inserted || Instant::now() >= deadline {\n");
+            out.push_str("            break inserted;\n");
+            out.push_str("        }\n");
+            out.push_str("        tokio::time::sleep(LOCK_POLL_INTERVAL).await;\n");
+            out.push_str("    };\n");
+        }
+    }
+    let hint = match backend {
+        ir::DatabaseBackend::Sqlite => {
+            "; if no other process is migrating, delete the row in __rustdbgen_migrations_lock"
+        }
+        _ => "",
+    };
+    out.push_str("    if !acquired {\n");
+    out.push_str("        anyhow::bail!(\n");
+    out.push_str(&format!(
+        "            \"timed out after {{:?}} waiting for the migration lock held by another process{}\",\n",
+        hint
+    ));
+    out.push_str("            timeout\n");
+    out.push_str("        );\n");
+    out.push_str("    }\n");
+    out.push_str("    ensure_history_table(conn).await\n");
+    out.push_str("}\n\n");
+
+    out.push_str("async fn unlock(conn: &mut Conn) -> anyhow::Result<()> {\n");
+    let unlock = match backend {
+        ir::DatabaseBackend::Postgres => format!("SELECT pg_advisory_unlock({})", ADVISORY_LOCK_KEY),
+        ir::DatabaseBackend::Mysql => format!("SELECT RELEASE_LOCK('{}')", LOCK_NAME),
+        ir::DatabaseBackend::Sqlite => "DELETE FROM __rustdbgen_migrations_lock WHERE id = 1".to_string(),
+    };
+    out.push_str(&format!("    (&mut *conn).execute(\"{}\").await?;\n", unlock));
+    out.push_str("    Ok(())\n");
+    out.push_str("}\n");
+    out
+}
+
+fn placeholder(backend: &ir::DatabaseBackend, n: usize) -> String {
+    match backend {
+        ir::DatabaseBackend::Postgres => format!("${}", n),
+        _ => "?".to_string(),
+    }
+}
+
+fn connection_type(backend: &ir::DatabaseBackend) -> &str {
+    match backend {
+        ir::DatabaseBackend::Postgres => "sqlx::PgConnection",
+        ir::DatabaseBackend::Mysql => "sqlx::MySqlConnection",
+        ir::DatabaseBackend::Sqlite => "sqlx::SqliteConnection",
+    }
+}
+
+fn pool_type(backend: &ir::DatabaseBackend) -> &str {
+    match backend {
+        ir::DatabaseBackend::Postgres => "sqlx::PgPool",
+        ir::DatabaseBackend::Mysql => "sqlx::MySqlPool",
+        ir::DatabaseBackend::Sqlite => "sqlx::SqlitePool",
+    }
+}
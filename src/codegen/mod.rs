@@ -12,13 +12,16 @@ pub mod app_main;
 pub mod auth;
 pub mod executor;
 pub mod config;
+pub mod migrations;
 
 use crate::ir;
 use anyhow::Result;
 use std::fs;
 use std::path::Path;
 
-pub fn generate_code_multi(ir: &ir::SchemaIR, output_dir: &Path) -> Result<()> {
+/// Write the generated crate to `output_dir`, embedding the migrations in
+/// `migrations_dir` so the server can apply or check them at startup.
+pub fn generate_code_multi(ir: &ir::SchemaIR, output_dir: &Path, migrations_dir: Option<&Path>) -> Result<()> {
     // Create output directory structure
     let src_dir = output_dir.join("src");
     let generated_dir = src_dir.join("generated");
@@ -73,6 +76,10 @@ pub fn generate_code_multi(ir: &ir::SchemaIR, output_dir: &Path) -> Result<()> {
     let config_code = config::generate_config_struct(ir);
     fs::write(generated_dir.join("config.rs"), config_code)?;
     
+    // Embed migrations
+    let migrations_code = migrations::generate_migrations_module(ir, migrations_dir)?;
+    fs::write(generated_dir.join("migrations.rs"), migrations_code)?;
+    
     // Generate mod.rs for generated module
     let mod_rs = generate_mod_rs(ir);
     fs::write(generated_dir.join("mod.rs"), mod_rs)?;
@@ -89,6 +96,136 @@ pub fn generate_code_multi(ir: &ir::SchemaIR, output_dir: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Render every generated module into a single string. Handy for tests and
+/// quick inspection; `generate_code_multi` is what writes the real crate.
+pub fn generate_code(ir: &ir::SchemaIR) -> String {
+    let mut out = String::new();
+    out.push_str(&models::generate_models(ir).unwrap_or_default());
+    out.push_str(&handlers_enhanced::generate_enhanced_crud_impls(ir));
+    out.push_str(&routes::generate_routes(ir));
+    out.push_str(&permissions::generate_permissions(ir));
+    out.push_str(&pagination::generate_pagination());
+    out.push_str(&router::generate_router(ir));
+    out.push_str(&auth::generate_auth_module(ir));
+    out
+}
+
+/// Primary key columns of `model` paired with the Rust type generated code
+/// uses for them. Models without any key keep the historical `id: uuid::Uuid`.
+pub fn primary_key_params(model: &ir::ModelDef) -> Vec<(String, String)> {
+    let params: Vec<(String, String)> = model
+        .primary_key_fields()
+        .into_iter()
+        .filter_map(|name| {
+            let field = model.fields.get(&name)?;
+            Some((models::escape_rust_keyword(&name), qualified_rust_type(&field.rust_type)))
+        })
+        .collect();
+    if params.is_empty() {
+        vec![("id".to_string(), "uuid::Uuid".to_string())]
+    } else {
+        params
+    }
+}
+
+/// Fully qualify schema types so they resolve outside the models module.
+fn qualified_rust_type(rust_type: &str) -> String {
+    match rust_type {
+        "Uuid" => "uuid::Uuid".to_string(),
+        "Boolean" => "bool".to_string(),
+        "Decimal" => "rust_decimal::Decimal".to_string(),
+        "DateTime<Utc>" => "chrono::DateTime<chrono::Utc>".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Rust type of a whole key: the column type, or a tuple for composite keys.
+pub fn primary_key_type(params: &[(String, String)]) -> String {
+    if params.len() == 1 {
+        params[0].1.clone()
+    } else {
+        format!(
+            "({})",
+            params.iter().map(|(_, t)| t.as_str()).collect::<Vec<_>>().join(", ")
+        )
+    }
+}
+
+/// `a: A, b: B` for function signatures.
+pub fn primary_key_signature(params: &[(String, String)]) -> String {
+    params
+        .iter()
+        .map(|(n, t)| format!("{}: {}", n, t))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `a, b` for forwarding key arguments.
+pub fn primary_key_args(params: &[(String, String)]) -> String {
+    params.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+/// Axum route segments, `/:id` or `/:a/:b` for composite keys.
+pub fn primary_key_path(params: &[(String, String)]) -> String {
+    params
+        .iter()
+        .map(|(n, _)| format!("/:{}", n.trim_start_matches("r#")))
+        .collect()
+}
+
+/// Axum `Path` extractor binding every key column.
+pub fn primary_key_extractor(params: &[(String, String)]) -> String {
+    if params.len() == 1 {
+        format!("Path({}): Path<{}>", params[0].0, params[0].1)
+    } else {
+        format!(
+            "Path(({})): Path<{}>",
+            primary_key_args(params),
+            primary_key_type(params)
+        )
+    }
+}
+
+/// Foreign keys on other models that point at `model_name`'s primary key and
+/// block deletes (`restrict` / `no_action`), as `(owning model, relation name)`.
+pub fn restricting_references<'a>(ir: &'a ir::SchemaIR, model_name: &str) -> Vec<(&'a str, &'a str)> {
+    let Some(model) = ir.models.get(model_name) else {
+        return Vec::new();
+    };
+    let pk_fields = model.primary_key_fields();
+    let mut refs = Vec::new();
+    for (other_name, other) in &ir.models {
+        for (rel_name, rel) in &other.relations {
+            if rel.references.model == model_name
+                && pk_fields.contains(&rel.references.field)
+                && matches!(
+                    rel.on_delete,
+                    ir::ReferentialAction::NoAction | ir::ReferentialAction::Restrict
+                )
+            {
+                refs.push((other_name.as_str(), rel_name.as_str()));
+            }
+        }
+    }
+    refs
+}
+
+/// Variant name used in `{Model}DeleteError` for a blocking foreign key.
+pub fn referenced_by_variant(other_model: &str, rel_name: &str) -> String {
+    let rel: String = rel_name
+        .split('_')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let mut c = p.chars();
+            match c.next() {
+                None => String::new(),
+                Some(f) => f.to_ascii_uppercase().to_string() + c.as_str(),
+            }
+        })
+        .collect();
+    format!("ReferencedBy{}{}", other_model, rel)
+}
+
 fn generate_mod_rs(_ir: &ir::SchemaIR) -> String {
     let mut out = String::new();
     
@@ -101,7 +238,8 @@ fn generate_mod_rs(_ir: &ir::SchemaIR) -> String {
     out.push_str("pub mod auth;\n");
     out.push_str("pub mod main;\n");
     out.push_str("pub mod executor;\n");
-    out.push_str("pub mod config;\n\n");
+    out.push_str("pub mod config;\n");
+    out.push_str("pub mod migrations;\n\n");
     
     out.push_str("pub use models::*;\n");
     out.push_str("pub use handlers::*;\n");
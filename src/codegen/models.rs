@@ -42,18 +42,15 @@ pub fn generate_models(ir: &ir::SchemaIR) -> anyhow::Result<String> {
 
 pub fn generate_enums(ir: &ir::SchemaIR) -> String {
     let mut out = String::new();
-    let mut enums: Vec<_> = ir.enums.iter().collect();
-    enums.sort_by(|a, b| a.0.cmp(b.0));
-
-    for (name, en) in enums {
+    for (name, en) in &ir.enums {
         out.push_str("#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::Type)]\n");
         out.push_str(&format!(
             "#[sqlx(type_name = \"{}\", rename_all = \"lowercase\")]\n",
-            name.to_lowercase()
+            ir.enum_type_name(name)
         ));
         out.push_str(&format!("pub enum {} {{\n", name));
         for variant in &en.variants {
-            let pascal_variant = pascal_case(variant);
+            let pascal_variant = pascal_case(variant.as_str());
             out.push_str(&format!("    {},\n", pascal_variant));
         }
         out.push_str("}\n\n");
@@ -65,7 +62,7 @@ pub fn generate_enums(ir: &ir::SchemaIR) -> String {
 const RUST_KEYWORDS: &[&str] = &[
     "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while", "async", "await", "dyn", "abstract", "become", "box", "do", "final", "macro", "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
 ];
-fn escape_rust_keyword(name: &str) -> String {
+pub(crate) fn escape_rust_keyword(name: &str) -> String {
     if RUST_KEYWORDS.contains(&name) {
         format!("r#{}", name)
     } else {
@@ -75,22 +72,27 @@ fn escape_rust_keyword(name: &str) -> String {
 
 pub fn generate_model_structs(ir: &ir::SchemaIR) -> String {
     let mut out = String::new();
-    let mut models: Vec<_> = ir.models.iter().collect();
-    models.sort_by(|a, b| a.0.cmp(b.0));
-
-    for (model_name, model) in models {
+    // JSON field names follow `rust_case_style`
+    let rename_all = crate::naming::serde_rename_all(&ir.meta.rust_case_style)
+        .map(|style| format!("#[serde(rename_all = \"{}\")]\n", style))
+        .unwrap_or_default();
+    for (model_name, model) in &ir.models {
         // Main struct
         let mut derives = ir.meta.default_derives.clone();
         derives.push("sqlx::FromRow".into());
         let derives_list = derives.join(", ");
-        out.push_str(&format!(
-            "#[derive({})]\npub struct {} {{\n",
-            derives_list, model_name
-        ));
-        let mut fields_vec: Vec<_> = model.fields.iter().collect();
-        fields_vec.sort_by(|a, b| a.0.cmp(b.0));
+        out.push_str(&format!("#[derive({})]\n", derives_list));
+        if derives.iter().any(|d| d.ends_with("Serialize") || d.ends_with("Deserialize")) {
+            out.push_str(&rename_all);
+        }
+        out.push_str(&format!("pub struct {} {{\n", model_name));
+        let fields_vec: Vec<_> = model.fields.iter().collect();
         for (field_name, field) in &fields_vec {
             let field_name_escaped = escape_rust_keyword(field_name);
+            let column = ir.column_name(model_name, field_name);
+            if column != **field_name {
+                out.push_str(&format!("    #[sqlx(rename = \"{}\")]\n", column));
+            }
             let mut rust_type = field.rust_type.clone();
             if rust_type == "Boolean" {
                 rust_type = "bool".to_string();
@@ -102,13 +104,44 @@ pub fn generate_model_structs(ir: &ir::SchemaIR) -> String {
         }
         out.push_str("}\n\n");
 
+        // Primary key type and accessor
+        let key_params = super::primary_key_params(model);
+        let key_type = super::primary_key_type(&key_params);
+        out.push_str(&format!("pub type {}Key = {};\n\n", model_name, key_type));
+        let key_values: Vec<String> = key_params
+            .iter()
+            .map(|(name, ty)| {
+                if is_copy_type(ty) {
+                    format!("self.{}", name)
+                } else {
+                    format!("self.{}.clone()", name)
+                }
+            })
+            .collect();
+        let key_expr = if key_values.len() == 1 {
+            key_values[0].clone()
+        } else {
+            format!("({})", key_values.join(", "))
+        };
+        out.push_str(&format!(
+            "impl {} {{\n    pub fn key(&self) -> {}Key {{\n        {}\n    }}\n}}\n\n",
+            model_name, model_name, key_expr
+        ));
+
+        let pk_fields = model.primary_key_fields();
+        let identity = model.key_generation() == ir::KeyGeneration::Identity;
+
         // New struct (fields without defaults and not soft-delete)
         out.push_str(&format!(
-            "#[derive(Debug, serde::Deserialize)]\npub struct {}New {{\n",
-            model_name
+            "#[derive(Debug, serde::Deserialize)]\n{}pub struct {}New {{\n",
+            rename_all, model_name
         ));
         for (field_name, field) in &fields_vec {
             if field.default.is_none() && *field_name != "deleted_at" {
+                // Identity keys are filled in by the database
+                if identity && pk_fields.contains(*field_name) {
+                    continue;
+                }
                 // Skip owner foreign key field if this model is owned
                 if let Some(owner_model) = &model.owned_by {
                     let owner_fk = format!("{}_id", owner_model.to_lowercase());
@@ -139,11 +172,11 @@ pub fn generate_model_structs(ir: &ir::SchemaIR) -> String {
 
         // Update struct (Option fields)
         out.push_str(&format!(
-            "#[derive(Debug, serde::Deserialize, Default)]\npub struct {}Update {{\n",
-            model_name
+            "#[derive(Debug, serde::Deserialize, Default)]\n{}pub struct {}Update {{\n",
+            rename_all, model_name
         ));
         for (field_name, field) in &fields_vec {
-            if field.default.is_none() {
+            if field.default.is_none() && !pk_fields.contains(*field_name) {
                 let field_name_escaped = escape_rust_keyword(field_name);
                 let mut rust_type = field.rust_type.clone();
                 
@@ -168,18 +201,21 @@ pub fn generate_model_structs(ir: &ir::SchemaIR) -> String {
     out
 }
 
+fn is_copy_type(rust_type: &str) -> bool {
+    matches!(
+        rust_type,
+        "uuid::Uuid" | "bool" | "i16" | "i32" | "i64" | "u32" | "u64" | "f32" | "f64"
+    )
+}
+
 pub fn generate_error_enums(ir: &ir::SchemaIR) -> String {
     let mut out = String::new();
-    let mut models: Vec<_> = ir.models.iter().collect();
-    models.sort_by(|a, b| a.0.cmp(b.0));
-
-    for (model_name, model) in models {
+    for (model_name, model) in &ir.models {
         out.push_str(&format!(
             "#[derive(Debug, Error)]\npub enum {}CreateError {{\n",
             model_name
         ));
-        let mut ucs: Vec<_> = model.unique_constraints.keys().collect();
-        ucs.sort();
+        let ucs: Vec<_> = model.unique_constraints.keys().collect();
         for uc_name in ucs {
             let var = pascal_case(uc_name);
             out.push_str(&format!(
@@ -188,12 +224,11 @@ pub fn generate_error_enums(ir: &ir::SchemaIR) -> String {
             ));
         }
         // Only generate FK errors for forward relations (where the FK is on this table)
-        let mut rels: Vec<_> = model
+        let rels: Vec<_> = model
             .relations
             .iter()
             .filter(|(_, rel)| rel.on != "id") // Heuristic: PK is 'id'
             .collect();
-        rels.sort_by(|a, b| a.0.cmp(b.0));
         for (rel_name, _) in rels {
             let var = format!("{}Fk", pascal_case(rel_name));
             out.push_str(&format!(
@@ -202,6 +237,22 @@ pub fn generate_error_enums(ir: &ir::SchemaIR) -> String {
             ));
         }
         out.push_str("    #[error(transparent)]\n    Database(#[from] sqlx::Error),\n}\n\n");
+
+        // Delete errors for foreign keys that still point at this row
+        out.push_str(&format!(
+            "#[derive(Debug, Error)]\npub enum {}DeleteError {{\n",
+            model_name
+        ));
+        for (other_model, rel_name) in super::restricting_references(ir, model_name) {
+            out.push_str(&format!(
+                "    #[error(\"{} is still referenced by {} through `{}`\")]\n    {},\n",
+                model_name,
+                other_model,
+                rel_name,
+                super::referenced_by_variant(other_model, rel_name)
+            ));
+        }
+        out.push_str("    #[error(transparent)]\n    Database(#[from] sqlx::Error),\n}\n\n");
     }
 
     out
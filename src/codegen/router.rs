@@ -2,8 +2,7 @@ use crate::ir;
 
 pub fn generate_router(ir: &ir::SchemaIR) -> String {
     let mut out = String::new();
-    let mut models: Vec<_> = ir.models.iter().collect();
-    models.sort_by(|a, b| a.0.cmp(b.0));
+    let models: Vec<_> = ir.models.iter().collect();
     
     out.push_str("use axum::{\n");
     out.push_str("    extract::{Path, Query, State},\n");
@@ -21,10 +20,11 @@ pub fn generate_router(ir: &ir::SchemaIR) -> String {
     out.push_str("pub fn create_router() -> Router<Arc<AppState>> {\n");
     out.push_str("    Router::new()\n");
     
-    for (model_name, _model) in &models {
+    for (model_name, model) in &models {
         let model_lower = model_name.to_lowercase();
         let route_path = format!("/{}", model_lower);
-        let route_path_with_id = format!("{}/:id", route_path);
+        let key_params = super::primary_key_params(model);
+        let route_path_with_id = format!("{}{}", route_path, super::primary_key_path(&key_params));
         
         // List handler
         out.push_str(&format!(
@@ -55,6 +55,22 @@ pub fn generate_router(ir: &ir::SchemaIR) -> String {
             "        .route(\"{}\", delete(delete_{}))\n",
             route_path_with_id, model_lower
         ));
+
+        // Nested many-to-many routes
+        for (rel_name, target_column) in many_to_many_routes(ir, model_name) {
+            out.push_str(&format!(
+                "        .route(\"{}/{}\", get(list_{}_{}))\n",
+                route_path_with_id, rel_name, model_lower, rel_name
+            ));
+            out.push_str(&format!(
+                "        .route(\"{}/{}/:{}\", put(attach_{}_{}))\n",
+                route_path_with_id, rel_name, target_column, model_lower, rel_name
+            ));
+            out.push_str(&format!(
+                "        .route(\"{}/{}/:{}\", delete(detach_{}_{}))\n",
+                route_path_with_id, rel_name, target_column, model_lower, rel_name
+            ));
+        }
     }
     
     out.push_str("}\n\n");
@@ -62,6 +78,9 @@ pub fn generate_router(ir: &ir::SchemaIR) -> String {
     // Generate handler functions
     for (model_name, model) in models {
         let model_lower = model_name.to_lowercase();
+        let key_params = super::primary_key_params(model);
+        let key_extractor = super::primary_key_extractor(&key_params);
+        let key_args = super::primary_key_args(&key_params);
         
         // List handler
         if model.owned_by.is_some() {
@@ -106,19 +125,17 @@ pub fn generate_router(ir: &ir::SchemaIR) -> String {
         }
         
         // Unique constraint errors
-        let mut unique_names: Vec<_> = model.unique_constraints.keys().collect();
-        unique_names.sort();
+        let unique_names: Vec<_> = model.unique_constraints.keys().collect();
         for unique_name in unique_names {
             let var = pascal_case(unique_name);
             out.push_str(&format!("                {}CreateError::{} => StatusCode::CONFLICT,\n", model_name, var));
         }
         
         // Foreign key errors (only for actual foreign keys where FK is on this table)
-        let mut rel_names: Vec<_> = model.relations.iter()
+        let rel_names: Vec<_> = model.relations.iter()
             .filter(|(_, rel)| rel.on != "id") // Heuristic: PK is 'id'
             .map(|(k, _)| k.clone())
             .collect();
-        rel_names.sort();
         for rel_name in rel_names {
             let var = format!("{}Fk", pascal_case(&rel_name));
             out.push_str(&format!("                {}CreateError::{} => StatusCode::BAD_REQUEST,\n", model_name, var));
@@ -132,63 +149,123 @@ pub fn generate_router(ir: &ir::SchemaIR) -> String {
         // Get handler
         if model.owned_by.is_some() {
             out.push_str(&format!(
-                "async fn get_{}(State(state): State<Arc<AppState>>, claims: Claims, Path(id): Path<uuid::Uuid>) -> Result<Json<{}>, (StatusCode, Json<Value>)> {{\n",
-                model_lower, model_name
+                "async fn get_{}(State(state): State<Arc<AppState>>, claims: Claims, {}) -> Result<Json<{}>, (StatusCode, Json<Value>)> {{\n",
+                model_lower, key_extractor, model_name
             ));
             out.push_str(&format!(
-                "    let item = {}::find(&state.pool, id, claims.sub)\n        .await\n        .map_err(|e| {{\n            if let sqlx::Error::RowNotFound = e {{\n                (StatusCode::NOT_FOUND, Json(json!({{\"error\": \"Not found\"}})))\n            }} else {{\n                (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({{\"error\": e.to_string()}})))\n            }}\n        }})?;\n    Ok(Json(item))\n}}\n\n",
-                model_name
+                "    let item = {}::find(&state.pool, {}, claims.sub)\n        .await\n        .map_err(|e| {{\n            if let sqlx::Error::RowNotFound = e {{\n                (StatusCode::NOT_FOUND, Json(json!({{\"error\": \"Not found\"}})))\n            }} else {{\n                (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({{\"error\": e.to_string()}})))\n            }}\n        }})?;\n    Ok(Json(item))\n}}\n\n",
+                model_name, key_args
             ));
         } else {
             out.push_str(&format!(
-                "async fn get_{}(State(state): State<Arc<AppState>>, Path(id): Path<uuid::Uuid>) -> Result<Json<{}>, (StatusCode, Json<Value>)> {{\n",
-                model_lower, model_name
+                "async fn get_{}(State(state): State<Arc<AppState>>, {}) -> Result<Json<{}>, (StatusCode, Json<Value>)> {{\n",
+                model_lower, key_extractor, model_name
             ));
             out.push_str(&format!(
-                "    let item = {}::find(&state.pool, id)\n        .await\n        .map_err(|e| {{\n            if let sqlx::Error::RowNotFound = e {{\n                (StatusCode::NOT_FOUND, Json(json!({{\"error\": \"Not found\"}})))\n            }} else {{\n                (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({{\"error\": e.to_string()}})))\n            }}\n        }})?;\n    Ok(Json(item))\n}}\n\n",
-                model_name
+                "    let item = {}::find(&state.pool, {})\n        .await\n        .map_err(|e| {{\n            if let sqlx::Error::RowNotFound = e {{\n                (StatusCode::NOT_FOUND, Json(json!({{\"error\": \"Not found\"}})))\n            }} else {{\n                (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({{\"error\": e.to_string()}})))\n            }}\n        }})?;\n    Ok(Json(item))\n}}\n\n",
+                model_name, key_args
             ));
         }
         
         // Update handler
         if model.owned_by.is_some() {
             out.push_str(&format!(
-                "async fn update_{}(State(state): State<Arc<AppState>>, claims: Claims, Path(id): Path<uuid::Uuid>, Json(item): Json<{}Update>) -> Result<Json<{}>, (StatusCode, Json<Value>)> {{\n",
-                model_lower, model_name, model_name
+                "async fn update_{}(State(state): State<Arc<AppState>>, claims: Claims, {}, Json(item): Json<{}Update>) -> Result<Json<{}>, (StatusCode, Json<Value>)> {{\n",
+                model_lower, key_extractor, model_name, model_name
             ));
             out.push_str(&format!(
-                "    let item = {}::update(&state.pool, id, claims.sub, &item)\n        .await\n        .map_err(|e| {{\n            if let sqlx::Error::RowNotFound = e {{\n                (StatusCode::NOT_FOUND, Json(json!({{\"error\": \"Not found\"}})))\n            }} else {{\n                (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({{\"error\": e.to_string()}})))\n            }}\n        }})?;\n    Ok(Json(item))\n}}\n\n",
-                model_name
+                "    let item = {}::update(&state.pool, {}, claims.sub, &item)\n        .await\n        .map_err(|e| {{\n            if let sqlx::Error::RowNotFound = e {{\n                (StatusCode::NOT_FOUND, Json(json!({{\"error\": \"Not found\"}})))\n            }} else {{\n                (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({{\"error\": e.to_string()}})))\n            }}\n        }})?;\n    Ok(Json(item))\n}}\n\n",
+                model_name, key_args
             ));
         } else {
             out.push_str(&format!(
-                "async fn update_{}(State(state): State<Arc<AppState>>, Path(id): Path<uuid::Uuid>, Json(item): Json<{}Update>) -> Result<Json<{}>, (StatusCode, Json<Value>)> {{\n",
-                model_lower, model_name, model_name
+                "async fn update_{}(State(state): State<Arc<AppState>>, {}, Json(item): Json<{}Update>) -> Result<Json<{}>, (StatusCode, Json<Value>)> {{\n",
+                model_lower, key_extractor, model_name, model_name
             ));
             out.push_str(&format!(
-                "    let item = {}::update(&state.pool, id, &item)\n        .await\n        .map_err(|e| {{\n            if let sqlx::Error::RowNotFound = e {{\n                (StatusCode::NOT_FOUND, Json(json!({{\"error\": \"Not found\"}})))\n            }} else {{\n                (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({{\"error\": e.to_string()}})))\n            }}\n        }})?;\n    Ok(Json(item))\n}}\n\n",
-                model_name
+                "    let item = {}::update(&state.pool, {}, &item)\n        .await\n        .map_err(|e| {{\n            if let sqlx::Error::RowNotFound = e {{\n                (StatusCode::NOT_FOUND, Json(json!({{\"error\": \"Not found\"}})))\n            }} else {{\n                (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({{\"error\": e.to_string()}})))\n            }}\n        }})?;\n    Ok(Json(item))\n}}\n\n",
+                model_name, key_args
             ));
         }
         
-        // Delete handler
+        // Delete handler; rows still referenced elsewhere are a conflict
+        let delete_arms: String = super::restricting_references(ir, model_name)
+            .into_iter()
+            .map(|(other_model, rel_name)| {
+                format!(
+                    "                {}DeleteError::{} => StatusCode::CONFLICT,\n",
+                    model_name,
+                    super::referenced_by_variant(other_model, rel_name)
+                )
+            })
+            .collect();
         if model.owned_by.is_some() {
             out.push_str(&format!(
-                "async fn delete_{}(State(state): State<Arc<AppState>>, claims: Claims, Path(id): Path<uuid::Uuid>) -> Result<Json<Value>, (StatusCode, Json<Value>)> {{\n",
-                model_lower
+                "async fn delete_{}(State(state): State<Arc<AppState>>, claims: Claims, {}) -> Result<Json<Value>, (StatusCode, Json<Value>)> {{\n",
+                model_lower, key_extractor
             ));
             out.push_str(&format!(
-                "    let affected = {}::delete(&state.pool, id, claims.sub)\n        .await\n        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({{\"error\": e.to_string()}}))))?;\n    if affected == 0 {{\n        return Err((StatusCode::NOT_FOUND, Json(json!({{\"error\": \"Not found\"}}))));\n    }}\n    Ok(Json(json!({{\"message\": \"Deleted successfully\"}})))\n}}\n\n",
-                model_name
+                "    let affected = {}::delete(&state.pool, {}, claims.sub)\n        .await\n        .map_err(|e| {{\n            let status = match e {{\n{}                {}DeleteError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,\n            }};\n            (status, Json(json!({{\"error\": e.to_string()}})))\n        }})?;\n    if affected == 0 {{\n        return Err((StatusCode::NOT_FOUND, Json(json!({{\"error\": \"Not found\"}}))));\n    }}\n    Ok(Json(json!({{\"message\": \"Deleted successfully\"}})))\n}}\n\n",
+                model_name, key_args, delete_arms, model_name
             ));
         } else {
             out.push_str(&format!(
-                "async fn delete_{}(State(state): State<Arc<AppState>>, Path(id): Path<uuid::Uuid>) -> Result<Json<Value>, (StatusCode, Json<Value>)> {{\n",
-                model_lower
+                "async fn delete_{}(State(state): State<Arc<AppState>>, {}) -> Result<Json<Value>, (StatusCode, Json<Value>)> {{\n",
+                model_lower, key_extractor
             ));
             out.push_str(&format!(
-                "    let affected = {}::delete(&state.pool, id)\n        .await\n        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({{\"error\": e.to_string()}}))))?;\n    if affected == 0 {{\n        return Err((StatusCode::NOT_FOUND, Json(json!({{\"error\": \"Not found\"}}))));\n    }}\n    Ok(Json(json!({{\"message\": \"Deleted successfully\"}})))\n}}\n\n",
-                model_name
+                "    let affected = {}::delete(&state.pool, {})\n        .await\n        .map_err(|e| {{\n            let status = match e {{\n{}                {}DeleteError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,\n            }};\n            (status, Json(json!({{\"error\": e.to_string()}})))\n        }})?;\n    if affected == 0 {{\n        return Err((StatusCode::NOT_FOUND, Json(json!({{\"error\": \"Not found\"}}))));\n    }}\n    Ok(Json(json!({{\"message\": \"Deleted successfully\"}})))\n}}\n\n",
+                model_name, key_args, delete_arms, model_name
+            ));
+        }
+
+        // Many-to-many handlers load the parent first so ownership is enforced
+        let (claims_param, owner_arg) = if model.owned_by.is_some() {
+            ("claims: Claims, ", ", claims.sub")
+        } else {
+            ("", "")
+        };
+        let load_parent = format!(
+            "    let item = {}::find(&state.pool, {}{})\n        .await\n        .map_err(|e| {{\n            if let sqlx::Error::RowNotFound = e {{\n                (StatusCode::NOT_FOUND, Json(json!({{\"error\": \"Not found\"}})))\n            }} else {{\n                (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({{\"error\": e.to_string()}})))\n            }}\n        }})?;\n",
+            model_name, key_args, owner_arg
+        );
+        for (rel_name, target_column) in many_to_many_routes(ir, model_name) {
+            let target_model = &model.many_to_many[&rel_name].model;
+            let key_type = &key_params[0].1;
+            let target_type = &super::primary_key_params(&ir.models[target_model])[0].1;
+            let pair_extractor = format!(
+                "Path(({}, {})): Path<({}, {})>",
+                key_args, target_column, key_type, target_type
+            );
+
+            out.push_str(&format!(
+                "async fn list_{}_{}(State(state): State<Arc<AppState>>, {}{}, Query(pagination): Query<Option<Pagination>>) -> Result<Json<Vec<{}>>, (StatusCode, Json<Value>)> {{\n",
+                model_lower, rel_name, claims_param, key_extractor, target_model
+            ));
+            out.push_str(&load_parent);
+            out.push_str(&format!(
+                "    let items = item.find_{}(&state.pool, pagination)\n        .await\n        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({{\"error\": e.to_string()}}))))?;\n    Ok(Json(items))\n}}\n\n",
+                rel_name
+            ));
+
+            out.push_str(&format!(
+                "async fn attach_{}_{}(State(state): State<Arc<AppState>>, {}{}) -> Result<Json<Value>, (StatusCode, Json<Value>)> {{\n",
+                model_lower, rel_name, claims_param, pair_extractor
+            ));
+            out.push_str(&load_parent);
+            out.push_str(&format!(
+                "    item.attach_{}(&state.pool, {})\n        .await\n        .map_err(|e| {{\n            let status = match &e {{\n                sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => StatusCode::BAD_REQUEST,\n                _ => StatusCode::INTERNAL_SERVER_ERROR,\n            }};\n            (status, Json(json!({{\"error\": e.to_string()}})))\n        }})?;\n    Ok(Json(json!({{\"message\": \"Attached successfully\"}})))\n}}\n\n",
+                rel_name, target_column
+            ));
+
+            out.push_str(&format!(
+                "async fn detach_{}_{}(State(state): State<Arc<AppState>>, {}{}) -> Result<Json<Value>, (StatusCode, Json<Value>)> {{\n",
+                model_lower, rel_name, claims_param, pair_extractor
+            ));
+            out.push_str(&load_parent);
+            out.push_str(&format!(
+                "    let affected = item.detach_{}(&state.pool, {})\n        .await\n        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({{\"error\": e.to_string()}}))))?;\n    if affected == 0 {{\n        return Err((StatusCode::NOT_FOUND, Json(json!({{\"error\": \"Not found\"}}))));\n    }}\n    Ok(Json(json!({{\"message\": \"Detached successfully\"}})))\n}}\n\n",
+                rel_name, target_column
             ));
         }
     }
@@ -196,6 +273,26 @@ pub fn generate_router(ir: &ir::SchemaIR) -> String {
     out
 }
 
+/// Many-to-many relations of `model_name` that get nested routes, as
+/// `(relation, target key parameter)`. Both sides need a single-column key.
+fn many_to_many_routes(ir: &ir::SchemaIR, model_name: &str) -> Vec<(String, String)> {
+    let Some(model) = ir.models.get(model_name) else {
+        return Vec::new();
+    };
+    if super::primary_key_params(model).len() != 1 {
+        return Vec::new();
+    }
+    model
+        .many_to_many
+        .keys()
+        .filter_map(|rel_name| {
+            let jt = ir.join_table(model_name, rel_name)?;
+            let target = ir.models.get(&jt.target_model)?;
+            (super::primary_key_params(target).len() == 1).then(|| (rel_name.clone(), jt.target_column))
+        })
+        .collect()
+}
+
 fn pascal_case(s: &str) -> String {
     let mut result = String::new();
     let mut capitalize = true;
@@ -6,10 +6,7 @@ pub fn generate_routes(ir: &ir::SchemaIR) -> String {
     out.push_str("use crate::generated::{Route, Permissions};\n\n");
     out.push_str("pub mod routes {\n");
     out.push_str("    use super::*;\n\n");
-    let mut routes_vec: Vec<_> = ir.routes.iter().collect();
-    routes_vec.sort_by(|a, b| a.0.cmp(b.0));
-    
-    for (name, route) in routes_vec {
+    for (name, route) in &ir.routes {
         let methods = route
             .methods
             .iter()
@@ -0,0 +1,555 @@
+//! Per-backend DDL rendering for migrations. Postgres gets plain `ALTER`
+//! statements, MySQL restates whole columns with `MODIFY COLUMN`, and SQLite,
+//! which cannot alter columns or constraints in place, rebuilds the table.
+
+use crate::ir::{self, DatabaseBackend};
+use crate::{column_type, relation_changed};
+use anyhow::Result;
+use indexmap::IndexMap;
+
+/// A table or type name without the `meta.db_schema` prefix.
+pub(crate) fn unqualified(name: &str) -> &str {
+    name.rsplit_once('.').map_or(name, |(_, name)| name)
+}
+
+/// `name` in the schema of `table`. Postgres looks index and sequence names
+/// up on the search path, not next to the table they belong to.
+pub(crate) fn in_schema_of(table: &str, name: &str) -> String {
+    match table.rsplit_once('.') {
+        Some((schema, _)) => format!("{}.{}", schema, name),
+        None => name.to_string(),
+    }
+}
+
+pub(crate) fn drop_index_stmt(backend: DatabaseBackend, table: &str, name: &str) -> String {
+    match backend {
+        DatabaseBackend::Mysql => format!("DROP INDEX {} ON {};\n", name, table),
+        _ => format!("DROP INDEX {};\n", in_schema_of(table, name)),
+    }
+}
+
+pub(crate) fn create_index_stmt(table: &str, name: &str, idx: &ir::IndexDef) -> String {
+    format!(
+        "CREATE {}INDEX {} ON {} ({});\n",
+        if idx.unique { "UNIQUE " } else { "" },
+        name,
+        table,
+        idx.fields.join(", ")
+    )
+}
+
+/// Postgres index creation that does not block writes. It cannot run inside
+/// a transaction, so migrations using it are marked no-transaction.
+pub(crate) fn create_index_concurrently_stmt(table: &str, name: &str, idx: &ir::IndexDef) -> String {
+    format!(
+        "CREATE {}INDEX CONCURRENTLY {} ON {} ({});\n",
+        if idx.unique { "UNIQUE " } else { "" },
+        name,
+        table,
+        idx.fields.join(", ")
+    )
+}
+
+pub(crate) fn drop_index_concurrently_stmt(table: &str, name: &str) -> String {
+    format!("DROP INDEX CONCURRENTLY {};\n", in_schema_of(table, name))
+}
+
+pub(crate) fn add_unique_stmt(table: &str, name: &str, uc: &ir::UniqueConstraintDef) -> String {
+    format!(
+        "ALTER TABLE {} ADD CONSTRAINT {} UNIQUE ({});\n",
+        table,
+        name,
+        uc.fields.join(", ")
+    )
+}
+
+pub(crate) fn drop_unique_stmt(backend: DatabaseBackend, table: &str, name: &str) -> String {
+    match backend {
+        DatabaseBackend::Mysql => format!("ALTER TABLE {} DROP INDEX {};\n", table, name),
+        _ => format!("ALTER TABLE {} DROP CONSTRAINT {};\n", table, name),
+    }
+}
+
+pub(crate) fn add_check_stmt(table: &str, name: &str, ck: &ir::CheckConstraintDef) -> String {
+    format!(
+        "ALTER TABLE {} ADD CONSTRAINT {} CHECK ({});\n",
+        table, name, ck.expression
+    )
+}
+
+pub(crate) fn drop_check_stmt(backend: DatabaseBackend, table: &str, name: &str) -> String {
+    match backend {
+        DatabaseBackend::Mysql => format!("ALTER TABLE {} DROP CHECK {};\n", table, name),
+        _ => format!("ALTER TABLE {} DROP CONSTRAINT {};\n", table, name),
+    }
+}
+
+/// `REFERENCES table (column)` with the relation's actions and deferrability.
+fn references_clause(backend: DatabaseBackend, rel: &ir::RelationDef) -> String {
+    let mut out = format!(
+        "REFERENCES {} ({})",
+        rel.references.model, rel.references.field
+    );
+    if rel.on_delete != ir::ReferentialAction::NoAction {
+        out.push_str(&format!(" ON DELETE {}", rel.on_delete.as_sql()));
+    }
+    if rel.on_update != ir::ReferentialAction::NoAction {
+        out.push_str(&format!(" ON UPDATE {}", rel.on_update.as_sql()));
+    }
+    if rel.deferrable && !matches!(backend, DatabaseBackend::Mysql) {
+        out.push_str(" DEFERRABLE");
+        if rel.initially_deferred {
+            out.push_str(" INITIALLY DEFERRED");
+        }
+    }
+    out
+}
+
+/// Render `ALTER TABLE ... ADD CONSTRAINT ... FOREIGN KEY` including the
+/// referential actions and deferrability declared on the relation.
+pub(crate) fn add_fk_stmt(
+    backend: DatabaseBackend,
+    table: &str,
+    name: &str,
+    rel: &ir::RelationDef,
+) -> String {
+    format!(
+        "ALTER TABLE {} ADD CONSTRAINT {} FOREIGN KEY ({}) {};\n",
+        table,
+        name,
+        rel.on,
+        references_clause(backend, rel)
+    )
+}
+
+pub(crate) fn drop_fk_stmt(backend: DatabaseBackend, table: &str, name: &str) -> String {
+    match backend {
+        DatabaseBackend::Mysql => format!("ALTER TABLE {} DROP FOREIGN KEY {};\n", table, name),
+        _ => format!("ALTER TABLE {} DROP CONSTRAINT {};\n", table, name),
+    }
+}
+
+/// Exclusion constraints are a Postgres feature; other backends skip them.
+pub(crate) fn supports_exclusion(backend: DatabaseBackend) -> bool {
+    matches!(backend, DatabaseBackend::Postgres)
+}
+
+/// SQLite has no `ADD CONSTRAINT`, so unique, check and foreign key
+/// constraints are declared inside `CREATE TABLE` instead.
+pub(crate) fn inlines_constraints(backend: DatabaseBackend) -> bool {
+    matches!(backend, DatabaseBackend::Sqlite)
+}
+
+/// `name TYPE [NOT NULL] [DEFAULT ...]` plus the identity suffix for
+/// database-generated keys.
+fn column_definition(
+    backend: DatabaseBackend,
+    name: &str,
+    ty: &str,
+    field: &ir::FieldDef,
+    identity: bool,
+) -> String {
+    let mut out = format!("{} {}", name, ty);
+    if !field.nullable {
+        out.push_str(" NOT NULL");
+    }
+    if let Some(def) = &field.default {
+        // SQLite only accepts literals and parenthesized expressions here
+        if matches!(backend, DatabaseBackend::Sqlite) && def.contains('(') && !def.starts_with('(') {
+            out.push_str(&format!(" DEFAULT ({})", def));
+        } else {
+            out.push_str(&format!(" DEFAULT {}", def));
+        }
+    }
+    if identity {
+        out.push_str(identity_clause(backend));
+    }
+    out
+}
+
+/// Render a `CREATE TABLE` statement for `model`, including its primary key.
+/// On SQLite the table's constraints are declared inline as well.
+pub(crate) fn create_table_stmt(
+    backend: DatabaseBackend,
+    enums: &IndexMap<String, ir::EnumDef>,
+    model_name: &str,
+    model: &ir::ModelDef,
+) -> Result<String> {
+    let identity = model.key_generation() == ir::KeyGeneration::Identity;
+    let pk_fields = model.primary_key_fields();
+    let mut lines = Vec::new();
+    for (field_name, field) in &model.fields {
+        let db_type = column_type(backend.clone(), enums, model_name, field_name, field)?;
+        lines.push(column_definition(
+            backend.clone(),
+            field_name,
+            &db_type,
+            field,
+            identity && pk_fields.contains(field_name),
+        ));
+    }
+    if let Some(pk) = &model.primary_key {
+        lines.push(format!("PRIMARY KEY ({})", pk.fields.join(", ")));
+    }
+    if inlines_constraints(backend.clone()) {
+        for (uc_name, uc) in &model.unique_constraints {
+            lines.push(format!("CONSTRAINT {} UNIQUE ({})", uc_name, uc.fields.join(", ")));
+        }
+        for (ck_name, ck) in &model.check_constraints {
+            lines.push(format!("CONSTRAINT {} CHECK ({})", ck_name, ck.expression));
+        }
+        for (rel_name, rel) in &model.relations {
+            lines.push(format!(
+                "CONSTRAINT {} FOREIGN KEY ({}) {}",
+                rel_name,
+                rel.on,
+                references_clause(backend.clone(), rel)
+            ));
+        }
+    }
+    Ok(format!(
+        "CREATE TABLE {} (\n    {}\n);\n\n",
+        model_name,
+        lines.join(",\n    ")
+    ))
+}
+
+pub(crate) fn add_column_stmt(
+    backend: DatabaseBackend,
+    enums: &IndexMap<String, ir::EnumDef>,
+    table: &str,
+    column: &str,
+    field: &ir::FieldDef,
+) -> Result<String> {
+    let db_type = column_type(backend.clone(), enums, table, column, field)?;
+    Ok(format!(
+        "ALTER TABLE {} ADD COLUMN {};\n",
+        table,
+        column_definition(backend, column, &db_type, field, false)
+    ))
+}
+
+pub(crate) fn drop_column_stmt(table: &str, column: &str) -> String {
+    format!("ALTER TABLE {} DROP COLUMN {};\n", table, column)
+}
+
+/// Statements taking `column` from `from` to `to` (type, default and
+/// nullability). Postgres alters each property on its own; MySQL restates the
+/// full column with `MODIFY COLUMN`. SQLite tables are rebuilt instead, see
+/// [`sqlite_rebuild_stmts`].
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn alter_column_stmts(
+    backend: DatabaseBackend,
+    from_enums: &IndexMap<String, ir::EnumDef>,
+    to_enums: &IndexMap<String, ir::EnumDef>,
+    table: &str,
+    column: &str,
+    from: &ir::FieldDef,
+    to: &ir::FieldDef,
+    identity: bool,
+) -> Result<String> {
+    let mut out = String::new();
+    match backend {
+        DatabaseBackend::Postgres => {
+            if from.db_type != to.db_type {
+                out.push_str(&format!(
+                    "ALTER TABLE {} ALTER COLUMN {} TYPE {};\n",
+                    table,
+                    column,
+                    column_type(backend.clone(), to_enums, table, column, to)?
+                ));
+            }
+            if from.default != to.default {
+                match &to.default {
+                    Some(def) => out.push_str(&format!(
+                        "ALTER TABLE {} ALTER COLUMN {} SET DEFAULT {};\n",
+                        table, column, def
+                    )),
+                    None => out.push_str(&format!(
+                        "ALTER TABLE {} ALTER COLUMN {} DROP DEFAULT;\n",
+                        table, column
+                    )),
+                }
+            }
+            if from.nullable != to.nullable {
+                out.push_str(&format!(
+                    "ALTER TABLE {} ALTER COLUMN {} {} NOT NULL;\n",
+                    table,
+                    column,
+                    if to.nullable { "DROP" } else { "SET" }
+                ));
+            }
+        }
+        DatabaseBackend::Mysql => {
+            let from_type = column_type(backend.clone(), from_enums, table, column, from)?;
+            let to_type = column_type(backend.clone(), to_enums, table, column, to)?;
+            if from_type != to_type || from.default != to.default || from.nullable != to.nullable {
+                out.push_str(&format!(
+                    "ALTER TABLE {} MODIFY COLUMN {};\n",
+                    table,
+                    column_definition(backend, column, &to_type, to, identity)
+                ));
+            }
+        }
+        DatabaseBackend::Sqlite => {}
+    }
+    Ok(out)
+}
+
+/// Column suffix that makes the database generate key values.
+pub(crate) fn identity_clause(backend: DatabaseBackend) -> &'static str {
+    match backend {
+        DatabaseBackend::Postgres => " GENERATED BY DEFAULT AS IDENTITY",
+        DatabaseBackend::Mysql => " AUTO_INCREMENT",
+        // An INTEGER PRIMARY KEY is already an alias for the rowid
+        DatabaseBackend::Sqlite => "",
+    }
+}
+
+/// Switch a key column between database-generated and supplied values.
+pub(crate) fn set_identity_stmt(
+    backend: DatabaseBackend,
+    enums: &IndexMap<String, ir::EnumDef>,
+    table: &str,
+    column: &str,
+    field: &ir::FieldDef,
+    identity: bool,
+) -> Result<String> {
+    Ok(match backend {
+        DatabaseBackend::Postgres if identity => format!(
+            "ALTER TABLE {} ALTER COLUMN {} ADD GENERATED BY DEFAULT AS IDENTITY;\n",
+            table, column
+        ),
+        DatabaseBackend::Postgres => {
+            format!("ALTER TABLE {} ALTER COLUMN {} DROP IDENTITY;\n", table, column)
+        }
+        DatabaseBackend::Mysql => {
+            let db_type = column_type(backend.clone(), enums, table, column, field)?;
+            format!(
+                "ALTER TABLE {} MODIFY COLUMN {};\n",
+                table,
+                column_definition(backend, column, &db_type, field, identity)
+            )
+        }
+        DatabaseBackend::Sqlite => String::new(),
+    })
+}
+
+pub(crate) fn drop_pk_stmt(backend: DatabaseBackend, table: &str) -> String {
+    match backend {
+        DatabaseBackend::Mysql => format!("ALTER TABLE {} DROP PRIMARY KEY;\n", table),
+        _ => format!("ALTER TABLE {} DROP CONSTRAINT {}_pkey;\n", table, unqualified(table)),
+    }
+}
+
+/// MySQL moves a table to whichever database its new name is in; Postgres
+/// keeps it in its schema and takes a bare name.
+pub(crate) fn rename_table_stmt(backend: DatabaseBackend, table: &str, new_name: &str) -> String {
+    let new_name = match backend {
+        DatabaseBackend::Mysql => new_name,
+        _ => unqualified(new_name),
+    };
+    format!("ALTER TABLE {} RENAME TO {};\n", table, new_name)
+}
+
+pub(crate) fn rename_column_stmt(table: &str, column: &str, new_name: &str) -> String {
+    format!("ALTER TABLE {} RENAME COLUMN {} TO {};\n", table, column, new_name)
+}
+
+/// `None` where the backend cannot rename indexes (SQLite); the differ then
+/// drops and recreates the index instead.
+pub(crate) fn rename_index_stmt(
+    backend: DatabaseBackend,
+    table: &str,
+    name: &str,
+    new_name: &str,
+) -> Option<String> {
+    match backend {
+        DatabaseBackend::Postgres => Some(format!(
+            "ALTER INDEX {} RENAME TO {};\n",
+            in_schema_of(table, name),
+            new_name
+        )),
+        DatabaseBackend::Mysql => Some(format!(
+            "ALTER TABLE {} RENAME INDEX {} TO {};\n",
+            table, name, new_name
+        )),
+        DatabaseBackend::Sqlite => None,
+    }
+}
+
+/// Only Postgres renames constraints in place; MySQL unique constraints are
+/// indexes and go through [`rename_index_stmt`].
+pub(crate) fn rename_constraint_stmt(
+    backend: DatabaseBackend,
+    table: &str,
+    name: &str,
+    new_name: &str,
+) -> Option<String> {
+    match backend {
+        DatabaseBackend::Postgres => Some(format!(
+            "ALTER TABLE {} RENAME CONSTRAINT {} TO {};\n",
+            table, name, new_name
+        )),
+        _ => None,
+    }
+}
+
+pub(crate) fn rename_sequence_stmt(name: &str, new_name: &str) -> String {
+    format!("ALTER SEQUENCE {} RENAME TO {};\n", name, unqualified(new_name))
+}
+
+/// Whether [`rename_index_stmt`] works on `backend`.
+pub(crate) fn renames_indexes(backend: DatabaseBackend) -> bool {
+    rename_index_stmt(backend, "", "", "").is_some()
+}
+
+/// Whether [`rename_constraint_stmt`] works on `backend`.
+pub(crate) fn renames_constraints(backend: DatabaseBackend) -> bool {
+    rename_constraint_stmt(backend, "", "", "").is_some()
+}
+
+/// `(from, to)` names of a renamed database object.
+pub(crate) type Rename = (String, String);
+
+/// Postgres keeps the `<table>_pkey` constraint and `<table>_<column>_seq`
+/// sequences under their old names when a table is renamed. Returns the
+/// `(from, to)` constraint and sequence renames moving them from the `from`
+/// prefix to `to`, so later key changes find them.
+pub(crate) fn table_object_renames(
+    backend: DatabaseBackend,
+    from: &str,
+    to: &str,
+    model: &ir::ModelDef,
+) -> (Vec<Rename>, Vec<Rename>) {
+    let (mut constraints, mut sequences) = (Vec::new(), Vec::new());
+    if !matches!(backend, DatabaseBackend::Postgres) {
+        return (constraints, sequences);
+    }
+    let pk = model.primary_key_fields();
+    if !pk.is_empty() {
+        constraints.push((format!("{}_pkey", unqualified(from)), format!("{}_pkey", unqualified(to))));
+    }
+    let identity = model.key_generation() == ir::KeyGeneration::Identity;
+    for (column, field) in &model.fields {
+        let serial = field
+            .db_type
+            .as_deref()
+            .is_some_and(|t| t.to_uppercase().contains("SERIAL"));
+        if serial || (identity && pk.contains(column)) {
+            sequences.push((format!("{}_{}_seq", from, column), format!("{}_{}_seq", to, column)));
+        }
+    }
+    (constraints, sequences)
+}
+
+pub(crate) fn add_pk_stmt(table: &str, fields: &[String]) -> String {
+    format!("ALTER TABLE {} ADD PRIMARY KEY ({});\n", table, fields.join(", "))
+}
+
+/// Columns carried over from `old` to `new`, as `(old column, new column)`,
+/// following `rename_from`.
+pub(crate) fn carried_columns(old: &ir::ModelDef, new: &ir::ModelDef) -> Vec<(String, String)> {
+    new.fields
+        .iter()
+        .filter_map(|(name, field)| match &field.rename_from {
+            Some(from) if old.fields.contains_key(from) => Some((from.clone(), name.clone())),
+            _ => old.fields.contains_key(name).then(|| (name.clone(), name.clone())),
+        })
+        .collect()
+}
+
+/// Whether SQLite can reach `new` from `old` with `ALTER TABLE` alone. It can
+/// rename and add plain nullable or defaulted columns, and indexes are
+/// separate objects; anything else means rebuilding the table.
+pub(crate) fn sqlite_needs_rebuild(old: &ir::ModelDef, new: &ir::ModelDef) -> bool {
+    let carried = carried_columns(old, new);
+    let renamed = |col: &String| {
+        carried
+            .iter()
+            .find(|(from, _)| from == col)
+            .map(|(_, to)| to.clone())
+            .unwrap_or_else(|| col.clone())
+    };
+    let renamed_all = |cols: &[String]| cols.iter().map(renamed).collect::<Vec<_>>();
+
+    if carried.len() != old.fields.len() {
+        return true;
+    }
+    for (from, to) in &carried {
+        let (a, b) = (&old.fields[from], &new.fields[to]);
+        if a.db_type != b.db_type || a.default != b.default || a.nullable != b.nullable {
+            return true;
+        }
+    }
+    let pk_fields = new.primary_key_fields();
+    for (name, field) in &new.fields {
+        if carried.iter().any(|(_, to)| to == name) {
+            continue;
+        }
+        let db_type = field.db_type.as_deref().unwrap_or("").to_uppercase();
+        let default = field.default.as_deref().unwrap_or("");
+        let addable = (field.nullable || !default.is_empty())
+            && !default.starts_with('(')
+            && !default.to_uppercase().starts_with("CURRENT_")
+            && !db_type.contains("PRIMARY KEY")
+            && !db_type.contains("UNIQUE")
+            && !pk_fields.contains(name);
+        if !addable {
+            return true;
+        }
+    }
+    if renamed_all(&old.primary_key_fields()) != pk_fields {
+        return true;
+    }
+    let uniques_differ = old.unique_constraints.len() != new.unique_constraints.len()
+        || old.unique_constraints.iter().any(|(name, uc)| {
+            new.unique_constraints
+                .get(name)
+                .is_none_or(|n| renamed_all(&uc.fields) != n.fields)
+        });
+    let checks_differ = old.check_constraints.len() != new.check_constraints.len()
+        || old.check_constraints.iter().any(|(name, ck)| {
+            new.check_constraints
+                .get(name)
+                .is_none_or(|n| n.expression != ck.expression)
+        });
+    let relations_differ = old.relations.len() != new.relations.len()
+        || old.relations.iter().any(|(name, rel)| {
+            new.relations.get(name).is_none_or(|n| {
+                let mut rel = rel.clone();
+                rel.on = renamed(&rel.on);
+                relation_changed(&rel, n)
+            })
+        });
+    uniques_differ || checks_differ || relations_differ
+}
+
+/// SQLite's table rebuild: create the new shape under a temporary name, copy
+/// the carried columns across, drop the old table, rename the new one into
+/// place and recreate its indexes. `columns` pairs source and target columns.
+pub(crate) fn sqlite_rebuild_stmts(
+    enums: &IndexMap<String, ir::EnumDef>,
+    table: &str,
+    model: &ir::ModelDef,
+    columns: &[(String, String)],
+) -> Result<String> {
+    let tmp = format!("{}__new", table);
+    let mut out = create_table_stmt(DatabaseBackend::Sqlite, enums, &tmp, model)?;
+    if !columns.is_empty() {
+        let (from, to): (Vec<_>, Vec<_>) = columns.iter().cloned().unzip();
+        out.push_str(&format!(
+            "INSERT INTO {} ({}) SELECT {} FROM {};\n",
+            tmp,
+            to.join(", "),
+            from.join(", "),
+            table
+        ));
+    }
+    out.push_str(&format!("DROP TABLE {};\n", table));
+    out.push_str(&format!("ALTER TABLE {} RENAME TO {};\n", tmp, table));
+    for (idx_name, idx) in &model.indexes {
+        out.push_str(&create_index_stmt(table, idx_name, idx));
+    }
+    Ok(out)
+}
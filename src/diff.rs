@@ -0,0 +1,1071 @@
+//! The difference between two schemas as a list of typed operations, and
+//! the SQL renderer that turns it into an up and a down migration.
+//! `generate_migration` is `diff_schemas` followed by [`SchemaDiff::to_sql`];
+//! other tools can inspect the operations instead of parsing SQL.
+
+use crate::dialect::{
+    add_check_stmt, add_column_stmt, add_fk_stmt, add_pk_stmt, add_unique_stmt, alter_column_stmts,
+    carried_columns, create_index_concurrently_stmt, create_index_stmt, create_table_stmt, drop_check_stmt,
+    drop_column_stmt, drop_fk_stmt, drop_index_concurrently_stmt, drop_index_stmt, drop_pk_stmt, drop_unique_stmt,
+    inlines_constraints, rename_column_stmt, rename_constraint_stmt,
+    rename_index_stmt, rename_sequence_stmt, rename_table_stmt, set_identity_stmt, sqlite_needs_rebuild,
+    sqlite_rebuild_stmts, supports_exclusion,
+};
+use crate::ir::{
+    self, CheckConstraintDef, DatabaseBackend, ExclusionConstraintDef, FieldDef, IndexDef, ModelDef,
+    RelationDef, SchemaIR, UniqueConstraintDef,
+};
+use crate::{
+    align_physical_names, column_type, create_enum_stmt, create_join_table_stmts, create_schema_stmt,
+    enum_labels, initial_migration_sql, join_table_model, migrations, mysql_enum_diff, pg_enum_diff,
+    physical_schema, relation_changed,
+};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// One change to the database. Tables, columns and enum types are named as
+/// the database sees them.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum SchemaOperation {
+    RenameTable { from: String, to: String },
+    RenameColumn { table: String, from: String, to: String },
+    RenameIndex { table: String, from: String, to: String },
+    RenameConstraint { table: String, from: String, to: String },
+    RenameSequence { from: String, to: String },
+    RenameEnum { from: String, to: String },
+    CreateEnum { name: String, variants: Vec<String> },
+    DropEnum { name: String, variants: Vec<String> },
+    AddEnumVariant { name: String, variant: String },
+    DropEnumVariant { name: String, variant: String },
+    RenameEnumVariant { name: String, from: String, to: String },
+    /// The variants end up in a different order than adding and dropping
+    /// alone would give
+    ReorderEnumVariants { name: String, variants: Vec<String> },
+    CreateTable { table: String, model: Box<ModelDef> },
+    DropTable { table: String, model: Box<ModelDef> },
+    CreateJoinTable { table: String, model: Box<ModelDef> },
+    DropJoinTable { table: String, model: Box<ModelDef> },
+    /// SQLite's copy-and-swap for changes it cannot make with `ALTER TABLE`.
+    /// `changes` lists what the copy amounts to, as the operations a backend
+    /// that can alter the table in place would run.
+    RebuildTable { table: String, from: Box<ModelDef>, to: Box<ModelDef>, changes: Vec<SchemaOperation> },
+    AddColumn { table: String, column: String, field: FieldDef },
+    DropColumn { table: String, column: String, field: FieldDef },
+    AlterColumnType { table: String, column: String, from: String, to: String },
+    AlterColumnDefault { table: String, column: String, from: Option<String>, to: Option<String> },
+    AlterColumnNullability { table: String, column: String, nullable: bool },
+    DropPrimaryKey { table: String, columns: Vec<String> },
+    AddPrimaryKey { table: String, columns: Vec<String> },
+    /// Switch a key column to or from database-generated values
+    ChangeKeyGeneration { table: String, column: String, identity: bool },
+    AddIndex { table: String, name: String, index: IndexDef, concurrently: bool },
+    DropIndex { table: String, name: String, index: IndexDef, concurrently: bool },
+    AddUniqueConstraint { table: String, name: String, constraint: UniqueConstraintDef },
+    DropUniqueConstraint { table: String, name: String, constraint: UniqueConstraintDef },
+    AddCheckConstraint { table: String, name: String, constraint: CheckConstraintDef },
+    DropCheckConstraint { table: String, name: String, constraint: CheckConstraintDef },
+    AddExclusionConstraint { table: String, name: String, constraint: ExclusionConstraintDef },
+    DropExclusionConstraint { table: String, name: String, constraint: ExclusionConstraintDef },
+    AddRelation { table: String, name: String, relation: RelationDef },
+    DropRelation { table: String, name: String, relation: RelationDef },
+}
+
+impl SchemaOperation {
+    /// Enum type whose variants this operation changes.
+    fn enum_variants_of(&self) -> Option<&str> {
+        match self {
+            SchemaOperation::AddEnumVariant { name, .. }
+            | SchemaOperation::DropEnumVariant { name, .. }
+            | SchemaOperation::RenameEnumVariant { name, .. }
+            | SchemaOperation::ReorderEnumVariants { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Table and column whose type, default or nullability this operation
+    /// changes.
+    fn altered_column(&self) -> Option<(&str, &str)> {
+        match self {
+            SchemaOperation::AlterColumnType { table, column, .. }
+            | SchemaOperation::AlterColumnDefault { table, column, .. }
+            | SchemaOperation::AlterColumnNullability { table, column, .. } => Some((table, column)),
+            _ => None,
+        }
+    }
+
+    /// Table and name of the index, constraint or foreign key this
+    /// operation adds or drops.
+    pub(crate) fn target(&self) -> Option<(&String, &String)> {
+        use SchemaOperation::*;
+        match self {
+            AddIndex { table, name, .. }
+            | DropIndex { table, name, .. }
+            | AddUniqueConstraint { table, name, .. }
+            | DropUniqueConstraint { table, name, .. }
+            | AddCheckConstraint { table, name, .. }
+            | DropCheckConstraint { table, name, .. }
+            | AddExclusionConstraint { table, name, .. }
+            | DropExclusionConstraint { table, name, .. }
+            | AddRelation { table, name, .. }
+            | DropRelation { table, name, .. } => Some((table, name)),
+            _ => None,
+        }
+    }
+
+    fn concurrent(&self) -> bool {
+        matches!(
+            self,
+            SchemaOperation::AddIndex { concurrently: true, .. }
+                | SchemaOperation::DropIndex { concurrently: true, .. }
+        )
+    }
+}
+
+/// What `diff_schemas` found: the operations taking the old schema to the
+/// new one, in the order the up migration runs them.
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaDiff {
+    pub backend: DatabaseBackend,
+    pub operations: Vec<SchemaOperation>,
+    /// Database view of the old schema with renames applied, `None` for an
+    /// initial migration
+    #[serde(skip)]
+    pub(crate) old: Option<SchemaIR>,
+    /// Database view of the new schema
+    #[serde(skip)]
+    pub(crate) new: SchemaIR,
+    /// Tables and `table.column`s the new schema drops with `dropped = true`,
+    /// in old database names
+    #[serde(skip)]
+    pub(crate) declared_drops: HashSet<String>,
+}
+
+/// Diff `old` against `new`. Without `old` every enum, table and join table
+/// of `new` is created.
+///
+/// Operations are ordered so each only depends on earlier ones: renames,
+/// new enum types and variants, new tables with their indexes and
+/// constraints, new join tables, changes to tables in both schemas (staged
+/// by [`change_stage`] around their columns), foreign keys of the new tables
+/// once every table exists, then dropped join tables, dropped tables (their
+/// foreign keys first) and dropped enum types. The down migration runs the
+/// same operations in reverse.
+pub fn diff_schemas(old: Option<&SchemaIR>, new: &SchemaIR) -> Result<SchemaDiff> {
+    let backend = new.meta.db_backend.clone();
+    let Some(old) = old else {
+        let new = physical_schema(new);
+        let mut operations = Vec::new();
+        for (name, en) in &new.enums {
+            operations.push(SchemaOperation::CreateEnum {
+                name: name.clone(),
+                variants: enum_labels(en),
+            });
+        }
+        for (table, model) in &new.models {
+            create_table_ops(&backend, table, model, &mut operations);
+        }
+        for jt in new.join_tables() {
+            let model = join_table_model(&new, &jt)?;
+            operations.push(SchemaOperation::CreateJoinTable {
+                table: jt.table,
+                model: Box::new(model),
+            });
+        }
+        for (table, model) in &new.models {
+            add_relation_ops(&backend, table, model, &mut operations);
+        }
+        return Ok(SchemaDiff {
+            backend,
+            operations,
+            old: None,
+            new,
+            declared_drops: HashSet::new(),
+        });
+    };
+
+    let declared_drops = new
+        .dropped
+        .iter()
+        .map(|d| match d.split_once('.') {
+            Some((model, field)) => {
+                format!("{}.{}", old.qualify(&old.table_name(model)), old.column_name(model, field))
+            }
+            None => old.qualify(&old.table_name(d)),
+        })
+        .collect();
+
+    // Diff physical names; tables and columns renamed by overrides or case
+    // styles are moved first so the old schema lines up with the new one
+    let (mut ops, old) = align_physical_names(old, new);
+    let new = physical_schema(new);
+
+    for (name, en) in &new.enums {
+        match old.enums.get(name) {
+            None => ops.push(SchemaOperation::CreateEnum {
+                name: name.clone(),
+                variants: enum_labels(en),
+            }),
+            Some(old_en) if old_en.variant_names() != en.variant_names() => {
+                enum_variant_ops(name, &enum_labels(old_en), en, &mut ops);
+            }
+            Some(_) => {}
+        }
+    }
+
+    let created: Vec<(&String, &ModelDef)> =
+        new.models.iter().filter(|(table, _)| !old.models.contains_key(*table)).collect();
+    for (table, model) in &created {
+        create_table_ops(&backend, table, model, &mut ops);
+    }
+
+    // Join tables for many-to-many relations. Introspected schemas see them
+    // as plain tables, so an old model with the same name counts as present.
+    let old_joins = old.join_tables();
+    let new_joins = new.join_tables();
+    for jt in &new_joins {
+        let exists = old_joins.iter().any(|o| o.table == jt.table) || old.models.contains_key(&jt.table);
+        if !exists {
+            ops.push(SchemaOperation::CreateJoinTable {
+                table: jt.table.clone(),
+                model: Box::new(join_table_model(&new, jt)?),
+            });
+        }
+    }
+
+    let dropped: Vec<(&String, &ModelDef)> = old
+        .models
+        .iter()
+        .filter(|(table, _)| {
+            !new.models.contains_key(*table) && !new_joins.iter().any(|jt| jt.table == **table)
+        })
+        .collect();
+    // Keys of dropped tables go before anything they point at changes
+    for (table, model) in &dropped {
+        drop_relation_ops(&backend, table, model, &mut ops);
+    }
+
+    let mut changed = Vec::new();
+    for (table, new_model) in &new.models {
+        if let Some(old_model) = old.models.get(table) {
+            model_ops(&backend, &old, &new, table, old_model, new_model, &mut changed)?;
+        }
+    }
+    changed.sort_by_key(change_stage);
+    ops.extend(changed);
+
+    for (table, model) in &created {
+        add_relation_ops(&backend, table, model, &mut ops);
+    }
+
+    for jt in &old_joins {
+        if !new_joins.iter().any(|n| n.table == jt.table) {
+            ops.push(SchemaOperation::DropJoinTable {
+                table: jt.table.clone(),
+                model: Box::new(join_table_model(&old, jt)?),
+            });
+        }
+    }
+    for (table, model) in &dropped {
+        drop_table_ops(&backend, table, model, &mut ops);
+    }
+
+    // Types go once no column uses them
+    for (name, old_en) in &old.enums {
+        if !new.enums.contains_key(name) {
+            ops.push(SchemaOperation::DropEnum {
+                name: name.clone(),
+                variants: enum_labels(old_en),
+            });
+        }
+    }
+
+    Ok(SchemaDiff {
+        backend,
+        operations: ops,
+        old: Some(old),
+        new,
+        declared_drops,
+    })
+}
+
+/// When an operation on a table in both schemas runs relative to the others:
+/// foreign keys are dropped first, then keys, indexes and constraints, so
+/// none is left over a column about to be dropped or altered. They are added
+/// back once the columns are in place, foreign keys last so the keys they
+/// point at exist. The sort is stable, keeping each table's order otherwise.
+fn change_stage(op: &SchemaOperation) -> u8 {
+    use SchemaOperation::*;
+    match op {
+        DropRelation { .. } => 0,
+        DropPrimaryKey { .. }
+        | DropIndex { .. }
+        | DropUniqueConstraint { .. }
+        | DropCheckConstraint { .. }
+        | DropExclusionConstraint { .. } => 1,
+        AddPrimaryKey { .. }
+        | ChangeKeyGeneration { .. }
+        | AddIndex { .. }
+        | AddUniqueConstraint { .. }
+        | AddCheckConstraint { .. }
+        | AddExclusionConstraint { .. } => 3,
+        AddRelation { .. } => 4,
+        _ => 2,
+    }
+}
+
+/// Create `table` with its indexes and constraints. SQLite declares the
+/// constraints in `CREATE TABLE`; foreign keys come later, from
+/// [`add_relation_ops`], once every table they may point at exists.
+fn create_table_ops(backend: &DatabaseBackend, table: &str, model: &ModelDef, ops: &mut Vec<SchemaOperation>) {
+    use SchemaOperation::*;
+    let t = || table.to_string();
+    ops.push(CreateTable {
+        table: t(),
+        model: Box::new(model.clone()),
+    });
+    for (name, index) in &model.indexes {
+        ops.push(AddIndex {
+            table: t(),
+            name: name.clone(),
+            index: index.clone(),
+            concurrently: false,
+        });
+    }
+    if !inlines_constraints(backend.clone()) {
+        for (name, constraint) in &model.unique_constraints {
+            ops.push(AddUniqueConstraint {
+                table: t(),
+                name: name.clone(),
+                constraint: constraint.clone(),
+            });
+        }
+        for (name, constraint) in &model.check_constraints {
+            ops.push(AddCheckConstraint {
+                table: t(),
+                name: name.clone(),
+                constraint: constraint.clone(),
+            });
+        }
+    }
+    if supports_exclusion(backend.clone()) {
+        for (name, constraint) in &model.exclusion_constraints {
+            ops.push(AddExclusionConstraint {
+                table: t(),
+                name: name.clone(),
+                constraint: constraint.clone(),
+            });
+        }
+    }
+}
+
+/// Foreign keys of a created table, unless declared in its `CREATE TABLE`.
+fn add_relation_ops(backend: &DatabaseBackend, table: &str, model: &ModelDef, ops: &mut Vec<SchemaOperation>) {
+    if inlines_constraints(backend.clone()) {
+        return;
+    }
+    for (name, relation) in &model.relations {
+        ops.push(SchemaOperation::AddRelation {
+            table: table.to_string(),
+            name: name.clone(),
+            relation: relation.clone(),
+        });
+    }
+}
+
+/// Foreign keys of a dropped table, so tables can go in any order.
+fn drop_relation_ops(backend: &DatabaseBackend, table: &str, model: &ModelDef, ops: &mut Vec<SchemaOperation>) {
+    if inlines_constraints(backend.clone()) {
+        return;
+    }
+    for (name, relation) in model.relations.iter().rev() {
+        ops.push(SchemaOperation::DropRelation {
+            table: table.to_string(),
+            name: name.clone(),
+            relation: relation.clone(),
+        });
+    }
+}
+
+/// [`create_table_ops`] undone: constraints and indexes, then the table, so
+/// the down migration restores all of them.
+fn drop_table_ops(backend: &DatabaseBackend, table: &str, model: &ModelDef, ops: &mut Vec<SchemaOperation>) {
+    let mut created = Vec::new();
+    create_table_ops(backend, table, model, &mut created);
+    for op in created.into_iter().rev() {
+        use SchemaOperation::*;
+        let t = table.to_string();
+        ops.push(match op {
+            CreateTable { model, .. } => DropTable { table: t, model },
+            AddIndex { name, index, .. } => DropIndex {
+                table: t,
+                name,
+                index,
+                concurrently: false,
+            },
+            AddUniqueConstraint { name, constraint, .. } => DropUniqueConstraint { table: t, name, constraint },
+            AddCheckConstraint { name, constraint, .. } => DropCheckConstraint { table: t, name, constraint },
+            AddExclusionConstraint { name, constraint, .. } => {
+                DropExclusionConstraint { table: t, name, constraint }
+            }
+            other => unreachable!("create_table_ops produced {:?}", other),
+        });
+    }
+}
+
+/// Variant operations taking an enum from the `old` labels to `new_en`,
+/// matching renames through `rename_from`.
+fn enum_variant_ops(name: &str, old: &[String], new_en: &ir::EnumDef, ops: &mut Vec<SchemaOperation>) {
+    let new_labels = enum_labels(new_en);
+    let mut current = old.to_vec();
+    for variant in &new_en.variants {
+        let Some(from) = &variant.rename_from else {
+            continue;
+        };
+        let (from, to) = (from.to_lowercase(), variant.name.to_lowercase());
+        if current.contains(&to) {
+            continue;
+        }
+        if let Some(pos) = current.iter().position(|l| *l == from) {
+            current[pos] = to.clone();
+            ops.push(SchemaOperation::RenameEnumVariant {
+                name: name.to_string(),
+                from,
+                to,
+            });
+        }
+    }
+    for label in new_labels.iter().filter(|l| !current.contains(l)) {
+        ops.push(SchemaOperation::AddEnumVariant {
+            name: name.to_string(),
+            variant: label.clone(),
+        });
+    }
+    for label in current.iter().filter(|l| !new_labels.contains(l)) {
+        ops.push(SchemaOperation::DropEnumVariant {
+            name: name.to_string(),
+            variant: label.clone(),
+        });
+    }
+    let kept: Vec<&String> = current.iter().filter(|l| new_labels.contains(l)).collect();
+    let in_order: Vec<&String> = new_labels.iter().filter(|l| current.contains(l)).collect();
+    if kept != in_order {
+        ops.push(SchemaOperation::ReorderEnumVariants {
+            name: name.to_string(),
+            variants: new_labels,
+        });
+    }
+}
+
+/// Whether `from` and `to` store differently typed columns. MySQL spells
+/// enum columns out inline, so a changed enum changes their type too.
+fn column_type_change(
+    backend: &DatabaseBackend,
+    old: &SchemaIR,
+    new: &SchemaIR,
+    table: &str,
+    column: &str,
+    from: &FieldDef,
+    to: &FieldDef,
+) -> Result<Option<(String, String)>> {
+    if from.db_type == to.db_type && !matches!(backend, DatabaseBackend::Mysql) {
+        return Ok(None);
+    }
+    let from_type = column_type(backend.clone(), &old.enums, table, column, from)?;
+    let to_type = column_type(backend.clone(), &new.enums, table, column, to)?;
+    Ok((from_type != to_type).then_some((from_type, to_type)))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn alter_column_ops(
+    backend: &DatabaseBackend,
+    old: &SchemaIR,
+    new: &SchemaIR,
+    table: &str,
+    column: &str,
+    from: &FieldDef,
+    to: &FieldDef,
+    ops: &mut Vec<SchemaOperation>,
+) -> Result<()> {
+    if matches!(backend, DatabaseBackend::Sqlite) {
+        return Ok(());
+    }
+    if let Some((from_type, to_type)) = column_type_change(backend, old, new, table, column, from, to)? {
+        ops.push(SchemaOperation::AlterColumnType {
+            table: table.to_string(),
+            column: column.to_string(),
+            from: from_type,
+            to: to_type,
+        });
+    }
+    if from.default != to.default {
+        ops.push(SchemaOperation::AlterColumnDefault {
+            table: table.to_string(),
+            column: column.to_string(),
+            from: from.default.clone(),
+            to: to.default.clone(),
+        });
+    }
+    if from.nullable != to.nullable {
+        ops.push(SchemaOperation::AlterColumnNullability {
+            table: table.to_string(),
+            column: column.to_string(),
+            nullable: to.nullable,
+        });
+    }
+    Ok(())
+}
+
+/// Operations on a table present in both schemas.
+#[allow(clippy::too_many_arguments)]
+fn model_ops(
+    backend: &DatabaseBackend,
+    old: &SchemaIR,
+    new: &SchemaIR,
+    table: &str,
+    old_model: &ModelDef,
+    new_model: &ModelDef,
+    ops: &mut Vec<SchemaOperation>,
+) -> Result<()> {
+    use SchemaOperation::*;
+    let t = || table.to_string();
+
+    // SQLite cannot alter columns or constraints; rebuild the table instead
+    if matches!(backend, DatabaseBackend::Sqlite) && sqlite_needs_rebuild(old_model, new_model) {
+        let mut changes = Vec::new();
+        model_ops(&DatabaseBackend::Postgres, old, new, table, old_model, new_model, &mut changes)?;
+        changes.retain(|op| {
+            !matches!(op, AddExclusionConstraint { .. } | DropExclusionConstraint { .. })
+        });
+        ops.push(RebuildTable {
+            table: t(),
+            from: Box::new(old_model.clone()),
+            to: Box::new(new_model.clone()),
+            changes,
+        });
+        return Ok(());
+    }
+
+    let (old_pk, new_pk) = (old_model.primary_key_fields(), new_model.primary_key_fields());
+    if old_pk != new_pk && !old_pk.is_empty() {
+        ops.push(DropPrimaryKey {
+            table: t(),
+            columns: old_pk.clone(),
+        });
+    }
+
+    let mut handled_new = HashSet::new();
+    let mut handled_old = HashSet::new();
+
+    for (new_name, new_field) in &new_model.fields {
+        if let Some(old_name) = &new_field.rename_from
+            && let Some(old_field) = old_model.fields.get(old_name)
+        {
+            ops.push(RenameColumn {
+                table: t(),
+                from: old_name.clone(),
+                to: new_name.clone(),
+            });
+            alter_column_ops(backend, old, new, table, new_name, old_field, new_field, ops)?;
+            handled_new.insert(new_name.clone());
+            handled_old.insert(old_name.clone());
+        }
+    }
+
+    for (column, field) in &new_model.fields {
+        if !handled_new.contains(column) && !old_model.fields.contains_key(column) {
+            ops.push(AddColumn {
+                table: t(),
+                column: column.clone(),
+                field: field.clone(),
+            });
+        }
+    }
+    for (column, field) in &old_model.fields {
+        if !handled_old.contains(column) && !new_model.fields.contains_key(column) {
+            ops.push(DropColumn {
+                table: t(),
+                column: column.clone(),
+                field: field.clone(),
+            });
+        }
+    }
+    for (column, new_field) in &new_model.fields {
+        if handled_new.contains(column) {
+            continue;
+        }
+        if let Some(old_field) = old_model.fields.get(column) {
+            alter_column_ops(backend, old, new, table, column, old_field, new_field, ops)?;
+        }
+    }
+
+    // the old key went before the columns under it were dropped or
+    // altered, the new one goes once they are in place
+    if old_pk != new_pk && !new_pk.is_empty() {
+        ops.push(AddPrimaryKey {
+            table: t(),
+            columns: new_pk.clone(),
+        });
+    }
+    let new_identity = new_model.key_generation() == ir::KeyGeneration::Identity;
+    let old_identity = old_model.key_generation() == ir::KeyGeneration::Identity;
+    if old_identity != new_identity {
+        for column in new_pk.iter().filter(|f| old_pk.contains(f)) {
+            if new_model.fields.contains_key(column) && old_model.fields.contains_key(column) {
+                ops.push(ChangeKeyGeneration {
+                    table: t(),
+                    column: column.clone(),
+                    identity: new_identity,
+                });
+            }
+        }
+    }
+
+    let concurrent = matches!(backend, DatabaseBackend::Postgres) && new_model.options.concurrent_indexes;
+    for (name, new_idx) in &new_model.indexes {
+        let add = AddIndex {
+            table: t(),
+            name: name.clone(),
+            index: new_idx.clone(),
+            concurrently: concurrent,
+        };
+        match old_model.indexes.get(name) {
+            None => ops.push(add),
+            Some(old_idx) if old_idx.fields != new_idx.fields || old_idx.unique != new_idx.unique => {
+                ops.push(DropIndex {
+                    table: t(),
+                    name: name.clone(),
+                    index: old_idx.clone(),
+                    concurrently: concurrent,
+                });
+                ops.push(add);
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, old_idx) in &old_model.indexes {
+        if !new_model.indexes.contains_key(name) {
+            ops.push(DropIndex {
+                table: t(),
+                name: name.clone(),
+                index: old_idx.clone(),
+                concurrently: false,
+            });
+        }
+    }
+
+    for (name, new_uc) in &new_model.unique_constraints {
+        let add = AddUniqueConstraint {
+            table: t(),
+            name: name.clone(),
+            constraint: new_uc.clone(),
+        };
+        match old_model.unique_constraints.get(name) {
+            None => ops.push(add),
+            Some(old_uc) if old_uc.fields != new_uc.fields => {
+                ops.push(DropUniqueConstraint {
+                    table: t(),
+                    name: name.clone(),
+                    constraint: old_uc.clone(),
+                });
+                ops.push(add);
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, new_ck) in &new_model.check_constraints {
+        let add = AddCheckConstraint {
+            table: t(),
+            name: name.clone(),
+            constraint: new_ck.clone(),
+        };
+        match old_model.check_constraints.get(name) {
+            None => ops.push(add),
+            Some(old_ck) if old_ck.expression != new_ck.expression => {
+                ops.push(DropCheckConstraint {
+                    table: t(),
+                    name: name.clone(),
+                    constraint: old_ck.clone(),
+                });
+                ops.push(add);
+            }
+            Some(_) => {}
+        }
+    }
+    let exclusions = supports_exclusion(backend.clone());
+    for (name, new_ex) in new_model.exclusion_constraints.iter().filter(|_| exclusions) {
+        let add = AddExclusionConstraint {
+            table: t(),
+            name: name.clone(),
+            constraint: new_ex.clone(),
+        };
+        match old_model.exclusion_constraints.get(name) {
+            None => ops.push(add),
+            Some(old_ex) if old_ex.definition != new_ex.definition => {
+                ops.push(DropExclusionConstraint {
+                    table: t(),
+                    name: name.clone(),
+                    constraint: old_ex.clone(),
+                });
+                ops.push(add);
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, old_uc) in &old_model.unique_constraints {
+        if !new_model.unique_constraints.contains_key(name) {
+            ops.push(DropUniqueConstraint {
+                table: t(),
+                name: name.clone(),
+                constraint: old_uc.clone(),
+            });
+        }
+    }
+    for (name, old_ck) in &old_model.check_constraints {
+        if !new_model.check_constraints.contains_key(name) {
+            ops.push(DropCheckConstraint {
+                table: t(),
+                name: name.clone(),
+                constraint: old_ck.clone(),
+            });
+        }
+    }
+    for (name, old_ex) in old_model.exclusion_constraints.iter().filter(|_| exclusions) {
+        if !new_model.exclusion_constraints.contains_key(name) {
+            ops.push(DropExclusionConstraint {
+                table: t(),
+                name: name.clone(),
+                constraint: old_ex.clone(),
+            });
+        }
+    }
+
+    for (name, new_rel) in &new_model.relations {
+        let add = AddRelation {
+            table: t(),
+            name: name.clone(),
+            relation: new_rel.clone(),
+        };
+        match old_model.relations.get(name) {
+            None => ops.push(add),
+            Some(old_rel) if relation_changed(old_rel, new_rel) => {
+                ops.push(DropRelation {
+                    table: t(),
+                    name: name.clone(),
+                    relation: old_rel.clone(),
+                });
+                ops.push(add);
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, old_rel) in &old_model.relations {
+        if !new_model.relations.contains_key(name) {
+            ops.push(DropRelation {
+                table: t(),
+                name: name.clone(),
+                relation: old_rel.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+impl SchemaDiff {
+    /// Whether the schemas are the same.
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Render the up and down migrations. The up migration runs the
+    /// operations in order inside a transaction; the down migration undoes
+    /// them newest first. Variant changes to one enum and property changes
+    /// to one column render together, as a backend may need a single
+    /// statement for them. Concurrent index builds run after the
+    /// transaction, and SQLite rebuilds with foreign key checks off, both
+    /// marking the files no-transaction. Both are empty when no operation
+    /// needs SQL.
+    pub fn to_sql(&self) -> Result<(String, String)> {
+        let Some(old) = &self.old else {
+            return initial_migration_sql(&self.new);
+        };
+        let mut up = String::new();
+        let mut down = String::new();
+        let mut up_concurrent = String::new();
+        let mut down_concurrent = String::new();
+        let mut rendered_enums: HashSet<&str> = HashSet::new();
+        let mut i = 0;
+        while i < self.operations.len() {
+            let op = &self.operations[i];
+            let (op_up, op_down) = if let Some(name) = op.enum_variants_of() {
+                if !rendered_enums.insert(name) {
+                    i += 1;
+                    continue;
+                }
+                self.render_enum(old, name)
+            } else if let Some(column) = op.altered_column() {
+                while self.operations.get(i + 1).and_then(|o| o.altered_column()) == Some(column) {
+                    i += 1;
+                }
+                self.render_column(old, column.0, column.1)?
+            } else {
+                self.render(old, op)?
+            };
+            if op.concurrent() {
+                up_concurrent.push_str(&op_up);
+                down_concurrent.insert_str(0, &op_down);
+            } else {
+                up.push_str(&op_up);
+                down.insert_str(0, &op_down);
+            }
+            i += 1;
+        }
+
+        if up.trim().is_empty() && up_concurrent.is_empty() {
+            return Ok((String::new(), String::new()));
+        }
+        // Tables moved to another `meta.db_schema`, or the first ones in
+        // it, need it to exist
+        if let Some(schema) = &self.new.meta.db_schema
+            && (old.meta.db_schema.as_ref() != Some(schema) || old.models.is_empty())
+        {
+            up.insert_str(0, &create_schema_stmt(schema));
+        }
+        let mut up_tx = String::new();
+        let mut down_tx = down_concurrent;
+        if !up.trim().is_empty() {
+            up_tx.push_str("BEGIN;\n");
+            up_tx.push_str(&up);
+            up_tx.push_str("COMMIT;\n");
+
+            down_tx.push_str("BEGIN;\n");
+            down_tx.push_str(&down);
+            down_tx.push_str("COMMIT;\n");
+        }
+        up_tx.push_str(&up_concurrent);
+
+        // Dropping a rebuilt table must not cascade into the rows that
+        // reference it; SQLite only honors this outside a transaction
+        let rebuilt = self
+            .operations
+            .iter()
+            .any(|op| matches!(op, SchemaOperation::RebuildTable { .. }));
+        if rebuilt {
+            for sql in [&mut up_tx, &mut down_tx] {
+                sql.insert_str(0, "PRAGMA foreign_keys = OFF;\n");
+                sql.push_str("PRAGMA foreign_keys = ON;\n");
+            }
+        }
+
+        // Both need statements outside the file's own transaction
+        if rebuilt || !up_concurrent.is_empty() {
+            for sql in [&mut up_tx, &mut down_tx] {
+                sql.insert_str(0, &format!("{}\n", migrations::NO_TRANSACTION_DIRECTIVE));
+            }
+        }
+        Ok((up_tx, down_tx))
+    }
+
+    /// Every variant change of enum `name`. Only Postgres has enum types;
+    /// MySQL restates the columns using it, SQLite needs nothing.
+    fn render_enum(&self, old: &SchemaIR, name: &str) -> (String, String) {
+        let (Some(old_en), Some(new_en)) = (old.enums.get(name), self.new.enums.get(name)) else {
+            return (String::new(), String::new());
+        };
+        match self.backend {
+            DatabaseBackend::Postgres => pg_enum_diff(old, &self.new, name, old_en, new_en),
+            DatabaseBackend::Mysql => mysql_enum_diff(old, &self.new, name, old_en, new_en),
+            DatabaseBackend::Sqlite => (String::new(), String::new()),
+        }
+    }
+
+    /// Type, default and nullability changes of one column.
+    fn render_column(&self, old: &SchemaIR, table: &str, column: &str) -> Result<(String, String)> {
+        let backend = self.backend.clone();
+        let (Some(old_model), Some(new_model)) = (old.models.get(table), self.new.models.get(table)) else {
+            return Ok((String::new(), String::new()));
+        };
+        let Some(to) = new_model.fields.get(column) else {
+            return Ok((String::new(), String::new()));
+        };
+        let old_column = to
+            .rename_from
+            .as_ref()
+            .filter(|from| old_model.fields.contains_key(*from))
+            .map_or(column, |from| from.as_str());
+        let Some(from) = old_model.fields.get(old_column) else {
+            return Ok((String::new(), String::new()));
+        };
+        let identity = |model: &ModelDef, column: &str| {
+            model.key_generation() == ir::KeyGeneration::Identity
+                && model.primary_key_fields().iter().any(|f| f == column)
+        };
+        let up = alter_column_stmts(
+            backend.clone(),
+            &old.enums,
+            &self.new.enums,
+            table,
+            column,
+            from,
+            to,
+            identity(new_model, column),
+        )?;
+        let down = alter_column_stmts(
+            backend,
+            &self.new.enums,
+            &old.enums,
+            table,
+            column,
+            to,
+            from,
+            identity(old_model, old_column),
+        )?;
+        Ok((up, down))
+    }
+
+    fn render(&self, old: &SchemaIR, op: &SchemaOperation) -> Result<(String, String)> {
+        use SchemaOperation::*;
+        let backend = self.backend.clone();
+        let new = &self.new;
+        let postgres = matches!(backend, DatabaseBackend::Postgres);
+        let pair = |up: String, down: String| Ok((up, down));
+        match op {
+            RenameTable { from, to } => pair(
+                rename_table_stmt(backend.clone(), from, to),
+                rename_table_stmt(backend, to, from),
+            ),
+            RenameColumn { table, from, to } => {
+                pair(rename_column_stmt(table, from, to), rename_column_stmt(table, to, from))
+            }
+            RenameIndex { table, from, to } => pair(
+                rename_index_stmt(backend.clone(), table, from, to).unwrap_or_default(),
+                rename_index_stmt(backend, table, to, from).unwrap_or_default(),
+            ),
+            RenameConstraint { table, from, to } => pair(
+                rename_constraint_stmt(backend.clone(), table, from, to).unwrap_or_default(),
+                rename_constraint_stmt(backend, table, to, from).unwrap_or_default(),
+            ),
+            RenameSequence { from, to } => pair(rename_sequence_stmt(from, to), rename_sequence_stmt(to, from)),
+            RenameEnum { from, to } if postgres => pair(
+                format!("ALTER TYPE {} RENAME TO {};\n", from, to),
+                format!("ALTER TYPE {} RENAME TO {};\n", to, from),
+            ),
+            RenameEnum { .. } => pair(String::new(), String::new()),
+            CreateEnum { name, variants } if postgres => pair(
+                create_enum_stmt(name, variants),
+                format!("DROP TYPE {};\n", name),
+            ),
+            DropEnum { name, variants } if postgres => pair(
+                format!("DROP TYPE {};\n", name),
+                create_enum_stmt(name, variants),
+            ),
+            CreateEnum { .. } | DropEnum { .. } => pair(String::new(), String::new()),
+            CreateTable { table, model } => pair(
+                create_table_stmt(backend, &new.enums, table, model)?,
+                format!("DROP TABLE {};\n\n", table),
+            ),
+            DropTable { table, model } => pair(
+                format!("DROP TABLE {};\n\n", table),
+                create_table_stmt(backend, &old.enums, table, model)?,
+            ),
+            CreateJoinTable { table, model } => pair(
+                create_join_table_stmts(backend, table, model)?,
+                format!("DROP TABLE {};\n\n", table),
+            ),
+            DropJoinTable { table, model } => pair(
+                format!("DROP TABLE {};\n\n", table),
+                create_join_table_stmts(backend, table, model)?,
+            ),
+            RebuildTable { table, from, to, .. } => {
+                let carried = carried_columns(from, to);
+                let back: Vec<_> = carried.iter().map(|(a, b)| (b.clone(), a.clone())).collect();
+                pair(
+                    sqlite_rebuild_stmts(&new.enums, table, to, &carried)?,
+                    sqlite_rebuild_stmts(&old.enums, table, from, &back)?,
+                )
+            }
+            AddColumn { table, column, field } => pair(
+                add_column_stmt(backend, &new.enums, table, column, field)?,
+                drop_column_stmt(table, column),
+            ),
+            DropColumn { table, column, field } => pair(
+                drop_column_stmt(table, column),
+                add_column_stmt(backend, &old.enums, table, column, field)?,
+            ),
+            DropPrimaryKey { table, columns } => pair(drop_pk_stmt(backend, table), add_pk_stmt(table, columns)),
+            AddPrimaryKey { table, columns } => pair(add_pk_stmt(table, columns), drop_pk_stmt(backend, table)),
+            ChangeKeyGeneration { table, column, identity } => {
+                let field = |ir: &SchemaIR| ir.models.get(table).and_then(|m| m.fields.get(column)).cloned();
+                let (Some(old_field), Some(new_field)) = (field(old), field(new)) else {
+                    return pair(String::new(), String::new());
+                };
+                pair(
+                    set_identity_stmt(backend.clone(), &new.enums, table, column, &new_field, *identity)?,
+                    set_identity_stmt(backend, &old.enums, table, column, &old_field, !*identity)?,
+                )
+            }
+            AddIndex { table, name, index, concurrently: true } => pair(
+                create_index_concurrently_stmt(table, name, index),
+                drop_index_concurrently_stmt(table, name),
+            ),
+            DropIndex { table, name, index, concurrently: true } => pair(
+                drop_index_concurrently_stmt(table, name),
+                create_index_concurrently_stmt(table, name, index),
+            ),
+            AddIndex { table, name, index, .. } => pair(
+                create_index_stmt(table, name, index),
+                drop_index_stmt(backend, table, name),
+            ),
+            DropIndex { table, name, index, .. } => pair(
+                drop_index_stmt(backend, table, name),
+                create_index_stmt(table, name, index),
+            ),
+            AddUniqueConstraint { table, name, constraint } => pair(
+                add_unique_stmt(table, name, constraint),
+                drop_unique_stmt(backend, table, name),
+            ),
+            DropUniqueConstraint { table, name, constraint } => pair(
+                drop_unique_stmt(backend, table, name),
+                add_unique_stmt(table, name, constraint),
+            ),
+            AddCheckConstraint { table, name, constraint } => pair(
+                add_check_stmt(table, name, constraint),
+                drop_check_stmt(backend, table, name),
+            ),
+            DropCheckConstraint { table, name, constraint } => pair(
+                drop_check_stmt(backend, table, name),
+                add_check_stmt(table, name, constraint),
+            ),
+            AddExclusionConstraint { table, name, constraint } => pair(
+                format!(
+                    "ALTER TABLE {} ADD CONSTRAINT {} EXCLUDE {};\n",
+                    table, name, constraint.definition
+                ),
+                format!("ALTER TABLE {} DROP CONSTRAINT {};\n", table, name),
+            ),
+            DropExclusionConstraint { table, name, constraint } => pair(
+                format!("ALTER TABLE {} DROP CONSTRAINT {};\n", table, name),
+                format!(
+                    "ALTER TABLE {} ADD CONSTRAINT {} EXCLUDE {};\n",
+                    table, name, constraint.definition
+                ),
+            ),
+            AddRelation { table, name, relation } => pair(
+                add_fk_stmt(backend.clone(), table, name, relation),
+                drop_fk_stmt(backend, table, name),
+            ),
+            DropRelation { table, name, relation } => pair(
+                drop_fk_stmt(backend.clone(), table, name),
+                add_fk_stmt(backend, table, name, relation),
+            ),
+            AddEnumVariant { .. }
+            | DropEnumVariant { .. }
+            | RenameEnumVariant { .. }
+            | ReorderEnumVariants { .. }
+            | AlterColumnType { .. }
+            | AlterColumnDefault { .. }
+            | AlterColumnNullability { .. } => unreachable!("grouped operations render in to_sql"),
+        }
+    }
+}
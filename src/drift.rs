@@ -0,0 +1,362 @@
+//! Detects drift: differences between a live database and what the
+//! migrations say it should be, such as a hand-added index, a changed
+//! default, a dropped constraint or an edited migration file.
+
+use crate::ir::{DatabaseBackend, ModelDef, SchemaIR};
+use crate::migrations::{MigrationState, migration_status};
+use crate::{IntrospectOptions, connect_any_pool, database_schema, introspect_schema_with_options};
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftCategory {
+    Table,
+    Column,
+    Default,
+    PrimaryKey,
+    Index,
+    UniqueConstraint,
+    CheckConstraint,
+    ForeignKey,
+    Enum,
+    /// The migration history disagrees with the files on disk
+    History,
+}
+
+impl DriftCategory {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DriftCategory::Table => "table",
+            DriftCategory::Column => "column",
+            DriftCategory::Default => "default",
+            DriftCategory::PrimaryKey => "primary-key",
+            DriftCategory::Index => "index",
+            DriftCategory::UniqueConstraint => "unique",
+            DriftCategory::CheckConstraint => "check",
+            DriftCategory::ForeignKey => "foreign-key",
+            DriftCategory::Enum => "enum",
+            DriftCategory::History => "history",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftSeverity {
+    /// Something extra or slower, which generated code does not rely on
+    Warning,
+    /// Something missing or changed that generated code or later
+    /// migrations rely on
+    Error,
+}
+
+impl DriftSeverity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DriftSeverity::Warning => "warning",
+            DriftSeverity::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Drift {
+    pub category: DriftCategory,
+    pub severity: DriftSeverity,
+    pub description: String,
+}
+
+impl Drift {
+    fn new(category: DriftCategory, severity: DriftSeverity, description: String) -> Self {
+        Drift {
+            category,
+            severity,
+            description,
+        }
+    }
+}
+
+/// Compare the database at `url` with `snapshot`, the schema the migrations
+/// in `dir` were generated for (`migrations/schema.json`), and its migration
+/// history with the files in `dir`.
+pub async fn detect_drift(url: &str, snapshot: &SchemaIR, dir: &str) -> Result<Vec<Drift>> {
+    let options = IntrospectOptions::for_schema(snapshot);
+    let actual = introspect_schema_with_options(url, &HashMap::new(), &options).await?;
+    let mut drift = schema_differences(&database_schema(snapshot)?, &database_schema(&actual)?);
+
+    let pool = connect_any_pool(url).await?;
+    for status in migration_status(&pool, dir).await? {
+        let (severity, what) = match status.state {
+            MigrationState::Applied | MigrationState::Squashed => continue,
+            MigrationState::Pending => (DriftSeverity::Warning, "has not been applied".to_string()),
+            MigrationState::Modified => (DriftSeverity::Error, "was edited after it was applied".to_string()),
+            MigrationState::Orphaned => (DriftSeverity::Warning, "is applied but missing on disk".to_string()),
+            MigrationState::Failed => (
+                DriftSeverity::Error,
+                format!("failed: {}", status.error.unwrap_or_default()),
+            ),
+        };
+        drift.push(Drift::new(
+            DriftCategory::History,
+            severity,
+            format!("migration {} {}", status.name, what),
+        ));
+    }
+    pool.close().await;
+    Ok(drift)
+}
+
+/// One line per drift, errors first.
+pub fn drift_summary(drift: &[Drift]) -> String {
+    let mut sorted: Vec<&Drift> = drift.iter().collect();
+    sorted.sort_by_key(|d| std::cmp::Reverse(d.severity));
+    let mut out = String::new();
+    for d in sorted {
+        out.push_str(&format!(
+            "  {:<9}{:<13}{}\n",
+            d.severity.label(),
+            d.category.label(),
+            d.description
+        ));
+    }
+    out
+}
+
+/// Ways the introspected `actual` differs from `expected`, both keyed by
+/// table and column name. Indexes and check constraints are matched by name;
+/// unique constraints and foreign keys by their columns, as SQLite does not
+/// report their names. Column types and check expressions are left out, as
+/// introspection reports them in the database's own spelling.
+pub(crate) fn schema_differences(expected: &SchemaIR, actual: &SchemaIR) -> Vec<Drift> {
+    use DriftCategory::*;
+    use DriftSeverity::*;
+    let mut out = Vec::new();
+    for (table, model) in &expected.models {
+        match actual.models.get(table) {
+            Some(found) => model_differences(table, model, found, &mut out),
+            None => out.push(Drift::new(Table, Error, format!("table {} is missing", table))),
+        }
+    }
+    for table in actual.models.keys() {
+        if !expected.models.contains_key(table) {
+            out.push(Drift::new(Table, Warning, format!("unexpected table {}", table)));
+        }
+    }
+    if matches!(actual.meta.db_backend, DatabaseBackend::Postgres) {
+        for (name, en) in &expected.enums {
+            let labels = |e: &crate::ir::EnumDef| e.variant_names().join(", ");
+            match actual.enums.get(name) {
+                Some(found) if labels(found) != labels(en) => out.push(Drift::new(
+                    Enum,
+                    Error,
+                    format!("enum {} has values ({}) instead of ({})", name, labels(found), labels(en)),
+                )),
+                Some(_) => {}
+                None => out.push(Drift::new(Enum, Error, format!("enum type {} is missing", name))),
+            }
+        }
+        for name in actual.enums.keys() {
+            if !expected.enums.contains_key(name) {
+                out.push(Drift::new(Enum, Warning, format!("unexpected enum type {}", name)));
+            }
+        }
+    }
+    out
+}
+
+/// Whether `s` is one parenthesized expression, `(a + b)` but not `(a) + (b)`.
+pub(crate) fn wrapped_in_parens(s: &str) -> bool {
+    if !s.starts_with('(') || !s.ends_with(')') {
+        return false;
+    }
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return i == s.len() - 1;
+        }
+    }
+    false
+}
+
+/// A column default as written in the schema and as reported by the
+/// database brought to one spelling: casts, wrapping parentheses and quotes
+/// removed.
+fn normalize_default(default: &str) -> String {
+    let cast = Regex::new(r#"::[\w\s."]+(\[\])?$"#).unwrap();
+    let mut d = default.trim().to_string();
+    loop {
+        let mut next = cast.replace(&d, "").trim().to_string();
+        if wrapped_in_parens(&next) {
+            next = next[1..next.len() - 1].trim().to_string();
+        }
+        if next == d {
+            break;
+        }
+        d = next;
+    }
+    d.trim_matches('\'').to_lowercase()
+}
+
+fn model_differences(table: &str, expected: &ModelDef, actual: &ModelDef, out: &mut Vec<Drift>) {
+    use DriftCategory::*;
+    use DriftSeverity::*;
+    let expected_pk = expected.primary_key_fields();
+    for (column, field) in &expected.fields {
+        let Some(found) = actual.fields.get(column) else {
+            out.push(Drift::new(Column, Error, format!("column {}.{} is missing", table, column)));
+            continue;
+        };
+        // SQLite reports key columns as nullable unless declared NOT NULL
+        if found.nullable != field.nullable && !expected_pk.contains(column) {
+            out.push(Drift::new(
+                Column,
+                Error,
+                format!(
+                    "column {}.{} is {}",
+                    table,
+                    column,
+                    if found.nullable { "nullable" } else { "NOT NULL" }
+                ),
+            ));
+        }
+        // sequences behind serial and identity keys
+        let found_default = found
+            .default
+            .as_deref()
+            .filter(|d| !d.starts_with("nextval("))
+            .map(normalize_default);
+        if field.default.as_deref().map(normalize_default) != found_default {
+            out.push(Drift::new(
+                Default,
+                Warning,
+                format!(
+                    "default of {}.{} is {} instead of {}",
+                    table,
+                    column,
+                    found.default.as_deref().unwrap_or("unset"),
+                    field.default.as_deref().unwrap_or("unset")
+                ),
+            ));
+        }
+    }
+    for column in actual.fields.keys() {
+        if !expected.fields.contains_key(column) {
+            out.push(Drift::new(Column, Warning, format!("unexpected column {}.{}", table, column)));
+        }
+    }
+
+    let actual_pk = actual.primary_key_fields();
+    if expected_pk != actual_pk {
+        out.push(Drift::new(
+            PrimaryKey,
+            Error,
+            format!("primary key of {} is {:?} instead of {:?}", table, actual_pk, expected_pk),
+        ));
+    }
+
+    for (name, idx) in &expected.indexes {
+        match actual.indexes.get(name) {
+            Some(found) if found.fields != idx.fields || found.unique != idx.unique => {
+                out.push(Drift::new(Index, Warning, format!("index {} on {} differs", name, table)))
+            }
+            Some(_) => {}
+            None => out.push(Drift::new(Index, Warning, format!("index {} on {} is missing", name, table))),
+        }
+    }
+    // indexes the database creates for unique constraints
+    let backs_constraint = |fields: &Vec<String>| {
+        expected.unique_constraints.values().any(|uc| &uc.fields == fields)
+            || actual.unique_constraints.values().any(|uc| &uc.fields == fields)
+    };
+    for (name, idx) in &actual.indexes {
+        let expected_here = expected.indexes.contains_key(name) || idx.unique && backs_constraint(&idx.fields);
+        if !expected_here {
+            out.push(Drift::new(Index, Warning, format!("unexpected index {} on {}", name, table)));
+        }
+    }
+
+    let unique_sets = |m: &ModelDef| -> Vec<Vec<String>> {
+        m.unique_constraints.values().map(|uc| uc.fields.clone()).collect()
+    };
+    let (expected_uniques, actual_uniques) = (unique_sets(expected), unique_sets(actual));
+    for fields in &expected_uniques {
+        if !actual_uniques.contains(fields) {
+            out.push(Drift::new(
+                UniqueConstraint,
+                Error,
+                format!("unique constraint on {} ({}) is missing", table, fields.join(", ")),
+            ));
+        }
+    }
+    for fields in &actual_uniques {
+        if !expected_uniques.contains(fields) {
+            out.push(Drift::new(
+                UniqueConstraint,
+                Warning,
+                format!("unexpected unique constraint on {} ({})", table, fields.join(", ")),
+            ));
+        }
+    }
+
+    // Postgres lists NOT NULL columns as check constraints too
+    let checks = |m: &ModelDef| -> Vec<String> {
+        m.check_constraints
+            .keys()
+            .filter(|name| !name.ends_with("_not_null"))
+            .cloned()
+            .collect()
+    };
+    let (expected_checks, actual_checks) = (checks(expected), checks(actual));
+    for name in &expected_checks {
+        if !actual_checks.contains(name) {
+            out.push(Drift::new(
+                CheckConstraint,
+                Error,
+                format!("check constraint {} on {} is missing", name, table),
+            ));
+        }
+    }
+    for name in &actual_checks {
+        if !expected_checks.contains(name) {
+            out.push(Drift::new(
+                CheckConstraint,
+                Warning,
+                format!("unexpected check constraint {} on {}", name, table),
+            ));
+        }
+    }
+
+    let references = |m: &ModelDef| -> Vec<String> {
+        m.relations
+            .values()
+            .map(|r| format!("{} -> {}.{}", r.on, r.references.model, r.references.field))
+            .collect()
+    };
+    let (expected_refs, actual_refs) = (references(expected), references(actual));
+    for reference in &expected_refs {
+        if !actual_refs.contains(reference) {
+            out.push(Drift::new(
+                ForeignKey,
+                Error,
+                format!("foreign key {}.{} is missing", table, reference),
+            ));
+        }
+    }
+    for reference in &actual_refs {
+        if !expected_refs.contains(reference) {
+            out.push(Drift::new(
+                ForeignKey,
+                Warning,
+                format!("unexpected foreign key {}.{}", table, reference),
+            ));
+        }
+    }
+}
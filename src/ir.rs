@@ -1,5 +1,6 @@
+use crate::naming;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "lowercase")]
@@ -16,17 +17,21 @@ pub struct SchemaIR {
     pub schema_version: String,
     pub meta: Meta,
     #[serde(default)]
-    pub enums: HashMap<String, EnumDef>,
+    pub enums: IndexMap<String, EnumDef>,
     #[serde(default)]
-    pub models: HashMap<String, ModelDef>,
+    pub models: IndexMap<String, ModelDef>,
     #[serde(default)]
-    pub routes: HashMap<String, RouteDef>,
+    pub routes: IndexMap<String, RouteDef>,
     #[serde(default)]
-    pub plugins: HashMap<String, PluginDef>,
+    pub plugins: IndexMap<String, PluginDef>,
     #[serde(default)]
-    pub macros: HashMap<String, MacroDef>,
+    pub macros: IndexMap<String, MacroDef>,
     #[serde(default)]
-    pub seeds: HashMap<String, SeedDef>,
+    pub seeds: IndexMap<String, SeedDef>,
+    /// Models and fields (`Model.field`) marked `dropped = true`; they are
+    /// removed on load and their drops count as declared
+    #[serde(skip)]
+    pub dropped: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -38,6 +43,10 @@ pub struct Meta {
     pub observability_provider: Option<String>,
     #[serde(default)]
     pub db_backend: DatabaseBackend,
+    /// Postgres schema or MySQL database holding the tables; the
+    /// connection's default (`public`, the URL's database) when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_schema: Option<String>,
     #[serde(default)]
     pub auth: AuthConfig,
 }
@@ -95,6 +104,7 @@ impl Default for Meta {
             default_derives: Vec::new(),
             observability_provider: None,
             db_backend: DatabaseBackend::Postgres,
+            db_schema: None,
             auth: AuthConfig::default(),
         }
     }
@@ -102,7 +112,86 @@ impl Default for Meta {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct EnumDef {
-    pub variants: Vec<String>,
+    pub variants: Vec<EnumVariant>,
+}
+
+impl EnumDef {
+    pub fn variant_names(&self) -> Vec<&str> {
+        self.variants.iter().map(|v| v.as_str()).collect()
+    }
+}
+
+/// An enum variant, written either as a bare name or as
+/// `{ name = "...", rename_from = "..." }` when the stored label changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumVariant {
+    pub name: String,
+    /// Previous label; the differ renames the value instead of recreating it
+    pub rename_from: Option<String>,
+}
+
+impl EnumVariant {
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+}
+
+impl std::fmt::Display for EnumVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl From<String> for EnumVariant {
+    fn from(name: String) -> Self {
+        EnumVariant { name, rename_from: None }
+    }
+}
+
+impl From<&str> for EnumVariant {
+    fn from(name: &str) -> Self {
+        EnumVariant::from(name.to_string())
+    }
+}
+
+impl Serialize for EnumVariant {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeStruct;
+        match &self.rename_from {
+            None => serializer.serialize_str(&self.name),
+            Some(from) => {
+                let mut st = serializer.serialize_struct("EnumVariant", 2)?;
+                st.serialize_field("name", &self.name)?;
+                st.serialize_field("rename_from", from)?;
+                st.end()
+            }
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for EnumVariant {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Helper {
+            Struct {
+                name: String,
+                #[serde(default)]
+                rename_from: Option<String>,
+            },
+            String(String),
+        }
+        match Helper::deserialize(deserializer)? {
+            Helper::Struct { name, rename_from } => Ok(EnumVariant { name, rename_from }),
+            Helper::String(name) => Ok(EnumVariant { name, rename_from: None }),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -115,17 +204,18 @@ pub struct TypeAlias {
 pub struct ModelDef {
     #[serde(default)]
     pub includes: Vec<String>,
-    pub fields: HashMap<String, FieldDef>,
     #[serde(default)]
-    pub indexes: HashMap<String, IndexDef>,
+    pub fields: IndexMap<String, FieldDef>,
     #[serde(default)]
-    pub relations: HashMap<String, RelationDef>,
+    pub indexes: IndexMap<String, IndexDef>,
     #[serde(default)]
-    pub unique_constraints: HashMap<String, UniqueConstraintDef>,
+    pub relations: IndexMap<String, RelationDef>,
     #[serde(default)]
-    pub check_constraints: HashMap<String, CheckConstraintDef>,
+    pub unique_constraints: IndexMap<String, UniqueConstraintDef>,
     #[serde(default)]
-    pub exclusion_constraints: HashMap<String, ExclusionConstraintDef>,
+    pub check_constraints: IndexMap<String, CheckConstraintDef>,
+    #[serde(default)]
+    pub exclusion_constraints: IndexMap<String, ExclusionConstraintDef>,
     #[serde(default)]
     pub permissions: Permissions,
     #[serde(default)]
@@ -133,22 +223,126 @@ pub struct ModelDef {
     /// Indicates which model owns this resource (for authorization purposes)
     #[serde(default)]
     pub owned_by: Option<String>,
+    /// Primary key columns; falls back to an inline `PRIMARY KEY` in a field's `db_type`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_key: Option<PrimaryKeyDef>,
+    /// Many-to-many relations backed by a generated join table
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub many_to_many: IndexMap<String, ManyToManyDef>,
+    /// Table name override; defaults to the model name in `db_case_style`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
+    /// Declares that the table should be dropped by the next migration
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dropped: bool,
+    /// Previous model name; the differ renames the table instead of recreating it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rename_from: Option<String>,
 }
 
 impl Default for ModelDef {
     fn default() -> Self {
         ModelDef {
             includes: Vec::new(),
-            fields: HashMap::new(),
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            fields: IndexMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Permissions::default(),
             options: ModelOptions::default(),
             owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
+        }
+    }
+}
+
+impl ModelDef {
+    /// Resolve the primary key columns in key order. An explicit `primary_key`
+    /// wins; otherwise fields whose `db_type` carries `PRIMARY KEY` are used,
+    /// and finally a plain `id` field.
+    pub fn primary_key_fields(&self) -> Vec<String> {
+        if let Some(pk) = &self.primary_key {
+            return pk.fields.clone();
+        }
+        let inline: Vec<String> = self
+            .fields
+            .iter()
+            .filter(|(_, f)| {
+                f.db_type
+                    .as_deref()
+                    .map(|t| t.to_uppercase().contains("PRIMARY KEY"))
+                    .unwrap_or(false)
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        if !inline.is_empty() {
+            return inline;
+        }
+        if self.fields.contains_key("id") {
+            vec!["id".to_string()]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// How the database fills in the primary key on insert
+    pub fn key_generation(&self) -> KeyGeneration {
+        self.primary_key
+            .as_ref()
+            .map(|pk| pk.generated.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyGeneration {
+    /// Values are supplied by the application or a column default
+    #[default]
+    None,
+    /// `GENERATED BY DEFAULT AS IDENTITY` / `AUTO_INCREMENT` / SQLite rowid
+    #[serde(alias = "serial", alias = "auto_increment")]
+    Identity,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PrimaryKeyDef {
+    pub fields: Vec<String>,
+    #[serde(default)]
+    pub generated: KeyGeneration,
+}
+
+impl<'de> serde::Deserialize<'de> for PrimaryKeyDef {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum Helper {
+            Struct {
+                fields: Vec<String>,
+                #[serde(default)]
+                generated: KeyGeneration,
+            },
+            List(Vec<String>),
+            String(String),
+        }
+        let pk = match Helper::deserialize(deserializer)? {
+            Helper::Struct { fields, generated } => PrimaryKeyDef { fields, generated },
+            Helper::List(fields) => PrimaryKeyDef { fields, generated: KeyGeneration::None },
+            Helper::String(field) => PrimaryKeyDef { fields: vec![field], generated: KeyGeneration::None },
+        };
+        if pk.fields.is_empty() {
+            return Err(serde::de::Error::custom("primary key needs at least one field"));
         }
+        Ok(pk)
     }
 }
 
@@ -158,12 +352,15 @@ pub struct ModelOptions {
     pub timestamps: bool,
     #[serde(default)]
     pub soft_delete: bool,
+    /// Build indexes added to the existing table with `CREATE INDEX CONCURRENTLY` (Postgres)
+    #[serde(default)]
+    pub concurrent_indexes: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct MacroDef {
     #[serde(default)]
-    pub fields: HashMap<String, FieldDef>,
+    pub fields: IndexMap<String, FieldDef>,
     #[serde(default)]
     pub options: ModelOptions,
 }
@@ -198,6 +395,12 @@ pub struct FieldDef {
     pub zod: Option<String>,
     #[serde(default)]
     pub storage: Option<StorageOptions>,
+    /// Column name override; defaults to the field name in `db_case_style`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<String>,
+    /// Declares that the column should be dropped by the next migration
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dropped: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -242,6 +445,159 @@ impl<'de> serde::Deserialize<'de> for FieldRef {
 pub struct RelationDef {
     pub on: String,
     pub references: FieldRef,
+    /// What happens to referencing rows when the referenced row is deleted
+    #[serde(default)]
+    pub on_delete: ReferentialAction,
+    /// What happens to referencing rows when the referenced key changes
+    #[serde(default)]
+    pub on_update: ReferentialAction,
+    /// Create the foreign key as `DEFERRABLE` (Postgres and SQLite only)
+    #[serde(default)]
+    pub deferrable: bool,
+    /// Check a deferrable key at commit instead of after each statement
+    #[serde(default)]
+    pub initially_deferred: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferentialAction {
+    #[default]
+    #[serde(alias = "no action")]
+    NoAction,
+    Restrict,
+    Cascade,
+    #[serde(alias = "set null")]
+    SetNull,
+    #[serde(alias = "set default")]
+    SetDefault,
+}
+
+impl ReferentialAction {
+    /// SQL keyword form, e.g. `SET NULL`
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            ReferentialAction::NoAction => "NO ACTION",
+            ReferentialAction::Restrict => "RESTRICT",
+            ReferentialAction::Cascade => "CASCADE",
+            ReferentialAction::SetNull => "SET NULL",
+            ReferentialAction::SetDefault => "SET DEFAULT",
+        }
+    }
+
+    /// Parse a rule as reported by `information_schema` or SQLite pragmas
+    pub fn from_sql(rule: &str) -> Self {
+        match rule.trim().to_uppercase().as_str() {
+            "RESTRICT" => ReferentialAction::Restrict,
+            "CASCADE" => ReferentialAction::Cascade,
+            "SET NULL" => ReferentialAction::SetNull,
+            "SET DEFAULT" => ReferentialAction::SetDefault,
+            _ => ReferentialAction::NoAction,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ManyToManyDef {
+    /// Model on the other side of the relation
+    pub model: String,
+    /// Join table name; defaults to both table names in alphabetical order, joined by `_`
+    #[serde(default)]
+    pub through: Option<String>,
+    /// Join column pointing at this model; defaults to `{model}_id`
+    #[serde(default)]
+    pub source_column: Option<String>,
+    /// Join column pointing at the other model; defaults to `{other}_id`
+    #[serde(default)]
+    pub target_column: Option<String>,
+}
+
+/// A many-to-many join table resolved from the side that declares it
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinTable {
+    pub table: String,
+    pub source_model: String,
+    pub source_column: String,
+    pub target_model: String,
+    pub target_column: String,
+}
+
+impl SchemaIR {
+    /// Database table for a model: its `table` override, else the model name in `db_case_style`
+    pub fn table_name(&self, model_name: &str) -> String {
+        self.models
+            .get(model_name)
+            .and_then(|m| m.table.clone())
+            .unwrap_or_else(|| naming::convert(model_name, &self.meta.db_case_style))
+    }
+
+    /// A table or type name as queries and DDL spell it: prefixed with
+    /// `meta.db_schema` when one is set
+    pub fn qualify(&self, name: &str) -> String {
+        match &self.meta.db_schema {
+            Some(schema) => format!("{}.{}", schema, name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Database column for a field: its `column` override, else the field name in `db_case_style`
+    pub fn column_name(&self, model_name: &str, field_name: &str) -> String {
+        self.models
+            .get(model_name)
+            .and_then(|m| m.fields.get(field_name))
+            .and_then(|f| f.column.clone())
+            .unwrap_or_else(|| naming::convert(field_name, &self.meta.db_case_style))
+    }
+
+    /// Database type name for a schema enum
+    pub fn enum_type_name(&self, enum_name: &str) -> String {
+        naming::convert(enum_name, &self.meta.db_case_style)
+    }
+
+    /// Field name as it appears in JSON, TypeScript and GraphQL
+    pub fn api_field_name(&self, field_name: &str) -> String {
+        if naming::serde_rename_all(&self.meta.rust_case_style).is_some() {
+            naming::convert(field_name, &self.meta.rust_case_style)
+        } else {
+            field_name.to_string()
+        }
+    }
+
+    /// Resolve the join table behind `model_name.many_to_many[rel_name]`
+    pub fn join_table(&self, model_name: &str, rel_name: &str) -> Option<JoinTable> {
+        let rel = self.models.get(model_name)?.many_to_many.get(rel_name)?;
+        let key_column = |model: &str| {
+            let snake = format!("{}_id", naming::convert(model, "snake"));
+            naming::convert(&snake, &self.meta.db_case_style)
+        };
+        let table = rel.through.clone().unwrap_or_else(|| {
+            let mut names = [self.table_name(model_name), self.table_name(&rel.model)];
+            names.sort();
+            names.join("_")
+        });
+        Some(JoinTable {
+            table,
+            source_model: model_name.to_string(),
+            source_column: rel.source_column.clone().unwrap_or_else(|| key_column(model_name)),
+            target_model: rel.model.clone(),
+            target_column: rel.target_column.clone().unwrap_or_else(|| key_column(&rel.model)),
+        })
+    }
+
+    /// Every join table once, oriented from the first model that declares it
+    pub fn join_tables(&self) -> Vec<JoinTable> {
+        let mut tables: Vec<JoinTable> = Vec::new();
+        for (model_name, model) in &self.models {
+            for rel_name in model.many_to_many.keys() {
+                if let Some(jt) = self.join_table(model_name, rel_name)
+                    && !tables.iter().any(|t| t.table == jt.table)
+                {
+                    tables.push(jt);
+                }
+            }
+        }
+        tables
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -296,5 +652,5 @@ pub struct PluginDef {
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct SeedDef {
     #[serde(default)]
-    pub rows: Vec<HashMap<String, toml::Value>>,
+    pub rows: Vec<IndexMap<String, toml::Value>>,
 }
@@ -1,37 +1,57 @@
 pub mod ir;
 pub mod codegen;
+pub mod naming;
+pub mod changes;
+pub mod diff;
+pub mod drift;
+mod dialect;
+pub mod migrations;
+pub mod sql_split;
+pub mod verify;
+pub use diff::{SchemaDiff, SchemaOperation, diff_schemas};
+pub use migrations::{
+    MigrateOptions, MigrationState, MigrationStatus, NO_TRANSACTION_DIRECTIVE, RepairAction,
+    RepairedMigration, RollbackTarget, RolledBackMigration, SNAPSHOT_SUFFIX, SQUASHES_DIRECTIVE, SquashedMigration,
+    apply_migrations, apply_migrations_with_options, baseline_migrations, migration_status,
+    prune_squashed_migrations, redo_migration, repair_migrations, rollback_migrations, squash_migrations,
+};
 use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use indexmap::IndexMap;
 use sqlx::Row;
-use std::collections::HashMap;
 use std::fs;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize, Default)]
 struct RawSchema {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     include: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     schema_version: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     meta: Option<ir::Meta>,
-    #[serde(default)]
-    enums: HashMap<String, ir::EnumDef>,
-    #[serde(default)]
-    models: HashMap<String, ir::ModelDef>,
-    #[serde(default)]
-    routes: HashMap<String, ir::RouteDef>,
-    #[serde(default)]
-    plugins: HashMap<String, ir::PluginDef>,
-    #[serde(default)]
-    macros: HashMap<String, ir::MacroDef>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    enums: IndexMap<String, ir::EnumDef>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    models: IndexMap<String, ir::ModelDef>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    routes: IndexMap<String, ir::RouteDef>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    plugins: IndexMap<String, ir::PluginDef>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    macros: IndexMap<String, ir::MacroDef>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
     #[serde(rename = "seed")]
-    seeds: HashMap<String, ir::SeedDef>,
+    seeds: IndexMap<String, ir::SeedDef>,
 }
 
 /// Load a schema from the given path, processing any `include` directives.
 use anyhow::{Context, Result, anyhow};
-use std::collections::HashSet;
+use dialect::{
+    add_check_stmt, add_fk_stmt, add_unique_stmt, create_index_stmt, create_table_stmt, drop_fk_stmt,
+    inlines_constraints, renames_constraints, renames_indexes, supports_exclusion, table_object_renames,
+    unqualified,
+};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 
 fn load_schema_inner(path: &str, visited: &mut HashSet<PathBuf>) -> Result<ir::SchemaIR> {
@@ -43,7 +63,14 @@ fn load_schema_inner(path: &str, visited: &mut HashSet<PathBuf>) -> Result<ir::S
 
     let text = fs::read_to_string(&canonical)
         .with_context(|| format!("failed to read schema file {}", canonical.display()))?;
-    let raw: RawSchema = toml::from_str(&text)?;
+    let ir = schema_from_text(&text, canonical.parent().unwrap_or(Path::new("")), visited)?;
+    visited.remove(&canonical);
+    Ok(ir)
+}
+
+/// Parse schema `text` and merge its includes, resolved against `base`.
+fn schema_from_text(text: &str, base: &Path, visited: &mut HashSet<PathBuf>) -> Result<ir::SchemaIR> {
+    let raw: RawSchema = toml::from_str(text)?;
     let mut ir = ir::SchemaIR {
         schema_version: raw.schema_version.unwrap_or_else(|| "1.0".into()),
         meta: raw.meta.unwrap_or_default(),
@@ -53,8 +80,8 @@ fn load_schema_inner(path: &str, visited: &mut HashSet<PathBuf>) -> Result<ir::S
         plugins: raw.plugins,
         macros: raw.macros,
         seeds: raw.seeds,
+        dropped: Vec::new(),
     };
-    let base = canonical.parent().unwrap_or(Path::new(""));
     for inc in raw.include {
         let child_path = base.join(&inc);
         let child_str = child_path
@@ -98,9 +125,12 @@ fn load_schema_inner(path: &str, visited: &mut HashSet<PathBuf>) -> Result<ir::S
             ir.seeds.insert(name, seed);
         }
     }
-    visited.remove(&canonical);
-    
-    // Convert unique indexes to unique constraints for error handling
+    unique_indexes_as_constraints(&mut ir);
+    Ok(ir)
+}
+
+/// Convert unique indexes to unique constraints for error handling
+fn unique_indexes_as_constraints(ir: &mut ir::SchemaIR) {
     for model in ir.models.values_mut() {
         let mut unique_indexes = Vec::new();
         for (idx_name, idx) in &model.indexes {
@@ -114,1024 +144,1467 @@ fn load_schema_inner(path: &str, visited: &mut HashSet<PathBuf>) -> Result<ir::S
             });
         }
     }
-    
-    Ok(ir)
 }
 
 pub fn load_schema(path: &str) -> Result<ir::SchemaIR> {
     let mut visited: HashSet<PathBuf> = HashSet::new();
-    load_schema_inner(path, &mut visited)
+    let ir = load_schema_inner(path, &mut visited)?;
+    Ok(take_dropped(ir))
 }
 
-fn drop_index_stmt(backend: ir::DatabaseBackend, table: &str, name: &str) -> String {
-    match backend {
-        ir::DatabaseBackend::Mysql => format!("DROP INDEX {} ON {}\n", name, table),
-        _ => format!("DROP INDEX {}\n", name),
-    }
+/// Load a schema from `text` not yet saved to disk, e.g. the editor's
+/// buffer. Includes resolve against `base`, the directory the file lives in.
+pub fn load_schema_text(text: &str, base: &Path) -> Result<ir::SchemaIR> {
+    let mut visited: HashSet<PathBuf> = HashSet::new();
+    let ir = schema_from_text(text, base, &mut visited)?;
+    Ok(take_dropped(ir))
 }
 
-fn drop_unique_stmt(backend: ir::DatabaseBackend, table: &str, name: &str) -> String {
-    match backend {
-        ir::DatabaseBackend::Mysql => format!("ALTER TABLE {} DROP INDEX {}\n", table, name),
-        _ => format!("ALTER TABLE {} DROP CONSTRAINT {}\n", table, name),
+fn take_dropped(mut ir: ir::SchemaIR) -> ir::SchemaIR {
+    // Models and fields marked `dropped` only exist to declare their removal
+    ir.models.retain(|name, model| {
+        if model.dropped {
+            ir.dropped.push(name.clone());
+        }
+        !model.dropped
+    });
+    for (model_name, model) in ir.models.iter_mut() {
+        model.fields.retain(|name, field| {
+            if field.dropped {
+                ir.dropped.push(format!("{}.{}", model_name, name));
+            }
+            !field.dropped
+        });
     }
+    ir
 }
 
-fn drop_check_stmt(backend: ir::DatabaseBackend, table: &str, name: &str) -> String {
-    match backend {
-        ir::DatabaseBackend::Mysql => format!("ALTER TABLE {} DROP CHECK {}\n", table, name),
-        _ => format!("ALTER TABLE {} DROP CONSTRAINT {}\n", table, name),
-    }
+/// Render a schema as a single `schema.model.toml` that `load_schema` reads back.
+pub fn schema_to_toml(ir: &ir::SchemaIR) -> Result<String> {
+    let raw = RawSchema {
+        schema_version: Some(ir.schema_version.clone()),
+        meta: Some(ir.meta.clone()),
+        enums: ir.enums.clone(),
+        models: ir.models.clone(),
+        routes: ir.routes.clone(),
+        plugins: ir.plugins.clone(),
+        macros: ir.macros.clone(),
+        seeds: ir.seeds.clone(),
+        ..Default::default()
+    };
+    Ok(toml::to_string_pretty(&raw)?)
 }
 
-fn drop_fk_stmt(backend: ir::DatabaseBackend, table: &str, name: &str) -> String {
-    match backend {
-        ir::DatabaseBackend::Mysql => format!("ALTER TABLE {} DROP FOREIGN KEY {}\n", table, name),
-        _ => format!("ALTER TABLE {} DROP CONSTRAINT {}\n", table, name),
+/// Write a schema as a root file at `root` holding `[meta]`, enums, routes,
+/// plugins, macros and an `include` list, plus one `<model>.toml` per model
+/// (with its seed rows) in `split_dir`. Returns the files written, root first.
+pub fn write_split_schema(ir: &ir::SchemaIR, root: &Path, split_dir: &Path) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(split_dir)
+        .with_context(|| format!("failed to create {}", split_dir.display()))?;
+    let base = match root.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let base = fs::canonicalize(base).with_context(|| format!("failed to canonicalize {}", base.display()))?;
+    let dir = fs::canonicalize(split_dir)
+        .with_context(|| format!("failed to canonicalize {}", split_dir.display()))?;
+    // includes resolve against the root file's directory
+    let prefix = dir.strip_prefix(&base).map(Path::to_path_buf).unwrap_or(dir.clone());
+
+    let mut root_raw = RawSchema {
+        schema_version: Some(ir.schema_version.clone()),
+        meta: Some(ir.meta.clone()),
+        enums: ir.enums.clone(),
+        routes: ir.routes.clone(),
+        plugins: ir.plugins.clone(),
+        macros: ir.macros.clone(),
+        seeds: ir.seeds.clone(),
+        ..Default::default()
+    };
+    let mut written = vec![root.to_path_buf()];
+    for (name, model) in &ir.models {
+        let file_name = format!("{}.toml", name);
+        let mut raw = RawSchema::default();
+        raw.models.insert(name.clone(), model.clone());
+        if let Some(seed) = root_raw.seeds.shift_remove(name) {
+            raw.seeds.insert(name.clone(), seed);
+        }
+        let path = split_dir.join(&file_name);
+        fs::write(&path, toml::to_string_pretty(&raw)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        let include = prefix.join(&file_name);
+        let include = include
+            .to_str()
+            .ok_or_else(|| anyhow!("non-UTF8 path: {}", include.display()))?;
+        root_raw.include.push(include.replace('\\', "/"));
+        written.push(path);
     }
+    fs::write(root, toml::to_string_pretty(&root_raw)?)
+        .with_context(|| format!("failed to write {}", root.display()))?;
+    Ok(written)
 }
 
-/// Generate SQL for an initial migration based on the provided schema.
-pub fn generate_initial_migration(ir: &ir::SchemaIR) -> Result<(String, String)> {
-    let mut up_body = String::new();
-    let mut down_body = String::new();
+fn relation_changed(old: &ir::RelationDef, new: &ir::RelationDef) -> bool {
+    old.on != new.on
+        || old.references.model != new.references.model
+        || old.references.field != new.references.field
+        || old.on_delete != new.on_delete
+        || old.on_update != new.on_update
+        || old.deferrable != new.deferrable
+        || (new.deferrable && old.initially_deferred != new.initially_deferred)
+}
 
-    let mut enums: Vec<_> = ir.enums.iter().collect();
-    enums.sort_by(|a, b| a.0.cmp(b.0));
-    for (enum_name, en) in enums {
-        up_body.push_str(&format!(
-            "CREATE TYPE {} AS ENUM ({});\n",
-            enum_name.to_lowercase(),
-            en.variants
-                .iter()
-                .map(|v| format!("'{}'", v.to_lowercase()))
-                .collect::<Vec<_>>()
-                .join(", ")
-        ));
-        down_body.push_str(&format!("DROP TYPE {};\n", enum_name.to_lowercase()));
+/// Column type as written in DDL. MySQL has no named enum types, so columns
+/// backed by a schema enum are spelled out inline as `ENUM(...)`.
+fn column_type(
+    backend: ir::DatabaseBackend,
+    enums: &IndexMap<String, ir::EnumDef>,
+    model_name: &str,
+    field_name: &str,
+    field: &ir::FieldDef,
+) -> Result<String> {
+    if matches!(backend, ir::DatabaseBackend::Mysql)
+        && let Some((_, en)) = field_enum(enums, field)
+    {
+        return Ok(format!("ENUM({})", enum_values_sql(&enum_labels(en))));
     }
+    field
+        .db_type
+        .clone()
+        .ok_or_else(|| anyhow!("missing db_type for {}.{}", model_name, field_name))
+}
+
+/// The schema enum a field stores, matched by its Rust type or by the
+/// enum's type name in `db_type`.
+fn field_enum<'a>(
+    enums: &'a IndexMap<String, ir::EnumDef>,
+    field: &ir::FieldDef,
+) -> Option<(&'a String, &'a ir::EnumDef)> {
+    let ty = field.rust_type.as_str();
+    let ty = ty
+        .strip_prefix("Option<")
+        .and_then(|s| s.strip_suffix('>'))
+        .unwrap_or(ty);
+    enums.get_key_value(ty).or_else(|| {
+        let db_type = field.db_type.as_deref()?;
+        enums
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(db_type))
+    })
+}
+
+/// Labels as stored in the database.
+fn enum_labels(en: &ir::EnumDef) -> Vec<String> {
+    en.variants.iter().map(|v| v.name.to_lowercase()).collect()
+}
+
+fn enum_values_sql(labels: &[String]) -> String {
+    labels
+        .iter()
+        .map(|l| format!("'{}'", l))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `CREATE SCHEMA`; on MySQL a schema is a database.
+fn create_schema_stmt(schema: &str) -> String {
+    format!("CREATE SCHEMA IF NOT EXISTS {};\n", schema)
+}
+
+fn create_enum_stmt(name: &str, labels: &[String]) -> String {
+    format!(
+        "CREATE TYPE {} AS ENUM ({});\n",
+        name,
+        enum_values_sql(labels)
+    )
+}
 
-    let mut models: Vec<_> = ir.models.iter().collect();
-    models.sort_by(|a, b| a.0.cmp(b.0));
-    for (model_name, model) in models {
-        up_body.push_str(&format!("CREATE TABLE {} (\n", model_name.to_lowercase()));
-        let mut first = true;
+/// A column typed with a schema enum: `(table, column, field)`.
+type EnumColumn<'a> = (String, &'a String, &'a ir::FieldDef);
+
+fn enum_columns<'a>(ir: &'a ir::SchemaIR, enum_name: &str) -> Vec<EnumColumn<'a>> {
+    let mut columns = Vec::new();
+    for (model_name, model) in &ir.models {
         for (field_name, field) in &model.fields {
-            if !first {
-                up_body.push_str(",\n");
-            }
-            first = false;
-            let db_type = field
-                .db_type
-                .as_deref()
-                .ok_or_else(|| anyhow!("missing db_type for {}.{}", model_name, field_name))?;
-            up_body.push_str(&format!("    {} {}", field_name, db_type));
-            if !field.nullable {
-                up_body.push_str(" NOT NULL");
-            }
-            if let Some(def) = &field.default {
-                up_body.push_str(&format!(" DEFAULT {}", def));
+            if field_enum(&ir.enums, field).is_some_and(|(name, _)| name == enum_name) {
+                columns.push((model_name.clone(), field_name, field));
             }
         }
-        up_body.push_str("\n);\n\n");
-
-        down_body.push_str(&format!("DROP TABLE {};", model_name.to_lowercase()));
-        down_body.push_str("\n\n");
     }
+    columns
+}
 
-    // Indexes
-    let mut models: Vec<_> = ir.models.iter().collect();
-    models.sort_by(|a, b| a.0.cmp(b.0));
-    for (model_name, model) in models {
-        let mut indexes: Vec<_> = model.indexes.iter().collect();
-        indexes.sort_by(|a, b| a.0.cmp(b.0));
-        for (idx_name, idx) in indexes {
-            up_body.push_str(&format!(
-                "CREATE {}INDEX {} ON {} ({});\n",
-                if idx.unique { "UNIQUE " } else { "" },
-                idx_name,
-                model_name.to_lowercase(),
-                idx.fields.join(", ")
-            ));
-            down_body.push_str(&drop_index_stmt(
-                ir.meta.db_backend.clone(),
-                &model_name.to_lowercase(),
-                idx_name,
-            ));
+/// Replace a Postgres enum type with one holding `labels`: the old type is
+/// renamed aside, every column is cast over through `text`, and the old type
+/// dropped. Column defaults are lifted off during the cast since Postgres
+/// cannot convert them automatically.
+fn enum_swap_stmts(name: &str, labels: &[String], columns: &[EnumColumn<'_>]) -> String {
+    let ty = name;
+    let mut out = format!("ALTER TYPE {} RENAME TO {}_old;\n", ty, unqualified(ty));
+    out.push_str(&create_enum_stmt(name, labels));
+    for (table, column, field) in columns {
+        if field.default.is_some() {
+            out.push_str(&format!("ALTER TABLE {} ALTER COLUMN {} DROP DEFAULT;\n", table, column));
         }
-    }
-
-    // Unique constraints
-    let mut models: Vec<_> = ir.models.iter().collect();
-    models.sort_by(|a, b| a.0.cmp(b.0));
-    for (model_name, model) in models {
-        let mut ucs: Vec<_> = model.unique_constraints.iter().collect();
-        ucs.sort_by(|a, b| a.0.cmp(b.0));
-        for (uc_name, uc) in ucs {
-            up_body.push_str(&format!(
-                "ALTER TABLE {} ADD CONSTRAINT {} UNIQUE ({});\n",
-                model_name.to_lowercase(),
-                uc_name,
-                uc.fields.join(", ")
-            ));
-            down_body.push_str(&drop_unique_stmt(
-                ir.meta.db_backend.clone(),
-                &model_name.to_lowercase(),
-                uc_name,
-            ));
+        out.push_str(&format!(
+            "ALTER TABLE {} ALTER COLUMN {} TYPE {} USING {}::text::{};\n",
+            table, column, ty, column, ty
+        ));
+        if let Some(def) = &field.default {
+            out.push_str(&format!("ALTER TABLE {} ALTER COLUMN {} SET DEFAULT {};\n", table, column, def));
         }
     }
+    out.push_str(&format!("DROP TYPE {}_old;\n", ty));
+    out
+}
 
-    // Check constraints
-    let mut models: Vec<_> = ir.models.iter().collect();
-    models.sort_by(|a, b| a.0.cmp(b.0));
-    for (model_name, model) in models {
-        let mut cks: Vec<_> = model.check_constraints.iter().collect();
-        cks.sort_by(|a, b| a.0.cmp(b.0));
-        for (ck_name, ck) in cks {
-            up_body.push_str(&format!(
-                "ALTER TABLE {} ADD CONSTRAINT {} CHECK ({});\n",
-                model_name.to_lowercase(),
-                ck_name,
-                ck.expression
-            ));
-            down_body.push_str(&drop_check_stmt(
-                ir.meta.db_backend.clone(),
-                &model_name.to_lowercase(),
-                ck_name,
-            ));
+/// Postgres statements evolving enum `name` from `old_en` to `new_en`.
+/// Renamed variants become `RENAME VALUE`; pure additions become
+/// `ADD VALUE [BEFORE|AFTER]`; anything else (removals, reordering) swaps the
+/// type. Values cannot be dropped from a Postgres enum, so the down migration
+/// swaps back whenever values were added.
+fn pg_enum_diff(
+    old_ir: &ir::SchemaIR,
+    new: &ir::SchemaIR,
+    name: &str,
+    old_en: &ir::EnumDef,
+    new_en: &ir::EnumDef,
+) -> (String, String) {
+    let ty = name;
+    let new_labels = enum_labels(new_en);
+    let mut current = enum_labels(old_en);
+    let mut renames = Vec::new();
+    for variant in &new_en.variants {
+        let Some(from) = &variant.rename_from else {
+            continue;
+        };
+        let (from, to) = (from.to_lowercase(), variant.name.to_lowercase());
+        if current.contains(&to) {
+            continue;
+        }
+        if let Some(pos) = current.iter().position(|l| *l == from) {
+            current[pos] = to.clone();
+            renames.push((from, to));
         }
     }
 
-    // Exclusion constraints
-    let mut models: Vec<_> = ir.models.iter().collect();
-    models.sort_by(|a, b| a.0.cmp(b.0));
-    for (model_name, model) in models {
-        let mut exs: Vec<_> = model.exclusion_constraints.iter().collect();
-        exs.sort_by(|a, b| a.0.cmp(b.0));
-        for (ex_name, ex) in exs {
-            up_body.push_str(&format!(
-                "ALTER TABLE {} ADD CONSTRAINT {} EXCLUDE {};\n",
-                model_name.to_lowercase(),
-                ex_name,
-                ex.definition
-            ));
-            down_body.push_str(&format!(
-                "ALTER TABLE {} DROP CONSTRAINT {};\n",
-                model_name.to_lowercase(),
-                ex_name
-            ));
-        }
+    let mut up = String::new();
+    let mut down = String::new();
+    for (from, to) in &renames {
+        up.push_str(&format!("ALTER TYPE {} RENAME VALUE '{}' TO '{}';\n", ty, from, to));
     }
 
-    // Foreign keys
-    let mut models: Vec<_> = ir.models.iter().collect();
-    models.sort_by(|a, b| a.0.cmp(b.0));
-    for (model_name, model) in models {
-        let mut rels: Vec<_> = model.relations.iter().collect();
-        rels.sort_by(|a, b| a.0.cmp(b.0));
-        for (rel_name, rel) in rels {
-            up_body.push_str(&format!(
-                "ALTER TABLE {} ADD CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {}.{}\n",
-                model_name.to_lowercase(),
-                rel_name,
-                rel.on,
-                rel.references.model.to_lowercase(),
-                rel.references.field
-            ));
-            down_body.push_str(&drop_fk_stmt(
-                ir.meta.db_backend.clone(),
-                &model_name.to_lowercase(),
-                rel_name,
-            ));
+    // Try to reach the new variant list by inserting values in place
+    let mut staged = current.clone();
+    let mut additions = String::new();
+    if current.iter().all(|l| new_labels.contains(l)) {
+        for (i, label) in new_labels.iter().enumerate() {
+            if staged.contains(label) {
+                continue;
+            }
+            let position = if i == 0 {
+                staged.first().map(|first| (0, format!(" BEFORE '{}'", first)))
+            } else {
+                let prev = &new_labels[i - 1];
+                staged.iter().position(|l| l == prev).map(|pos| {
+                    if pos + 1 == staged.len() {
+                        (pos + 1, String::new())
+                    } else {
+                        (pos + 1, format!(" AFTER '{}'", prev))
+                    }
+                })
+            };
+            let (pos, clause) = position.unwrap_or((staged.len(), String::new()));
+            additions.push_str(&format!("ALTER TYPE {} ADD VALUE '{}'{};\n", ty, label, clause));
+            staged.insert(pos, label.clone());
         }
     }
 
-    let mut up = String::new();
-    up.push_str("BEGIN;\n");
-    up.push_str(&up_body);
-    up.push_str("COMMIT;\n");
-
-    let mut down = String::new();
-    down.push_str("BEGIN;\n");
-    down.push_str(&down_body);
-    down.push_str("COMMIT;\n");
+    if staged == new_labels {
+        up.push_str(&additions);
+    } else {
+        // Defaults follow the new schema where the column still exists
+        let columns: Vec<EnumColumn<'_>> = enum_columns(old_ir, name)
+            .into_iter()
+            .map(|(table, column, field)| {
+                let current_field = new
+                    .models
+                    .get(&table)
+                    .and_then(|model| model.fields.get(column))
+                    .unwrap_or(field);
+                (table, column, current_field)
+            })
+            .collect();
+        up.push_str(&enum_swap_stmts(name, &new_labels, &columns));
+    }
 
-    Ok((up, down))
+    if current != new_labels {
+        down.push_str(&enum_swap_stmts(name, &current, &enum_columns(new, name)));
+    }
+    for (from, to) in renames.iter().rev() {
+        down.push_str(&format!("ALTER TYPE {} RENAME VALUE '{}' TO '{}';\n", ty, to, from));
+    }
+    (up, down)
 }
 
-/// Generate SQL migration by diffing `old` schema against `new`.
-/// If `old` is `None`, this is equivalent to `generate_initial_migration`.
-pub fn generate_migration(
-    old: Option<&ir::SchemaIR>,
+/// MySQL statements evolving the inline `ENUM(...)` columns of enum `name`.
+/// Renames widen the column to hold both labels, rewrite the rows, then
+/// narrow it to the new list.
+fn mysql_enum_diff(
+    old_ir: &ir::SchemaIR,
     new: &ir::SchemaIR,
-) -> Result<(String, String)> {
-    if let Some(old_ir) = old {
-        let mut up = String::new();
-        let mut down = String::new();
-
-        // Enum differences
-        let mut enums: Vec<_> = new.enums.iter().collect();
-        enums.sort_by(|a, b| a.0.cmp(b.0));
-        for (name, en) in enums {
-            match old_ir.enums.get(name) {
-                None => {
-                    up.push_str(&format!(
-                        "CREATE TYPE {} AS ENUM ({});\n",
-                        name.to_lowercase(),
-                        en.variants
-                            .iter()
-                            .map(|v| format!("'{}'", v.to_lowercase()))
-                            .collect::<Vec<_>>()
-                            .join(", ")
-                    ));
-                    down.push_str(&format!("DROP TYPE {};\n", name.to_lowercase()));
-                }
-                Some(old_en) => {
-                    if old_en.variants != en.variants {
-                        up.push_str(&format!("DROP TYPE {};\n", name.to_lowercase()));
-                        up.push_str(&format!(
-                            "CREATE TYPE {} AS ENUM ({});\n",
-                            name.to_lowercase(),
-                            en.variants
-                                .iter()
-                                .map(|v| format!("'{}'", v.to_lowercase()))
-                                .collect::<Vec<_>>()
-                                .join(", ")
-                        ));
-                        down.push_str(&format!("DROP TYPE {};\n", name.to_lowercase()));
-                        down.push_str(&format!(
-                            "CREATE TYPE {} AS ENUM ({});\n",
-                            name.to_lowercase(),
-                            old_en
-                                .variants
-                                .iter()
-                                .map(|v| format!("'{}'", v.to_lowercase()))
-                                .collect::<Vec<_>>()
-                                .join(", ")
-                        ));
-                    }
-                }
-            }
-        }
+    name: &str,
+    old_en: &ir::EnumDef,
+    new_en: &ir::EnumDef,
+) -> (String, String) {
+    let old_labels = enum_labels(old_en);
+    let new_labels = enum_labels(new_en);
+    let renames: Vec<(String, String)> = new_en
+        .variants
+        .iter()
+        .filter_map(|v| {
+            let from = v.rename_from.as_ref()?.to_lowercase();
+            let to = v.name.to_lowercase();
+            (old_labels.contains(&from) && !old_labels.contains(&to)).then_some((from, to))
+        })
+        .collect();
 
-        let mut old_enums: Vec<_> = old_ir.enums.iter().collect();
-        old_enums.sort_by(|a, b| a.0.cmp(b.0));
-        for (name, old_en) in old_enums {
-            if !new.enums.contains_key(name) {
-                up.push_str(&format!("DROP TYPE {};\n", name.to_lowercase()));
-                down.push_str(&format!(
-                    "CREATE TYPE {} AS ENUM ({});\n",
-                    name.to_lowercase(),
-                    old_en
-                        .variants
-                        .iter()
-                        .map(|v| format!("'{}'", v.to_lowercase()))
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                ));
-            }
+    let modify = |table: &str, column: &str, field: &ir::FieldDef, labels: &[String]| {
+        let mut stmt = format!(
+            "ALTER TABLE {} MODIFY COLUMN {} ENUM({})",
+            table,
+            column,
+            enum_values_sql(labels)
+        );
+        if !field.nullable {
+            stmt.push_str(" NOT NULL");
         }
-
-        // New models
-        let mut new_models: Vec<_> = new.models.iter().collect();
-        new_models.sort_by(|a, b| a.0.cmp(b.0));
-        for (model_name, model) in new_models {
-            if !old_ir.models.contains_key(model_name) {
-                up.push_str(&format!("CREATE TABLE {} (\n", model_name.to_lowercase()));
-                let mut first = true;
-                for (field_name, field) in &model.fields {
-                    if !first {
-                        up.push_str(",\n");
-                    }
-                    first = false;
-                    let db_type = field.db_type.as_deref().ok_or_else(|| {
-                        anyhow!("missing db_type for {}.{}", model_name, field_name)
-                    })?;
-                    up.push_str(&format!("    {} {}", field_name, db_type));
-                    if !field.nullable {
-                        up.push_str(" NOT NULL");
-                    }
-                    if let Some(def) = &field.default {
-                        up.push_str(&format!(" DEFAULT {}", def));
-                    }
+        if let Some(def) = &field.default {
+            stmt.push_str(&format!(" DEFAULT {}", def));
+        }
+        stmt.push_str(";\n");
+        stmt
+    };
+    let evolve = |columns: Vec<EnumColumn<'_>>,
+                  from_labels: &[String],
+                  to_labels: &[String],
+                  renames: &[(String, String)]| {
+        let mut out = String::new();
+        for (table, column, field) in columns {
+            if !renames.is_empty() {
+                let mut widened = from_labels.to_vec();
+                widened.extend(to_labels.iter().filter(|l| !from_labels.contains(l)).cloned());
+                out.push_str(&modify(&table, column, field, &widened));
+                for (from, to) in renames {
+                    out.push_str(&format!(
+                        "UPDATE {} SET {} = '{}' WHERE {} = '{}';\n",
+                        table, column, to, column, from
+                    ));
                 }
-                up.push_str("\n);\n\n");
-
-                down.push_str(&format!("DROP TABLE {};\n\n", model_name.to_lowercase()));
             }
+            out.push_str(&modify(&table, column, field, to_labels));
         }
+        out
+    };
 
-        // Removed models
-        let mut old_models: Vec<_> = old_ir.models.iter().collect();
-        old_models.sort_by(|a, b| a.0.cmp(b.0));
-        for (model_name, model) in old_models {
-            if !new.models.contains_key(model_name) {
-                up.push_str(&format!("DROP TABLE {};\n\n", model_name.to_lowercase()));
-                down.push_str(&format!("CREATE TABLE {} (\n", model_name.to_lowercase()));
-                let mut first = true;
-                for (field_name, field) in &model.fields {
-                    if !first {
-                        down.push_str(",\n");
-                    }
-                    first = false;
-                    let db_type = field.db_type.as_deref().ok_or_else(|| {
-                        anyhow!("missing db_type for {}.{}", model_name, field_name)
-                    })?;
-                    down.push_str(&format!("    {} {}", field_name, db_type));
-                    if !field.nullable {
-                        down.push_str(" NOT NULL");
-                    }
-                    if let Some(def) = &field.default {
-                        down.push_str(&format!(" DEFAULT {}", def));
-                    }
-                }
-                down.push_str("\n);\n\n");
-            }
-        }
-
-        // Existing models - field diffs
-        let mut new_models: Vec<_> = new.models.iter().collect();
-        new_models.sort_by(|a, b| a.0.cmp(b.0));
-        for (model_name, new_model) in new_models {
-            if let Some(old_model) = old_ir.models.get(model_name) {
-                use std::collections::HashSet;
-                let mut handled_new = HashSet::new();
-                let mut handled_old = HashSet::new();
-
-                // Renamed fields
-                let mut new_fields: Vec<_> = new_model.fields.iter().collect();
-                new_fields.sort_by(|a, b| a.0.cmp(b.0));
-                for (new_name, new_field) in new_fields {
-                    if let Some(old_name) = &new_field.rename_from {
-                        if let Some(old_field) = old_model.fields.get(old_name) {
-                            up.push_str(&format!(
-                                "ALTER TABLE {} RENAME COLUMN {} TO {};\n",
-                                model_name.to_lowercase(),
-                                old_name,
-                                new_name
-                            ));
-                            down.push_str(&format!(
-                                "ALTER TABLE {} RENAME COLUMN {} TO {};\n",
-                                model_name.to_lowercase(),
-                                new_name,
-                                old_name
-                            ));
-
-                            if old_field.db_type != new_field.db_type {
-                                up.push_str(&format!(
-                                    "ALTER TABLE {} ALTER COLUMN {} TYPE {};\n",
-                                    model_name.to_lowercase(),
-                                    new_name,
-                                    new_field.db_type.as_deref().ok_or_else(|| anyhow!(
-                                        "missing db_type for {}.{}",
-                                        model_name,
-                                        new_name
-                                    ))?
-                                ));
-                                down.push_str(&format!(
-                                    "ALTER TABLE {} ALTER COLUMN {} TYPE {};\n",
-                                    model_name.to_lowercase(),
-                                    new_name,
-                                    old_field.db_type.as_deref().ok_or_else(|| anyhow!(
-                                        "missing db_type for {}.{}",
-                                        model_name,
-                                        new_name
-                                    ))?
-                                ));
-                            }
-                            if old_field.default != new_field.default {
-                                match &new_field.default {
-                                    Some(def) => up.push_str(&format!(
-                                        "ALTER TABLE {} ALTER COLUMN {} SET DEFAULT {};\n",
-                                        model_name.to_lowercase(),
-                                        new_name,
-                                        def
-                                    )),
-                                    None => up.push_str(&format!(
-                                        "ALTER TABLE {} ALTER COLUMN {} DROP DEFAULT;\n",
-                                        model_name.to_lowercase(),
-                                        new_name
-                                    )),
-                                }
-                                match &old_field.default {
-                                    Some(def) => down.push_str(&format!(
-                                        "ALTER TABLE {} ALTER COLUMN {} SET DEFAULT {};\n",
-                                        model_name.to_lowercase(),
-                                        new_name,
-                                        def
-                                    )),
-                                    None => down.push_str(&format!(
-                                        "ALTER TABLE {} ALTER COLUMN {} DROP DEFAULT;\n",
-                                        model_name.to_lowercase(),
-                                        new_name
-                                    )),
-                                }
-                            }
-                            if old_field.nullable != new_field.nullable {
-                                if new_field.nullable {
-                                    up.push_str(&format!(
-                                        "ALTER TABLE {} ALTER COLUMN {} DROP NOT NULL;\n",
-                                        model_name.to_lowercase(),
-                                        new_name
-                                    ));
-                                } else {
-                                    up.push_str(&format!(
-                                        "ALTER TABLE {} ALTER COLUMN {} SET NOT NULL;\n",
-                                        model_name.to_lowercase(),
-                                        new_name
-                                    ));
-                                }
-                                if old_field.nullable {
-                                    down.push_str(&format!(
-                                        "ALTER TABLE {} ALTER COLUMN {} DROP NOT NULL;\n",
-                                        model_name.to_lowercase(),
-                                        new_name
-                                    ));
-                                } else {
-                                    down.push_str(&format!(
-                                        "ALTER TABLE {} ALTER COLUMN {} SET NOT NULL;\n",
-                                        model_name.to_lowercase(),
-                                        new_name
-                                    ));
-                                }
-                            }
-
-                            handled_new.insert(new_name.clone());
-                            handled_old.insert(old_name.clone());
-                        }
-                    }
-                }
-
-                // Added fields
-                let mut new_fields: Vec<_> = new_model.fields.iter().collect();
-                new_fields.sort_by(|a, b| a.0.cmp(b.0));
-                for (field_name, field) in new_fields {
-                    if handled_new.contains(field_name) {
-                        continue;
-                    }
-                    if !old_model.fields.contains_key(field_name) {
-                        let db_type = field.db_type.as_deref().ok_or_else(|| {
-                            anyhow!("missing db_type for {}.{}", model_name, field_name)
-                        })?;
-                        up.push_str(&format!(
-                            "ALTER TABLE {} ADD COLUMN {} {}",
-                            model_name.to_lowercase(),
-                            field_name,
-                            db_type
-                        ));
-                        if !field.nullable {
-                            up.push_str(" NOT NULL");
-                        }
-                        if let Some(def) = &field.default {
-                            up.push_str(&format!(" DEFAULT {}", def));
-                        }
-                        up.push_str(";\n");
-                        down.push_str(&format!(
-                            "ALTER TABLE {} DROP COLUMN {};\n",
-                            model_name.to_lowercase(),
-                            field_name
-                        ));
-                    }
-                }
-
-                // Removed fields
-                let mut old_fields: Vec<_> = old_model.fields.iter().collect();
-                old_fields.sort_by(|a, b| a.0.cmp(b.0));
-                for (field_name, field) in old_fields {
-                    if handled_old.contains(field_name) {
-                        continue;
-                    }
-                    if !new_model.fields.contains_key(field_name) {
-                        up.push_str(&format!(
-                            "ALTER TABLE {} DROP COLUMN {};\n",
-                            model_name.to_lowercase(),
-                            field_name
-                        ));
-                        let db_type = field.db_type.as_deref().ok_or_else(|| {
-                            anyhow!("missing db_type for {}.{}", model_name, field_name)
-                        })?;
-                        down.push_str(&format!(
-                            "ALTER TABLE {} ADD COLUMN {} {}",
-                            model_name.to_lowercase(),
-                            field_name,
-                            db_type
-                        ));
-                        if !field.nullable {
-                            down.push_str(" NOT NULL");
-                        }
-                        if let Some(def) = &field.default {
-                            down.push_str(&format!(" DEFAULT {}", def));
-                        }
-                        down.push_str(";\n");
-                    }
-                }
-
-                // Modified fields
-                let mut new_fields: Vec<_> = new_model.fields.iter().collect();
-                new_fields.sort_by(|a, b| a.0.cmp(b.0));
-                for (field_name, new_field) in new_fields {
-                    if handled_new.contains(field_name) {
-                        continue;
-                    }
-                    if let Some(old_field) = old_model.fields.get(field_name) {
-                        if old_field.db_type != new_field.db_type {
-                            up.push_str(&format!(
-                                "ALTER TABLE {} ALTER COLUMN {} TYPE {};\n",
-                                model_name.to_lowercase(),
-                                field_name,
-                                new_field.db_type.as_deref().ok_or_else(|| anyhow!(
-                                    "missing db_type for {}.{}",
-                                    model_name,
-                                    field_name
-                                ))?
-                            ));
-                            down.push_str(&format!(
-                                "ALTER TABLE {} ALTER COLUMN {} TYPE {};\n",
-                                model_name.to_lowercase(),
-                                field_name,
-                                old_field.db_type.as_deref().ok_or_else(|| anyhow!(
-                                    "missing db_type for {}.{}",
-                                    model_name,
-                                    field_name
-                                ))?
-                            ));
-                        }
-                        if old_field.default != new_field.default {
-                            match &new_field.default {
-                                Some(def) => up.push_str(&format!(
-                                    "ALTER TABLE {} ALTER COLUMN {} SET DEFAULT {};\n",
-                                    model_name.to_lowercase(),
-                                    field_name,
-                                    def
-                                )),
-                                None => up.push_str(&format!(
-                                    "ALTER TABLE {} ALTER COLUMN {} DROP DEFAULT;\n",
-                                    model_name.to_lowercase(),
-                                    field_name
-                                )),
-                            }
-                            match &old_field.default {
-                                Some(def) => down.push_str(&format!(
-                                    "ALTER TABLE {} ALTER COLUMN {} SET DEFAULT {};\n",
-                                    model_name.to_lowercase(),
-                                    field_name,
-                                    def
-                                )),
-                                None => down.push_str(&format!(
-                                    "ALTER TABLE {} ALTER COLUMN {} DROP DEFAULT;\n",
-                                    model_name.to_lowercase(),
-                                    field_name
-                                )),
-                            }
-                        }
-                        if old_field.nullable != new_field.nullable {
-                            if new_field.nullable {
-                                up.push_str(&format!(
-                                    "ALTER TABLE {} ALTER COLUMN {} DROP NOT NULL;\n",
-                                    model_name.to_lowercase(),
-                                    field_name
-                                ));
-                            } else {
-                                up.push_str(&format!(
-                                    "ALTER TABLE {} ALTER COLUMN {} SET NOT NULL;\n",
-                                    model_name.to_lowercase(),
-                                    field_name
-                                ));
-                            }
-                            if old_field.nullable {
-                                down.push_str(&format!(
-                                    "ALTER TABLE {} ALTER COLUMN {} DROP NOT NULL;\n",
-                                    model_name.to_lowercase(),
-                                    field_name
-                                ));
-                            } else {
-                                down.push_str(&format!(
-                                    "ALTER TABLE {} ALTER COLUMN {} SET NOT NULL;\n",
-                                    model_name.to_lowercase(),
-                                    field_name
-                                ));
-                            }
-                        }
-                    }
-                }
-
-                // Index changes
-                let mut new_indexes: Vec<_> = new_model.indexes.iter().collect();
-                new_indexes.sort_by(|a, b| a.0.cmp(b.0));
-                for (idx_name, new_idx) in new_indexes {
-                    match old_model.indexes.get(idx_name) {
-                        None => {
-                            up.push_str(&format!(
-                                "CREATE {}INDEX {} ON {} ({});\n",
-                                if new_idx.unique { "UNIQUE " } else { "" },
-                                idx_name,
-                                model_name.to_lowercase(),
-                                new_idx.fields.join(", ")
-                            ));
-                            down.push_str(&drop_index_stmt(
-                                new.meta.db_backend.clone(),
-                                &model_name.to_lowercase(),
-                                idx_name,
-                            ));
-                        }
-                        Some(old_idx) => {
-                            if old_idx.fields != new_idx.fields || old_idx.unique != new_idx.unique
-                            {
-                                up.push_str(&drop_index_stmt(
-                                    new.meta.db_backend.clone(),
-                                    &model_name.to_lowercase(),
-                                    idx_name,
-                                ));
-                                up.push_str(&format!(
-                                    "CREATE {}INDEX {} ON {} ({});\n",
-                                    if new_idx.unique { "UNIQUE " } else { "" },
-                                    idx_name,
-                                    model_name.to_lowercase(),
-                                    new_idx.fields.join(", ")
-                                ));
-                                down.push_str(&drop_index_stmt(
-                                    new.meta.db_backend.clone(),
-                                    &model_name.to_lowercase(),
-                                    idx_name,
-                                ));
-                                down.push_str(&format!(
-                                    "CREATE {}INDEX {} ON {} ({});\n",
-                                    if old_idx.unique { "UNIQUE " } else { "" },
-                                    idx_name,
-                                    model_name.to_lowercase(),
-                                    old_idx.fields.join(", ")
-                                ));
-                            }
-                        }
-                    }
-                }
-
-                let mut old_indexes: Vec<_> = old_model.indexes.iter().collect();
-                old_indexes.sort_by(|a, b| a.0.cmp(b.0));
-                for (idx_name, old_idx) in old_indexes {
-                    if !new_model.indexes.contains_key(idx_name) {
-                        up.push_str(&drop_index_stmt(
-                            new.meta.db_backend.clone(),
-                            &model_name.to_lowercase(),
-                            idx_name,
-                        ));
-                        down.push_str(&format!(
-                            "CREATE {}INDEX {} ON {} ({});\n",
-                            if old_idx.unique { "UNIQUE " } else { "" },
-                            idx_name,
-                            model_name.to_lowercase(),
-                            old_idx.fields.join(", ")
-                        ));
-                    }
-                }
-
-                // Unique constraint changes
-                let mut new_ucs: Vec<_> = new_model.unique_constraints.iter().collect();
-                new_ucs.sort_by(|a, b| a.0.cmp(b.0));
-                for (uc_name, new_uc) in new_ucs {
-                    match old_model.unique_constraints.get(uc_name) {
-                        None => {
-                            up.push_str(&format!(
-                                "ALTER TABLE {} ADD CONSTRAINT {} UNIQUE ({});\n",
-                                model_name.to_lowercase(),
-                                uc_name,
-                                new_uc.fields.join(", ")
-                            ));
-                            down.push_str(&drop_unique_stmt(
-                                new.meta.db_backend.clone(),
-                                &model_name.to_lowercase(),
-                                uc_name,
-                            ));
-                        }
-                        Some(old_uc) => {
-                            if old_uc.fields != new_uc.fields {
-                                up.push_str(&drop_unique_stmt(
-                                    new.meta.db_backend.clone(),
-                                    &model_name.to_lowercase(),
-                                    uc_name,
-                                ));
-                                up.push_str(&format!(
-                                    "ALTER TABLE {} ADD CONSTRAINT {} UNIQUE ({});\n",
-                                    model_name.to_lowercase(),
-                                    uc_name,
-                                    new_uc.fields.join(", ")
-                                ));
-                                down.push_str(&drop_unique_stmt(
-                                    new.meta.db_backend.clone(),
-                                    &model_name.to_lowercase(),
-                                    uc_name,
-                                ));
-                                down.push_str(&format!(
-                                    "ALTER TABLE {} ADD CONSTRAINT {} UNIQUE ({});\n",
-                                    model_name.to_lowercase(),
-                                    uc_name,
-                                    old_uc.fields.join(", ")
-                                ));
-                            }
-                        }
-                    }
-                }
-
-                // Check constraint changes
-                let mut new_cks: Vec<_> = new_model.check_constraints.iter().collect();
-                new_cks.sort_by(|a, b| a.0.cmp(b.0));
-                for (ck_name, new_ck) in new_cks {
-                    match old_model.check_constraints.get(ck_name) {
-                        None => {
-                            up.push_str(&format!(
-                                "ALTER TABLE {} ADD CONSTRAINT {} CHECK ({});\n",
-                                model_name.to_lowercase(),
-                                ck_name,
-                                new_ck.expression
-                            ));
-                            down.push_str(&drop_check_stmt(
-                                new.meta.db_backend.clone(),
-                                &model_name.to_lowercase(),
-                                ck_name,
-                            ));
-                        }
-                        Some(old_ck) => {
-                            if old_ck.expression != new_ck.expression {
-                                up.push_str(&drop_check_stmt(
-                                    new.meta.db_backend.clone(),
-                                    &model_name.to_lowercase(),
-                                    ck_name,
-                                ));
-                                up.push_str(&format!(
-                                    "ALTER TABLE {} ADD CONSTRAINT {} CHECK ({});\n",
-                                    model_name.to_lowercase(),
-                                    ck_name,
-                                    new_ck.expression
-                                ));
-                                down.push_str(&drop_check_stmt(
-                                    new.meta.db_backend.clone(),
-                                    &model_name.to_lowercase(),
-                                    ck_name,
-                                ));
-                                down.push_str(&format!(
-                                    "ALTER TABLE {} ADD CONSTRAINT {} CHECK ({});\n",
-                                    model_name.to_lowercase(),
-                                    ck_name,
-                                    old_ck.expression
-                                ));
-                            }
-                        }
-                    }
-                }
-
-                // Exclusion constraint changes
-                let mut new_exs: Vec<_> = new_model.exclusion_constraints.iter().collect();
-                new_exs.sort_by(|a, b| a.0.cmp(b.0));
-                for (ex_name, new_ex) in new_exs {
-                    match old_model.exclusion_constraints.get(ex_name) {
-                        None => {
-                            up.push_str(&format!(
-                                "ALTER TABLE {} ADD CONSTRAINT {} EXCLUDE {};\n",
-                                model_name.to_lowercase(),
-                                ex_name,
-                                new_ex.definition
-                            ));
-                            down.push_str(&format!(
-                                "ALTER TABLE {} DROP CONSTRAINT {};\n",
-                                model_name.to_lowercase(),
-                                ex_name
-                            ));
-                        }
-                        Some(old_ex) => {
-                            if old_ex.definition != new_ex.definition {
-                                up.push_str(&format!(
-                                    "ALTER TABLE {} DROP CONSTRAINT {};\n",
-                                    model_name.to_lowercase(),
-                                    ex_name
-                                ));
-                                up.push_str(&format!(
-                                    "ALTER TABLE {} ADD CONSTRAINT {} EXCLUDE {};\n",
-                                    model_name.to_lowercase(),
-                                    ex_name,
-                                    new_ex.definition
-                                ));
-                                down.push_str(&format!(
-                                    "ALTER TABLE {} DROP CONSTRAINT {};\n",
-                                    model_name.to_lowercase(),
-                                    ex_name
-                                ));
-                                down.push_str(&format!(
-                                    "ALTER TABLE {} ADD CONSTRAINT {} EXCLUDE {};\n",
-                                    model_name.to_lowercase(),
-                                    ex_name,
-                                    old_ex.definition
-                                ));
-                            }
-                        }
-                    }
-                }
-
-                let mut old_ucs: Vec<_> = old_model.unique_constraints.iter().collect();
-                old_ucs.sort_by(|a, b| a.0.cmp(b.0));
-                for (uc_name, old_uc) in old_ucs {
-                    if !new_model.unique_constraints.contains_key(uc_name) {
-                        up.push_str(&drop_unique_stmt(
-                            new.meta.db_backend.clone(),
-                            &model_name.to_lowercase(),
-                            uc_name,
-                        ));
-                        down.push_str(&format!(
-                            "ALTER TABLE {} ADD CONSTRAINT {} UNIQUE ({});\n",
-                            model_name.to_lowercase(),
-                            uc_name,
-                            old_uc.fields.join(", ")
-                        ));
-                    }
-                }
+    let reversed: Vec<(String, String)> = renames.iter().map(|(f, t)| (t.clone(), f.clone())).collect();
+    let up = evolve(enum_columns(old_ir, name), &old_labels, &new_labels, &renames);
+    let down = evolve(enum_columns(new, name), &new_labels, &old_labels, &reversed);
+    (up, down)
+}
 
-                let mut old_cks: Vec<_> = old_model.check_constraints.iter().collect();
-                old_cks.sort_by(|a, b| a.0.cmp(b.0));
-                for (ck_name, old_ck) in old_cks {
-                    if !new_model.check_constraints.contains_key(ck_name) {
-                        up.push_str(&drop_check_stmt(
-                            new.meta.db_backend.clone(),
-                            &model_name.to_lowercase(),
-                            ck_name,
-                        ));
-                        down.push_str(&format!(
-                            "ALTER TABLE {} ADD CONSTRAINT {} CHECK ({});\n",
-                            model_name.to_lowercase(),
-                            ck_name,
-                            old_ck.expression
-                        ));
-                    }
-                }
+/// Build the table definition behind a many-to-many join table: one column per
+/// side typed like that side's key, a composite primary key, and cascading
+/// foreign keys so links disappear with either row.
+fn join_table_model(ir: &ir::SchemaIR, jt: &ir::JoinTable) -> Result<ir::ModelDef> {
+    let mut model = ir::ModelDef::default();
+    let sides = [
+        (&jt.source_model, &jt.source_column),
+        (&jt.target_model, &jt.target_column),
+    ];
+    for (model_name, column) in sides {
+        let target = ir
+            .models
+            .get(model_name.as_str())
+            .ok_or_else(|| anyhow!("join table {} references unknown model {}", jt.table, model_name))?;
+        let pk_fields = target.primary_key_fields();
+        let [pk_field] = pk_fields.as_slice() else {
+            anyhow::bail!(
+                "join table {} needs a single-column primary key on {}",
+                jt.table,
+                model_name
+            );
+        };
+        let key = target
+            .fields
+            .get(pk_field)
+            .ok_or_else(|| anyhow!("missing primary key field {}.{}", model_name, pk_field))?;
+        let db_type = key
+            .db_type
+            .as_deref()
+            .ok_or_else(|| anyhow!("missing db_type for {}.{}", model_name, pk_field))?;
+        model.fields.insert(
+            column.clone(),
+            ir::FieldDef {
+                rust_type: key.rust_type.clone(),
+                db_type: Some(join_column_type(db_type)),
+                default: None,
+                nullable: false,
+                rename_from: None,
+                tags: Vec::new(),
+                zod: None,
+                storage: None,
+                column: None,
+                dropped: false,
+            },
+        );
+        model.relations.insert(
+            format!("{}_{}_fkey", unqualified(&jt.table), column),
+            ir::RelationDef {
+                on: column.clone(),
+                references: ir::FieldRef {
+                    model: model_name.clone(),
+                    field: pk_field.clone(),
+                },
+                on_delete: ir::ReferentialAction::Cascade,
+                on_update: ir::ReferentialAction::NoAction,
+                deferrable: false,
+                initially_deferred: false,
+            },
+        );
+    }
+    // The primary key already covers lookups from the source side
+    model.indexes.insert(
+        format!("{}_{}_idx", unqualified(&jt.table), jt.target_column),
+        ir::IndexDef {
+            fields: vec![jt.target_column.clone()],
+            unique: false,
+        },
+    );
+    model.primary_key = Some(ir::PrimaryKeyDef {
+        fields: vec![jt.source_column.clone(), jt.target_column.clone()],
+        generated: ir::KeyGeneration::None,
+    });
+    Ok(model)
+}
 
-                let mut old_exs: Vec<_> = old_model.exclusion_constraints.iter().collect();
-                old_exs.sort_by(|a, b| a.0.cmp(b.0));
-                for (ex_name, old_ex) in old_exs {
-                    if !new_model.exclusion_constraints.contains_key(ex_name) {
-                        up.push_str(&format!(
-                            "ALTER TABLE {} DROP CONSTRAINT {};\n",
-                            model_name.to_lowercase(),
-                            ex_name
-                        ));
-                        down.push_str(&format!(
-                            "ALTER TABLE {} ADD CONSTRAINT {} EXCLUDE {};\n",
-                            model_name.to_lowercase(),
-                            ex_name,
-                            old_ex.definition
-                        ));
-                    }
-                }
+/// Column type for a join table column referencing a key of type `db_type`.
+fn join_column_type(db_type: &str) -> String {
+    let re = Regex::new(r"(?i)\s*PRIMARY\s+KEY").unwrap();
+    let base = re.replace_all(db_type, "").trim().to_string();
+    match base.to_uppercase().as_str() {
+        "SERIAL" => "INTEGER".to_string(),
+        "BIGSERIAL" => "BIGINT".to_string(),
+        "SMALLSERIAL" => "SMALLINT".to_string(),
+        _ => base,
+    }
+}
 
-                // Relation changes
-                let mut new_rels: Vec<_> = new_model.relations.iter().collect();
-                new_rels.sort_by(|a, b| a.0.cmp(b.0));
-                for (rel_name, new_rel) in new_rels {
-                    match old_model.relations.get(rel_name) {
-                        None => {
-                            // Added relation
-                            let target = format!(
-                                "{}.{}",
-                                new_rel.references.model.to_lowercase(),
-                                new_rel.references.field
-                            );
-                            up.push_str(&format!(
-                                "ALTER TABLE {} ADD CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {}\n",
-                                model_name.to_lowercase(),
-                                rel_name,
-                                new_rel.on,
-                                target
-                            ));
-                            down.push_str(&drop_fk_stmt(
-                                new.meta.db_backend.clone(),
-                                &model_name.to_lowercase(),
-                                rel_name,
-                            ));
-                        }
-                        Some(old_rel) => {
-                            if old_rel.on != new_rel.on
-                                || old_rel.references.model != new_rel.references.model
-                                || old_rel.references.field != new_rel.references.field
-                            {
-                                // Modified relation
-                                up.push_str(&drop_fk_stmt(
-                                    new.meta.db_backend.clone(),
-                                    &model_name.to_lowercase(),
-                                    rel_name,
-                                ));
-                                up.push_str(&format!(
-                                    "ALTER TABLE {} ADD CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {}.{}\n",
-                                    model_name.to_lowercase(),
-                                    rel_name,
-                                    new_rel.on,
-                                    new_rel.references.model.to_lowercase(),
-                                    new_rel.references.field
-                                ));
-                                down.push_str(&drop_fk_stmt(
-                                    new.meta.db_backend.clone(),
-                                    &model_name.to_lowercase(),
-                                    rel_name,
-                                ));
-                                down.push_str(&format!(
-                                    "ALTER TABLE {} ADD CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {}.{}\n",
-                                    model_name.to_lowercase(),
-                                    rel_name,
-                                    old_rel.on,
-                                    old_rel.references.model.to_lowercase(),
-                                    old_rel.references.field
-                                ));
-                            }
-                        }
-                    }
-                }
+/// `CREATE TABLE` for a join table followed by its index and foreign keys.
+fn create_join_table_stmts(backend: ir::DatabaseBackend, table: &str, model: &ir::ModelDef) -> Result<String> {
+    let mut out = create_table_stmt(backend.clone(), &IndexMap::new(), table, model)?;
+    for (idx_name, idx) in &model.indexes {
+        out.push_str(&create_index_stmt(table, idx_name, idx));
+    }
+    if !inlines_constraints(backend.clone()) {
+        for (rel_name, rel) in &model.relations {
+            out.push_str(&add_fk_stmt(backend.clone(), table, rel_name, rel));
+        }
+    }
+    Ok(out)
+}
 
-                // Removed relations
-                let mut old_rels: Vec<_> = old_model.relations.iter().collect();
-                old_rels.sort_by(|a, b| a.0.cmp(b.0));
-                for (rel_name, rel) in old_rels {
-                    if !new_model.relations.contains_key(rel_name) {
-                        up.push_str(&drop_fk_stmt(
-                            new.meta.db_backend.clone(),
-                            &model_name.to_lowercase(),
-                            rel_name,
-                        ));
-                        down.push_str(&format!(
-                            "ALTER TABLE {} ADD CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {}.{}\n",
-                            model_name.to_lowercase(),
-                            rel_name,
-                            rel.on,
-                            rel.references.model.to_lowercase(),
-                            rel.references.field
-                        ));
+/// The schema as the database sees it: models keyed by table name, fields by
+/// column name and enums by type name, tables and types qualified with
+/// `meta.db_schema`, with every reference (indexes, keys, relations, join
+/// tables) rewritten to match. Migrations are generated from
+/// this view so naming rules live in one place.
+fn physical_schema(ir: &ir::SchemaIR) -> ir::SchemaIR {
+    let mut out = ir.clone();
+    out.meta.db_case_style = "preserve".into();
+    let table = |model: &str| ir.qualify(&ir.table_name(model));
+    out.enums = ir
+        .enums
+        .iter()
+        .map(|(name, en)| (ir.qualify(&ir.enum_type_name(name)), en.clone()))
+        .collect();
+    out.models = IndexMap::new();
+    for (model_name, model) in &ir.models {
+        let column = |field: &str| ir.column_name(model_name, field);
+        let columns = |fields: &[String]| fields.iter().map(|f| column(f)).collect::<Vec<_>>();
+        let mut m = model.clone();
+        m.table = None;
+        m.fields = model
+            .fields
+            .iter()
+            .map(|(name, field)| {
+                let mut f = field.clone();
+                f.column = None;
+                f.rename_from = field.rename_from.as_ref().map(|from| column(from));
+                if let Some((enum_name, _)) = field_enum(&ir.enums, field) {
+                    let ty = ir.qualify(&ir.enum_type_name(enum_name));
+                    if field.db_type.as_deref().is_some_and(|t| t.eq_ignore_ascii_case(enum_name)) {
+                        f.db_type = Some(ty.clone());
                     }
+                    f.rust_type = if field.rust_type.starts_with("Option<") {
+                        format!("Option<{}>", ty)
+                    } else {
+                        ty
+                    };
                 }
+                (column(name), f)
+            })
+            .collect();
+        for idx in m.indexes.values_mut() {
+            idx.fields = columns(&idx.fields);
+        }
+        for uc in m.unique_constraints.values_mut() {
+            uc.fields = columns(&uc.fields);
+        }
+        if let Some(pk) = &mut m.primary_key {
+            pk.fields = columns(&pk.fields);
+        }
+        for rel in m.relations.values_mut() {
+            rel.on = column(&rel.on);
+            rel.references.field = ir.column_name(&rel.references.model, &rel.references.field);
+            rel.references.model = table(&rel.references.model);
+        }
+        for (rel_name, m2m) in m.many_to_many.iter_mut() {
+            if let Some(jt) = ir.join_table(model_name, rel_name) {
+                m2m.model = table(&jt.target_model);
+                m2m.through = Some(ir.qualify(&jt.table));
+                m2m.source_column = Some(jt.source_column);
+                m2m.target_column = Some(jt.target_column);
             }
         }
+        out.models.insert(table(model_name), m);
+    }
+    out
+}
 
-        if up.trim().is_empty() {
-            Ok((String::new(), String::new()))
-        } else {
-            let mut up_tx = String::new();
-            up_tx.push_str("BEGIN;\n");
-            up_tx.push_str(&up);
-            up_tx.push_str("COMMIT;\n");
+/// [`physical_schema`] with the generated join tables added as models: every
+/// table a fully migrated database should hold.
+pub(crate) fn database_schema(ir: &ir::SchemaIR) -> Result<ir::SchemaIR> {
+    let mut out = physical_schema(ir);
+    for jt in out.join_tables() {
+        let model = join_table_model(&out, &jt)?;
+        out.models.insert(jt.table.clone(), model);
+    }
+    Ok(out)
+}
 
-            let mut down_tx = String::new();
-            down_tx.push_str("BEGIN;\n");
-            down_tx.push_str(&down);
-            down_tx.push_str("COMMIT;\n");
+/// Move `from` to `to` in `map`, keeping its position.
+fn rename_key<T>(map: &mut IndexMap<String, T>, from: &str, to: &str) {
+    if let Some(idx) = map.get_index_of(from) {
+        let (_, value) = map.shift_remove_index(idx).unwrap();
+        map.shift_insert(idx, to.to_string(), value);
+    }
+}
 
-            Ok((up_tx, down_tx))
+/// Entries of `old` missing from `new` paired with an identical entry of
+/// `new` missing from `old`: the same object under a new name.
+fn renamed_entries<T>(
+    old: &IndexMap<String, T>,
+    new: &IndexMap<String, T>,
+    same: impl Fn(&T, &T) -> bool,
+) -> Vec<(String, String)> {
+    let mut out: Vec<(String, String)> = Vec::new();
+    for (old_name, old_def) in old.iter().filter(|(n, _)| !new.contains_key(*n)) {
+        let found = new.iter().find(|(n, def)| {
+            !old.contains_key(*n) && !out.iter().any(|(_, to)| to == *n) && same(old_def, def)
+        });
+        if let Some((new_name, _)) = found {
+            out.push((old_name.clone(), new_name.clone()));
         }
-    } else {
-        generate_initial_migration(new)
     }
+    out
 }
 
-
-pub fn generate_code_multi(ir: &ir::SchemaIR, output_dir: &std::path::Path) -> anyhow::Result<()> {
-    codegen::generate_code_multi(ir, output_dir)
+/// Table or enum type name generated for `name` before `db_case_style` was
+/// applied to them.
+fn legacy_name(name: &str) -> String {
+    name.to_lowercase()
 }
 
-
-/// Introspect the connected Postgres database and build a `SchemaIR` representing
-/// the discovered tables, columns, indexes and constraints.
-pub async fn introspect_schema(
-    url: &str,
-    db_type_map: &std::collections::HashMap<String, String>,
-) -> Result<ir::SchemaIR> {
-    match infer_backend_from_url(url) {
-        Some(ir::DatabaseBackend::Postgres) => {
-            let pool = sqlx::PgPool::connect(url).await?;
-            introspect_schema_postgres(&pool, db_type_map).await
-        }
-        Some(ir::DatabaseBackend::Sqlite) => {
-            let pool = sqlx::SqlitePool::connect(url).await?;
-            introspect_schema_sqlite(&pool, db_type_map).await
+/// Renames the differ can make in place instead of dropping and recreating:
+/// models renamed with `rename_from`, and models or fields that keep their
+/// name but map to a different table or column (a changed `table`/`column`
+/// override or case style). Tables and enum types still under the lowercase
+/// names used before case styles applied (`recurringtransaction`,
+/// `accounttype`) are renamed to their `db_case_style` names. A renamed model
+/// also carries its indexes, constraints, sequences and join tables over to
+/// their new names. Returns
+/// the rename operations and `old`'s physical view with those renames
+/// applied, so the rest of the diff lines up.
+fn align_physical_names(
+    old: &ir::SchemaIR,
+    new: &ir::SchemaIR,
+) -> (Vec<SchemaOperation>, ir::SchemaIR) {
+    let backend = new.meta.db_backend.clone();
+    let renames_indexes = renames_indexes(backend.clone());
+    let renames_constraints = renames_constraints(backend.clone());
+    let mut ops = Vec::new();
+    let mut old_phys = physical_schema(old);
+    let new_phys = physical_schema(new);
+    let same_schema = old.meta.db_schema == new.meta.db_schema;
+
+    for name in new.enums.keys() {
+        let new_type = new.qualify(&new.enum_type_name(name));
+        let old_type = if old.enums.contains_key(name) {
+            old.qualify(&old.enum_type_name(name))
+        } else {
+            new.qualify(&legacy_name(name))
+        };
+        if old_type == new_type
+            || !same_schema
+            || !old_phys.enums.contains_key(&old_type)
+            || old_phys.enums.contains_key(&new_type)
+            || new_phys.enums.contains_key(&old_type)
+        {
+            continue;
         }
-        Some(ir::DatabaseBackend::Mysql) => {
-            let pool = sqlx::MySqlPool::connect(url).await?;
-            introspect_schema_mysql(&pool, db_type_map).await
+        rename_key(&mut old_phys.enums, &old_type, &new_type);
+        for field in old_phys.models.values_mut().flat_map(|m| m.fields.values_mut()) {
+            if field.db_type.as_deref().is_some_and(|t| t.eq_ignore_ascii_case(&old_type)) {
+                field.db_type = Some(new_type.clone());
+            }
+            if field.rust_type == old_type {
+                field.rust_type = new_type.clone();
+            } else if field.rust_type == format!("Option<{}>", old_type) {
+                field.rust_type = format!("Option<{}>", new_type);
+            }
         }
-        _ => Err(anyhow!("unsupported database url")),
+        ops.push(SchemaOperation::RenameEnum {
+            from: old_type,
+            to: new_type,
+        });
     }
-}
 
-async fn introspect_schema_postgres(
-    pool: &sqlx::PgPool,
-    db_type_map: &std::collections::HashMap<String, String>,
-) -> Result<ir::SchemaIR> {
-    let table_rows = sqlx::query(
-        "SELECT table_name FROM information_schema.tables \
-         WHERE table_schema = 'public' AND table_type = 'BASE TABLE'",
-    )
-    .fetch_all(pool)
-    .await?;
+    let mut moved_tables: Vec<(String, String)> = Vec::new();
+    for (model_name, new_model) in &new.models {
+        let new_table = new.qualify(&new.table_name(model_name));
+        let legacy_table = new.qualify(&legacy_name(model_name));
+        let old_name = if old.models.contains_key(model_name) {
+            model_name.as_str()
+        } else if let Some(from) = &new_model.rename_from
+            && old.models.contains_key(from)
+            && !new.models.contains_key(from)
+        {
+            from.as_str()
+        } else if let Some(legacy) = old.models.keys().find(|m| {
+            !new.models.contains_key(*m)
+                && old.qualify(&old.table_name(m)) == legacy_table
+                && !new_phys.models.contains_key(&legacy_table)
+                && !old_phys.models.contains_key(&new_table)
+        }) {
+            legacy.as_str()
+        } else {
+            continue;
+        };
+        let old_table = old.qualify(&old.table_name(old_name));
+        // A table changing `meta.db_schema` is recreated there, not renamed
+        if old_table != new_table && same_schema && !old_phys.models.contains_key(&new_table) {
+            rename_key(&mut old_phys.models, &old_table, &new_table);
+            let model = &old_phys.models[&new_table];
+            ops.push(SchemaOperation::RenameTable {
+                from: old_table.clone(),
+                to: new_table.clone(),
+            });
+            let (constraints, sequences) = table_object_renames(backend.clone(), &old_table, &new_table, model);
+            for (from, to) in constraints {
+                ops.push(SchemaOperation::RenameConstraint {
+                    table: new_table.clone(),
+                    from,
+                    to,
+                });
+            }
+            for (from, to) in sequences {
+                ops.push(SchemaOperation::RenameSequence { from, to });
+            }
+            moved_tables.push((old_table, new_table.clone()));
+        }
+        let Some(model) = old_phys.models.get_mut(&new_table) else {
+            continue;
+        };
+        for field_name in new_model.fields.keys() {
+            if !old.models[old_name].fields.contains_key(field_name) {
+                continue;
+            }
+            let old_col = old.column_name(old_name, field_name);
+            let new_col = new.column_name(model_name, field_name);
+            if old_col == new_col || model.fields.contains_key(&new_col) {
+                continue;
+            }
+            ops.push(SchemaOperation::RenameColumn {
+                table: new_table.clone(),
+                from: old_col.clone(),
+                to: new_col.clone(),
+            });
+            rename_key(&mut model.fields, &old_col, &new_col);
+            let rename = |cols: &mut Vec<String>| {
+                for c in cols.iter_mut() {
+                    if *c == old_col {
+                        *c = new_col.clone();
+                    }
+                }
+            };
+            for idx in model.indexes.values_mut() {
+                rename(&mut idx.fields);
+            }
+            for uc in model.unique_constraints.values_mut() {
+                rename(&mut uc.fields);
+            }
+            if let Some(pk) = &mut model.primary_key {
+                rename(&mut pk.fields);
+            }
+            for rel in model.relations.values_mut() {
+                if rel.on == old_col {
+                    rel.on = new_col.clone();
+                }
+            }
+        }
+
+        // A renamed model's indexes and constraints often follow the table
+        // name; rename the ones that are otherwise unchanged
+        if old_name == model_name {
+            continue;
+        }
+        let target = &new_phys.models[&new_table];
+        // Columns as the new model names them, following field renames
+        let carried = |column: &String| {
+            target
+                .fields
+                .iter()
+                .find(|(_, f)| f.rename_from.as_ref() == Some(column))
+                .map_or(column.clone(), |(name, _)| name.clone())
+        };
+        let carried_all = |columns: &[String]| columns.iter().map(carried).collect::<Vec<_>>();
+        let rename_index = |from: String, to: String| SchemaOperation::RenameIndex {
+            table: new_table.clone(),
+            from,
+            to,
+        };
+        let rename_constraint = |from: String, to: String| SchemaOperation::RenameConstraint {
+            table: new_table.clone(),
+            from,
+            to,
+        };
+        for (from, to) in renamed_entries(&model.indexes, &target.indexes, |a, b| {
+            carried_all(&a.fields) == b.fields && a.unique == b.unique
+        }) {
+            if renames_indexes {
+                rename_key(&mut model.indexes, &from, &to);
+                // Unique indexes double as unique constraints of the same name
+                rename_key(&mut model.unique_constraints, &from, &to);
+                ops.push(rename_index(from, to));
+            }
+        }
+        // MySQL unique constraints are indexes
+        let mysql = matches!(backend, ir::DatabaseBackend::Mysql);
+        for (from, to) in renamed_entries(&model.unique_constraints, &target.unique_constraints, |a, b| {
+            carried_all(&a.fields) == b.fields
+        }) {
+            if mysql && renames_indexes {
+                rename_key(&mut model.unique_constraints, &from, &to);
+                ops.push(rename_index(from, to));
+            } else if renames_constraints {
+                rename_key(&mut model.unique_constraints, &from, &to);
+                ops.push(rename_constraint(from, to));
+            }
+        }
+        for (from, to) in renamed_entries(&model.check_constraints, &target.check_constraints, |a, b| {
+            a.expression == b.expression
+        }) {
+            if renames_constraints {
+                rename_key(&mut model.check_constraints, &from, &to);
+                ops.push(rename_constraint(from, to));
+            }
+        }
+        for (from, to) in renamed_entries(&model.exclusion_constraints, &target.exclusion_constraints, |a, b| {
+            a.definition == b.definition
+        }) {
+            if renames_constraints {
+                rename_key(&mut model.exclusion_constraints, &from, &to);
+                ops.push(rename_constraint(from, to));
+            }
+        }
+        let moved = |table: &str| {
+            moved_tables
+                .iter()
+                .find(|(from, _)| from == table)
+                .map_or(table, |(_, to)| to.as_str())
+                .to_string()
+        };
+        for (from, to) in renamed_entries(&model.relations, &target.relations, |a, b| {
+            carried(&a.on) == b.on
+                && moved(&a.references.model) == b.references.model
+                && a.references.field == b.references.field
+                && a.on_delete == b.on_delete
+                && a.on_update == b.on_update
+                && a.deferrable == b.deferrable
+                && a.initially_deferred == b.initially_deferred
+        }) {
+            if renames_constraints {
+                rename_key(&mut model.relations, &from, &to);
+                ops.push(rename_constraint(from, to));
+            }
+        }
+    }
+
+    // Foreign keys follow a renamed table on every backend
+    for model in old_phys.models.values_mut() {
+        for (from, to) in &moved_tables {
+            for rel in model.relations.values_mut() {
+                if rel.references.model == *from {
+                    rel.references.model = to.clone();
+                }
+            }
+            for m2m in model.many_to_many.values_mut() {
+                if m2m.model == *from {
+                    m2m.model = to.clone();
+                }
+            }
+        }
+    }
+
+    // Join tables are named after the tables they link, so renaming a model
+    // renames its join tables and, where they follow the model, key columns
+    let old_joins = old_phys.join_tables();
+    let new_joins = new_phys.join_tables();
+    for old_jt in old_joins.iter().filter(|o| !new_joins.iter().any(|n| n.table == o.table)) {
+        let Some(new_jt) = new_joins.iter().find(|n| {
+            !old_joins.iter().any(|o| o.table == n.table)
+                && ((n.source_model == old_jt.source_model && n.target_model == old_jt.target_model)
+                    || (n.source_model == old_jt.target_model && n.target_model == old_jt.source_model))
+        }) else {
+            continue;
+        };
+        let same_way = new_jt.source_model == old_jt.source_model;
+        let columns = if same_way {
+            [
+                (&old_jt.source_column, &new_jt.source_column),
+                (&old_jt.target_column, &new_jt.target_column),
+            ]
+        } else {
+            [
+                (&old_jt.source_column, &new_jt.target_column),
+                (&old_jt.target_column, &new_jt.source_column),
+            ]
+        };
+        let (old_table, new_table) = (&old_jt.table, &new_jt.table);
+        ops.push(SchemaOperation::RenameTable {
+            from: old_table.clone(),
+            to: new_table.clone(),
+        });
+        let (old_name, new_name) = (unqualified(old_table), unqualified(new_table));
+        for (from, to) in columns.iter().filter(|(from, to)| from != to) {
+            ops.push(SchemaOperation::RenameColumn {
+                table: new_table.clone(),
+                from: from.to_string(),
+                to: to.to_string(),
+            });
+        }
+        if same_way && renames_indexes {
+            ops.push(SchemaOperation::RenameIndex {
+                table: new_table.clone(),
+                from: format!("{}_{}_idx", old_name, old_jt.target_column),
+                to: format!("{}_{}_idx", new_name, new_jt.target_column),
+            });
+        }
+        let mut constraints = vec![(format!("{}_pkey", old_name), format!("{}_pkey", new_name))];
+        for (from, to) in &columns {
+            constraints.push((
+                format!("{}_{}_fkey", old_name, from),
+                format!("{}_{}_fkey", new_name, to),
+            ));
+        }
+        for (from, to) in constraints.into_iter().filter(|_| renames_constraints) {
+            ops.push(SchemaOperation::RenameConstraint {
+                table: new_table.clone(),
+                from,
+                to,
+            });
+        }
+        for model in old_phys.models.values_mut() {
+            for m2m in model.many_to_many.values_mut() {
+                if m2m.through.as_ref() != Some(old_table) {
+                    continue;
+                }
+                m2m.through = Some(new_table.clone());
+                for column in [&mut m2m.source_column, &mut m2m.target_column].into_iter().flatten() {
+                    if let Some((_, to)) = columns.iter().find(|(from, _)| *from == column) {
+                        *column = to.to_string();
+                    }
+                }
+            }
+        }
+    }
+    (ops, old_phys)
+}
+
+/// Generate SQL for an initial migration based on the provided schema.
+pub fn generate_initial_migration(ir: &ir::SchemaIR) -> Result<(String, String)> {
+    initial_migration_sql(&physical_schema(ir))
+}
+
+/// The initial migration of a schema already in its [`physical_schema`] view.
+fn initial_migration_sql(ir: &ir::SchemaIR) -> Result<(String, String)> {
+    let backend = ir.meta.db_backend.clone();
+    let mut up_body = String::new();
+    let mut down_body = String::new();
+
+    // Left in place on the way down, other tables may live there
+    if let Some(schema) = &ir.meta.db_schema {
+        up_body.push_str(&create_schema_stmt(schema));
+    }
+
+    // Only Postgres has named enum types; MySQL inlines them per column
+    let mut drop_types = String::new();
+    if matches!(backend, ir::DatabaseBackend::Postgres) {
+        for (enum_name, en) in &ir.enums {
+            up_body.push_str(&create_enum_stmt(enum_name, &enum_labels(en)));
+            drop_types.push_str(&format!("DROP TYPE {};\n", enum_name));
+        }
+    }
+
+    // Dropped newest first; indexes and constraints go with their tables
+    for (model_name, model) in &ir.models {
+        up_body.push_str(&create_table_stmt(backend.clone(), &ir.enums, model_name, model)?);
+
+        down_body.insert_str(0, &format!("DROP TABLE {};\n\n", model_name));
+    }
+
+    // Indexes
+    for (model_name, model) in &ir.models {
+        for (idx_name, idx) in &model.indexes {
+            up_body.push_str(&create_index_stmt(model_name, idx_name, idx));
+        }
+    }
+
+    // Constraints; SQLite already declared them in CREATE TABLE
+    if !inlines_constraints(backend.clone()) {
+        for (model_name, model) in &ir.models {
+            for (uc_name, uc) in &model.unique_constraints {
+                up_body.push_str(&add_unique_stmt(model_name, uc_name, uc));
+            }
+        }
+
+        for (model_name, model) in &ir.models {
+            for (ck_name, ck) in &model.check_constraints {
+                up_body.push_str(&add_check_stmt(model_name, ck_name, ck));
+            }
+        }
+    }
+
+    // Exclusion constraints
+    if supports_exclusion(backend.clone()) {
+        for (model_name, model) in &ir.models {
+            for (ex_name, ex) in &model.exclusion_constraints {
+                up_body.push_str(&format!(
+                    "ALTER TABLE {} ADD CONSTRAINT {} EXCLUDE {};\n",
+                    model_name,
+                    ex_name,
+                    ex.definition
+                ));
+            }
+        }
+    }
+
+    // Foreign keys; dropped first so tables can go in any order
+    let mut fk_down = String::new();
+    if !inlines_constraints(backend.clone()) {
+        for (model_name, model) in &ir.models {
+            for (rel_name, rel) in &model.relations {
+                up_body.push_str(&add_fk_stmt(backend.clone(), model_name, rel_name, rel));
+                fk_down.push_str(&drop_fk_stmt(backend.clone(), model_name, rel_name));
+            }
+        }
+    }
+
+    // Join tables for many-to-many relations; dropped before the tables they link
+    let mut join_down = String::new();
+    for jt in ir.join_tables() {
+        let model = join_table_model(ir, &jt)?;
+        up_body.push('\n');
+        up_body.push_str(&create_join_table_stmts(backend.clone(), &jt.table, &model)?);
+        join_down.push_str(&format!("DROP TABLE {};\n\n", jt.table));
+    }
+    down_body.insert_str(0, &join_down);
+    down_body.insert_str(0, &fk_down);
+    // Types can only go once no table uses them
+    down_body.push_str(&drop_types);
+
+    let mut up = String::new();
+    up.push_str("BEGIN;\n");
+    up.push_str(&up_body);
+    up.push_str("COMMIT;\n");
+
+    let mut down = String::new();
+    down.push_str("BEGIN;\n");
+    down.push_str(&down_body);
+    down.push_str("COMMIT;\n");
+
+    Ok((up, down))
+}
+
+/// Generate SQL migration by diffing `old` schema against `new`.
+/// If `old` is `None`, this is equivalent to `generate_initial_migration`.
+/// See [`diff_schemas`] for the operations behind the SQL.
+pub fn generate_migration(
+    old: Option<&ir::SchemaIR>,
+    new: &ir::SchemaIR,
+) -> Result<(String, String)> {
+    diff_schemas(old, new)?.to_sql()
+}
+
+pub fn generate_code(ir: &ir::SchemaIR) -> String {
+    codegen::generate_code(ir)
+}
+
+/// Write the backend crate to `output_dir`. Migrations in `migrations_dir`
+/// are embedded and applied or checked when the server starts.
+pub fn generate_code_multi(
+    ir: &ir::SchemaIR,
+    output_dir: &std::path::Path,
+    migrations_dir: Option<&std::path::Path>,
+) -> anyhow::Result<()> {
+    codegen::generate_code_multi(ir, output_dir, migrations_dir)
+}
+
+
+/// Which tables introspection reads. The migration history and lock tables
+/// are always left out.
+#[derive(Debug, Clone, Default)]
+pub struct IntrospectOptions {
+    /// Postgres schema or MySQL database to read; `public` or the URL's
+    /// database when unset. Anything else becomes `meta.db_schema`.
+    pub schema: Option<String>,
+    /// Table name globs (`*`, `?`) to read; every table when empty
+    pub include: Vec<String>,
+    /// Table name globs to skip
+    pub exclude: Vec<String>,
+}
+
+impl IntrospectOptions {
+    /// Options reading the tables of `ir`'s `meta.db_schema`.
+    pub fn for_schema(ir: &ir::SchemaIR) -> Self {
+        IntrospectOptions {
+            schema: ir.meta.db_schema.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn includes_table(&self, table: &str) -> bool {
+        !table.starts_with("__rustdbgen")
+            && (self.include.is_empty() || self.include.iter().any(|g| glob_matches(g, table)))
+            && !self.exclude.iter().any(|g| glob_matches(g, table))
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` stands for any run of
+/// characters and `?` for one.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let re = regex::escape(pattern).replace(r"\*", ".*").replace(r"\?", ".");
+    Regex::new(&format!("^{}$", re)).is_ok_and(|re| re.is_match(name))
+}
+
+/// Introspect the connected database and build a `SchemaIR` representing
+/// the discovered tables, columns, keys, relations, enums, indexes and
+/// constraints. The result can be written out as a schema file and fed back
+/// into `generate`: diffing it against the database yields no changes.
+pub async fn introspect_schema(
+    url: &str,
+    db_type_map: &std::collections::HashMap<String, String>,
+) -> Result<ir::SchemaIR> {
+    introspect_schema_with_options(url, db_type_map, &IntrospectOptions::default()).await
+}
+
+/// [`introspect_schema`] reading the schema and tables `options` select.
+/// Foreign keys to tables left out are dropped along with them.
+pub async fn introspect_schema_with_options(
+    url: &str,
+    db_type_map: &std::collections::HashMap<String, String>,
+    options: &IntrospectOptions,
+) -> Result<ir::SchemaIR> {
+    Ok(introspect_schema_timed(url, db_type_map, options).await?.0)
+}
+
+/// How long one step of introspection took and how many rows it read.
+#[derive(Debug, Clone)]
+pub struct IntrospectTiming {
+    pub step: &'static str,
+    pub rows: usize,
+    pub elapsed: std::time::Duration,
+}
+
+/// Collects an [`IntrospectTiming`] per step, each timed from the end of
+/// the one before.
+struct Stopwatch {
+    last: std::time::Instant,
+    timings: Vec<IntrospectTiming>,
+}
+
+impl Stopwatch {
+    fn start() -> Self {
+        Stopwatch {
+            last: std::time::Instant::now(),
+            timings: Vec::new(),
+        }
+    }
+
+    fn lap(&mut self, step: &'static str, rows: usize) {
+        let now = std::time::Instant::now();
+        self.timings.push(IntrospectTiming {
+            step,
+            rows,
+            elapsed: now - self.last,
+        });
+        self.last = now;
+    }
+}
+
+/// [`introspect_schema_with_options`] along with how long connecting, each
+/// catalog query and assembling the schema took.
+pub async fn introspect_schema_timed(
+    url: &str,
+    db_type_map: &std::collections::HashMap<String, String>,
+    options: &IntrospectOptions,
+) -> Result<(ir::SchemaIR, Vec<IntrospectTiming>)> {
+    let mut stopwatch = Stopwatch::start();
+    let mut ir = match infer_backend_from_url(url) {
+        Some(ir::DatabaseBackend::Postgres) => {
+            let pool = sqlx::PgPool::connect(url).await?;
+            stopwatch.lap("connect", 0);
+            introspect_schema_postgres(&pool, db_type_map, options, &mut stopwatch).await
+        }
+        Some(ir::DatabaseBackend::Sqlite) => {
+            let pool = sqlx::SqlitePool::connect(url).await?;
+            stopwatch.lap("connect", 0);
+            introspect_schema_sqlite(&pool, db_type_map, options, &mut stopwatch).await
+        }
+        Some(ir::DatabaseBackend::Mysql) => {
+            let pool = sqlx::MySqlPool::connect(url).await?;
+            stopwatch.lap("connect", 0);
+            introspect_schema_mysql(&pool, db_type_map, options, &mut stopwatch).await
+        }
+        _ => Err(anyhow!("unsupported database url")),
+    }?;
+    let tables: HashSet<String> = ir.models.keys().cloned().collect();
+    for model in ir.models.values_mut() {
+        model.relations.retain(|_, rel| tables.contains(&rel.references.model));
+    }
+    link_enum_columns(&mut ir);
+    // the same shape `load_schema` gives the written schema
+    unique_indexes_as_constraints(&mut ir);
+    Ok((ir, stopwatch.timings))
+}
+
+/// Type columns whose database type is an introspected enum with that enum.
+fn link_enum_columns(ir: &mut ir::SchemaIR) {
+    for model in ir.models.values_mut() {
+        for field in model.fields.values_mut() {
+            let Some(db_type) = field.db_type.as_deref() else {
+                continue;
+            };
+            if let Some(name) = ir.enums.keys().find(|name| name.eq_ignore_ascii_case(db_type)) {
+                field.rust_type = name.clone();
+            }
+        }
+    }
+}
+
+/// Rust type of an introspected column. `[db_types]` entries are tried on
+/// the lowercased full type (`numeric(15,2)`), then as patterns where `*`
+/// stands for anything (`numeric(*,*)`, the longest matching pattern
+/// winning), then on the base type (`numeric`). Arrays without an entry of
+/// their own become a `Vec` of their element type.
+fn introspected_rust_type(
+    backend: &ir::DatabaseBackend,
+    db_type_map: &std::collections::HashMap<String, String>,
+    db_type: &str,
+) -> String {
+    let lower = db_type.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let lower = lower.replace(", ", ",");
+    let entry = |key: &str| {
+        db_type_map
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, ty)| ty.clone())
+    };
+    if let Some(ty) = entry(&lower) {
+        return ty;
+    }
+    let pattern = db_type_map
+        .iter()
+        .filter(|(k, _)| k.contains('*') && glob_matches(&k.to_lowercase().replace(", ", ","), &lower))
+        .max_by_key(|(k, _)| (k.len(), k.as_str()));
+    if let Some((_, ty)) = pattern {
+        return ty.clone();
+    }
+    if let Some(element) = lower.strip_suffix("[]") {
+        return format!("Vec<{}>", introspected_rust_type(backend, db_type_map, element));
+    }
+    // `numeric(15,2)`, `timestamp(3) with time zone`, MySQL `int unsigned`
+    let unsigned = lower.contains(" unsigned");
+    let base = match lower.split_once('(') {
+        Some((head, rest)) => format!("{}{}", head, rest.split_once(')').map_or("", |(_, tail)| tail)),
+        None => lower.clone(),
+    };
+    let base = base.replace(" unsigned", "").replace(" zerofill", "").trim().to_string();
+    if let Some(ty) = entry(&base) {
+        return ty;
+    }
+    let ty = match base.as_str() {
+        "tinyint" if lower == "tinyint(1)" => "bool",
+        "tinyint" if unsigned => "u8",
+        "smallint" if unsigned => "u16",
+        "int" | "integer" | "mediumint" if unsigned => "u32",
+        "bigint" if unsigned => "u64",
+        "smallint" | "int2" | "smallserial" => "i16",
+        // SQLite integers are 64 bit
+        "integer" | "int" if matches!(backend, ir::DatabaseBackend::Sqlite) => "i64",
+        "integer" | "int" | "int4" | "mediumint" | "serial" => "i32",
+        "bigint" | "int8" | "bigserial" => "i64",
+        "tinyint" => "i8",
+        "boolean" | "bool" => "bool",
+        "real" | "float4" | "float" => "f32",
+        "double precision" | "float8" | "double" => "f64",
+        "numeric" | "decimal" => "Decimal",
+        "uuid" => "Uuid",
+        "timestamp with time zone" | "timestamptz" | "timestamp" | "datetime" => "DateTime<Utc>",
+        "timestamp without time zone" => "chrono::NaiveDateTime",
+        "date" => "chrono::NaiveDate",
+        "json" | "jsonb" => "serde_json::Value",
+        "bytea" | "blob" | "binary" | "varbinary" => "Vec<u8>",
+        _ => "String",
+    };
+    ty.to_string()
+}
+
+/// The full type of a Postgres column from its `information_schema.columns`
+/// row: `character varying(255)`, `numeric(15,2)`, `timestamp(3) with time
+/// zone`, `integer[]`, or the name of an enum or other type of its own,
+/// qualified when it lives outside `schema`.
+fn postgres_column_type(row: &sqlx::postgres::PgRow, schema: &str) -> String {
+    let data_type: String = row.get("data_type");
+    let udt_schema: String = row.get("udt_schema");
+    let udt_name: String = row.get("udt_name");
+    let length: Option<i32> = row.get("length");
+    let precision: Option<i32> = row.get("precision");
+    let scale: Option<i32> = row.get("scale");
+    let datetime_precision: Option<i32> = row.get("datetime_precision");
+    match data_type.as_str() {
+        "USER-DEFINED" if udt_schema == schema || udt_schema == "pg_catalog" => udt_name,
+        "USER-DEFINED" => format!("{}.{}", udt_schema, udt_name),
+        // element types are only known by their internal names
+        "ARRAY" => {
+            let element = udt_name.trim_start_matches('_');
+            let element = match element {
+                "int2" => "smallint",
+                "int4" => "integer",
+                "int8" => "bigint",
+                "float4" => "real",
+                "float8" => "double precision",
+                "bool" => "boolean",
+                "varchar" => "character varying",
+                "bpchar" => "character",
+                "timestamptz" => "timestamp with time zone",
+                "timestamp" => "timestamp without time zone",
+                "timetz" => "time with time zone",
+                "time" => "time without time zone",
+                other => other,
+            };
+            format!("{}[]", element)
+        }
+        "character varying" | "character" | "bit" | "bit varying" => match length {
+            Some(n) => format!("{}({})", data_type, n),
+            None => data_type,
+        },
+        "numeric" => match (precision, scale) {
+            (Some(p), Some(s)) => format!("numeric({},{})", p, s),
+            (Some(p), None) => format!("numeric({})", p),
+            _ => data_type,
+        },
+        // 6 is the default precision, which Postgres does not spell out
+        t if t.starts_with("time") => match datetime_precision.filter(|&p| p != 6) {
+            Some(p) => match t.split_once(' ') {
+                Some((base, zone)) => format!("{}({}) {}", base, p, zone),
+                None => format!("{}({})", t, p),
+            },
+            None => data_type,
+        },
+        _ => data_type,
+    }
+}
+
+/// A column default as the schema would spell it. Sequence defaults of
+/// serial columns and `NULL` mean no default, and Postgres casts of literals
+/// (`'a'::text`, `'-1'::integer`) are dropped. MySQL reports string defaults
+/// unquoted, so `quote` quotes those.
+fn introspected_default(default: &str, quote: bool) -> Option<String> {
+    let cast = Regex::new(r#"^('(?:[^']|'')*'|-?[\d.]+|NULL)::([\w\s."]+)(\[\])?$"#).unwrap();
+    let number = Regex::new(r"^'(-?[\d.]+)'$").unwrap();
+    let mut d = default.trim().to_string();
+    while let Some(caps) = cast.captures(&d) {
+        let numeric = matches!(
+            caps[2].trim(),
+            "smallint" | "integer" | "bigint" | "numeric" | "real" | "double precision"
+        );
+        d = match number.captures(&caps[1]) {
+            Some(n) if numeric => n[1].to_string(),
+            _ => caps[1].to_string(),
+        };
+    }
+    if d.starts_with("nextval(") || d.eq_ignore_ascii_case("NULL") {
+        return None;
+    }
+    if quote && !d.starts_with('\'') {
+        return Some(format!("'{}'", d.replace('\'', "''")));
+    }
+    Some(d)
+}
+
+/// A check constraint's expression without the parentheses and backquotes
+/// the database adds around it.
+fn introspected_check(expression: &str) -> String {
+    let mut e = expression.trim();
+    e = e.strip_prefix("CHECK").map(str::trim).unwrap_or(e);
+    while drift::wrapped_in_parens(e) {
+        e = e[1..e.len() - 1].trim();
+    }
+    e.replace('`', "")
+}
+
+/// Build a `PrimaryKeyDef` from introspected key columns, marking it as
+/// identity when every key column is database-generated.
+fn introspected_primary_key(
+    fields: Vec<String>,
+    generated_columns: &[String],
+) -> Option<ir::PrimaryKeyDef> {
+    if fields.is_empty() {
+        return None;
+    }
+    let generated = if fields.iter().all(|f| generated_columns.contains(f)) {
+        ir::KeyGeneration::Identity
+    } else {
+        ir::KeyGeneration::None
+    };
+    Some(ir::PrimaryKeyDef { fields, generated })
+}
+
+async fn introspect_schema_postgres(
+    pool: &sqlx::PgPool,
+    db_type_map: &std::collections::HashMap<String, String>,
+    options: &IntrospectOptions,
+    stopwatch: &mut Stopwatch,
+) -> Result<ir::SchemaIR> {
+    let schema = options.schema.as_deref().unwrap_or("public");
+    // one query per kind of catalog entry for the whole schema, so large
+    // schemas on remote servers do not pay a round trip per table
+    let table_rows = sqlx::query(
+        "SELECT table_name FROM information_schema.tables \
+         WHERE table_schema = $1 AND table_type = 'BASE TABLE' \
+         ORDER BY table_name",
+    )
+    .bind(schema)
+    .fetch_all(pool)
+    .await?;
+    stopwatch.lap("tables", table_rows.len());
 
     let enum_rows = sqlx::query(
-        "SELECT t.typname, e.enumlabel FROM pg_type t \n             JOIN pg_enum e ON t.oid = e.enumtypid \n             JOIN pg_namespace n ON n.oid = t.typnamespace \n             WHERE n.nspname = 'public' ORDER BY t.typname, e.enumsortorder",
+        "SELECT t.typname, e.enumlabel FROM pg_type t \
+         JOIN pg_enum e ON t.oid = e.enumtypid \
+         JOIN pg_namespace n ON n.oid = t.typnamespace \
+         WHERE n.nspname = $1 \
+         ORDER BY t.typname, e.enumsortorder",
+    )
+    .bind(schema)
+    .fetch_all(pool)
+    .await?;
+    stopwatch.lap("enums", enum_rows.len());
+
+    let column_rows = sqlx::query(
+        "SELECT table_name, column_name, data_type, udt_schema, udt_name, is_nullable, column_default, is_identity, \
+         character_maximum_length::int AS length, numeric_precision::int AS precision, \
+         numeric_scale::int AS scale, datetime_precision::int AS datetime_precision \
+         FROM information_schema.columns \
+         WHERE table_schema = $1 \
+         ORDER BY table_name, ordinal_position",
+    )
+    .bind(schema)
+    .fetch_all(pool)
+    .await?;
+    stopwatch.lap("columns", column_rows.len());
+
+    // primary keys and indexes using pg_index and pg_attribute for
+    // reliability, without the indexes behind unique and exclusion
+    // constraints
+    let index_rows = sqlx::query(
+        "SELECT t.relname AS table_name, i.relname AS indexname, ix.indisprimary, ix.indisunique, a.attname \
+         FROM pg_class t \
+         JOIN pg_namespace n ON n.oid = t.relnamespace \
+         JOIN pg_index ix ON t.oid = ix.indrelid \
+         JOIN pg_class i ON i.oid = ix.indexrelid \
+         JOIN LATERAL unnest(ix.indkey) WITH ORDINALITY AS ord(attnum, ordinality) ON true \
+         JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = ord.attnum \
+         WHERE n.nspname = $1 AND (ix.indisprimary OR NOT EXISTS \
+         (SELECT 1 FROM pg_constraint c WHERE c.conindid = ix.indexrelid AND c.contype IN ('u', 'x'))) \
+         ORDER BY table_name, indexname, ord.ordinality",
+    )
+    .bind(schema)
+    .fetch_all(pool)
+    .await?;
+    stopwatch.lap("indexes", index_rows.len());
+
+    let uc_rows = sqlx::query(
+        "SELECT tc.table_name, tc.constraint_name, kcu.column_name FROM information_schema.table_constraints tc \
+         JOIN information_schema.key_column_usage kcu ON tc.constraint_name = kcu.constraint_name \
+         AND tc.table_schema = kcu.table_schema AND tc.table_name = kcu.table_name \
+         WHERE tc.table_schema = $1 AND tc.constraint_type = 'UNIQUE' \
+         ORDER BY tc.table_name, tc.constraint_name, kcu.ordinal_position",
+    )
+    .bind(schema)
+    .fetch_all(pool)
+    .await?;
+    stopwatch.lap("unique", uc_rows.len());
+
+    // check and exclusion constraints, without the NOT NULL ones
+    // information_schema lists
+    let constraint_rows = sqlx::query(
+        "SELECT t.relname AS table_name, c.conname, c.contype::text AS contype, \
+         pg_get_constraintdef(c.oid) AS def FROM pg_constraint c \
+         JOIN pg_class t ON t.oid = c.conrelid \
+         JOIN pg_namespace n ON n.oid = t.relnamespace \
+         WHERE n.nspname = $1 AND c.contype IN ('c', 'x') \
+         ORDER BY table_name, c.conname",
+    )
+    .bind(schema)
+    .fetch_all(pool)
+    .await?;
+    stopwatch.lap("checks", constraint_rows.len());
+
+    // each key column paired with the referenced column at the same
+    // position of the key it points at
+    let fk_rows = sqlx::query(
+        "SELECT tc.table_name, tc.constraint_name, kcu.column_name, kcu.ordinal_position::int AS position, \
+         rkcu.table_name AS foreign_table, \
+         rkcu.column_name AS foreign_column, rc.update_rule, rc.delete_rule, tc.is_deferrable, tc.initially_deferred \
+         FROM information_schema.table_constraints tc \
+         JOIN information_schema.key_column_usage kcu ON tc.constraint_name = kcu.constraint_name \
+         AND tc.table_schema = kcu.table_schema AND tc.table_name = kcu.table_name \
+         JOIN information_schema.referential_constraints rc ON rc.constraint_name = tc.constraint_name AND rc.constraint_schema = tc.table_schema \
+         JOIN information_schema.key_column_usage rkcu ON rkcu.constraint_name = rc.unique_constraint_name \
+         AND rkcu.constraint_schema = rc.unique_constraint_schema \
+         AND rkcu.ordinal_position = kcu.position_in_unique_constraint \
+         WHERE tc.table_schema = $1 AND tc.constraint_type = 'FOREIGN KEY' \
+         ORDER BY tc.table_name, tc.constraint_name, kcu.ordinal_position",
     )
+    .bind(schema)
     .fetch_all(pool)
     .await?;
-    let mut enums: HashMap<String, ir::EnumDef> = HashMap::new();
+    stopwatch.lap("foreign keys", fk_rows.len());
+
+    let mut enums: IndexMap<String, ir::EnumDef> = IndexMap::new();
     for r in enum_rows {
         let name: String = r.get("typname");
         let label: String = r.get("enumlabel");
@@ -1139,511 +1612,747 @@ async fn introspect_schema_postgres(
             .entry(name)
             .or_insert_with(|| ir::EnumDef { variants: vec![] })
             .variants
-            .push(label);
+            .push(label.into());
     }
 
-    let mut models = HashMap::new();
-
-    for row in table_rows {
-        let table_name: String = row.get("table_name");
-        let column_rows = sqlx::query(
-            "SELECT column_name, data_type, is_nullable, column_default \
-             FROM information_schema.columns \
-             WHERE table_schema='public' AND table_name=$1 \
-             ORDER BY ordinal_position",
-        )
-        .bind(&table_name)
-        .fetch_all(pool)
-        .await?;
+    let mut models: IndexMap<String, ir::ModelDef> = table_rows
+        .iter()
+        .map(|row| row.get::<String, _>("table_name"))
+        .filter(|table| options.includes_table(table))
+        .map(|table| (table, ir::ModelDef::default()))
+        .collect();
 
-        let mut fields = HashMap::new();
-        for c in column_rows {
-            let col_name: String = c.get("column_name");
-            let data_type: String = c.get("data_type");
-            let nullable: String = c.get("is_nullable");
-            let default: Option<String> = c.try_get("column_default").ok();
-            let rust_type = db_type_map
-                .get(&data_type)
-                .cloned()
-                .unwrap_or_else(|| "String".to_string());
-            fields.insert(
-                col_name,
-                ir::FieldDef {
-                    rust_type: rust_type.to_string(),
-                    db_type: Some(data_type),
-                    default,
-                    nullable: nullable == "YES",
-                    rename_from: None,
-                    tags: Vec::new(),
-                    zod: None,
-                    storage: None,
-                },
-            );
-        }
+    let mut identity_columns: HashMap<String, Vec<String>> = HashMap::new();
+    for c in column_rows {
+        let table: String = c.get("table_name");
+        let Some(model) = models.get_mut(&table) else {
+            continue;
+        };
+        let col_name: String = c.get("column_name");
+        let data_type = postgres_column_type(&c, schema);
+        let nullable: String = c.get("is_nullable");
+        let default: Option<String> = c.try_get("column_default").ok().flatten();
+        let is_identity: String = c.get("is_identity");
+        // identity and serial columns
+        if is_identity == "YES" || default.as_deref().is_some_and(|d| d.starts_with("nextval(")) {
+            identity_columns.entry(table).or_default().push(col_name.clone());
+        }
+        let rust_type = introspected_rust_type(&ir::DatabaseBackend::Postgres, db_type_map, &data_type);
+        model.fields.insert(
+            col_name,
+            ir::FieldDef {
+                rust_type,
+                db_type: Some(data_type),
+                default: default.and_then(|d| introspected_default(&d, false)),
+                nullable: nullable == "YES",
+                rename_from: None,
+                tags: Vec::new(),
+                zod: None,
+                storage: None,
+                column: None,
+                dropped: false,
+            },
+        );
+    }
 
-        // indexes using pg_index and pg_attribute for reliability
-        let index_rows = sqlx::query(
-            "SELECT i.relname AS indexname, ix.indisunique, a.attname, ord.ordinality \
-             FROM pg_class t \
-             JOIN pg_index ix ON t.oid = ix.indrelid \
-             JOIN pg_class i ON i.oid = ix.indexrelid \
-             JOIN LATERAL unnest(ix.indkey) WITH ORDINALITY AS ord(attnum, ordinality) ON true \
-             JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = ord.attnum \
-             WHERE t.relname = $1 AND NOT ix.indisprimary \
-             ORDER BY indexname, ordinality",
-        )
-        .bind(&table_name)
-        .fetch_all(pool)
-        .await?;
-        let mut indexes: HashMap<String, ir::IndexDef> = HashMap::new();
-        for r in index_rows {
-            let name: String = r.get("indexname");
-            let col: String = r.get("attname");
-            let unique: bool = r.get("indisunique");
-            indexes
-                .entry(name)
-                .or_insert_with(|| ir::IndexDef {
-                    fields: Vec::new(),
-                    unique,
-                })
-                .fields
-                .push(col);
+    // primary key columns in key order
+    let mut pk_fields: HashMap<String, Vec<String>> = HashMap::new();
+    for r in index_rows {
+        let table: String = r.get("table_name");
+        let Some(model) = models.get_mut(&table) else {
+            continue;
+        };
+        let col: String = r.get("attname");
+        if r.get::<bool, _>("indisprimary") {
+            pk_fields.entry(table).or_default().push(col);
+            continue;
         }
+        let name: String = r.get("indexname");
+        let unique: bool = r.get("indisunique");
+        model
+            .indexes
+            .entry(name)
+            .or_insert_with(|| ir::IndexDef {
+                fields: Vec::new(),
+                unique,
+            })
+            .fields
+            .push(col);
+    }
+    for (table, model) in models.iter_mut() {
+        model.primary_key = introspected_primary_key(
+            pk_fields.remove(table).unwrap_or_default(),
+            identity_columns.get(table).map(Vec::as_slice).unwrap_or_default(),
+        );
+    }
 
-        // unique constraints
-        let uc_rows = sqlx::query(
-            "SELECT tc.constraint_name, kcu.column_name FROM information_schema.table_constraints tc \
-             JOIN information_schema.key_column_usage kcu ON tc.constraint_name = kcu.constraint_name AND tc.table_name = kcu.table_name \
-             WHERE tc.table_schema='public' AND tc.table_name=$1 AND tc.constraint_type='UNIQUE' \
-             ORDER BY kcu.ordinal_position",
-        )
-        .bind(&table_name)
-        .fetch_all(pool)
-        .await?;
-        let mut unique_constraints: HashMap<String, ir::UniqueConstraintDef> = HashMap::new();
-        for r in uc_rows {
-            let name: String = r.get("constraint_name");
-            let col: String = r.get("column_name");
-            unique_constraints
-                .entry(name)
-                .or_insert_with(|| ir::UniqueConstraintDef { fields: vec![] })
-                .fields
-                .push(col);
-        }
+    for r in uc_rows {
+        let table: String = r.get("table_name");
+        let Some(model) = models.get_mut(&table) else {
+            continue;
+        };
+        let name: String = r.get("constraint_name");
+        let col: String = r.get("column_name");
+        model
+            .unique_constraints
+            .entry(name)
+            .or_insert_with(|| ir::UniqueConstraintDef { fields: vec![] })
+            .fields
+            .push(col);
+    }
 
-        // check constraints
-        let ck_rows = sqlx::query(
-            "SELECT tc.constraint_name, cc.check_clause FROM information_schema.table_constraints tc \
-             JOIN information_schema.check_constraints cc ON tc.constraint_name = cc.constraint_name \
-             WHERE tc.table_schema='public' AND tc.table_name=$1 AND tc.constraint_type='CHECK'",
-        )
-        .bind(&table_name)
-        .fetch_all(pool)
-        .await?;
-        let mut check_constraints = HashMap::new();
-        for r in ck_rows {
-            let name: String = r.get("constraint_name");
-            let expr: String = r.get("check_clause");
-            check_constraints.insert(name, ir::CheckConstraintDef { expression: expr });
-        }
-
-        // exclusion constraints
-        let ex_rows = sqlx::query(
-            "SELECT conname, pg_get_constraintdef(oid) AS def FROM pg_constraint \
-             WHERE contype = 'x' AND conrelid = $1::regclass",
-        )
-        .bind(&table_name)
-        .fetch_all(pool)
-        .await?;
-        let mut exclusion_constraints = HashMap::new();
-        for r in ex_rows {
-            let name: String = r.get("conname");
-            let def: String = r.get("def");
+    for r in constraint_rows {
+        let table: String = r.get("table_name");
+        let Some(model) = models.get_mut(&table) else {
+            continue;
+        };
+        let name: String = r.get("conname");
+        let def: String = r.get("def");
+        if r.get::<String, _>("contype") == "x" {
             let definition = def
                 .split_once("EXCLUDE")
                 .map(|(_, rest)| rest)
                 .unwrap_or("")
                 .trim()
                 .to_string();
-            exclusion_constraints.insert(name, ir::ExclusionConstraintDef { definition });
-        }
-
-        // foreign keys
-        let fk_rows = sqlx::query(
-            "SELECT tc.constraint_name, kcu.column_name, ccu.table_name AS foreign_table, ccu.column_name AS foreign_column \
-             FROM information_schema.table_constraints tc \
-             JOIN information_schema.key_column_usage kcu ON tc.constraint_name = kcu.constraint_name AND tc.table_name = kcu.table_name \
-             JOIN information_schema.constraint_column_usage ccu ON ccu.constraint_name = tc.constraint_name \
-             WHERE tc.table_schema='public' AND tc.table_name=$1 AND tc.constraint_type='FOREIGN KEY'",
-        )
-        .bind(&table_name)
-        .fetch_all(pool)
-        .await?;
-        let mut relations = HashMap::new();
-        for r in fk_rows {
-            let name: String = r.get("constraint_name");
-            let col: String = r.get("column_name");
-            let foreign_table: String = r.get("foreign_table");
-            let foreign_column: String = r.get("foreign_column");
-            relations.insert(
+            model
+                .exclusion_constraints
+                .insert(name, ir::ExclusionConstraintDef { definition });
+        } else {
+            model.check_constraints.insert(
                 name,
-                ir::RelationDef {
-                    on: col,
-                    references: ir::FieldRef {
-                        model: foreign_table,
-                        field: foreign_column,
-                    },
+                ir::CheckConstraintDef {
+                    expression: introspected_check(&def),
                 },
             );
         }
+    }
 
-        models.insert(
-            table_name.to_string(),
-            ir::ModelDef {
-                includes: Vec::new(),
-                fields,
-                indexes,
-                relations,
-                unique_constraints,
-                check_constraints,
-                exclusion_constraints,
-                permissions: ir::Permissions::default(),
-                options: ir::ModelOptions::default(),
-                owned_by: None,
+    // a relation is a single column, so composite foreign keys are left out
+    let mut fk_widths: HashMap<(String, String), usize> = HashMap::new();
+    for r in &fk_rows {
+        *fk_widths
+            .entry((r.get("table_name"), r.get("constraint_name")))
+            .or_default() += 1;
+    }
+
+    for r in fk_rows {
+        let table: String = r.get("table_name");
+        let Some(model) = models.get_mut(&table) else {
+            continue;
+        };
+        let name: String = r.get("constraint_name");
+        let width = fk_widths[&(table.clone(), name.clone())];
+        if width > 1 {
+            if r.get::<i32, _>("position") == 1 {
+                eprintln!(
+                    "warning: skipping composite foreign key {} on {} ({} columns); relations have one column",
+                    name, table, width
+                );
+            }
+            continue;
+        }
+        let col: String = r.get("column_name");
+        let foreign_table: String = r.get("foreign_table");
+        let foreign_column: String = r.get("foreign_column");
+        let update_rule: String = r.get("update_rule");
+        let delete_rule: String = r.get("delete_rule");
+        let is_deferrable: String = r.get("is_deferrable");
+        let initially_deferred: String = r.get("initially_deferred");
+        model.relations.insert(
+            name,
+            ir::RelationDef {
+                on: col,
+                references: ir::FieldRef {
+                    model: foreign_table,
+                    field: foreign_column,
+                },
+                on_delete: ir::ReferentialAction::from_sql(&delete_rule),
+                on_update: ir::ReferentialAction::from_sql(&update_rule),
+                deferrable: is_deferrable == "YES",
+                initially_deferred: initially_deferred == "YES",
             },
         );
     }
+    stopwatch.lap("assemble", models.len());
 
     Ok(ir::SchemaIR {
         schema_version: "1.0".into(),
         meta: ir::Meta {
             rust_case_style: "camel".into(),
-            db_case_style: "snake".into(),
+            // Introspected names are already the database's own
+            db_case_style: "preserve".into(),
             default_derives: vec![],
             observability_provider: None,
             db_backend: ir::DatabaseBackend::Postgres,
+            db_schema: (schema != "public").then(|| schema.to_string()),
             auth: ir::AuthConfig::default(),
         },
         enums,
         models,
-        routes: HashMap::new(),
-        plugins: HashMap::new(),
-        macros: HashMap::new(),
-        seeds: HashMap::new(),
+        routes: IndexMap::new(),
+        plugins: IndexMap::new(),
+        macros: IndexMap::new(),
+        seeds: IndexMap::new(),
+        dropped: Vec::new(),
     })
 }
 
+/// A `CONSTRAINT name KIND (...)` clause of a SQLite `CREATE TABLE`.
+struct SqliteConstraint {
+    name: String,
+    /// `UNIQUE`, `CHECK`, `FOREIGN KEY` or `PRIMARY KEY`
+    kind: String,
+    /// What the parentheses after the kind hold
+    body: String,
+    /// `body` split into column names, for keys
+    columns: Vec<String>,
+}
+
+/// An index `pragma_index_list` reports, with its columns in order.
+struct SqliteIndex {
+    unique: bool,
+    /// `c` for `CREATE INDEX`, `u` for a UNIQUE constraint, `pk` for the key
+    origin: Option<String>,
+    columns: Vec<String>,
+}
+
+/// The named table constraints of a SQLite `CREATE TABLE` statement.
+fn sqlite_named_constraints(create_sql: &str) -> Vec<SqliteConstraint> {
+    let re = Regex::new(
+        r#"(?i)CONSTRAINT\s+[`"\[]?(\w+)[`"\]]?\s+(UNIQUE|CHECK|FOREIGN\s+KEY|PRIMARY\s+KEY)\s*\("#,
+    )
+    .unwrap();
+    let mut out = Vec::new();
+    for caps in re.captures_iter(create_sql) {
+        let start = caps.get(0).unwrap().end();
+        let mut depth = 1;
+        let mut in_quote = false;
+        let mut end = None;
+        for (i, c) in create_sql[start..].char_indices() {
+            match c {
+                '\'' => in_quote = !in_quote,
+                '(' if !in_quote => depth += 1,
+                ')' if !in_quote => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = Some(start + i);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let Some(end) = end else { continue };
+        let body = create_sql[start..end].trim().to_string();
+        let kind = caps[2].split_whitespace().collect::<Vec<_>>().join(" ").to_uppercase();
+        let columns = body
+            .split(',')
+            .map(|c| c.trim().trim_matches(|q| q == '`' || q == '"' || q == '[' || q == ']').to_string())
+            .collect();
+        out.push(SqliteConstraint {
+            name: caps[1].to_string(),
+            kind,
+            body,
+            columns,
+        });
+    }
+    out
+}
+
 async fn introspect_schema_sqlite(
     pool: &sqlx::SqlitePool,
     db_type_map: &std::collections::HashMap<String, String>,
+    options: &IntrospectOptions,
+    stopwatch: &mut Stopwatch,
 ) -> Result<ir::SchemaIR> {
-    let table_rows = sqlx::query(
-        "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'",
-    )
+    if let Some(schema) = &options.schema {
+        anyhow::bail!("SQLite has no schemas, cannot introspect {}", schema);
+    }
+    // the pragmas as table-valued functions, joined to every table at once;
+    // they do not report constraint names, so those come from the SQL
+    let tables = "FROM sqlite_master m";
+    let only_tables = "WHERE m.type = 'table' AND m.name NOT LIKE 'sqlite_%'";
+    let table_rows = sqlx::query(&format!("SELECT m.name, m.sql {} {} ORDER BY m.name", tables, only_tables))
+        .fetch_all(pool)
+        .await?;
+    stopwatch.lap("tables", table_rows.len());
+
+    let column_rows = sqlx::query(&format!(
+        "SELECT m.name AS table_name, p.name, p.type, p.\"notnull\", p.dflt_value, p.pk \
+         {} JOIN pragma_table_info(m.name) p {} ORDER BY m.name, p.cid",
+        tables, only_tables
+    ))
     .fetch_all(pool)
     .await?;
+    stopwatch.lap("columns", column_rows.len());
+
+    let index_rows = sqlx::query(&format!(
+        "SELECT m.name AS table_name, il.name AS index_name, il.\"unique\", il.origin, ii.name AS column_name \
+         {} JOIN pragma_index_list(m.name) il JOIN pragma_index_info(il.name) ii {} \
+         ORDER BY m.name, il.seq, ii.seqno",
+        tables, only_tables
+    ))
+    .fetch_all(pool)
+    .await?;
+    stopwatch.lap("indexes", index_rows.len());
 
-    let mut models = HashMap::new();
-    let re_check = Regex::new(r#"(?i)CONSTRAINT\s+`?(\w+)`?\s+CHECK\s*\(([^)]+)\)"#).unwrap();
+    let fk_rows = sqlx::query(&format!(
+        "SELECT m.name AS table_name, fk.id, fk.seq, fk.\"from\", fk.\"table\", fk.\"to\", fk.on_update, fk.on_delete \
+         {} JOIN pragma_foreign_key_list(m.name) fk {} ORDER BY m.name, fk.id, fk.seq",
+        tables, only_tables
+    ))
+    .fetch_all(pool)
+    .await?;
+    stopwatch.lap("foreign keys", fk_rows.len());
 
+    let mut models = IndexMap::new();
+    let mut named = HashMap::new();
     for row in table_rows {
         let table_name: String = row.get("name");
+        if !options.includes_table(&table_name) {
+            continue;
+        }
+        let create_sql: String = row.get("sql");
+        named.insert(table_name.clone(), sqlite_named_constraints(&create_sql));
+        models.insert(table_name, ir::ModelDef::default());
+    }
 
-        let column_rows = sqlx::query(&format!("PRAGMA table_info(`{}`)", table_name))
-            .fetch_all(pool)
-            .await?;
-
-        let mut fields = HashMap::new();
-        for c in column_rows {
-            let col_name: String = c.get("name");
-            let data_type: String = c.get("type");
-            let notnull: i64 = c.get("notnull");
-            let default: Option<String> = c.try_get("dflt_value").ok();
-            let rust_type = db_type_map
-                .get(&data_type.to_lowercase())
-                .cloned()
-                .unwrap_or_else(|| "String".to_string());
-            fields.insert(
-                col_name,
-                ir::FieldDef {
-                    rust_type,
-                    db_type: Some(data_type),
-                    default,
-                    nullable: notnull == 0,
-                    rename_from: None,
-                    tags: Vec::new(),
-                    zod: None,
-                    storage: None,
-                },
-            );
+    let mut pk_columns: HashMap<String, Vec<(i64, String)>> = HashMap::new();
+    let mut rowid_columns: HashMap<String, Vec<String>> = HashMap::new();
+    for c in column_rows {
+        let table_name: String = c.get("table_name");
+        let Some(model) = models.get_mut(&table_name) else {
+            continue;
+        };
+        let col_name: String = c.get("name");
+        let data_type: String = c.get("type");
+        let notnull: i64 = c.get("notnull");
+        let default: Option<String> = c.try_get::<Option<String>, _>("dflt_value").ok().flatten();
+        let pk: i64 = c.get("pk");
+        if pk > 0 {
+            pk_columns.entry(table_name.clone()).or_default().push((pk, col_name.clone()));
+            if data_type.eq_ignore_ascii_case("INTEGER") {
+                rowid_columns.entry(table_name).or_default().push(col_name.clone());
+            }
         }
+        let rust_type = introspected_rust_type(&ir::DatabaseBackend::Sqlite, db_type_map, &data_type);
+        model.fields.insert(
+            col_name,
+            ir::FieldDef {
+                rust_type,
+                db_type: Some(data_type),
+                default: default.and_then(|d| introspected_default(&d, false)),
+                nullable: notnull == 0,
+                rename_from: None,
+                tags: Vec::new(),
+                zod: None,
+                storage: None,
+                column: None,
+                dropped: false,
+            },
+        );
+    }
+    for (table_name, model) in models.iter_mut() {
+        let mut columns = pk_columns.remove(table_name).unwrap_or_default();
+        columns.sort();
+        let pk_fields: Vec<String> = columns.into_iter().map(|(_, c)| c).collect();
+        // Only a lone INTEGER key aliases the rowid
+        let rowid = match pk_fields.len() {
+            1 => rowid_columns.remove(table_name).unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        model.primary_key = introspected_primary_key(pk_fields, &rowid);
+    }
 
-        let index_rows = sqlx::query(&format!("PRAGMA index_list(`{}`)", table_name))
-            .fetch_all(pool)
-            .await?;
-        let mut indexes = HashMap::new();
-        let mut unique_constraints = HashMap::new();
-        for r in index_rows {
-            let idx_name: String = r.get("name");
-            let unique: i64 = r.get("unique");
-            let origin: Option<String> = r.try_get("origin").ok();
-            let info = sqlx::query(&format!("PRAGMA index_info(`{}`)", idx_name))
-                .fetch_all(pool)
-                .await?;
-            let mut fields_vec = Vec::new();
-            for i in info {
-                let col: String = i.get("name");
-                fields_vec.push(col);
-            }
-            if unique != 0 && origin.as_deref() == Some("u") {
-                unique_constraints.insert(
-                    idx_name.clone(),
+    // index columns in order, grouped by table and index
+    let mut table_indexes: IndexMap<(String, String), SqliteIndex> = IndexMap::new();
+    for r in index_rows {
+        let table_name: String = r.get("table_name");
+        let idx_name: String = r.get("index_name");
+        let unique: i64 = r.get("unique");
+        let origin: Option<String> = r.try_get("origin").ok();
+        // expression columns have no name
+        let column: Option<String> = r.try_get("column_name").ok().flatten();
+        table_indexes
+            .entry((table_name, idx_name))
+            .or_insert_with(|| SqliteIndex {
+                unique: unique != 0,
+                origin,
+                columns: Vec::new(),
+            })
+            .columns
+            .extend(column);
+    }
+    for ((table_name, idx_name), index) in table_indexes {
+        let Some(model) = models.get_mut(&table_name) else {
+            continue;
+        };
+        match index.origin.as_deref() {
+            Some("pk") => {}
+            // the index behind a UNIQUE constraint
+            Some("u") if index.unique => {
+                let name = named[&table_name]
+                    .iter()
+                    .find(|c| c.kind == "UNIQUE" && c.columns == index.columns)
+                    .map(|c| c.name.clone())
+                    .unwrap_or_else(|| format!("{}_{}_key", table_name, index.columns.join("_")));
+                model.unique_constraints.insert(
+                    name,
                     ir::UniqueConstraintDef {
-                        fields: fields_vec.clone(),
+                        fields: index.columns,
+                    },
+                );
+            }
+            _ => {
+                model.indexes.insert(
+                    idx_name,
+                    ir::IndexDef {
+                        fields: index.columns,
+                        unique: index.unique,
                     },
                 );
             }
-            indexes.insert(
-                idx_name.clone(),
-                ir::IndexDef {
-                    fields: fields_vec,
-                    unique: unique != 0,
-                },
-            );
         }
+    }
 
-        let fk_rows = sqlx::query(&format!("PRAGMA foreign_key_list(`{}`)", table_name))
-            .fetch_all(pool)
-            .await?;
-        let mut relations = HashMap::new();
-        for fk in fk_rows {
-            let id: i64 = fk.get("id");
-            let seq: i64 = fk.get("seq");
-            let from: String = fk.get("from");
-            let to_table: String = fk.get("table");
-            let to_col: String = fk.get("to");
-            relations.insert(
-                format!("fk_{}_{}_{}", table_name, id, seq),
-                ir::RelationDef {
-                    on: from,
-                    references: ir::FieldRef {
-                        model: to_table,
-                        field: to_col,
-                    },
+    for fk in fk_rows {
+        let table_name: String = fk.get("table_name");
+        let Some(model) = models.get_mut(&table_name) else {
+            continue;
+        };
+        let id: i64 = fk.get("id");
+        let seq: i64 = fk.get("seq");
+        let from: String = fk.get("from");
+        let to_table: String = fk.get("table");
+        let to_col: String = fk.get("to");
+        let on_update: String = fk.get("on_update");
+        let on_delete: String = fk.get("on_delete");
+        let name = named[&table_name]
+            .iter()
+            .find(|c| c.kind == "FOREIGN KEY" && c.columns.get(seq as usize) == Some(&from))
+            .map(|c| c.name.clone())
+            .unwrap_or_else(|| format!("fk_{}_{}_{}", table_name, id, seq));
+        model.relations.insert(
+            name,
+            ir::RelationDef {
+                on: from,
+                references: ir::FieldRef {
+                    model: to_table,
+                    field: to_col,
                 },
-            );
-        }
+                on_delete: ir::ReferentialAction::from_sql(&on_delete),
+                on_update: ir::ReferentialAction::from_sql(&on_update),
+                // The pragma does not report deferrability
+                deferrable: false,
+                initially_deferred: false,
+            },
+        );
+    }
 
-        let create_row =
-            sqlx::query("SELECT sql FROM sqlite_master WHERE type='table' AND name = ?")
-                .bind(&table_name)
-                .fetch_one(pool)
-                .await?;
-        let create_sql: String = create_row.get("sql");
-        let mut check_constraints = HashMap::new();
-        for cap in re_check.captures_iter(&create_sql) {
-            check_constraints.insert(
-                cap[1].to_string(),
+    for (table_name, model) in models.iter_mut() {
+        for c in named[table_name].iter().filter(|c| c.kind == "CHECK") {
+            model.check_constraints.insert(
+                c.name.clone(),
                 ir::CheckConstraintDef {
-                    expression: cap[2].to_string(),
+                    expression: introspected_check(&c.body),
                 },
             );
         }
-
-        // unique_constraints collected earlier from index parsing
-
-        models.insert(
-            table_name.clone(),
-            ir::ModelDef {
-                includes: Vec::new(),
-                fields,
-                indexes,
-                relations,
-                unique_constraints,
-                check_constraints,
-                exclusion_constraints: HashMap::new(),
-                permissions: ir::Permissions::default(),
-                options: ir::ModelOptions::default(),
-                owned_by: None,
-            },
-        );
     }
+    stopwatch.lap("assemble", models.len());
 
     Ok(ir::SchemaIR {
         schema_version: "1.0".into(),
         meta: ir::Meta {
             rust_case_style: "camel".into(),
-            db_case_style: "snake".into(),
+            // Introspected names are already the database's own
+            db_case_style: "preserve".into(),
             default_derives: vec![],
             observability_provider: None,
             db_backend: ir::DatabaseBackend::Sqlite,
+            db_schema: None,
             auth: ir::AuthConfig::default(),
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models,
-        routes: HashMap::new(),
-        plugins: HashMap::new(),
-        macros: HashMap::new(),
-        seeds: HashMap::new(),
+        routes: IndexMap::new(),
+        plugins: IndexMap::new(),
+        macros: IndexMap::new(),
+        seeds: IndexMap::new(),
+        dropped: Vec::new(),
     })
 }
 
 async fn introspect_schema_mysql(
     pool: &sqlx::MySqlPool,
     db_type_map: &std::collections::HashMap<String, String>,
+    options: &IntrospectOptions,
+    stopwatch: &mut Stopwatch,
 ) -> Result<ir::SchemaIR> {
-    let table_rows = sqlx::query("SHOW TABLES").fetch_all(pool).await?;
+    let current: Option<String> = sqlx::query_scalar("SELECT DATABASE()").fetch_one(pool).await?;
+    let schema = options
+        .schema
+        .clone()
+        .or(current.clone())
+        .ok_or_else(|| anyhow!("no database selected; give one in the URL or as the schema"))?;
+    // one information_schema query per kind of object for the whole database
+    let table_rows = sqlx::query(
+        "SELECT TABLE_NAME FROM information_schema.TABLES \
+         WHERE TABLE_SCHEMA = ? AND TABLE_TYPE = 'BASE TABLE' \
+         ORDER BY TABLE_NAME",
+    )
+    .bind(&schema)
+    .fetch_all(pool)
+    .await?;
+    stopwatch.lap("tables", table_rows.len());
 
-    let mut models = HashMap::new();
+    let column_rows = sqlx::query(
+        "SELECT TABLE_NAME, COLUMN_NAME, COLUMN_TYPE, IS_NULLABLE, COLUMN_DEFAULT, EXTRA \
+         FROM information_schema.COLUMNS \
+         WHERE TABLE_SCHEMA = ? \
+         ORDER BY TABLE_NAME, ORDINAL_POSITION",
+    )
+    .bind(&schema)
+    .fetch_all(pool)
+    .await?;
+    stopwatch.lap("columns", column_rows.len());
 
-    for row in table_rows {
-        let table_name: String = row.get(0);
-        let column_rows = sqlx::query(&format!("SHOW COLUMNS FROM `{}`", table_name))
-            .fetch_all(pool)
-            .await?;
-
-        let mut fields = HashMap::new();
-        for c in column_rows {
-            let col_name: String = c.get("Field");
-            let data_type: String = c.get("Type");
-            let nullable: String = c.get("Null");
-            let default: Option<String> = c.try_get("Default").ok();
-            let rust_type = db_type_map
-                .get(&data_type.to_lowercase())
-                .cloned()
-                .unwrap_or_else(|| "String".to_string());
-            fields.insert(
-                col_name,
-                ir::FieldDef {
-                    rust_type,
-                    db_type: Some(data_type),
-                    default,
-                    nullable: nullable == "YES",
-                    rename_from: None,
-                    tags: Vec::new(),
-                    zod: None,
-                    storage: None,
-                },
-            );
-        }
+    let index_rows = sqlx::query(
+        "SELECT TABLE_NAME, INDEX_NAME, COLUMN_NAME, NON_UNIQUE \
+         FROM information_schema.STATISTICS \
+         WHERE TABLE_SCHEMA = ? \
+         ORDER BY TABLE_NAME, INDEX_NAME, SEQ_IN_INDEX",
+    )
+    .bind(&schema)
+    .fetch_all(pool)
+    .await?;
+    stopwatch.lap("indexes", index_rows.len());
+
+    let uc_rows = sqlx::query(
+        "SELECT tc.TABLE_NAME, tc.CONSTRAINT_NAME, kcu.COLUMN_NAME \
+         FROM information_schema.TABLE_CONSTRAINTS tc \
+         JOIN information_schema.KEY_COLUMN_USAGE kcu \
+           ON tc.CONSTRAINT_NAME = kcu.CONSTRAINT_NAME AND tc.TABLE_SCHEMA = kcu.TABLE_SCHEMA \
+           AND tc.TABLE_NAME = kcu.TABLE_NAME \
+         WHERE tc.TABLE_SCHEMA = ? AND tc.CONSTRAINT_TYPE = 'UNIQUE' \
+         ORDER BY tc.TABLE_NAME, tc.CONSTRAINT_NAME, kcu.ORDINAL_POSITION",
+    )
+    .bind(&schema)
+    .fetch_all(pool)
+    .await?;
+    stopwatch.lap("unique", uc_rows.len());
+
+    // check constraints (MySQL 8+)
+    let ck_rows = sqlx::query(
+        "SELECT tc.TABLE_NAME, tc.CONSTRAINT_NAME, cc.CHECK_CLAUSE \
+         FROM information_schema.TABLE_CONSTRAINTS tc \
+         JOIN information_schema.CHECK_CONSTRAINTS cc \
+           ON tc.CONSTRAINT_SCHEMA = cc.CONSTRAINT_SCHEMA AND tc.CONSTRAINT_NAME = cc.CONSTRAINT_NAME \
+         WHERE tc.TABLE_SCHEMA = ? AND tc.CONSTRAINT_TYPE = 'CHECK' \
+         ORDER BY tc.TABLE_NAME, tc.CONSTRAINT_NAME",
+    )
+    .bind(&schema)
+    .fetch_all(pool)
+    .await?;
+    stopwatch.lap("checks", ck_rows.len());
+
+    let fk_rows = sqlx::query(
+        "SELECT kcu.TABLE_NAME, kcu.CONSTRAINT_NAME, kcu.COLUMN_NAME, kcu.REFERENCED_TABLE_NAME, \
+         kcu.REFERENCED_COLUMN_NAME, rc.UPDATE_RULE, rc.DELETE_RULE \
+         FROM information_schema.KEY_COLUMN_USAGE kcu \
+         JOIN information_schema.REFERENTIAL_CONSTRAINTS rc \
+           ON rc.CONSTRAINT_SCHEMA = kcu.TABLE_SCHEMA AND rc.CONSTRAINT_NAME = kcu.CONSTRAINT_NAME \
+         WHERE kcu.TABLE_SCHEMA = ? AND kcu.REFERENCED_TABLE_NAME IS NOT NULL \
+         ORDER BY kcu.TABLE_NAME, kcu.CONSTRAINT_NAME, kcu.ORDINAL_POSITION",
+    )
+    .bind(&schema)
+    .fetch_all(pool)
+    .await?;
+    stopwatch.lap("foreign keys", fk_rows.len());
 
-        // indexes
-        let index_rows = sqlx::query(&format!("SHOW INDEX FROM `{}`", table_name))
-            .fetch_all(pool)
-            .await?;
-        let mut indexes: HashMap<String, ir::IndexDef> = HashMap::new();
-        for r in index_rows {
-            let idx_name: String = r.get("Key_name");
-            let col_name: String = r.get("Column_name");
-            let non_unique: i64 = r.get("Non_unique");
-            let entry = indexes.entry(idx_name).or_insert_with(|| ir::IndexDef {
-                fields: Vec::new(),
-                unique: non_unique == 0,
-            });
-            entry.fields.push(col_name);
-        }
+    let mut models: IndexMap<String, ir::ModelDef> = table_rows
+        .iter()
+        .map(|row| row.get::<String, _>(0))
+        .filter(|table| options.includes_table(table))
+        .map(|table| (table, ir::ModelDef::default()))
+        .collect();
+    let mut enums: IndexMap<String, ir::EnumDef> = IndexMap::new();
+    let label = Regex::new(r"'((?:[^']|'')*)'").unwrap();
 
-        // unique constraints
-        let uc_rows = sqlx::query(
-            "SELECT tc.CONSTRAINT_NAME, kcu.COLUMN_NAME \
-             FROM information_schema.TABLE_CONSTRAINTS tc \
-             JOIN information_schema.KEY_COLUMN_USAGE kcu \
-               ON tc.CONSTRAINT_NAME = kcu.CONSTRAINT_NAME AND tc.TABLE_NAME = kcu.TABLE_NAME \
-             WHERE tc.TABLE_SCHEMA = DATABASE() AND tc.TABLE_NAME = ? AND tc.CONSTRAINT_TYPE = 'UNIQUE' \
-             ORDER BY kcu.ORDINAL_POSITION",
-        )
-        .bind(&table_name)
-        .fetch_all(pool)
-        .await?;
-        let mut unique_constraints = HashMap::new();
-        for r in uc_rows {
-            let name: String = r.get("CONSTRAINT_NAME");
-            let col: String = r.get("COLUMN_NAME");
-            unique_constraints
-                .entry(name)
-                .or_insert_with(|| ir::UniqueConstraintDef { fields: vec![] })
-                .fields
-                .push(col);
-        }
-
-        // check constraints (MySQL 8+)
-        let ck_rows = sqlx::query(
-            "SELECT tc.CONSTRAINT_NAME, cc.CHECK_CLAUSE \
-             FROM information_schema.TABLE_CONSTRAINTS tc \
-             JOIN information_schema.CHECK_CONSTRAINTS cc \
-               ON tc.CONSTRAINT_NAME = cc.CONSTRAINT_NAME \
-             WHERE tc.TABLE_SCHEMA = DATABASE() AND tc.TABLE_NAME = ? AND tc.CONSTRAINT_TYPE = 'CHECK'",
-        )
-        .bind(&table_name)
-        .fetch_all(pool)
-        .await?;
-        let mut check_constraints = HashMap::new();
-        for r in ck_rows {
-            let name: String = r.get("CONSTRAINT_NAME");
-            let clause: String = r.get("CHECK_CLAUSE");
-            check_constraints.insert(name, ir::CheckConstraintDef { expression: clause });
-        }
-
-        // foreign keys
-        let fk_rows = sqlx::query(
-            "SELECT CONSTRAINT_NAME, COLUMN_NAME, REFERENCED_TABLE_NAME, REFERENCED_COLUMN_NAME \
-             FROM information_schema.KEY_COLUMN_USAGE \
-             WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND REFERENCED_TABLE_NAME IS NOT NULL",
-        )
-        .bind(&table_name)
-        .fetch_all(pool)
-        .await?;
-        let mut relations = HashMap::new();
-        for fk in fk_rows {
-            let cname: String = fk.get("CONSTRAINT_NAME");
-            let column: String = fk.get("COLUMN_NAME");
-            let to_table: String = fk.get("REFERENCED_TABLE_NAME");
-            let to_col: String = fk.get("REFERENCED_COLUMN_NAME");
-            relations.insert(
-                cname.clone(),
-                ir::RelationDef {
-                    on: column,
-                    references: ir::FieldRef {
-                        model: to_table,
-                        field: to_col,
-                    },
-                },
-            );
+    let mut auto_increment_columns: HashMap<String, Vec<String>> = HashMap::new();
+    for c in column_rows {
+        let table_name: String = c.get("TABLE_NAME");
+        let Some(model) = models.get_mut(&table_name) else {
+            continue;
+        };
+        let col_name: String = c.get("COLUMN_NAME");
+        let data_type: String = c.get("COLUMN_TYPE");
+        let nullable: String = c.get("IS_NULLABLE");
+        let default: Option<String> = c.try_get("COLUMN_DEFAULT").ok().flatten();
+        let extra: String = c.try_get("EXTRA").unwrap_or_default();
+        if extra.to_lowercase().contains("auto_increment") {
+            auto_increment_columns.entry(table_name.clone()).or_default().push(col_name.clone());
+        }
+        let lower = data_type.to_lowercase();
+        // string defaults are reported without their quotes
+        let quote = !extra.to_uppercase().contains("DEFAULT_GENERATED")
+            && ["char", "varchar", "tinytext", "text", "mediumtext", "longtext", "enum", "set"]
+                .contains(&lower.split('(').next().unwrap_or_default());
+        let default = default.and_then(|d| introspected_default(&d, quote));
+        // inline ENUM(...) columns become enums of their own, shared by
+        // columns with the same labels
+        let data_type = if lower.starts_with("enum(") {
+            let variants: Vec<ir::EnumVariant> = label
+                .captures_iter(&data_type)
+                .map(|l| l[1].replace("''", "'").into())
+                .collect();
+            let same = enums
+                .iter()
+                .find(|(_, en)| en.variants == variants)
+                .map(|(name, _)| name.clone());
+            same.unwrap_or_else(|| {
+                let name = format!("{}_{}", table_name, col_name);
+                enums.insert(name.clone(), ir::EnumDef { variants });
+                name
+            })
+        } else {
+            data_type
+        };
+        let rust_type = introspected_rust_type(&ir::DatabaseBackend::Mysql, db_type_map, &data_type);
+        model.fields.insert(
+            col_name,
+            ir::FieldDef {
+                rust_type,
+                db_type: Some(data_type),
+                default,
+                nullable: nullable == "YES",
+                rename_from: None,
+                tags: Vec::new(),
+                zod: None,
+                storage: None,
+                column: None,
+                dropped: false,
+            },
+        );
+    }
+
+    let mut pk_fields: HashMap<String, Vec<String>> = HashMap::new();
+    for r in index_rows {
+        let table_name: String = r.get("TABLE_NAME");
+        let Some(model) = models.get_mut(&table_name) else {
+            continue;
+        };
+        let idx_name: String = r.get("INDEX_NAME");
+        // functional key parts have no column
+        let Some(col_name) = r.try_get::<Option<String>, _>("COLUMN_NAME").ok().flatten() else {
+            continue;
+        };
+        let non_unique: i64 = r.get("NON_UNIQUE");
+        if idx_name == "PRIMARY" {
+            pk_fields.entry(table_name).or_default().push(col_name);
+            continue;
         }
+        let entry = model.indexes.entry(idx_name).or_insert_with(|| ir::IndexDef {
+            fields: Vec::new(),
+            unique: non_unique == 0,
+        });
+        entry.fields.push(col_name);
+    }
+    for (table_name, model) in models.iter_mut() {
+        model.primary_key = introspected_primary_key(
+            pk_fields.remove(table_name).unwrap_or_default(),
+            auto_increment_columns.get(table_name).map(Vec::as_slice).unwrap_or_default(),
+        );
+    }
+
+    for r in uc_rows {
+        let table_name: String = r.get("TABLE_NAME");
+        let Some(model) = models.get_mut(&table_name) else {
+            continue;
+        };
+        let name: String = r.get("CONSTRAINT_NAME");
+        let col: String = r.get("COLUMN_NAME");
+        model
+            .unique_constraints
+            .entry(name)
+            .or_insert_with(|| ir::UniqueConstraintDef { fields: vec![] })
+            .fields
+            .push(col);
+    }
+
+    for r in ck_rows {
+        let table_name: String = r.get("TABLE_NAME");
+        let Some(model) = models.get_mut(&table_name) else {
+            continue;
+        };
+        let name: String = r.get("CONSTRAINT_NAME");
+        let clause: String = r.get("CHECK_CLAUSE");
+        model.check_constraints.insert(
+            name,
+            ir::CheckConstraintDef {
+                expression: introspected_check(&clause),
+            },
+        );
+    }
 
-        models.insert(
-            table_name.clone(),
-            ir::ModelDef {
-                includes: Vec::new(),
-                fields,
-                indexes,
-                relations,
-                unique_constraints,
-                check_constraints,
-                exclusion_constraints: HashMap::new(),
-                permissions: ir::Permissions::default(),
-                options: ir::ModelOptions::default(),
-                owned_by: None,
+    for fk in fk_rows {
+        let table_name: String = fk.get("TABLE_NAME");
+        let Some(model) = models.get_mut(&table_name) else {
+            continue;
+        };
+        let cname: String = fk.get("CONSTRAINT_NAME");
+        let column: String = fk.get("COLUMN_NAME");
+        let to_table: String = fk.get("REFERENCED_TABLE_NAME");
+        let to_col: String = fk.get("REFERENCED_COLUMN_NAME");
+        let update_rule: String = fk.get("UPDATE_RULE");
+        let delete_rule: String = fk.get("DELETE_RULE");
+        model.relations.insert(
+            cname,
+            ir::RelationDef {
+                on: column,
+                references: ir::FieldRef {
+                    model: to_table,
+                    field: to_col,
+                },
+                on_delete: ir::ReferentialAction::from_sql(&delete_rule),
+                on_update: ir::ReferentialAction::from_sql(&update_rule),
+                // MySQL checks foreign keys immediately
+                deferrable: false,
+                initially_deferred: false,
             },
         );
     }
 
+    // indexes MySQL keeps for unique constraints and foreign keys
+    for model in models.values_mut() {
+        let (unique_constraints, relations) = (&model.unique_constraints, &model.relations);
+        model.indexes.retain(|name, idx| {
+            !unique_constraints.contains_key(name)
+                && relations.get(name).is_none_or(|rel| idx.fields != [rel.on.clone()])
+        });
+    }
+    stopwatch.lap("assemble", models.len());
+
     Ok(ir::SchemaIR {
         schema_version: "1.0".into(),
         meta: ir::Meta {
             rust_case_style: "camel".into(),
-            db_case_style: "snake".into(),
+            // Introspected names are already the database's own
+            db_case_style: "preserve".into(),
             default_derives: vec![],
             observability_provider: None,
             db_backend: ir::DatabaseBackend::Mysql,
+            db_schema: (current.as_ref() != Some(&schema)).then_some(schema),
             auth: ir::AuthConfig::default(),
         },
-        enums: HashMap::new(),
+        enums,
         models,
-        routes: HashMap::new(),
-        plugins: HashMap::new(),
-        macros: HashMap::new(),
-        seeds: HashMap::new(),
+        routes: IndexMap::new(),
+        plugins: IndexMap::new(),
+        macros: IndexMap::new(),
+        seeds: IndexMap::new(),
+        dropped: Vec::new(),
     })
 }
 
@@ -1659,6 +2368,56 @@ pub fn lint_schema(ir: &ir::SchemaIR) -> Vec<String> {
     role_variants.insert(&ir.meta.auth.anonymous_role);
     let mut route_paths: std::collections::HashMap<&str, &str> = std::collections::HashMap::new();
 
+    // case styles must be ones the generators understand
+    for (key, style) in [
+        ("rust_case_style", &ir.meta.rust_case_style),
+        ("db_case_style", &ir.meta.db_case_style),
+    ] {
+        if !naming::CASE_STYLES.contains(&style.as_str()) {
+            errors.push(format!(
+                "meta.{} {} is not one of {}",
+                key,
+                style,
+                naming::CASE_STYLES.join(", ")
+            ));
+        }
+    }
+
+    // SQLite has no schemas, only attached database files
+    if ir.meta.db_schema.is_some() && matches!(ir.meta.db_backend, ir::DatabaseBackend::Sqlite) {
+        errors.push("meta.db_schema is not supported on SQLite".to_string());
+    }
+
+    // models and fields must not collide once mapped to database names
+    let mut tables: std::collections::HashMap<String, &str> = std::collections::HashMap::new();
+    for (model_name, model) in &ir.models {
+        let table = ir.table_name(model_name);
+        if let Some(existing) = tables.insert(table.clone(), model_name) {
+            errors.push(format!(
+                "Models {} and {} both map to table {}",
+                existing, model_name, table
+            ));
+        }
+        if let Some(from) = &model.rename_from
+            && ir.models.contains_key(from)
+        {
+            errors.push(format!(
+                "Model {} renames from {}, which is still declared",
+                model_name, from
+            ));
+        }
+        let mut columns: std::collections::HashMap<String, &str> = std::collections::HashMap::new();
+        for field_name in model.fields.keys() {
+            let column = ir.column_name(model_name, field_name);
+            if let Some(existing) = columns.insert(column.clone(), field_name) {
+                errors.push(format!(
+                    "Fields {}.{} and {}.{} both map to column {}",
+                    model_name, existing, model_name, field_name, column
+                ));
+            }
+        }
+    }
+
     // check for duplicate route paths
     for (route_name, route) in &ir.routes {
         if let Some(existing) = route_paths.insert(&route.path, route_name) {
@@ -1697,13 +2456,57 @@ pub fn lint_schema(ir: &ir::SchemaIR) -> Vec<String> {
         }
     }
 
+    for (enum_name, en) in &ir.enums {
+        let mut seen = std::collections::HashSet::new();
+        for variant in &en.variants {
+            // Labels are stored lowercased, so case-only differences collide
+            if !seen.insert(variant.name.to_lowercase()) {
+                errors.push(format!(
+                    "Enum {} declares variant {} more than once",
+                    enum_name, variant.name
+                ));
+            }
+            if let Some(from) = &variant.rename_from
+                && en.variants.iter().any(|v| v.name.eq_ignore_ascii_case(from))
+            {
+                errors.push(format!(
+                    "Enum {} variant {} is renamed from {} which is still declared",
+                    enum_name, variant.name, from
+                ));
+            }
+        }
+    }
+
     for (model_name, model) in &ir.models {
         // check relations
         for (rel_name, rel) in &model.relations {
-            if !model.fields.contains_key(&rel.on) {
-                errors.push(format!(
+            match model.fields.get(&rel.on) {
+                None => errors.push(format!(
                     "Relation {} on model {} references unknown field {}",
                     rel_name, model_name, rel.on
+                )),
+                Some(field) => {
+                    let actions = [&rel.on_delete, &rel.on_update];
+                    if actions.contains(&&ir::ReferentialAction::SetNull) && !field.nullable {
+                        errors.push(format!(
+                            "Relation {} on model {} uses set_null but field {} is not nullable",
+                            rel_name, model_name, rel.on
+                        ));
+                    }
+                    if actions.contains(&&ir::ReferentialAction::SetDefault)
+                        && field.default.is_none()
+                    {
+                        errors.push(format!(
+                            "Relation {} on model {} uses set_default but field {} has no default",
+                            rel_name, model_name, rel.on
+                        ));
+                    }
+                }
+            }
+            if rel.initially_deferred && !rel.deferrable {
+                errors.push(format!(
+                    "Relation {} on model {} is initially_deferred but not deferrable",
+                    rel_name, model_name
                 ));
             }
             let target_model = &rel.references.model;
@@ -1737,6 +2540,72 @@ pub fn lint_schema(ir: &ir::SchemaIR) -> Vec<String> {
                 )),
             }
         }
+        // primary key
+        if let Some(pk) = &model.primary_key {
+            for f in &pk.fields {
+                if !model.fields.contains_key(f) {
+                    errors.push(format!(
+                        "Primary key on model {} references unknown field {}",
+                        model_name, f
+                    ));
+                }
+            }
+            if pk.generated == ir::KeyGeneration::Identity && pk.fields.len() > 1 {
+                errors.push(format!(
+                    "Primary key on model {} cannot be an identity key with multiple fields",
+                    model_name
+                ));
+            }
+            let inline: Vec<&String> = model
+                .fields
+                .iter()
+                .filter(|(_, f)| {
+                    f.db_type
+                        .as_deref()
+                        .map(|t| t.to_uppercase().contains("PRIMARY KEY"))
+                        .unwrap_or(false)
+                })
+                .map(|(name, _)| name)
+                .collect();
+            for name in inline {
+                errors.push(format!(
+                    "Field {}.{} declares PRIMARY KEY in db_type while the model sets primary_key",
+                    model_name, name
+                ));
+            }
+        }
+        // many-to-many relations
+        for (rel_name, m2m) in &model.many_to_many {
+            let Some(target) = ir.models.get(&m2m.model) else {
+                errors.push(format!(
+                    "Many-to-many relation {} on model {} references unknown model {}",
+                    rel_name, model_name, m2m.model
+                ));
+                continue;
+            };
+            for (side, side_model) in [(model_name, model), (&m2m.model, target)] {
+                if side_model.primary_key_fields().len() != 1 {
+                    errors.push(format!(
+                        "Many-to-many relation {} on model {} needs a single-column primary key on {}",
+                        rel_name, model_name, side
+                    ));
+                }
+            }
+            if let Some(jt) = ir.join_table(model_name, rel_name) {
+                if jt.source_column == jt.target_column {
+                    errors.push(format!(
+                        "Many-to-many relation {} on model {} uses column {} for both sides; set source_column and target_column",
+                        rel_name, model_name, jt.source_column
+                    ));
+                }
+                if ir.models.keys().any(|m| ir.table_name(m) == jt.table) {
+                    errors.push(format!(
+                        "Many-to-many relation {} on model {} uses join table {} which is already a model",
+                        rel_name, model_name, jt.table
+                    ));
+                }
+            }
+        }
         // check indexes
         for (idx_name, idx) in &model.indexes {
             for f in &idx.fields {
@@ -1867,6 +2736,62 @@ pub fn lint_schema(ir: &ir::SchemaIR) -> Vec<String> {
     errors
 }
 
+/// Lint `new` against the previous schema `old` (the migration snapshot):
+/// warns when the diff drops a table and creates another with the same
+/// columns, which usually means a rename that is missing `rename_from`.
+pub fn lint_schema_changes(old: &ir::SchemaIR, new: &ir::SchemaIR) -> Vec<String> {
+    let mut warnings = Vec::new();
+    // Schemas that do not diff are reported when the migration is generated
+    let Ok(diff) = diff_schemas(Some(old), new) else {
+        return warnings;
+    };
+    let shape = |model: &ir::ModelDef| {
+        let mut columns: Vec<(String, String, bool)> = model
+            .fields
+            .iter()
+            .map(|(column, field)| {
+                (column.clone(), field.db_type.clone().unwrap_or_default().to_uppercase(), field.nullable)
+            })
+            .collect();
+        columns.sort();
+        columns
+    };
+    let model_name = |ir: &ir::SchemaIR, table: &str| {
+        ir.models
+            .keys()
+            .find(|name| ir.qualify(&ir.table_name(name)) == table)
+            .cloned()
+            .unwrap_or_else(|| table.to_string())
+    };
+    let dropped: Vec<(&String, &ir::ModelDef)> = diff
+        .operations
+        .iter()
+        .filter_map(|op| match op {
+            SchemaOperation::DropTable { table, model } => Some((table, &**model)),
+            _ => None,
+        })
+        .collect();
+    for op in &diff.operations {
+        let SchemaOperation::CreateTable { table, model } = op else {
+            continue;
+        };
+        let columns = shape(model);
+        if columns.is_empty() {
+            continue;
+        }
+        for (old_table, _) in dropped.iter().filter(|(_, old_model)| shape(old_model) == columns) {
+            let old_name = model_name(old, old_table);
+            warnings.push(format!(
+                "Model {} has the same columns as removed model {}; set rename_from = \"{}\" to rename the table instead of dropping it",
+                model_name(new, table),
+                old_name,
+                old_name
+            ));
+        }
+    }
+    warnings
+}
+
 /// Apply macros to models by merging macro fields and options.
 pub fn apply_macros(ir: &mut ir::SchemaIR) {
     for model in ir.models.values_mut() {
@@ -1903,6 +2828,8 @@ pub fn apply_model_options(ir: &mut ir::SchemaIR) {
                     tags: Vec::new(),
                     zod: None,
                     storage: None,
+                    column: None,
+                    dropped: false,
                 });
             model
                 .fields
@@ -1916,6 +2843,8 @@ pub fn apply_model_options(ir: &mut ir::SchemaIR) {
                     tags: Vec::new(),
                     zod: None,
                     storage: None,
+                    column: None,
+                    dropped: false,
                 });
         }
         if model.options.soft_delete {
@@ -1931,6 +2860,8 @@ pub fn apply_model_options(ir: &mut ir::SchemaIR) {
                     tags: Vec::new(),
                     zod: None,
                     storage: None,
+                    column: None,
+                    dropped: false,
                 });
         }
     }
@@ -2010,7 +2941,20 @@ pub fn generate_typescript(ir: &ir::SchemaIR) -> String {
             } else {
                 ts_type
             };
-            out.push_str(&format!("    {}: {};\n", field_name, ts_type));
+            out.push_str(&format!("    {}: {};\n", ir.api_field_name(field_name), ts_type));
+        }
+        // Related rows are only present when the API embeds them
+        let pk_fields = model.primary_key_fields();
+        for (rel_name, rel) in &model.relations {
+            let target_model = &rel.references.model;
+            if pk_fields.contains(&rel.on) {
+                out.push_str(&format!("    {}?: {}[];\n", ir.api_field_name(rel_name), target_model));
+            } else {
+                out.push_str(&format!("    {}?: {} | null;\n", ir.api_field_name(rel_name), target_model));
+            }
+        }
+        for (rel_name, m2m) in &model.many_to_many {
+            out.push_str(&format!("    {}?: {}[];\n", ir.api_field_name(rel_name), m2m.model));
         }
         out.push_str("}\n\n");
 
@@ -2027,7 +2971,7 @@ pub fn generate_typescript(ir: &ir::SchemaIR) -> String {
             if field.nullable {
                 expr.push_str(".nullable()");
             }
-            out.push_str(&format!("    {}: {},\n", field_name, expr));
+            out.push_str(&format!("    {}: {},\n", ir.api_field_name(field_name), expr));
         }
         out.push_str("});\n\n");
     }
@@ -2125,12 +3069,24 @@ pub fn generate_graphql_schema(ir: &ir::SchemaIR) -> String {
             if !field.nullable {
                 gql_type.push('!');
             }
-            out.push_str(&format!("    {}: {}\n", field_name, gql_type));
+            out.push_str(&format!("    {}: {}\n", ir.api_field_name(field_name), gql_type));
         }
-        // Relations
+        // Relations; a relation keyed on this model's own primary key points
+        // back at many rows of the target
+        let pk_fields = model.primary_key_fields();
         for (rel_name, rel) in &model.relations {
             let target_model = &rel.references.model;
-            out.push_str(&format!("    {}: {}!\n", rel_name, target_model));
+            let gql_type = if pk_fields.contains(&rel.on) {
+                format!("[{}!]!", target_model)
+            } else if model.fields.get(&rel.on).is_some_and(|f| f.nullable) {
+                target_model.clone()
+            } else {
+                format!("{}!", target_model)
+            };
+            out.push_str(&format!("    {}: {}\n", ir.api_field_name(rel_name), gql_type));
+        }
+        for (rel_name, m2m) in &model.many_to_many {
+            out.push_str(&format!("    {}: [{}!]!\n", ir.api_field_name(rel_name), m2m.model));
         }
         out.push_str("}\n\n");
     }
@@ -2145,7 +3101,7 @@ pub fn generate_graphql_schema(ir: &ir::SchemaIR) -> String {
                 if !field.nullable {
                     gql_type.push('!');
                 }
-                out.push_str(&format!("    {}: {}\n", field_name, gql_type));
+                out.push_str(&format!("    {}: {}\n", ir.api_field_name(field_name), gql_type));
             }
         }
         out.push_str("}\n\n");
@@ -2155,33 +3111,52 @@ pub fn generate_graphql_schema(ir: &ir::SchemaIR) -> String {
         for (field_name, field) in &model.fields {
             if field.default.is_none() {
                 let gql_type = map_type(&field.rust_type);
-                out.push_str(&format!("    {}: {}\n", field_name, gql_type));
+                out.push_str(&format!("    {}: {}\n", ir.api_field_name(field_name), gql_type));
             }
         }
         out.push_str("}\n\n");
     }
 
+    // Key arguments, one per primary key column
+    let key_args = |model: &ir::ModelDef| -> String {
+        let fields = model.primary_key_fields();
+        if fields.is_empty() {
+            return "id: ID!".to_string();
+        }
+        fields
+            .iter()
+            .map(|f| {
+                let gql_type = match model.fields.get(f).map(|fd| fd.rust_type.as_str()) {
+                    Some("Uuid") | Some("String") | None => "ID".to_string(),
+                    Some(other) => map_type(other),
+                };
+                format!("{}: {}!", ir.api_field_name(f), gql_type)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
     // Query type
     out.push_str("type Query {\n");
-    for model_name in ir.models.keys() {
+    for (model_name, model) in &ir.models {
         let lower = model_name.to_lowercase();
-        out.push_str(&format!("    {}(id: ID!): {}\n", lower, model_name));
+        out.push_str(&format!("    {}({}): {}\n", lower, key_args(model), model_name));
         out.push_str(&format!("    list{}s: [{}!]!\n", model_name, model_name));
     }
     out.push_str("}\n\n");
 
     // Mutation type
     out.push_str("type Mutation {\n");
-    for model_name in ir.models.keys() {
+    for (model_name, model) in &ir.models {
         out.push_str(&format!(
             "    create{}(input: Create{}Input!): {}!\n",
             model_name, model_name, model_name
         ));
         out.push_str(&format!(
-            "    update{}(id: ID!, input: Update{}Input!): {}!\n",
-            model_name, model_name, model_name
+            "    update{}({}, input: Update{}Input!): {}!\n",
+            model_name, key_args(model), model_name, model_name
         ));
-        out.push_str(&format!("    delete{}(id: ID!): Boolean!\n", model_name));
+        out.push_str(&format!("    delete{}({}): Boolean!\n", model_name, key_args(model)));
     }
     out.push_str("}\n");
 
@@ -2261,11 +3236,11 @@ pub fn generate_seed_sql(ir: &ir::SchemaIR) -> String {
     let mut out = String::new();
     for (model, seed) in &ir.seeds {
         for row in &seed.rows {
-            let cols: Vec<_> = row.keys().cloned().collect();
+            let cols: Vec<_> = row.keys().map(|k| ir.column_name(model, k)).collect();
             let vals: Vec<_> = row.values().map(format_val).collect();
             out.push_str(&format!(
                 "INSERT INTO {} ({}) VALUES ({});\n",
-                model.to_lowercase(),
+                ir.qualify(&ir.table_name(model)),
                 cols.join(", "),
                 vals.join(", ")
             ));
@@ -2295,12 +3270,11 @@ pub fn run_plugin(
     }
     let mut child = cmd.spawn()?;
 
-    if let Some(mut stdin) = child.stdin.take() {
-        if let Err(e) = serde_json::to_writer(&mut stdin, ir) {
-            if !(e.is_io() && e.io_error_kind() == Some(std::io::ErrorKind::BrokenPipe)) {
-                return Err(e.into());
-            }
-        }
+    if let Some(mut stdin) = child.stdin.take()
+        && let Err(e) = serde_json::to_writer(&mut stdin, ir)
+        && !(e.is_io() && e.io_error_kind() == Some(std::io::ErrorKind::BrokenPipe))
+    {
+        return Err(e.into());
     }
 
     let output = child.wait_with_output()?;
@@ -2314,11 +3288,12 @@ pub fn run_plugin(
 /// Apply the seed data to a database using the provided connection pool.
 pub async fn apply_seed_data(pool: &sqlx::AnyPool, ir: &ir::SchemaIR) -> anyhow::Result<()> {
     let sql = generate_seed_sql(ir);
-    for stmt in sql.split(';') {
-        let trimmed = stmt.trim();
-        if !trimmed.is_empty() {
-            sqlx::query(trimmed).execute(pool).await?;
-        }
+    let statements = sql_split::split_statements(&sql, &migrations::pool_backend(pool))?;
+    for (index, stmt) in statements.iter().enumerate() {
+        sqlx::query(&stmt.sql)
+            .execute(pool)
+            .await
+            .with_context(|| format!("seed statement {} (line {}) failed", index + 1, stmt.line))?;
     }
     Ok(())
 }
@@ -2338,90 +3313,6 @@ pub fn pull_schema(registry_path: &str, local_path: &str) -> anyhow::Result<()>
     Ok(())
 }
 
-/// Apply any pending migrations in the given directory to the database.
-/// Migration files must end with `.up.sql` and will be executed in name order.
-pub async fn apply_migrations(pool: &sqlx::AnyPool, dir: &str) -> anyhow::Result<()> {
-    use std::fs;
-    use sha2::{Digest, Sha256};
-    use std::time::Instant;
-
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS __rustdbgen_migrations (
-            name TEXT PRIMARY KEY,
-            hash TEXT NOT NULL,
-            applied_at TEXT NOT NULL,
-            execution_time_ms INTEGER,
-            success BOOLEAN NOT NULL
-        )",
-    )
-    .execute(pool)
-    .await?;
-
-    let mut entries: Vec<_> = fs::read_dir(dir)?
-        .filter_map(|e| e.ok())
-        .map(|e| e.path())
-        .filter(|p| {
-            p.extension().map(|ext| ext == "sql").unwrap_or(false)
-                && p.file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|n| n.ends_with(".up.sql") || n.ends_with(".data.sql"))
-                    .unwrap_or(false)
-        })
-        .collect();
-    entries.sort();
-
-    for suffix in [".up.sql", ".data.sql"] {
-        for path in entries.iter().filter(|p| {
-            p.file_name()
-                .and_then(|n| n.to_str())
-                .map(|n| n.ends_with(suffix))
-                .unwrap_or(false)
-        }) {
-            let name = path
-                .file_name()
-                .and_then(|n| n.to_str())
-                .ok_or_else(|| anyhow::anyhow!("invalid migration filename"))?;
-            let sql = fs::read_to_string(path)?;
-            let hash = format!("{:x}", Sha256::digest(sql.as_bytes()));
-            if let Some((stored_hash,)) =
-                sqlx::query_as::<_, (String,)>(
-                    "SELECT hash FROM __rustdbgen_migrations WHERE name = ?"
-                )
-                .bind(name)
-                .fetch_optional(pool)
-                .await?
-            {
-                if stored_hash != hash {
-                    anyhow::bail!("migration {} has changed after being applied", name);
-                }
-                continue;
-            }
-
-            let start = Instant::now();
-            let mut tx = pool.begin().await?;
-            for stmt in sql.split(';') {
-                let trimmed = stmt.trim();
-                if !trimmed.is_empty() {
-                    sqlx::query(trimmed).execute(&mut *tx).await?;
-                }
-            }
-            tx.commit().await?;
-            let duration = start.elapsed().as_millis() as i64;
-
-            sqlx::query(
-                "INSERT INTO __rustdbgen_migrations (name, hash, applied_at, execution_time_ms, success) VALUES (?, ?, ?, ?, 1)"
-            )
-            .bind(name)
-            .bind(hash)
-            .bind(chrono::Utc::now().to_rfc3339())
-            .bind(duration)
-            .execute(pool)
-            .await?;
-        }
-    }
-    Ok(())
-}
-
 /// Create a timestamped data migration file with placeholder contents.
 pub fn create_data_migration(name: &str) -> anyhow::Result<String> {
     use std::fs;
@@ -2433,31 +3324,71 @@ pub fn create_data_migration(name: &str) -> anyhow::Result<String> {
     Ok(path)
 }
 
-/// Serve a minimal web editor for the schema.
-pub async fn serve_editor(addr: &str, schema_path: &str) -> anyhow::Result<()> {
-    use axum::{routing::{get, post}, Router, extract::State, response::Html, http::StatusCode};
+/// Serve a minimal web editor for the schema. `POST /preview` diffs the
+/// posted schema against the last migration snapshot and returns the
+/// operations, their classified changes, lint warnings and the SQL.
+pub async fn serve_editor(
+    addr: &str,
+    schema_path: &str,
+    aliases: HashMap<String, ir::TypeAlias>,
+) -> anyhow::Result<()> {
+    use axum::{routing::{get, post}, Router, extract::State, response::Html, http::StatusCode, Json};
     use std::sync::Arc;
-    use std::path::PathBuf;
 
-    let schema = Arc::new(PathBuf::from(schema_path));
+    struct Editor {
+        schema: PathBuf,
+        aliases: HashMap<String, ir::TypeAlias>,
+    }
+
+    let editor = Arc::new(Editor {
+        schema: PathBuf::from(schema_path),
+        aliases,
+    });
 
     async fn index() -> Html<&'static str> {
         Html(include_str!("../web/editor.html"))
     }
 
-    async fn get_schema(State(p): State<Arc<PathBuf>>) -> Result<String, StatusCode> {
-        tokio::fs::read_to_string(&*p).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    async fn get_schema(State(e): State<Arc<Editor>>) -> Result<String, StatusCode> {
+        tokio::fs::read_to_string(&e.schema).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
     }
 
-    async fn save_schema(State(p): State<Arc<PathBuf>>, body: String) -> Result<StatusCode, StatusCode> {
-        tokio::fs::write(&*p, body).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    async fn save_schema(State(e): State<Arc<Editor>>, body: String) -> Result<StatusCode, StatusCode> {
+        tokio::fs::write(&e.schema, body).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
         Ok(StatusCode::OK)
     }
 
+    async fn preview(
+        State(e): State<Arc<Editor>>,
+        body: String,
+    ) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+        let invalid = |err: anyhow::Error| (StatusCode::UNPROCESSABLE_ENTITY, format!("{:#}", err));
+        let base = e.schema.parent().unwrap_or(Path::new(""));
+        let mut ir = load_schema_text(&body, base).map_err(invalid)?;
+        apply_macros(&mut ir);
+        apply_type_aliases(&mut ir, &e.aliases);
+        apply_model_options(&mut ir);
+        let prev: Option<ir::SchemaIR> = tokio::fs::read_to_string("migrations/schema.json")
+            .await
+            .ok()
+            .and_then(|t| serde_json::from_str(&t).ok());
+        let diff = diff_schemas(prev.as_ref(), &ir).map_err(invalid)?;
+        let (up, down) = diff.to_sql().map_err(invalid)?;
+        let warnings = prev.map(|prev| lint_schema_changes(&prev, &ir)).unwrap_or_default();
+        Ok(Json(serde_json::json!({
+            "operations": diff.operations,
+            "changes": changes::classify_changes(&diff),
+            "warnings": warnings,
+            "up": up,
+            "down": down,
+        })))
+    }
+
     let app = Router::new()
         .route("/", get(index))
         .route("/schema", get(get_schema).post(save_schema))
-        .with_state(schema);
+        .route("/preview", post(preview))
+        .with_state(editor);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
     axum::serve(listener, app.into_make_service()).await?;
@@ -1,16 +1,33 @@
 use anyhow::Result;
 use chrono::Utc;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use rustdbgen::changes::{change_summary, classify_changes, guard_destructive};
+use rustdbgen::drift::{detect_drift, drift_summary};
 use rustdbgen::ir::{SchemaIR, TypeAlias};
+use rustdbgen::verify::verify_migrations_in_shadow;
 use rustdbgen::{
-    apply_macros, apply_migrations, apply_model_options, apply_seed_data, apply_type_aliases,
-    generate_code_multi, generate_graphql_schema, generate_migration, generate_seed_sql,
-    generate_ts_client, generate_typescript, introspect_schema, lint_schema, load_schema,
-    pull_schema, push_schema, run_plugin,
+    apply_macros, apply_migrations_with_options, apply_model_options, apply_seed_data, apply_type_aliases,
+    baseline_migrations, diff_schemas, generate_code_multi, generate_graphql_schema,
+    generate_seed_sql, generate_ts_client, generate_typescript, introspect_schema_timed, introspect_schema_with_options, lint_schema,
+    lint_schema_changes, load_schema, migration_status, prune_squashed_migrations, pull_schema, push_schema,
+    redo_migration, repair_migrations, rollback_migrations, run_plugin, schema_to_toml, squash_migrations,
+    write_split_schema, IntrospectOptions, MigrateOptions, MigrationState, RepairAction, RollbackTarget, SNAPSHOT_SUFFIX,
 };
 use std::fs;
 use which::which;
 
+// --url if given, otherwise DATABASE_URL
+fn database_url(url: Option<String>) -> Result<String> {
+    url.or_else(|| std::env::var("DATABASE_URL").ok())
+        .ok_or_else(|| anyhow::anyhow!("DATABASE_URL not specified"))
+}
+
+fn migrate_options(lock_timeout: u64) -> MigrateOptions {
+    MigrateOptions {
+        lock_timeout: std::time::Duration::from_secs(lock_timeout),
+    }
+}
+
 // load type aliases from type_map.toml if present
 fn load_type_aliases() -> std::collections::HashMap<String, TypeAlias> {
     fs::read_to_string("type_map.toml")
@@ -54,6 +71,9 @@ enum Commands {
         /// Path to write the generated code. Prints to stdout if not set.
         #[arg(long)]
         out: Option<String>,
+        /// Migrations to embed in the generated server
+        #[arg(long, default_value = "migrations")]
+        migrations: String,
     },
     /// Generate TypeScript types and route constants from the schema
     GenerateTs {
@@ -75,12 +95,39 @@ enum Commands {
     },
     /// Introspect an existing database and print a schema snapshot
     Introspect {
-        /// Output path for the introspected schema TOML
+        /// Output path for the introspected schema TOML; with `--split-dir`
+        /// the root file, defaulting to schema.model.toml
         #[arg(long)]
         out: Option<String>,
+        /// Write one file per table to this directory and include them from
+        /// the root schema file
+        #[arg(long)]
+        split_dir: Option<String>,
         /// Database URL; defaults to the DATABASE_URL environment variable
         #[arg(long)]
         url: Option<String>,
+        /// Postgres schema or MySQL database to read instead of the default
+        #[arg(long)]
+        schema: Option<String>,
+        /// Only read tables matching these globs
+        #[arg(long, value_delimiter = ',')]
+        include: Vec<String>,
+        /// Skip tables matching these globs
+        #[arg(long, value_delimiter = ',')]
+        exclude: Vec<String>,
+        /// Print how long each catalog query took to stderr
+        #[arg(long)]
+        verbose: bool,
+    },
+    /// Compare a live database with migrations/schema.json and the applied
+    /// migration history
+    Drift {
+        /// Database URL; defaults to the DATABASE_URL environment variable
+        #[arg(long)]
+        url: Option<String>,
+        /// Print the differences as JSON
+        #[arg(long)]
+        json: bool,
     },
     /// Generate a SQL migration
     Migrate {
@@ -143,18 +190,105 @@ enum MigrateCommands {
         /// Optionally introspect this database to use as the previous schema
         #[arg(long)]
         url: Option<String>,
+        /// Write the migration even if it drops tables, columns or data
+        #[arg(long)]
+        allow_destructive: bool,
     },
     /// Check if a migration is needed without creating files
     Check {
         /// Optionally introspect this database to use as the previous schema
         #[arg(long)]
         url: Option<String>,
+        /// Print a change summary, or the diff's operations as JSON
+        #[arg(long, value_enum, default_value_t = CheckFormat::Text)]
+        format: CheckFormat,
     },
     /// Apply pending migrations to the target database
     Apply {
         /// Database URL; defaults to the DATABASE_URL environment variable
         #[arg(long)]
         url: Option<String>,
+        /// Seconds to wait for another process holding the migration lock
+        #[arg(long, default_value_t = 60)]
+        lock_timeout: u64,
+    },
+    /// Roll back applied migrations by running their down files
+    Down {
+        /// Number of migrations to roll back
+        #[arg(long, default_value_t = 1, conflicts_with = "to")]
+        steps: usize,
+        /// Roll back every migration applied after this one
+        #[arg(long)]
+        to: Option<String>,
+        /// Database URL; defaults to the DATABASE_URL environment variable
+        #[arg(long)]
+        url: Option<String>,
+        /// Seconds to wait for another process holding the migration lock
+        #[arg(long, default_value_t = 60)]
+        lock_timeout: u64,
+    },
+    /// Roll back the last applied migration and apply it again
+    Redo {
+        /// Database URL; defaults to the DATABASE_URL environment variable
+        #[arg(long)]
+        url: Option<String>,
+        /// Seconds to wait for another process holding the migration lock
+        #[arg(long, default_value_t = 60)]
+        lock_timeout: u64,
+    },
+    /// Apply and roll back every migration on a throwaway database and compare
+    /// the results with the schema
+    Verify {
+        /// Postgres or MySQL server to create the shadow database on; SQLite
+        /// schemas default to a temporary file
+        #[arg(long)]
+        shadow_url: Option<String>,
+    },
+    /// Describe an existing database in an initial migration and mark it
+    /// applied without running it
+    Baseline {
+        /// Database URL; defaults to the DATABASE_URL environment variable
+        #[arg(long)]
+        url: Option<String>,
+        /// Seconds to wait for another process holding the migration lock
+        #[arg(long, default_value_t = 60)]
+        lock_timeout: u64,
+        /// Postgres schema or MySQL database to adopt instead of the default
+        #[arg(long)]
+        schema: Option<String>,
+        /// Only adopt tables matching these globs
+        #[arg(long, value_delimiter = ',')]
+        include: Vec<String>,
+        /// Leave out tables matching these globs
+        #[arg(long, value_delimiter = ',')]
+        exclude: Vec<String>,
+    },
+    /// Replace the migrations up to and including one with a single
+    /// equivalent migration
+    Squash {
+        /// Last migration to squash
+        #[arg(long, required_unless_present = "prune")]
+        up_to: Option<String>,
+        /// Delete the files of migrations already squashed instead
+        #[arg(long, conflicts_with = "up_to")]
+        prune: bool,
+    },
+    /// Clear failed attempts and re-hash edited migrations after a manual fix
+    Repair {
+        /// Only repair this migration
+        name: Option<String>,
+        /// Database URL; defaults to the DATABASE_URL environment variable
+        #[arg(long)]
+        url: Option<String>,
+        /// Seconds to wait for another process holding the migration lock
+        #[arg(long, default_value_t = 60)]
+        lock_timeout: u64,
+    },
+    /// List applied, pending, modified, orphaned and failed migrations
+    Status {
+        /// Database URL; defaults to the DATABASE_URL environment variable
+        #[arg(long)]
+        url: Option<String>,
     },
     /// Create a template data migration SQL file
     GenerateData {
@@ -163,6 +297,12 @@ enum MigrateCommands {
     },
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum CheckFormat {
+    Text,
+    Json,
+}
+
 #[derive(Subcommand)]
 enum RegistryCommands {
     /// Copy local schema to registry path
@@ -184,7 +324,7 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Generate { out } => {
+        Commands::Generate { out, migrations } => {
             let mut ir: SchemaIR = load_schema("schema.model.toml")?;
             apply_macros(&mut ir);
             let aliases = load_type_aliases();
@@ -192,7 +332,11 @@ async fn main() -> Result<()> {
             apply_model_options(&mut ir);
             
             let path = out.unwrap_or_else(|| "generated".to_string());
-            generate_code_multi(&ir, std::path::Path::new(&path))?;
+            generate_code_multi(
+                &ir,
+                std::path::Path::new(&path),
+                Some(std::path::Path::new(&migrations)),
+            )?;
         }
         Commands::GenerateTs { out } => {
             let mut ir: SchemaIR = load_schema("schema.model.toml")?;
@@ -233,21 +377,73 @@ async fn main() -> Result<()> {
                 println!("{}", gql);
             }
         }
-        Commands::Introspect { out, url } => {
+        Commands::Drift { url, json } => {
+            let url = database_url(url)?;
+            let snapshot: SchemaIR = serde_json::from_str(
+                &fs::read_to_string("migrations/schema.json")
+                    .map_err(|e| anyhow::anyhow!("cannot read migrations/schema.json: {}", e))?,
+            )?;
+            let drift = detect_drift(&url, &snapshot, "migrations").await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&drift)?);
+            } else if drift.is_empty() {
+                println!("No drift detected");
+            } else {
+                println!("Drift:\n{}", drift_summary(&drift));
+            }
+            if !drift.is_empty() {
+                anyhow::bail!("Drift detected");
+            }
+        }
+        Commands::Introspect {
+            out,
+            split_dir,
+            url,
+            schema,
+            include,
+            exclude,
+            verbose,
+        } => {
             let url = url
                 .or_else(|| std::env::var("DATABASE_URL").ok())
                 .ok_or_else(|| anyhow::anyhow!("DATABASE_URL not specified"))?;
             let db_map = load_db_type_map();
-            let ir = introspect_schema(&url, &db_map).await?;
-            let toml = toml::to_string_pretty(&ir)?;
-            if let Some(path) = out {
-                fs::write(path, toml)?;
+            let options = IntrospectOptions {
+                schema,
+                include,
+                exclude,
+            };
+            let (ir, timings) = introspect_schema_timed(&url, &db_map, &options).await?;
+            if verbose {
+                for t in &timings {
+                    eprintln!("  {:<14}{:>8} rows {:>8} ms", t.step, t.rows, t.elapsed.as_millis());
+                }
+                let total: std::time::Duration = timings.iter().map(|t| t.elapsed).sum();
+                eprintln!("  {:<14}{:>22} ms", "total", total.as_millis());
+            }
+            if let Some(dir) = split_dir {
+                let root = out.unwrap_or_else(|| "schema.model.toml".into());
+                let written =
+                    write_split_schema(&ir, std::path::Path::new(&root), std::path::Path::new(&dir))?;
+                for path in written {
+                    println!("Wrote {}", path.display());
+                }
             } else {
-                println!("{}", toml);
+                let toml = schema_to_toml(&ir)?;
+                if let Some(path) = out {
+                    fs::write(path, toml)?;
+                } else {
+                    println!("{}", toml);
+                }
             }
         }
         Commands::Migrate {
-            command: MigrateCommands::Generate { name, url },
+            command:
+                MigrateCommands::Generate {
+                    name,
+                    url,
+                    allow_destructive,
+                },
         } => {
             let mut ir: SchemaIR = load_schema("schema.model.toml")?;
             apply_macros(&mut ir);
@@ -257,20 +453,34 @@ async fn main() -> Result<()> {
             let prev_ir: Option<SchemaIR> =
                 if let Some(url) = url.or_else(|| std::env::var("DATABASE_URL").ok()) {
                     let db_map = load_db_type_map();
-                    Some(introspect_schema(&url, &db_map).await?)
+                    let options = IntrospectOptions::for_schema(&ir);
+                    Some(introspect_schema_with_options(&url, &db_map, &options).await?)
                 } else {
                     fs::read_to_string("migrations/schema.json")
                         .ok()
                         .and_then(|t| serde_json::from_str(&t).ok())
                 };
-            let (up_sql, down_sql) = generate_migration(prev_ir.as_ref(), &ir)?;
+            if let Some(prev) = &prev_ir {
+                for w in lint_schema_changes(prev, &ir) {
+                    eprintln!("warning: {}", w);
+                }
+            }
+            let diff = diff_schemas(prev_ir.as_ref(), &ir)?;
+            let (up_sql, down_sql) = diff.to_sql()?;
+            let changes = classify_changes(&diff);
+            if !changes.is_empty() {
+                println!("Changes:\n{}", change_summary(&changes));
+            }
+            guard_destructive(&changes, allow_destructive)?;
             fs::create_dir_all("migrations")?;
             let ts = Utc::now().format("%Y%m%d%H%M%S");
             let up_path = format!("migrations/{}_{}.up.sql", ts, name);
             let down_path = format!("migrations/{}_{}.down.sql", ts, name);
             fs::write(&up_path, up_sql)?;
             fs::write(&down_path, down_sql)?;
-            fs::write("migrations/schema.json", serde_json::to_string_pretty(&ir)?)?;
+            let snapshot = serde_json::to_string_pretty(&ir)?;
+            fs::write(format!("migrations/{}_{}{}", ts, name, SNAPSHOT_SUFFIX), &snapshot)?;
+            fs::write("migrations/schema.json", snapshot)?;
             println!("Created migration: {}", up_path);
         }
         Commands::Migrate {
@@ -280,7 +490,7 @@ async fn main() -> Result<()> {
             println!("Created data migration: {}", path);
         }
         Commands::Migrate {
-            command: MigrateCommands::Check { url },
+            command: MigrateCommands::Check { url, format },
         } => {
             let mut ir: SchemaIR = load_schema("schema.model.toml")?;
             apply_macros(&mut ir);
@@ -290,29 +500,200 @@ async fn main() -> Result<()> {
             let prev_ir: Option<SchemaIR> =
                 if let Some(url) = url.or_else(|| std::env::var("DATABASE_URL").ok()) {
                     let db_map = load_db_type_map();
-                    Some(introspect_schema(&url, &db_map).await?)
+                    let options = IntrospectOptions::for_schema(&ir);
+                    Some(introspect_schema_with_options(&url, &db_map, &options).await?)
                 } else {
                     fs::read_to_string("migrations/schema.json")
                         .ok()
                         .and_then(|t| serde_json::from_str(&t).ok())
                 };
-            let (up_sql, _down_sql) = generate_migration(prev_ir.as_ref(), &ir)?;
-            if up_sql.trim().is_empty() {
-                println!("Schema is up to date");
-            } else {
+            let diff = diff_schemas(prev_ir.as_ref(), &ir)?;
+            let (up_sql, _down_sql) = diff.to_sql()?;
+            match format {
+                CheckFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+                CheckFormat::Text if up_sql.trim().is_empty() => println!("Schema is up to date"),
+                CheckFormat::Text => {
+                    let changes = classify_changes(&diff);
+                    println!("Changes:\n{}", change_summary(&changes));
+                }
+            }
+            if !up_sql.trim().is_empty() {
                 anyhow::bail!("Pending migration detected");
             }
         }
         Commands::Migrate {
-            command: MigrateCommands::Apply { url },
+            command: MigrateCommands::Apply { url, lock_timeout },
         } => {
-            let url = url
-                .or_else(|| std::env::var("DATABASE_URL").ok())
-                .ok_or_else(|| anyhow::anyhow!("DATABASE_URL not specified"))?;
-            let pool = rustdbgen::connect_any_pool(&url).await?;
-            apply_migrations(&pool, "migrations").await?;
+            let pool = rustdbgen::connect_any_pool(&database_url(url)?).await?;
+            for status in migration_status(&pool, "migrations").await? {
+                if status.state == MigrationState::Failed {
+                    eprintln!(
+                        "previous attempt of {} failed: {}",
+                        status.name,
+                        status.error.unwrap_or_default()
+                    );
+                }
+            }
+            apply_migrations_with_options(&pool, "migrations", &migrate_options(lock_timeout)).await?;
             println!("Migrations applied");
         }
+        Commands::Migrate {
+            command: MigrateCommands::Down {
+                    steps,
+                    to,
+                    url,
+                    lock_timeout,
+                },
+        } => {
+            let pool = rustdbgen::connect_any_pool(&database_url(url)?).await?;
+            let target = match to {
+                Some(name) => RollbackTarget::To(name),
+                None => RollbackTarget::Steps(steps),
+            };
+            let rolled_back = rollback_migrations(&pool, "migrations", target, &migrate_options(lock_timeout)).await?;
+            if rolled_back.is_empty() {
+                println!("Nothing to roll back");
+            }
+            for migration in rolled_back {
+                if migration.reverted {
+                    println!("Rolled back {}", migration.name);
+                } else {
+                    eprintln!(
+                        "warning: {} has no .data.down.sql; removed it from the history without undoing its data changes",
+                        migration.name
+                    );
+                }
+            }
+        }
+        Commands::Migrate {
+            command: MigrateCommands::Redo { url, lock_timeout },
+        } => {
+            let pool = rustdbgen::connect_any_pool(&database_url(url)?).await?;
+            match redo_migration(&pool, "migrations", &migrate_options(lock_timeout)).await? {
+                Some(name) => println!("Redid {}", name),
+                None => println!("Nothing to redo"),
+            }
+        }
+        Commands::Migrate {
+            command: MigrateCommands::Status { url },
+        } => {
+            let pool = rustdbgen::connect_any_pool(&database_url(url)?).await?;
+            for status in migration_status(&pool, "migrations").await? {
+                let applied_at = status.applied_at.unwrap_or_default();
+                let ms = status
+                    .execution_time_ms
+                    .map(|ms| format!("{}ms", ms))
+                    .unwrap_or_default();
+                let line = format!(
+                    "{:<10}{:<50}{:<34}{}",
+                    status.state.label(),
+                    status.name,
+                    applied_at,
+                    ms
+                );
+                println!("{}", line.trim_end());
+                if let Some(error) = status.error {
+                    println!("          error: {}", error);
+                }
+                if let Some(statement) = status.failed_statement {
+                    println!("          statement: {}", statement);
+                }
+                if !status.replaces.is_empty() {
+                    println!("          squashes: {}", status.replaces.join(", "));
+                }
+            }
+        }
+        Commands::Migrate {
+            command: MigrateCommands::Verify { shadow_url },
+        } => {
+            let mut ir: SchemaIR = load_schema("schema.model.toml")?;
+            apply_macros(&mut ir);
+            let aliases = load_type_aliases();
+            apply_type_aliases(&mut ir, &aliases);
+            apply_model_options(&mut ir);
+            let report = verify_migrations_in_shadow(&ir, "migrations", shadow_url.as_deref()).await?;
+            for name in &report.applied {
+                println!("Applied {}", name);
+            }
+            for name in &report.rolled_back {
+                println!("Rolled back {}", name);
+            }
+            for problem in &report.problems {
+                eprintln!("error: {}", problem);
+            }
+            if !report.passed() {
+                anyhow::bail!("Migration verification failed");
+            }
+            println!("Migrations verified");
+        }
+        Commands::Migrate {
+            command:
+                MigrateCommands::Baseline {
+                    url,
+                    lock_timeout,
+                    schema,
+                    include,
+                    exclude,
+                },
+        } => {
+            let pool = rustdbgen::connect_any_pool(&database_url(url)?).await?;
+            let options = IntrospectOptions {
+                schema,
+                include,
+                exclude,
+            };
+            let name = baseline_migrations(
+                &pool,
+                "migrations",
+                &load_db_type_map(),
+                &options,
+                &migrate_options(lock_timeout),
+            )
+            .await?;
+            println!("Created baseline migration migrations/{} and marked it applied", name);
+        }
+        Commands::Migrate {
+            command: MigrateCommands::Squash { up_to, .. },
+        } => match up_to {
+            Some(up_to) => {
+                let squashed = squash_migrations("migrations", &up_to)?;
+                println!(
+                    "Squashed {} migrations into migrations/{}; their files stay until `migrate squash --prune`",
+                    squashed.replaces.len(),
+                    squashed.name
+                );
+            }
+            None => {
+                for name in prune_squashed_migrations("migrations")? {
+                    println!("Pruned migrations/{}", name);
+                }
+            }
+        },
+        Commands::Migrate {
+            command: MigrateCommands::Repair {
+                    name,
+                    url,
+                    lock_timeout,
+                },
+        } => {
+            let pool = rustdbgen::connect_any_pool(&database_url(url)?).await?;
+            let repaired = repair_migrations(
+                &pool,
+                "migrations",
+                name.as_deref(),
+                &migrate_options(lock_timeout),
+            )
+            .await?;
+            if repaired.is_empty() {
+                println!("Nothing to repair");
+            }
+            for migration in repaired {
+                match migration.action {
+                    RepairAction::ClearedFailure => println!("Cleared failed attempt of {}", migration.name),
+                    RepairAction::Rehashed => println!("Re-hashed {}", migration.name),
+                }
+            }
+        }
         Commands::Lint => {
             let mut ir: SchemaIR = load_schema("schema.model.toml")?;
             apply_macros(&mut ir);
@@ -320,6 +701,14 @@ async fn main() -> Result<()> {
             apply_type_aliases(&mut ir, &aliases);
             apply_model_options(&mut ir);
             let errors = lint_schema(&ir);
+            let snapshot: Option<SchemaIR> = fs::read_to_string("migrations/schema.json")
+                .ok()
+                .and_then(|t| serde_json::from_str(&t).ok());
+            if let Some(prev) = &snapshot {
+                for w in lint_schema_changes(prev, &ir) {
+                    eprintln!("warning: {}", w);
+                }
+            }
             if errors.is_empty() {
                 println!("Schema lint passed");
             } else {
@@ -413,7 +802,7 @@ async fn main() -> Result<()> {
             }
         }
         Commands::Serve { addr, schema } => {
-            rustdbgen::serve_editor(&addr, &schema).await?;
+            rustdbgen::serve_editor(&addr, &schema, load_type_aliases()).await?;
         }
     }
 
@@ -0,0 +1,1038 @@
+//! Running migration files against a database and keeping the
+//! `__rustdbgen_migrations` history in step: apply, roll back, redo, status,
+//! repair, baseline and squash. Commands that change the schema hold a backend-specific lock for
+//! their whole run so concurrent deploys cannot apply a migration twice.
+
+use crate::{IntrospectOptions, generate_initial_migration, infer_backend_from_url, introspect_schema_with_options};
+use crate::ir::{DatabaseBackend, SchemaIR};
+use crate::sql_split::{Statement, split_statements};
+use anyhow::{Context, Result, anyhow, bail};
+use sha2::{Digest, Sha256};
+use sqlx::pool::PoolConnection;
+use sqlx::{Any, AnyConnection, AnyPool, Connection};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub(crate) const UP_SUFFIX: &str = ".up.sql";
+const DATA_SUFFIX: &str = ".data.sql";
+const DOWN_SUFFIX: &str = ".down.sql";
+/// Optional down file of a data migration
+const DATA_DOWN_SUFFIX: &str = ".data.down.sql";
+/// Schema snapshot written next to each generated migration
+pub const SNAPSHOT_SUFFIX: &str = ".schema.json";
+
+/// Header comment that makes the runner execute a migration file outside a
+/// transaction, statement by statement. Needed for statements Postgres
+/// refuses inside one, such as `CREATE INDEX CONCURRENTLY`.
+pub const NO_TRANSACTION_DIRECTIVE: &str = "-- rustdbgen:no-transaction";
+
+/// Header comment of a squashed migration, one line per migration file it
+/// replaces: `-- rustdbgen:squashes 20240101000000_init.up.sql`.
+pub const SQUASHES_DIRECTIVE: &str = "-- rustdbgen:squashes";
+
+/// MySQL `GET_LOCK` name.
+pub(crate) const LOCK_NAME: &str = "__rustdbgen_migrations";
+/// Postgres advisory lock key, the bytes of "rdbgmigr".
+pub(crate) const ADVISORY_LOCK_KEY: i64 = 0x7264_6267_6d69_6772;
+/// How often SQLite and Postgres retry a lock held by another process.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    /// Recorded in the history with a matching hash
+    Applied,
+    /// Present on disk but not applied yet
+    Pending,
+    /// Applied, but the file changed since
+    Modified,
+    /// Recorded in the history without a file on disk
+    Orphaned,
+    /// The last attempt to apply it failed
+    Failed,
+    /// Not applied, and replaced by a squashed migration; kept on disk until
+    /// `prune_squashed_migrations` removes it
+    Squashed,
+}
+
+impl MigrationState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::Modified => "modified",
+            MigrationState::Orphaned => "orphaned",
+            MigrationState::Failed => "failed",
+            MigrationState::Squashed => "squashed",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub name: String,
+    pub state: MigrationState,
+    pub applied_at: Option<String>,
+    pub execution_time_ms: Option<i64>,
+    /// Why the last attempt failed, for [`MigrationState::Failed`]
+    pub error: Option<String>,
+    /// The statement that failed, when the file could be parsed
+    pub failed_statement: Option<String>,
+    /// Migrations this squashed migration took the place of
+    pub replaces: Vec<String>,
+}
+
+/// The migration `squash_migrations` wrote and the files it replaced.
+#[derive(Debug, Clone)]
+pub struct SquashedMigration {
+    pub name: String,
+    pub replaces: Vec<String>,
+}
+
+/// What `repair_migrations` did to a history row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepairAction {
+    /// Removed the record of a failed attempt so the migration runs again
+    ClearedFailure,
+    /// Stored the current hash of a file edited after it was applied
+    Rehashed,
+}
+
+#[derive(Debug, Clone)]
+pub struct RepairedMigration {
+    pub name: String,
+    pub action: RepairAction,
+}
+
+/// A migration `rollback_migrations` removed from the history.
+#[derive(Debug, Clone)]
+pub struct RolledBackMigration {
+    pub name: String,
+    /// A down file undid its changes. False for a data migration without a
+    /// `.data.down.sql`, whose data changes stay in place.
+    pub reverted: bool,
+}
+
+/// How far `rollback_migrations` goes back.
+#[derive(Debug, Clone)]
+pub enum RollbackTarget {
+    /// Undo the last `n` applied migrations
+    Steps(usize),
+    /// Undo everything applied after the named migration, keeping it
+    To(String),
+}
+
+/// Settings for the commands that apply or roll back migrations.
+#[derive(Debug, Clone)]
+pub struct MigrateOptions {
+    /// How long to wait for another process holding the migration lock
+    pub lock_timeout: Duration,
+}
+
+impl Default for MigrateOptions {
+    fn default() -> Self {
+        MigrateOptions {
+            lock_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+struct HistoryRow {
+    name: String,
+    hash: String,
+    applied_at: String,
+    execution_time_ms: Option<i64>,
+    /// Set for failed attempts only
+    error: Option<String>,
+    failed_statement: Option<String>,
+    /// Migrations a squashed migration took the place of
+    replaces: Vec<String>,
+}
+
+impl HistoryRow {
+    fn failed(&self) -> bool {
+        self.error.is_some()
+    }
+}
+
+/// A statement of a migration file that the database rejected.
+struct FailedStatement {
+    index: usize,
+    line: usize,
+    sql: String,
+    error: sqlx::Error,
+}
+
+impl FailedStatement {
+    fn into_error(self, name: &str) -> anyhow::Error {
+        anyhow::Error::new(self.error).context(format!(
+            "{}: statement {} (line {}) failed",
+            name,
+            self.index + 1,
+            self.line
+        ))
+    }
+}
+
+/// The backend a pool is connected to, going by its URL.
+pub(crate) fn pool_backend(pool: &AnyPool) -> DatabaseBackend {
+    infer_backend_from_url(pool.connect_options().database_url.as_str()).unwrap_or_default()
+}
+
+/// Rewrite `?` placeholders for Postgres, which numbers its parameters.
+fn placeholders(backend: &DatabaseBackend, sql: &str) -> String {
+    if !matches!(backend, DatabaseBackend::Postgres) {
+        return sql.to_string();
+    }
+    let mut out = String::new();
+    let mut n = 0;
+    for c in sql.chars() {
+        if c == '?' {
+            n += 1;
+            out.push_str(&format!("${}", n));
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Take the migration lock on a connection of its own, waiting up to
+/// `options.lock_timeout`. Postgres uses a session advisory lock and MySQL a
+/// named lock, both released if the process dies. SQLite has neither, so it
+/// inserts the single row of `__rustdbgen_migrations_lock`.
+async fn lock(
+    pool: &AnyPool,
+    options: &MigrateOptions,
+) -> Result<(PoolConnection<Any>, DatabaseBackend)> {
+    let backend = pool_backend(pool);
+    let mut conn = pool.acquire().await?;
+    let deadline = Instant::now() + options.lock_timeout;
+    let acquired = match backend {
+        DatabaseBackend::Postgres => loop {
+            // cast, as the Any driver cannot decode Postgres booleans
+            let (locked,): (i32,) = sqlx::query_as("SELECT pg_try_advisory_lock($1)::int")
+                .bind(ADVISORY_LOCK_KEY)
+                .fetch_one(&mut *conn)
+                .await?;
+            if locked == 1 || Instant::now() >= deadline {
+                break locked == 1;
+            }
+            tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+        },
+        DatabaseBackend::Mysql => {
+            let (locked,): (Option<i64>,) = sqlx::query_as("SELECT GET_LOCK(?, ?)")
+                .bind(LOCK_NAME)
+                .bind(options.lock_timeout.as_secs_f64())
+                .fetch_one(&mut *conn)
+                .await?;
+            locked == Some(1)
+        }
+        DatabaseBackend::Sqlite => {
+            sqlx::query(
+                "CREATE TABLE IF NOT EXISTS __rustdbgen_migrations_lock (
+                    id INTEGER PRIMARY KEY,
+                    locked_at TEXT NOT NULL
+                )",
+            )
+            .execute(&mut *conn)
+            .await?;
+            loop {
+                let inserted = sqlx::query(
+                    "INSERT OR IGNORE INTO __rustdbgen_migrations_lock (id, locked_at) VALUES (1, ?)",
+                )
+                .bind(chrono::Utc::now().to_rfc3339())
+                .execute(&mut *conn)
+                .await?
+                .rows_affected()
+                    == 1;
+                if inserted || Instant::now() >= deadline {
+                    break inserted;
+                }
+                tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+            }
+        }
+    };
+    if !acquired {
+        let hint = match backend {
+            DatabaseBackend::Sqlite => {
+                "; if no other process is migrating, delete the row in __rustdbgen_migrations_lock"
+            }
+            _ => "",
+        };
+        bail!(
+            "timed out after {:?} waiting for the migration lock held by another process{}",
+            options.lock_timeout,
+            hint
+        );
+    }
+    ensure_history_table(&mut conn).await?;
+    Ok((conn, backend))
+}
+
+async fn unlock(conn: &mut AnyConnection, backend: &DatabaseBackend) -> Result<()> {
+    match backend {
+        DatabaseBackend::Postgres => {
+            sqlx::query("SELECT pg_advisory_unlock($1)::int")
+                .bind(ADVISORY_LOCK_KEY)
+                .execute(conn)
+                .await?;
+        }
+        DatabaseBackend::Mysql => {
+            sqlx::query("SELECT RELEASE_LOCK(?)")
+                .bind(LOCK_NAME)
+                .execute(conn)
+                .await?;
+        }
+        DatabaseBackend::Sqlite => {
+            sqlx::query("DELETE FROM __rustdbgen_migrations_lock WHERE id = 1")
+                .execute(conn)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) async fn ensure_history_table(conn: &mut AnyConnection) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS __rustdbgen_migrations (
+            name VARCHAR(255) PRIMARY KEY,
+            hash TEXT NOT NULL,
+            applied_at TEXT NOT NULL,
+            execution_time_ms INTEGER,
+            success BOOLEAN NOT NULL,
+            error TEXT,
+            failed_statement TEXT,
+            replaces TEXT
+        )",
+    )
+    .execute(&mut *conn)
+    .await?;
+    // histories created before failed attempts and squashes were recorded
+    for column in ["error", "failed_statement", "replaces"] {
+        if sqlx::query(&format!("SELECT {} FROM __rustdbgen_migrations WHERE 1 = 0", column))
+            .execute(&mut *conn)
+            .await
+            .is_err()
+        {
+            sqlx::query(&format!("ALTER TABLE __rustdbgen_migrations ADD COLUMN {} TEXT", column))
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// History rows in the order they were applied or attempted. Failures are
+/// told apart by their `error`, as the Any driver cannot decode Postgres
+/// booleans, and the text columns are coalesced because it cannot decode a
+/// NULL one either.
+async fn history(conn: &mut AnyConnection) -> Result<Vec<HistoryRow>> {
+    let rows = sqlx::query_as::<_, (String, String, String, Option<i64>, String, String, String)>(
+        "SELECT name, hash, applied_at, execution_time_ms, COALESCE(error, ''), COALESCE(failed_statement, ''), \
+         COALESCE(replaces, '') FROM __rustdbgen_migrations ORDER BY applied_at, name",
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(
+            |(name, hash, applied_at, execution_time_ms, error, failed_statement, replaces)| HistoryRow {
+                name,
+                hash,
+                applied_at,
+                execution_time_ms,
+                error: Some(error).filter(|e| !e.is_empty()),
+                failed_statement: Some(failed_statement).filter(|s| !s.is_empty()),
+                replaces: replaces
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|n| !n.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            },
+        )
+        .collect())
+}
+
+/// Applied migrations, leaving out failed attempts.
+async fn applied_history(conn: &mut AnyConnection) -> Result<Vec<HistoryRow>> {
+    Ok(history(conn).await?.into_iter().filter(|r| !r.failed()).collect())
+}
+
+/// `.up.sql` files in name order followed by `.data.sql` files, the order
+/// `apply_migrations` runs them in.
+pub(crate) fn migration_files(dir: &str) -> Result<Vec<PathBuf>> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+    let mut files = Vec::new();
+    for suffix in [UP_SUFFIX, DATA_SUFFIX] {
+        files.extend(
+            entries
+                .iter()
+                .filter(|p| file_name(p).is_some_and(|n| n.ends_with(suffix)))
+                .cloned(),
+        );
+    }
+    Ok(files)
+}
+
+pub(crate) fn file_name(path: &Path) -> Option<&str> {
+    path.file_name().and_then(|n| n.to_str())
+}
+
+pub(crate) fn file_hash(sql: &str) -> String {
+    format!("{:x}", Sha256::digest(sql.as_bytes()))
+}
+
+/// Whether the comment lines heading `sql` carry [`NO_TRANSACTION_DIRECTIVE`].
+pub(crate) fn is_no_transaction(sql: &str) -> bool {
+    sql.lines()
+        .map(str::trim)
+        .take_while(|line| line.is_empty() || line.starts_with("--"))
+        .any(|line| line == NO_TRANSACTION_DIRECTIVE)
+}
+
+/// Migration files named by the [`SQUASHES_DIRECTIVE`] lines heading `sql`.
+pub(crate) fn squashed_names(sql: &str) -> Vec<String> {
+    sql.lines()
+        .map(str::trim)
+        .take_while(|line| line.is_empty() || line.starts_with("--"))
+        .filter_map(|line| line.strip_prefix(SQUASHES_DIRECTIVE))
+        .filter(|rest| rest.starts_with(char::is_whitespace))
+        .map(|rest| rest.trim().to_string())
+        .collect()
+}
+
+/// Each squashed migration in `dir` with the files it replaces.
+pub(crate) fn squashes(dir: &str) -> Result<Vec<(String, Vec<String>)>> {
+    let mut out = Vec::new();
+    for path in migration_files(dir)? {
+        let name = file_name(&path).ok_or_else(|| anyhow!("invalid migration filename"))?;
+        let replaces = squashed_names(&fs::read_to_string(&path)?);
+        if !replaces.is_empty() {
+            out.push((name.to_string(), replaces));
+        }
+    }
+    Ok(out)
+}
+
+/// Statements of a migration file. The `BEGIN;`/`COMMIT;` wrapper is
+/// dropped because the runner already executes each file in a transaction,
+/// unless the file opted out of it, in which case its own transaction
+/// statements run as written.
+pub(crate) fn migration_statements(backend: &DatabaseBackend, sql: &str) -> Result<Vec<Statement>> {
+    let keep_transaction = is_no_transaction(sql);
+    Ok(split_statements(sql, backend)?
+        .into_iter()
+        .filter(|stmt| {
+            keep_transaction
+                || !matches!(
+                    stmt.sql.to_uppercase().as_str(),
+                    "BEGIN" | "BEGIN TRANSACTION" | "START TRANSACTION" | "COMMIT" | "END"
+                )
+        })
+        .collect())
+}
+
+async fn execute_statements(
+    conn: &mut AnyConnection,
+    statements: &[Statement],
+) -> std::result::Result<(), FailedStatement> {
+    for (index, stmt) in statements.iter().enumerate() {
+        if let Err(error) = sqlx::query(&stmt.sql).execute(&mut *conn).await {
+            return Err(FailedStatement {
+                index,
+                line: stmt.line,
+                sql: stmt.sql.clone(),
+                error,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Run every statement of the migration file `name` on `conn`, naming the
+/// failing statement by index and line.
+async fn run_statements(
+    conn: &mut AnyConnection,
+    backend: &DatabaseBackend,
+    name: &str,
+    sql: &str,
+) -> Result<()> {
+    let statements = migration_statements(backend, sql).with_context(|| format!("cannot parse {}", name))?;
+    execute_statements(conn, &statements)
+        .await
+        .map_err(|failed| failed.into_error(name))
+}
+
+/// Run `sql` in a transaction and record it in the history in the same
+/// transaction. Files marked with [`NO_TRANSACTION_DIRECTIVE`] run directly
+/// on the connection and are recorded once every statement succeeded. A
+/// failure is recorded too, with the error and the failing statement.
+pub(crate) async fn apply_file(
+    conn: &mut AnyConnection,
+    backend: &DatabaseBackend,
+    name: &str,
+    sql: &str,
+) -> Result<()> {
+    let start = Instant::now();
+    let statements = match migration_statements(backend, sql) {
+        Ok(statements) => statements,
+        Err(e) => {
+            let err = e.context(format!("cannot parse {}", name));
+            record(conn, backend, name, sql, start, Some((&err, None))).await?;
+            return Err(err);
+        }
+    };
+    if is_no_transaction(sql) {
+        if let Err(failed) = execute_statements(conn, &statements).await {
+            // leave a transaction the file opened, or the failure cannot be recorded
+            let _ = sqlx::query("ROLLBACK").execute(&mut *conn).await;
+            let statement = failed.sql.clone();
+            let err = failed.into_error(name);
+            record(conn, backend, name, sql, start, Some((&err, Some(&statement)))).await?;
+            return Err(err);
+        }
+        return record(conn, backend, name, sql, start, None).await;
+    }
+    let mut tx = conn.begin().await?;
+    if let Err(failed) = execute_statements(&mut tx, &statements).await {
+        tx.rollback().await?;
+        let statement = failed.sql.clone();
+        let err = failed.into_error(name);
+        record(conn, backend, name, sql, start, Some((&err, Some(&statement)))).await?;
+        return Err(err);
+    }
+    record(&mut tx, backend, name, sql, start, None).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Write the history row of an attempt to apply `name`, replacing the row of
+/// an earlier failed attempt. `failure` holds the error and failing
+/// statement of an attempt that did not succeed. A squashed migration's row
+/// lists the migrations it replaces.
+async fn record(
+    conn: &mut AnyConnection,
+    backend: &DatabaseBackend,
+    name: &str,
+    sql: &str,
+    start: Instant,
+    failure: Option<(&anyhow::Error, Option<&str>)>,
+) -> Result<()> {
+    let duration = start.elapsed().as_millis() as i64;
+    forget_applied(conn, backend, name).await?;
+    // The Any driver prepares a NULL bind as an integer and caches the
+    // statement by its text, so the optional columns always bind text and
+    // NULLIF turns the empty ones back into NULL.
+    sqlx::query(&placeholders(
+        backend,
+        "INSERT INTO __rustdbgen_migrations \
+         (name, hash, applied_at, execution_time_ms, success, error, failed_statement, replaces) \
+         VALUES (?, ?, ?, ?, ?, NULLIF(?, ''), NULLIF(?, ''), NULLIF(?, ''))",
+    ))
+    .bind(name)
+    .bind(file_hash(sql))
+    .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true))
+    .bind(duration)
+    .bind(failure.is_none())
+    .bind(failure.map(|(err, _)| format!("{:#}", err)).unwrap_or_default())
+    .bind(failure.and_then(|(_, statement)| statement).unwrap_or_default())
+    .bind(squashed_names(sql).join(", "))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn forget_applied(conn: &mut AnyConnection, backend: &DatabaseBackend, name: &str) -> Result<()> {
+    sqlx::query(&placeholders(
+        backend,
+        "DELETE FROM __rustdbgen_migrations WHERE name = ?",
+    ))
+    .bind(name)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Apply any pending migrations in the given directory to the database.
+/// Migration files must end with `.up.sql` and will be executed in name order.
+pub async fn apply_migrations(pool: &AnyPool, dir: &str) -> Result<()> {
+    apply_migrations_with_options(pool, dir, &MigrateOptions::default()).await
+}
+
+/// [`apply_migrations`] with explicit options.
+pub async fn apply_migrations_with_options(
+    pool: &AnyPool,
+    dir: &str,
+    options: &MigrateOptions,
+) -> Result<()> {
+    let (mut conn, backend) = lock(pool, options).await?;
+    let result = apply_pending(&mut conn, &backend, dir).await;
+    let unlocked = unlock(&mut conn, &backend).await;
+    result?;
+    unlocked
+}
+
+/// Apply the migrations that are not in the history. A migration whose last
+/// attempt failed inside a transaction left nothing behind and is retried;
+/// one that ran without a transaction may be half applied, so it blocks
+/// until the database is fixed by hand and `repair_migrations` clears it.
+///
+/// A squashed migration is not run on a database that applied every
+/// migration it replaces: it is recorded in their place instead. The
+/// replaced files still on disk only run to bring a database that applied
+/// some of them up to the squash.
+async fn apply_pending(conn: &mut AnyConnection, backend: &DatabaseBackend, dir: &str) -> Result<()> {
+    let mut recorded = history(conn).await?;
+    let squashes = squashes(dir)?;
+    for path in migration_files(dir)? {
+        let name = file_name(&path).ok_or_else(|| anyhow!("invalid migration filename"))?;
+        let sql = fs::read_to_string(&path)?;
+        let applied = |recorded: &[HistoryRow], name: &str| recorded.iter().any(|r| r.name == name && !r.failed());
+        if let Some(row) = recorded.iter().find(|r| r.name == name) {
+            if row.failed() {
+                if is_no_transaction(&sql) {
+                    bail!(
+                        "{}\nthe migration runs without a transaction, so the statements before the \
+                         failing one stay applied; fix the database by hand, then run `migrate repair`",
+                        previous_failure(row)
+                    );
+                }
+            } else if row.hash != file_hash(&sql) {
+                bail!("migration {} has changed after being applied", name);
+            } else {
+                continue;
+            }
+        }
+        if let Some((squash, replaces)) = squashes.iter().find(|(_, replaces)| replaces.iter().any(|r| r == name)) {
+            let part_way = !applied(&recorded, squash) && replaces.iter().any(|r| applied(&recorded, r));
+            if !part_way {
+                continue;
+            }
+        }
+        let replaces = squashed_names(&sql);
+        let done: Vec<&str> = replaces
+            .iter()
+            .filter(|r| applied(&recorded, r))
+            .map(String::as_str)
+            .collect();
+        if !replaces.is_empty() && done.len() == replaces.len() {
+            adopt_squashed(conn, backend, name, &sql, &replaces).await?;
+            continue;
+        }
+        if !done.is_empty() {
+            bail!(
+                "{} squashes migrations this database applied only some of ({}), and the \
+                 original files of the rest were pruned; restore them and apply again",
+                name,
+                done.join(", ")
+            );
+        }
+        apply_file(conn, backend, name, &sql).await?;
+        recorded = history(conn).await?;
+    }
+    Ok(())
+}
+
+/// Replace the history rows of `replaces` with one for the squashed
+/// migration `name`, in one transaction, without running it.
+async fn adopt_squashed(
+    conn: &mut AnyConnection,
+    backend: &DatabaseBackend,
+    name: &str,
+    sql: &str,
+    replaces: &[String],
+) -> Result<()> {
+    let start = Instant::now();
+    let mut tx = conn.begin().await?;
+    for replaced in replaces {
+        forget_applied(&mut tx, backend, replaced).await?;
+    }
+    record(&mut tx, backend, name, sql, start, None).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// "previous attempt failed" report of a failed history row.
+fn previous_failure(row: &HistoryRow) -> String {
+    let mut report = format!(
+        "previous attempt of {} failed at {}: {}",
+        row.name,
+        row.applied_at,
+        row.error.as_deref().unwrap_or_default()
+    );
+    if let Some(statement) = &row.failed_statement {
+        report.push_str(&format!("\nfailing statement: {}", statement));
+    }
+    report
+}
+
+/// Every migration on disk or in the history, in apply order, with orphaned
+/// history rows last.
+pub async fn migration_status(pool: &AnyPool, dir: &str) -> Result<Vec<MigrationStatus>> {
+    let mut conn = pool.acquire().await?;
+    ensure_history_table(&mut conn).await?;
+    let applied = history(&mut conn).await?;
+    let squashes = squashes(dir)?;
+    let mut out = Vec::new();
+    let files = migration_files(dir)?;
+    for path in &files {
+        let name = file_name(path).ok_or_else(|| anyhow!("invalid migration filename"))?;
+        let row = applied.iter().find(|r| r.name == name);
+        let squashed = squashes.iter().any(|(_, replaces)| replaces.iter().any(|r| r == name));
+        let state = match row {
+            None if squashed => MigrationState::Squashed,
+            None => MigrationState::Pending,
+            Some(row) if row.failed() => MigrationState::Failed,
+            Some(row) if row.hash == file_hash(&fs::read_to_string(path)?) => {
+                MigrationState::Applied
+            }
+            Some(_) => MigrationState::Modified,
+        };
+        out.push(MigrationStatus {
+            name: name.to_string(),
+            state,
+            applied_at: row.map(|r| r.applied_at.clone()),
+            execution_time_ms: row.and_then(|r| r.execution_time_ms),
+            error: row.and_then(|r| r.error.clone()),
+            failed_statement: row.and_then(|r| r.failed_statement.clone()),
+            replaces: row.map(|r| r.replaces.clone()).unwrap_or_default(),
+        });
+    }
+    for row in &applied {
+        if !files
+            .iter()
+            .any(|p| file_name(p) == Some(row.name.as_str()))
+        {
+            out.push(MigrationStatus {
+                name: row.name.clone(),
+                state: MigrationState::Orphaned,
+                applied_at: Some(row.applied_at.clone()),
+                execution_time_ms: row.execution_time_ms,
+                error: row.error.clone(),
+                failed_statement: row.failed_statement.clone(),
+                replaces: row.replaces.clone(),
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Whether `name` refers to the migration file `file`, given in full or
+/// without its `.up.sql`/`.data.sql` suffix.
+fn names_migration(file: &str, name: &str) -> bool {
+    file == name
+        || file
+            .strip_suffix(UP_SUFFIX)
+            .or_else(|| file.strip_suffix(DATA_SUFFIX))
+            .is_some_and(|stem| stem == name)
+}
+
+/// Roll back applied migrations, newest first. Each down file runs in its own
+/// transaction together with the removal of the history row, unless it is
+/// marked with [`NO_TRANSACTION_DIRECTIVE`]. Data migrations are undone by
+/// their optional `.data.down.sql`; without one only their history row is
+/// removed. Returns the migrations that were rolled back.
+pub async fn rollback_migrations(
+    pool: &AnyPool,
+    dir: &str,
+    target: RollbackTarget,
+    options: &MigrateOptions,
+) -> Result<Vec<RolledBackMigration>> {
+    let (mut conn, backend) = lock(pool, options).await?;
+    let result = rollback(&mut conn, &backend, dir, target).await;
+    let unlocked = unlock(&mut conn, &backend).await;
+    let rolled_back = result?;
+    unlocked?;
+    Ok(rolled_back)
+}
+
+pub(crate) async fn rollback(
+    conn: &mut AnyConnection,
+    backend: &DatabaseBackend,
+    dir: &str,
+    target: RollbackTarget,
+) -> Result<Vec<RolledBackMigration>> {
+    let mut applied = applied_history(conn).await?;
+    applied.reverse();
+    let count = match &target {
+        RollbackTarget::Steps(n) => (*n).min(applied.len()),
+        RollbackTarget::To(name) => applied
+            .iter()
+            .position(|r| names_migration(&r.name, name))
+            .ok_or_else(|| anyhow!("migration {} has not been applied", name))?,
+    };
+
+    let mut rolled_back = Vec::new();
+    for row in applied.iter().take(count) {
+        let down_name = if let Some(stem) = row.name.strip_suffix(UP_SUFFIX) {
+            format!("{}{}", stem, DOWN_SUFFIX)
+        } else if let Some(stem) = row.name.strip_suffix(DATA_SUFFIX) {
+            format!("{}{}", stem, DATA_DOWN_SUFFIX)
+        } else {
+            bail!("migration {} cannot be rolled back", row.name);
+        };
+        let down_path = Path::new(dir).join(&down_name);
+        if row.name.ends_with(DATA_SUFFIX) && !down_path.exists() {
+            forget_applied(conn, backend, &row.name).await?;
+            rolled_back.push(RolledBackMigration {
+                name: row.name.clone(),
+                reverted: false,
+            });
+            continue;
+        }
+        let sql = fs::read_to_string(&down_path)
+            .map_err(|e| anyhow!("cannot read {}: {}", down_path.display(), e))?;
+        if is_no_transaction(&sql) {
+            run_statements(conn, backend, &down_name, &sql).await?;
+            forget_applied(conn, backend, &row.name).await?;
+        } else {
+            let mut tx = conn.begin().await?;
+            run_statements(&mut tx, backend, &down_name, &sql).await?;
+            forget_applied(&mut tx, backend, &row.name).await?;
+            tx.commit().await?;
+        }
+        rolled_back.push(RolledBackMigration {
+            name: row.name.clone(),
+            reverted: true,
+        });
+    }
+    Ok(rolled_back)
+}
+
+/// Roll back the last applied migration and apply it again. Returns its
+/// name, or `None` when nothing has been applied.
+pub async fn redo_migration(
+    pool: &AnyPool,
+    dir: &str,
+    options: &MigrateOptions,
+) -> Result<Option<String>> {
+    let (mut conn, backend) = lock(pool, options).await?;
+    let result = redo(&mut conn, &backend, dir).await;
+    let unlocked = unlock(&mut conn, &backend).await;
+    let redone = result?;
+    unlocked?;
+    Ok(redone)
+}
+
+async fn redo(
+    conn: &mut AnyConnection,
+    backend: &DatabaseBackend,
+    dir: &str,
+) -> Result<Option<String>> {
+    let rolled_back = rollback(conn, backend, dir, RollbackTarget::Steps(1)).await?;
+    let Some(RolledBackMigration { name, .. }) = rolled_back.into_iter().next() else {
+        return Ok(None);
+    };
+    let sql = fs::read_to_string(Path::new(dir).join(&name))?;
+    apply_file(conn, backend, &name, &sql).await?;
+    Ok(Some(name))
+}
+
+/// Clear failed attempts and store the current hash of migrations edited
+/// after they were applied, for all migrations or only the one named `name`.
+/// Meant for after the database was fixed by hand.
+pub async fn repair_migrations(
+    pool: &AnyPool,
+    dir: &str,
+    name: Option<&str>,
+    options: &MigrateOptions,
+) -> Result<Vec<RepairedMigration>> {
+    let (mut conn, backend) = lock(pool, options).await?;
+    let result = repair(&mut conn, &backend, dir, name).await;
+    let unlocked = unlock(&mut conn, &backend).await;
+    let repaired = result?;
+    unlocked?;
+    Ok(repaired)
+}
+
+async fn repair(
+    conn: &mut AnyConnection,
+    backend: &DatabaseBackend,
+    dir: &str,
+    name: Option<&str>,
+) -> Result<Vec<RepairedMigration>> {
+    let rows: Vec<HistoryRow> = history(conn)
+        .await?
+        .into_iter()
+        .filter(|r| name.is_none_or(|name| names_migration(&r.name, name)))
+        .collect();
+    if let (Some(name), true) = (name, rows.is_empty()) {
+        bail!("migration {} is not in the history", name);
+    }
+    let mut repaired = Vec::new();
+    for row in rows {
+        if row.failed() {
+            forget_applied(conn, backend, &row.name).await?;
+            repaired.push(RepairedMigration {
+                name: row.name,
+                action: RepairAction::ClearedFailure,
+            });
+            continue;
+        }
+        let Ok(sql) = fs::read_to_string(Path::new(dir).join(&row.name)) else {
+            continue;
+        };
+        let hash = file_hash(&sql);
+        if hash != row.hash {
+            sqlx::query(&placeholders(
+                backend,
+                "UPDATE __rustdbgen_migrations SET hash = ? WHERE name = ?",
+            ))
+            .bind(hash)
+            .bind(&row.name)
+            .execute(&mut *conn)
+            .await?;
+            repaired.push(RepairedMigration {
+                name: row.name,
+                action: RepairAction::Rehashed,
+            });
+        }
+    }
+    Ok(repaired)
+}
+
+/// Adopt an existing database: introspect the tables `introspect` selects,
+/// write an initial migration and `schema.json` describing them to `dir`,
+/// and record that migration as applied without running it, so later
+/// migrations are diffed from what the database holds. Refuses databases
+/// that already have a migration history and directories that already hold
+/// migrations. Returns the migration's file name.
+pub async fn baseline_migrations(
+    pool: &AnyPool,
+    dir: &str,
+    db_type_map: &HashMap<String, String>,
+    introspect: &IntrospectOptions,
+    options: &MigrateOptions,
+) -> Result<String> {
+    let (mut conn, backend) = lock(pool, options).await?;
+    let url = pool.connect_options().database_url.to_string();
+    let result = baseline(&mut conn, &backend, &url, dir, db_type_map, introspect).await;
+    let unlocked = unlock(&mut conn, &backend).await;
+    let name = result?;
+    unlocked?;
+    Ok(name)
+}
+
+async fn baseline(
+    conn: &mut AnyConnection,
+    backend: &DatabaseBackend,
+    url: &str,
+    dir: &str,
+    db_type_map: &HashMap<String, String>,
+    introspect: &IntrospectOptions,
+) -> Result<String> {
+    if !history(conn).await?.is_empty() {
+        bail!("the database already has a migration history");
+    }
+    fs::create_dir_all(dir)?;
+    if !migration_files(dir)?.is_empty() {
+        bail!("{} already holds migrations", dir);
+    }
+    let ir = introspect_schema_with_options(url, db_type_map, introspect).await?;
+    let (up, down) = generate_initial_migration(&ir)?;
+    let stem = format!("{}_baseline", chrono::Utc::now().format("%Y%m%d%H%M%S"));
+    let name = format!("{}{}", stem, UP_SUFFIX);
+    let dir = Path::new(dir);
+    fs::write(dir.join(&name), &up)?;
+    fs::write(dir.join(format!("{}{}", stem, DOWN_SUFFIX)), down)?;
+    let snapshot = serde_json::to_string_pretty(&ir)?;
+    fs::write(dir.join(format!("{}{}", stem, SNAPSHOT_SUFFIX)), &snapshot)?;
+    fs::write(dir.join("schema.json"), snapshot)?;
+    record(conn, backend, &name, &up, Instant::now(), None).await?;
+    Ok(name)
+}
+
+/// Replace the up migrations in `dir` from the first through `up_to` with a
+/// single migration generated by `generate_initial_migration` from the
+/// schema snapshot taken after `up_to`: its `.schema.json` file, or
+/// `schema.json` when it is the newest migration. The squashed file is
+/// named after `up_to` so later migrations still sort after it, and lists
+/// the files it replaces in [`SQUASHES_DIRECTIVE`] lines. Data migrations
+/// are left in place and keep running in order after the up migrations.
+///
+/// Databases that applied every replaced migration record the squashed one
+/// in their place on the next apply instead of running it. The replaced
+/// files stay on disk, so databases part way through them can still catch
+/// up, until [`prune_squashed_migrations`] removes them.
+pub fn squash_migrations(dir: &str, up_to: &str) -> Result<SquashedMigration> {
+    let replaced: Vec<String> = squashes(dir)?.into_iter().flat_map(|(_, replaces)| replaces).collect();
+    let ups: Vec<PathBuf> = migration_files(dir)?
+        .into_iter()
+        .filter(|p| file_name(p).is_some_and(|n| n.ends_with(UP_SUFFIX) && !replaced.iter().any(|r| r == n)))
+        .collect();
+    let last = ups
+        .iter()
+        .position(|p| file_name(p).is_some_and(|n| names_migration(n, up_to)))
+        .ok_or_else(|| anyhow!("no migration named {}", up_to))?;
+    if last == 0 {
+        bail!("{} is the first migration; there is nothing to squash into it", up_to);
+    }
+    let replaces: Vec<String> = ups[..=last]
+        .iter()
+        .filter_map(|p| file_name(p).map(str::to_string))
+        .collect();
+    let stem = replaces[last].trim_end_matches(UP_SUFFIX);
+    let dir = Path::new(dir);
+
+    let snapshot_path = dir.join(format!("{}{}", stem, SNAPSHOT_SUFFIX));
+    let snapshot = if snapshot_path.exists() {
+        fs::read_to_string(&snapshot_path)?
+    } else if last == ups.len() - 1 {
+        fs::read_to_string(dir.join("schema.json")).context("cannot read schema.json")?
+    } else {
+        bail!(
+            "there is no schema snapshot {}{} to squash from; only migrations generated \
+             with a snapshot, or the newest one, can be squashed up to",
+            stem,
+            SNAPSHOT_SUFFIX
+        );
+    };
+    let ir: SchemaIR = serde_json::from_str(&snapshot)
+        .with_context(|| format!("cannot parse the schema snapshot of {}", replaces[last]))?;
+    let (up, down) = generate_initial_migration(&ir)?;
+
+    let squashed_stem = format!("{}_squashed", stem);
+    let name = format!("{}{}", squashed_stem, UP_SUFFIX);
+    if dir.join(&name).exists() {
+        bail!("{} already exists", name);
+    }
+    let mut header = String::new();
+    for replaced in &replaces {
+        header.push_str(&format!("{} {}\n", SQUASHES_DIRECTIVE, replaced));
+    }
+    fs::write(dir.join(&name), format!("{}{}", header, up))?;
+    fs::write(dir.join(format!("{}{}", squashed_stem, DOWN_SUFFIX)), down)?;
+    fs::write(dir.join(format!("{}{}", squashed_stem, SNAPSHOT_SUFFIX)), &snapshot)?;
+    Ok(SquashedMigration { name, replaces })
+}
+
+/// Delete the files squashed migrations in `dir` replace, once every
+/// database has applied the squash or all of the files. Returns the up
+/// migrations removed.
+pub fn prune_squashed_migrations(dir: &str) -> Result<Vec<String>> {
+    let mut pruned = Vec::new();
+    for (_, replaces) in squashes(dir)? {
+        for replaced in replaces {
+            let replaced_stem = replaced.trim_end_matches(UP_SUFFIX);
+            let up = Path::new(dir).join(&replaced);
+            if !up.exists() {
+                continue;
+            }
+            for suffix in [UP_SUFFIX, DOWN_SUFFIX, SNAPSHOT_SUFFIX] {
+                let path = Path::new(dir).join(format!("{}{}", replaced_stem, suffix));
+                if path.exists() {
+                    fs::remove_file(path)?;
+                }
+            }
+            pruned.push(replaced);
+        }
+    }
+    Ok(pruned)
+}
@@ -0,0 +1,77 @@
+/// Case styles accepted by `meta.rust_case_style` and `meta.db_case_style`.
+/// `preserve` keeps names exactly as written in the schema.
+pub const CASE_STYLES: &[&str] = &[
+    "snake",
+    "camel",
+    "pascal",
+    "kebab",
+    "screaming_snake",
+    "lower",
+    "preserve",
+];
+
+/// Split an identifier into lowercase words on `_`, `-`, spaces and case
+/// changes, keeping acronyms together (`HTTPLog` -> `http`, `log`).
+pub fn words(s: &str) -> Vec<String> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut words = Vec::new();
+    let mut current = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' || c == '-' || c.is_whitespace() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if c.is_uppercase() && !current.is_empty() {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                words.push(std::mem::take(&mut current));
+            }
+        }
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Convert `s` to the given case style; unknown styles leave it unchanged.
+pub fn convert(s: &str, style: &str) -> String {
+    let w = words(s);
+    match style {
+        "snake" => w.join("_"),
+        "camel" => w
+            .iter()
+            .enumerate()
+            .map(|(i, word)| if i == 0 { word.clone() } else { capitalize(word) })
+            .collect(),
+        "pascal" => w.iter().map(|word| capitalize(word)).collect(),
+        "kebab" => w.join("-"),
+        "screaming_snake" => w.join("_").to_uppercase(),
+        "lower" => w.concat(),
+        _ => s.to_string(),
+    }
+}
+
+/// `#[serde(rename_all = ...)]` value producing the same names as [`convert`]
+/// applied to a snake_case Rust field.
+pub fn serde_rename_all(style: &str) -> Option<&'static str> {
+    match style {
+        "camel" => Some("camelCase"),
+        "pascal" => Some("PascalCase"),
+        "kebab" => Some("kebab-case"),
+        "screaming_snake" => Some("SCREAMING_SNAKE_CASE"),
+        _ => None,
+    }
+}
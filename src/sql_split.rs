@@ -0,0 +1,210 @@
+//! Splitting SQL scripts into statements. Semicolons only end a statement
+//! outside of string literals, quoted identifiers, comments, Postgres
+//! dollar-quoted bodies and SQLite trigger bodies. MySQL scripts may change
+//! the delimiter with the client-side `DELIMITER` command.
+
+use crate::ir::DatabaseBackend;
+use anyhow::{Result, bail};
+
+/// One statement of a script, without its delimiter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    pub sql: String,
+    /// 1-based line the statement starts on
+    pub line: usize,
+}
+
+fn newlines(b: &[u8]) -> usize {
+    b.iter().filter(|&&c| c == b'\n').count()
+}
+
+fn is_ident(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+/// Index just past the closing `quote` of a literal opened at `start`.
+/// A doubled quote is an escaped quote; with `backslash` a backslash escapes
+/// the next character.
+fn quoted_end(b: &[u8], start: usize, quote: u8, backslash: bool) -> Option<usize> {
+    let mut i = start + 1;
+    while i < b.len() {
+        if backslash && b[i] == b'\\' {
+            i += 2;
+        } else if b[i] == quote {
+            if b.get(i + 1) == Some(&quote) {
+                i += 2;
+            } else {
+                return Some(i + 1);
+            }
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Index just past a `/* */` comment opened at `start`. Postgres comments nest.
+fn block_comment_end(b: &[u8], start: usize, nested: bool) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+    while i + 1 < b.len() {
+        if b[i] == b'/' && b[i + 1] == b'*' && (nested || depth == 0) {
+            depth += 1;
+            i += 2;
+        } else if b[i] == b'*' && b[i + 1] == b'/' {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return Some(i);
+            }
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// The `$tag$` opening a dollar-quoted string at `start`, if there is one.
+/// `$1` style parameters are not tags.
+fn dollar_tag(sql: &str, start: usize) -> Option<&str> {
+    let b = sql.as_bytes();
+    if start > 0 && is_ident(b[start - 1]) {
+        return None;
+    }
+    let mut j = start + 1;
+    while j < b.len() && is_ident(b[j]) {
+        j += 1;
+    }
+    if j < b.len() && b[j] == b'$' && !b.get(start + 1).is_some_and(u8::is_ascii_digit) {
+        Some(&sql[start..=j])
+    } else {
+        None
+    }
+}
+
+/// Split `sql` into statements for `backend`. Comments before a statement are
+/// dropped and comment-only statements are skipped.
+pub fn split_statements(sql: &str, backend: &DatabaseBackend) -> Result<Vec<Statement>> {
+    let mysql = matches!(backend, DatabaseBackend::Mysql);
+    let postgres = matches!(backend, DatabaseBackend::Postgres);
+    let sqlite = matches!(backend, DatabaseBackend::Sqlite);
+    let b = sql.as_bytes();
+    let mut out = Vec::new();
+    let mut delimiter = ";".to_string();
+    let mut line = 1;
+    let mut i = 0;
+    // byte offset and line of the statement being read
+    let mut start: Option<(usize, usize)> = None;
+    // leading keywords, to recognise CREATE TRIGGER
+    let mut words: Vec<String> = Vec::new();
+    let mut trigger = false;
+    let mut block_depth = 0usize;
+
+    while i < b.len() {
+        let c = b[i];
+        if start.is_none()
+            && mysql
+            && b.len() > i + 9
+            && b[i..i + 9].eq_ignore_ascii_case(b"DELIMITER")
+            && b[i + 9].is_ascii_whitespace()
+        {
+            let end = sql[i..].find('\n').map_or(b.len(), |n| i + n);
+            let new = sql[i + 9..end].trim();
+            if new.is_empty() {
+                bail!("DELIMITER without a delimiter on line {}", line);
+            }
+            delimiter = new.to_string();
+            i = end;
+            continue;
+        }
+        if c == b'\n' {
+            line += 1;
+            i += 1;
+            continue;
+        }
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if (c == b'-' && b.get(i + 1) == Some(&b'-')) || (mysql && c == b'#') {
+            i = sql[i..].find('\n').map_or(b.len(), |n| i + n);
+            continue;
+        }
+        if c == b'/' && b.get(i + 1) == Some(&b'*') {
+            let Some(end) = block_comment_end(b, i, postgres) else {
+                bail!("unterminated block comment starting on line {}", line);
+            };
+            line += newlines(&b[i..end]);
+            i = end;
+            continue;
+        }
+        if block_depth == 0 && b[i..].starts_with(delimiter.as_bytes()) {
+            if let Some((s, l)) = start.take() {
+                out.push(Statement {
+                    sql: sql[s..i].trim_end().to_string(),
+                    line: l,
+                });
+            }
+            words.clear();
+            trigger = false;
+            i += delimiter.len();
+            continue;
+        }
+        if start.is_none() {
+            start = Some((i, line));
+        }
+
+        let end = match c {
+            b'\'' => {
+                let escape_string = postgres
+                    && i > 0
+                    && b[i - 1].eq_ignore_ascii_case(&b'e')
+                    && (i < 2 || !is_ident(b[i - 2]));
+                match quoted_end(b, i, c, mysql || escape_string) {
+                    Some(end) => end,
+                    None => bail!("unterminated string literal starting on line {}", line),
+                }
+            }
+            b'"' | b'`' if c == b'"' || mysql => match quoted_end(b, i, c, mysql) {
+                Some(end) => end,
+                None => bail!("unterminated quoted identifier starting on line {}", line),
+            },
+            b'$' if postgres => match dollar_tag(sql, i) {
+                Some(tag) => match sql[i + tag.len()..].find(tag) {
+                    Some(n) => i + tag.len() + n + tag.len(),
+                    None => bail!("unterminated dollar-quoted string starting on line {}", line),
+                },
+                None => i + 1,
+            },
+            _ if is_ident(c) => {
+                let mut j = i + 1;
+                while j < b.len() && is_ident(b[j]) {
+                    j += 1;
+                }
+                let word = sql[i..j].to_ascii_uppercase();
+                if sqlite && !trigger && words.len() < 3 {
+                    trigger = word == "TRIGGER" && words.first().is_some_and(|w| w == "CREATE");
+                    words.push(word.clone());
+                }
+                if trigger {
+                    match word.as_str() {
+                        "BEGIN" | "CASE" => block_depth += 1,
+                        "END" => block_depth = block_depth.saturating_sub(1),
+                        _ => {}
+                    }
+                }
+                j
+            }
+            _ => i + 1,
+        };
+        line += newlines(&b[i..end]);
+        i = end;
+    }
+    if let Some((s, l)) = start {
+        out.push(Statement {
+            sql: sql[s..].trim_end().to_string(),
+            line: l,
+        });
+    }
+    Ok(out)
+}
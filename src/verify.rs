@@ -0,0 +1,212 @@
+//! Proving a migrations directory against a throwaway shadow database: every
+//! up migration must apply, the result must match the schema, and every down
+//! migration must restore the database it started from.
+
+use crate::drift::schema_differences;
+use crate::ir::{DatabaseBackend, SchemaIR};
+use crate::migrations::{
+    RollbackTarget, UP_SUFFIX, apply_file, ensure_history_table, file_name, migration_files,
+    pool_backend, rollback, squashes,
+};
+use crate::{
+    IntrospectOptions, connect_any_pool, database_schema, infer_backend_from_url, introspect_schema_with_options,
+};
+use anyhow::{Result, anyhow, bail};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// A database that exists only for the length of a verification run.
+pub struct ShadowDatabase {
+    pub url: String,
+    /// Server connection the database was created from, for dropping it
+    server_url: Option<String>,
+    name: String,
+    /// SQLite shadow file
+    path: Option<PathBuf>,
+}
+
+fn shadow_name() -> String {
+    format!("rustdbgen_shadow_{}", uuid::Uuid::new_v4().simple())
+}
+
+impl ShadowDatabase {
+    /// A fresh SQLite file in the system's temporary directory.
+    pub fn sqlite() -> Result<Self> {
+        let name = shadow_name();
+        let path = std::env::temp_dir().join(format!("{}.db", name));
+        fs::write(&path, "")?;
+        Ok(ShadowDatabase {
+            url: format!("sqlite://{}", path.display()),
+            server_url: None,
+            name,
+            path: Some(path),
+        })
+    }
+
+    /// A new, uniquely named database on the Postgres or MySQL server behind
+    /// `server_url`. The user must be allowed to create databases.
+    pub async fn create(server_url: &str) -> Result<Self> {
+        if matches!(infer_backend_from_url(server_url), Some(DatabaseBackend::Sqlite) | None) {
+            bail!("a shadow database server must be a Postgres or MySQL URL");
+        }
+        let name = shadow_name();
+        let pool = connect_any_pool(server_url).await?;
+        sqlx::query(&format!("CREATE DATABASE {}", name))
+            .execute(&pool)
+            .await?;
+        pool.close().await;
+        Ok(ShadowDatabase {
+            url: with_database(server_url, &name),
+            server_url: Some(server_url.to_string()),
+            name,
+            path: None,
+        })
+    }
+
+    /// Drop the database, or delete the SQLite file.
+    pub async fn drop_database(self) -> Result<()> {
+        if let Some(path) = &self.path {
+            fs::remove_file(path)?;
+        }
+        let Some(server_url) = &self.server_url else {
+            return Ok(());
+        };
+        let force = match infer_backend_from_url(server_url) {
+            Some(DatabaseBackend::Postgres) => " WITH (FORCE)",
+            _ => "",
+        };
+        let pool = connect_any_pool(server_url).await?;
+        sqlx::query(&format!("DROP DATABASE {}{}", self.name, force))
+            .execute(&pool)
+            .await?;
+        pool.close().await;
+        Ok(())
+    }
+}
+
+/// `url` pointing at database `name` instead of the one it names.
+fn with_database(url: &str, name: &str) -> String {
+    let (url, query) = match url.split_once('?') {
+        Some((url, query)) => (url, Some(query)),
+        None => (url, None),
+    };
+    let authority_start = url.find("://").map(|i| i + 3).unwrap_or(0);
+    let base = match url[authority_start..].find('/') {
+        Some(i) => &url[..authority_start + i],
+        None => url,
+    };
+    match query {
+        Some(query) => format!("{}/{}?{}", base, name, query),
+        None => format!("{}/{}", base, name),
+    }
+}
+
+/// What `verify_migrations` found. Verification passed when `problems` is
+/// empty.
+#[derive(Debug, Clone, Default)]
+pub struct VerificationReport {
+    /// Up migrations applied, in order
+    pub applied: Vec<String>,
+    /// Migrations rolled back, newest first
+    pub rolled_back: Vec<String>,
+    pub problems: Vec<String>,
+}
+
+impl VerificationReport {
+    pub fn passed(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Apply every `.up.sql` migration in `dir` to the empty database at
+/// `shadow_url` one at a time, skipping those a squashed migration replaces,
+/// compare the result with `ir`, then roll back one step at a time and
+/// compare each state with the one recorded before that migration was
+/// applied. Data migrations are not run. A migration that
+/// fails to apply or roll back ends the run with the failure as a problem.
+///
+/// The states are compared the way `detect_drift` compares a live database,
+/// and every difference counts as a problem.
+pub async fn verify_migrations(ir: &SchemaIR, dir: &str, shadow_url: &str) -> Result<VerificationReport> {
+    let pool = connect_any_pool(shadow_url).await?;
+    let backend = pool_backend(&pool);
+    let mut conn = pool.acquire().await?;
+    let options = IntrospectOptions::for_schema(ir);
+    let empty = snapshot(shadow_url, &options).await?;
+    if !empty.models.is_empty() {
+        bail!("the shadow database must be empty, but it has tables");
+    }
+    ensure_history_table(&mut conn).await?;
+
+    let mut report = VerificationReport::default();
+    let mut states = vec![empty];
+    let replaced: Vec<String> = squashes(dir)?.into_iter().flat_map(|(_, replaces)| replaces).collect();
+    for path in migration_files(dir)? {
+        let name = file_name(&path).ok_or_else(|| anyhow!("invalid migration filename"))?;
+        if !name.ends_with(UP_SUFFIX) || replaced.iter().any(|r| r == name) {
+            continue;
+        }
+        let sql = fs::read_to_string(&path)?;
+        if let Err(e) = apply_file(&mut conn, &backend, name, &sql).await {
+            report.problems.push(format!("applying {} failed: {:#}", name, e));
+            return Ok(report);
+        }
+        report.applied.push(name.to_string());
+        states.push(snapshot(shadow_url, &options).await?);
+    }
+
+    let expected = database_schema(ir)?;
+    let reached = states.last().expect("states start with the empty database");
+    for drift in schema_differences(&expected, reached) {
+        report.problems.push(format!("after all migrations: {}", drift.description));
+    }
+
+    for name in report.applied.iter().rev() {
+        if let Err(e) = rollback(&mut conn, &backend, dir, RollbackTarget::Steps(1)).await {
+            report.problems.push(format!("rolling back {} failed: {:#}", name, e));
+            break;
+        }
+        states.pop();
+        let before = states.last().expect("one state per applied migration");
+        for drift in schema_differences(before, &snapshot(shadow_url, &options).await?) {
+            report.problems.push(format!(
+                "rolling back {} leaves a different schema: {}",
+                name, drift.description
+            ));
+        }
+        report.rolled_back.push(name.clone());
+    }
+    drop(conn);
+    pool.close().await;
+    Ok(report)
+}
+
+/// Introspect the shadow database as [`database_schema`] lays schemas out.
+async fn snapshot(url: &str, options: &IntrospectOptions) -> Result<SchemaIR> {
+    database_schema(&introspect_schema_with_options(url, &HashMap::new(), options).await?)
+}
+
+/// Run [`verify_migrations`] against a shadow database created for the
+/// purpose: on the Postgres or MySQL server behind `server_url` if given,
+/// otherwise a temporary SQLite file. The database is dropped afterwards.
+pub async fn verify_migrations_in_shadow(
+    ir: &SchemaIR,
+    dir: &str,
+    server_url: Option<&str>,
+) -> Result<VerificationReport> {
+    // the migrations would create their tables in that real database
+    if ir.meta.db_schema.is_some() && matches!(ir.meta.db_backend, DatabaseBackend::Mysql) {
+        bail!("MySQL migrations with meta.db_schema cannot be verified in a shadow database");
+    }
+    let shadow = match server_url {
+        Some(url) => ShadowDatabase::create(url).await?,
+        None if matches!(ir.meta.db_backend, DatabaseBackend::Sqlite) => ShadowDatabase::sqlite()?,
+        None => bail!("verifying Postgres or MySQL migrations needs a shadow database server URL"),
+    };
+    let result = verify_migrations(ir, dir, &shadow.url).await;
+    let dropped = shadow.drop_database().await;
+    let report = result?;
+    dropped?;
+    Ok(report)
+}
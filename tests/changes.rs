@@ -0,0 +1,167 @@
+use rustdbgen::changes::{ChangeRisk, change_summary, classify_changes, guard_destructive};
+use rustdbgen::{diff_schemas, generate_migration};
+
+mod common;
+use common::load_inline_schema;
+
+const SCHEMA: &str = r#"
+[meta]
+rust_case_style = "snake"
+db_case_style = "snake"
+default_derives = []
+
+[enums.Period]
+variants = ["Monthly", "Yearly"]
+
+[models.Budget]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "BIGINT" }
+fields.name = { type = "String", db_type = "VARCHAR(100)", nullable = false }
+fields.amount = { type = "Decimal", db_type = "DECIMAL(15,2)", nullable = false }
+fields.period = { type = "Period", db_type = "period", nullable = false }
+fields.notes = { type = "String", db_type = "TEXT", nullable = true }
+
+[models.Category]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "BIGINT" }
+fields.name = { type = "String", db_type = "TEXT", nullable = false }
+"#;
+
+fn risk_of(changes: &[rustdbgen::changes::SchemaChange], description: &str) -> ChangeRisk {
+    changes
+        .iter()
+        .find(|c| c.description.starts_with(description))
+        .unwrap_or_else(|| panic!("no change {:?} in {:#?}", description, changes))
+        .risk
+}
+
+#[test]
+fn removed_model_is_destructive_and_refused() {
+    let old = load_inline_schema(SCHEMA);
+    let new = load_inline_schema(&SCHEMA.replace("[models.Category]", "[models.Ignored]").replace(
+        "[models.Budget]\nprimary_key = \"id\"",
+        "[models.Budgeted]\nprimary_key = \"id\"",
+    ));
+    let changes = classify_changes(&diff_schemas(Some(&old), &new).unwrap());
+    assert_eq!(risk_of(&changes, "drop table budget"), ChangeRisk::Destructive);
+    assert_eq!(risk_of(&changes, "create table budgeted"), ChangeRisk::Safe);
+    let err = guard_destructive(&changes, false).unwrap_err().to_string();
+    assert!(err.contains("drop table budget"));
+    assert!(err.contains("--allow-destructive"));
+    assert!(guard_destructive(&changes, true).is_ok());
+}
+
+#[test]
+fn declared_drops_are_allowed() {
+    let old = load_inline_schema(SCHEMA);
+    let new = load_inline_schema(
+        &SCHEMA
+            .replace("[models.Category]", "[models.Category]\ndropped = true")
+            .replace("db_type = \"TEXT\", nullable = true }", "db_type = \"TEXT\", nullable = true, dropped = true }"),
+    );
+    assert!(!new.models.contains_key("Category"));
+    assert!(!new.models["Budget"].fields.contains_key("notes"));
+
+    let changes = classify_changes(&diff_schemas(Some(&old), &new).unwrap());
+    let table = changes.iter().find(|c| c.description == "drop table category").unwrap();
+    assert!(table.declared);
+    let column = changes.iter().find(|c| c.description == "drop column budget.notes").unwrap();
+    assert!(column.declared);
+    assert!(guard_destructive(&changes, false).is_ok());
+    assert!(change_summary(&changes).contains("  destructive  drop table category (declared)\n"));
+
+    let (up, _) = generate_migration(Some(&old), &new).unwrap();
+    assert!(up.contains("DROP TABLE category;"));
+    assert!(up.contains("ALTER TABLE budget DROP COLUMN notes;"));
+}
+
+#[test]
+fn type_changes_are_classified_by_width() {
+    let old = load_inline_schema(SCHEMA);
+    let new = load_inline_schema(
+        &SCHEMA
+            .replace("VARCHAR(100)", "VARCHAR(20)")
+            .replace("DECIMAL(15,2)", "DECIMAL(18,2)")
+            .replace(
+                "fields.id = { type = \"i64\", db_type = \"BIGINT\" }\nfields.name = { type = \"String\", db_type = \"TEXT\"",
+                "fields.id = { type = \"i64\", db_type = \"INTEGER\" }\nfields.name = { type = \"String\", db_type = \"TEXT\"",
+            ),
+    );
+    let changes = classify_changes(&diff_schemas(Some(&old), &new).unwrap());
+    assert_eq!(risk_of(&changes, "narrow type of budget.name"), ChangeRisk::Destructive);
+    assert_eq!(risk_of(&changes, "change type of budget.amount"), ChangeRisk::Safe);
+    assert_eq!(risk_of(&changes, "narrow type of category.id"), ChangeRisk::Destructive);
+    assert!(guard_destructive(&changes, false).is_err());
+}
+
+#[test]
+fn reviewable_changes_do_not_block() {
+    let old = load_inline_schema(SCHEMA);
+    let new = load_inline_schema(
+        &SCHEMA
+            .replace("variants = [\"Monthly\", \"Yearly\"]", "variants = [\"Weekly\", \"Monthly\", \"Yearly\"]")
+            .replace("db_type = \"TEXT\", nullable = true }", "db_type = \"TEXT\", nullable = false }")
+            .replace(
+                "[models.Category]\nprimary_key = \"id\"",
+                "[models.Category]\nprimary_key = \"id\"\nfields.slug = { type = \"String\", db_type = \"TEXT\", nullable = false }\nfields.color = { type = \"String\", db_type = \"TEXT\", nullable = true }",
+            ),
+    );
+    let changes = classify_changes(&diff_schemas(Some(&old), &new).unwrap());
+    assert_eq!(risk_of(&changes, "add enum value period.Weekly"), ChangeRisk::Safe);
+    assert_eq!(risk_of(&changes, "make budget.notes NOT NULL"), ChangeRisk::NeedsReview);
+    assert_eq!(risk_of(&changes, "add NOT NULL column category.slug"), ChangeRisk::NeedsReview);
+    assert_eq!(risk_of(&changes, "add column category.color"), ChangeRisk::Safe);
+    assert!(guard_destructive(&changes, false).is_ok());
+
+    let summary = change_summary(&changes);
+    let review = summary.find("needs-review").unwrap();
+    let safe = summary.find("safe").unwrap();
+    assert!(review < safe);
+}
+
+#[test]
+fn moved_primary_key_is_one_change() {
+    let old = load_inline_schema(SCHEMA);
+    let new = load_inline_schema(&SCHEMA.replace(
+        "[models.Category]\nprimary_key = \"id\"",
+        "[models.Category]\nprimary_key = \"name\"",
+    ));
+    let changes = classify_changes(&diff_schemas(Some(&old), &new).unwrap());
+    let descriptions: Vec<&str> = changes.iter().map(|c| c.description.as_str()).collect();
+    assert_eq!(descriptions, ["change primary key of category"]);
+    assert_eq!(changes[0].risk, ChangeRisk::NeedsReview);
+}
+
+#[test]
+fn removed_enum_values_are_destructive() {
+    let old = load_inline_schema(SCHEMA);
+    let new = load_inline_schema(&SCHEMA.replace("variants = [\"Monthly\", \"Yearly\"]", "variants = [\"Monthly\"]"));
+    let changes = classify_changes(&diff_schemas(Some(&old), &new).unwrap());
+    assert_eq!(risk_of(&changes, "remove enum value period.Yearly"), ChangeRisk::Destructive);
+}
+
+#[test]
+fn changes_follow_the_diff_operations() {
+    let old = load_inline_schema(SCHEMA);
+    let new = load_inline_schema(&format!(
+        "{}\n[models.Tag]\nprimary_key = \"id\"\nfields.id = {{ type = \"i64\", db_type = \"BIGINT\" }}\nfields.label = {{ type = \"String\", db_type = \"TEXT\", nullable = false }}\nindexes.tag_label_idx = {{ fields = [\"label\"], unique = true }}\n",
+        SCHEMA
+    ));
+    let diff = diff_schemas(Some(&old), &new).unwrap();
+    assert!(diff.operations.iter().any(|op| matches!(op, rustdbgen::SchemaOperation::AddIndex { .. })));
+    let changes = classify_changes(&diff);
+    let descriptions: Vec<&str> = changes.iter().map(|c| c.description.as_str()).collect();
+    assert_eq!(descriptions, ["create table tag"]);
+}
+
+#[test]
+fn sqlite_rebuilds_are_classified_by_column() {
+    let sqlite = |s: &str| s.replace("default_derives = []", "default_derives = []\ndb_backend = \"sqlite\"");
+    let old = load_inline_schema(&sqlite(SCHEMA));
+    let new = load_inline_schema(&sqlite(&SCHEMA.replace("VARCHAR(100)", "VARCHAR(20)")));
+    let diff = diff_schemas(Some(&old), &new).unwrap();
+    assert!(diff.operations.iter().any(|op| matches!(op, rustdbgen::SchemaOperation::RebuildTable { .. })));
+    let changes = classify_changes(&diff);
+    assert_eq!(risk_of(&changes, "narrow type of budget.name"), ChangeRisk::Destructive);
+    assert_eq!(changes.len(), 1);
+}
@@ -0,0 +1,8 @@
+use rustdbgen::{ir::SchemaIR, load_schema_text};
+use std::path::Path;
+
+/// A schema written out in the test itself, with includes resolved against
+/// the crate root.
+pub fn load_inline_schema(text: &str) -> SchemaIR {
+    load_schema_text(text, Path::new(".")).unwrap()
+}
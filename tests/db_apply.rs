@@ -119,3 +119,43 @@ async fn hash_mismatch_errors() {
 
     pg.stop_db().await.unwrap();
 }
+
+#[tokio::test]
+#[ignore]
+async fn failure_after_applied_migration_is_recorded() {
+    sqlx::any::install_default_drivers();
+    let pg_settings = PgSettings {
+        database_dir: std::env::temp_dir().join("pg_embed_test_failure"),
+        port: 5452,
+        user: "postgres".into(),
+        password: "password".into(),
+        auth_method: PgAuthMethod::Plain,
+        persistent: false,
+        timeout: Some(std::time::Duration::from_secs(15)),
+        migration_dir: None,
+    };
+    let fetch_settings = PgFetchSettings { version: PG_V14, ..Default::default() };
+    let mut pg = PgEmbed::new(pg_settings, fetch_settings).await.unwrap();
+    pg.setup().await.unwrap();
+    pg.start_db().await.unwrap();
+
+    let pool = AnyPool::connect(&pg.db_uri).await.unwrap();
+
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("0001_init.up.sql"), "CREATE TABLE item (id BIGINT PRIMARY KEY);\n").unwrap();
+    fs::write(dir.path().join("0002_bad.up.sql"), "ALTER TABLE item DROP COLUMN missing;\n").unwrap();
+
+    let err = apply_migrations(&pool, dir.path().to_str().unwrap()).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("statement 1 (line 1) failed"));
+
+    let (error, statement): (String, String) = sqlx::query_as(
+        "SELECT error, failed_statement FROM __rustdbgen_migrations WHERE name = '0002_bad.up.sql'",
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert!(error.contains("missing"));
+    assert_eq!(statement, "ALTER TABLE item DROP COLUMN missing");
+
+    pg.stop_db().await.unwrap();
+}
@@ -74,10 +74,10 @@ async fn check_constraint_round_trip() {
     let old_ir = introspect_schema(&pg.db_uri, &db_map).await.unwrap();
 
     let mut new_ir = base_ir.clone();
-    if let Some(model) = new_ir.models.get_mut("Post") {
-        if let Some(check) = model.check_constraints.get_mut("post_title_length") {
-            check.expression = "char_length(title) >= 3".into();
-        }
+    if let Some(model) = new_ir.models.get_mut("Post")
+        && let Some(check) = model.check_constraints.get_mut("post_title_length")
+    {
+        check.expression = "char_length(title) >= 3".into();
     }
 
     let (up, down) = generate_migration(Some(&old_ir), &new_ir).unwrap();
@@ -2,8 +2,10 @@ use pg_embed::pg_enums::PgAuthMethod;
 use pg_embed::pg_fetch::{PG_V14, PgFetchSettings};
 use pg_embed::postgres::{PgEmbed, PgSettings};
 use rustdbgen::{
-    apply_type_aliases, generate_initial_migration, generate_migration, introspect_schema,
-    ir::{SchemaIR, TypeAlias},
+    IntrospectOptions, apply_migrations, apply_type_aliases, connect_any_pool, diff_schemas,
+    generate_initial_migration, generate_migration, introspect_schema, introspect_schema_timed,
+    introspect_schema_with_options,
+    ir::{KeyGeneration, ReferentialAction, SchemaIR, TypeAlias},
     load_schema,
 };
 use sqlx::AnyPool;
@@ -11,6 +13,9 @@ use tempfile::NamedTempFile;
 use std::collections::HashMap;
 use std::fs;
 
+mod common;
+use common::load_inline_schema;
+
 fn load_db_map() -> HashMap<String, String> {
     toml::from_str::<toml::Value>(&std::fs::read_to_string("type_map.toml").unwrap())
         .unwrap()
@@ -82,6 +87,48 @@ async fn introspect_round_trip() {
     pg.stop_db().await.unwrap();
 }
 
+#[tokio::test]
+#[ignore]
+async fn foreign_keys_pair_columns_by_position() {
+    sqlx::any::install_default_drivers();
+    let pg_settings = PgSettings {
+        database_dir: std::env::temp_dir().join("pg_embed_test_fk"),
+        port: 5438,
+        user: "postgres".into(),
+        password: "password".into(),
+        auth_method: PgAuthMethod::Plain,
+        persistent: false,
+        timeout: Some(std::time::Duration::from_secs(15)),
+        migration_dir: None,
+    };
+    let fetch_settings = PgFetchSettings { version: PG_V14, ..Default::default() };
+    let mut pg = PgEmbed::new(pg_settings, fetch_settings).await.unwrap();
+    pg.setup().await.unwrap();
+    pg.start_db().await.unwrap();
+
+    let pool = AnyPool::connect(&pg.db_uri).await.unwrap();
+    for stmt in [
+        "CREATE TABLE parent (a INT, b INT, code TEXT UNIQUE, PRIMARY KEY (a, b))",
+        "CREATE TABLE child (id INT PRIMARY KEY, pa INT, pb INT, pcode TEXT, \
+         CONSTRAINT child_parent_fk FOREIGN KEY (pb, pa) REFERENCES parent (b, a), \
+         CONSTRAINT child_code_fk FOREIGN KEY (pcode) REFERENCES parent (code))",
+    ] {
+        sqlx::query(stmt).execute(&pool).await.unwrap();
+    }
+
+    let introspected = introspect_schema(&pg.db_uri, &load_db_map()).await.unwrap();
+    let relations = &introspected.models["child"].relations;
+    // a composite key has no single-column relation to become
+    assert_eq!(relations.keys().collect::<Vec<_>>(), vec!["child_code_fk"]);
+    let code = &relations["child_code_fk"];
+    assert_eq!(
+        (code.on.as_str(), code.references.model.as_str(), code.references.field.as_str()),
+        ("pcode", "parent", "code")
+    );
+
+    pg.stop_db().await.unwrap();
+}
+
 #[tokio::test]
 #[ignore]
 async fn sqlite_introspection_round_trip() {
@@ -107,3 +154,175 @@ async fn sqlite_introspection_round_trip() {
     assert!(up.trim().is_empty());
     assert!(down.trim().is_empty());
 }
+
+const PETS: &str = r#"
+[meta]
+rust_case_style = "snake"
+db_case_style = "snake"
+default_derives = []
+db_backend = "sqlite"
+
+[models.Owner]
+primary_key = { fields = ["id"], generated = "identity" }
+fields.id = { type = "i64", db_type = "INTEGER" }
+fields.email = { type = "String", db_type = "VARCHAR(255)", nullable = false }
+indexes.owner_email_key = { fields = ["email"], unique = true }
+
+[models.Pet]
+primary_key = { fields = ["id"], generated = "identity" }
+fields.id = { type = "i64", db_type = "INTEGER" }
+fields.name = { type = "String", db_type = "TEXT", nullable = false }
+fields.legs = { type = "i64", db_type = "INTEGER", nullable = false, default = "4" }
+fields.owner_id = { type = "i64", db_type = "INTEGER", nullable = true }
+relations.owner = { on = "owner_id", references = { model = "Owner", field = "id" }, on_delete = "cascade" }
+unique_constraints.pet_name_owner_unique = { fields = ["name", "owner_id"] }
+check_constraints.pet_legs_check = { expression = "legs >= 0 AND (legs % 2) = 0" }
+indexes.pet_owner_idx = { fields = ["owner_id"] }
+"#;
+
+#[tokio::test]
+async fn introspected_schema_round_trips() {
+    let ir = load_inline_schema(PETS);
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    let (up, _) = generate_initial_migration(&ir).unwrap();
+    fs::write(migrations.join("20240101000000_init.up.sql"), up).unwrap();
+    fs::write(dir.path().join("test.db"), "").unwrap();
+    let url = format!("sqlite://{}", dir.path().join("test.db").display());
+    let pool = connect_any_pool(&url).await.unwrap();
+    apply_migrations(&pool, migrations.to_str().unwrap()).await.unwrap();
+
+    let introspected = introspect_schema(&url, &HashMap::new()).await.unwrap();
+    // the migration history is not part of the schema
+    assert_eq!(introspected.models.keys().collect::<Vec<_>>(), vec!["owner", "pet"]);
+    let pet = &introspected.models["pet"];
+    let key = pet.primary_key.as_ref().unwrap();
+    assert_eq!(key.fields, vec!["id"]);
+    // a lone INTEGER key is the rowid
+    assert_eq!(key.generated, KeyGeneration::Identity);
+    assert_eq!(pet.fields["legs"].rust_type, "i64");
+    assert_eq!(pet.fields["legs"].default.as_deref(), Some("4"));
+    let owner = &pet.relations["owner"];
+    assert_eq!((owner.on.as_str(), owner.references.model.as_str()), ("owner_id", "owner"));
+    assert_eq!(owner.on_delete, ReferentialAction::Cascade);
+    assert_eq!(pet.unique_constraints["pet_name_owner_unique"].fields, vec!["name", "owner_id"]);
+    assert_eq!(pet.check_constraints["pet_legs_check"].expression, "legs >= 0 AND (legs % 2) = 0");
+    // the index SQLite keeps for the unique constraint is not listed
+    assert_eq!(pet.indexes.keys().collect::<Vec<_>>(), vec!["pet_owner_idx"]);
+
+    assert!(diff_schemas(Some(&ir), &introspected).unwrap().is_empty());
+
+    let written = dir.path().join("introspected.toml");
+    fs::write(&written, toml::to_string_pretty(&introspected).unwrap()).unwrap();
+    let reloaded = load_schema(written.to_str().unwrap()).unwrap();
+    assert!(diff_schemas(Some(&introspected), &reloaded).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn introspection_filters_tables() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("test.db"), "").unwrap();
+    let url = format!("sqlite://{}", dir.path().join("test.db").display());
+    let pool = connect_any_pool(&url).await.unwrap();
+    for stmt in [
+        "CREATE TABLE legacy_user (id INTEGER PRIMARY KEY)",
+        "CREATE TABLE legacy_audit (id INTEGER PRIMARY KEY)",
+        "CREATE TABLE account (id INTEGER PRIMARY KEY, \
+         CONSTRAINT account_legacy_fkey FOREIGN KEY (id) REFERENCES legacy_user (id))",
+        "CREATE TABLE invoice (id INTEGER PRIMARY KEY, \
+         CONSTRAINT invoice_account_fkey FOREIGN KEY (id) REFERENCES account (id))",
+    ] {
+        sqlx::query(stmt).execute(&pool).await.unwrap();
+    }
+
+    let options = IntrospectOptions {
+        exclude: vec!["legacy_*".into()],
+        ..Default::default()
+    };
+    let ir = introspect_schema_with_options(&url, &HashMap::new(), &options).await.unwrap();
+    assert_eq!(ir.models.keys().collect::<Vec<_>>(), vec!["account", "invoice"]);
+    // keys to tables left out go with them
+    assert!(ir.models["account"].relations.is_empty());
+    assert!(ir.models["invoice"].relations.contains_key("invoice_account_fkey"));
+
+    let options = IntrospectOptions {
+        include: vec!["legacy_?ser".into(), "inv*".into()],
+        ..Default::default()
+    };
+    let ir = introspect_schema_with_options(&url, &HashMap::new(), &options).await.unwrap();
+    assert_eq!(ir.models.keys().collect::<Vec<_>>(), vec!["invoice", "legacy_user"]);
+
+    let options = IntrospectOptions {
+        schema: Some("billing".into()),
+        ..Default::default()
+    };
+    assert!(introspect_schema_with_options(&url, &HashMap::new(), &options).await.is_err());
+}
+
+#[tokio::test]
+async fn introspection_maps_full_column_types() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("test.db"), "").unwrap();
+    let url = format!("sqlite://{}", dir.path().join("test.db").display());
+    let pool = connect_any_pool(&url).await.unwrap();
+    sqlx::query(
+        "CREATE TABLE invoice (id INTEGER PRIMARY KEY, total NUMERIC(15, 2), rate NUMERIC(5,4), \
+         code VARCHAR(3), note VARCHAR(255), amount NUMERIC)",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let db_map = HashMap::from([
+        ("numeric(*,*)".to_string(), "rust_decimal::Decimal".to_string()),
+        ("numeric(5,4)".to_string(), "f64".to_string()),
+        ("varchar(*)".to_string(), "String".to_string()),
+        ("VARCHAR(3)".to_string(), "Currency".to_string()),
+    ]);
+    let ir = introspect_schema(&url, &db_map).await.unwrap();
+    let invoice = &ir.models["invoice"];
+    let rust_type = |column: &str| invoice.fields[column].rust_type.as_str();
+    // the declared type is kept as written
+    assert_eq!(invoice.fields["total"].db_type.as_deref(), Some("NUMERIC(15, 2)"));
+    assert_eq!(rust_type("total"), "rust_decimal::Decimal");
+    // an exact entry wins over a pattern
+    assert_eq!(rust_type("rate"), "f64");
+    assert_eq!(rust_type("code"), "Currency");
+    assert_eq!(rust_type("note"), "String");
+    // no pattern matches, so the base type decides
+    assert_eq!(rust_type("amount"), "Decimal");
+}
+
+#[tokio::test]
+async fn introspection_times_each_query() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("test.db"), "").unwrap();
+    let url = format!("sqlite://{}", dir.path().join("test.db").display());
+    let pool = connect_any_pool(&url).await.unwrap();
+    for stmt in [
+        "CREATE TABLE owner (id INTEGER PRIMARY KEY, email TEXT NOT NULL UNIQUE)",
+        "CREATE TABLE pet (id INTEGER PRIMARY KEY, owner_id INTEGER REFERENCES owner (id), name TEXT)",
+        "CREATE INDEX pet_owner_idx ON pet (owner_id)",
+    ] {
+        sqlx::query(stmt).execute(&pool).await.unwrap();
+    }
+
+    let (ir, timings) = introspect_schema_timed(&url, &HashMap::new(), &Default::default())
+        .await
+        .unwrap();
+    assert_eq!(ir.models.len(), 2);
+    // one query per kind of object, not per table
+    let steps: Vec<(&str, usize)> = timings.iter().map(|t| (t.step, t.rows)).collect();
+    assert_eq!(
+        steps,
+        vec![
+            ("connect", 0),
+            ("tables", 2),
+            ("columns", 5),
+            ("indexes", 2),
+            ("foreign keys", 1),
+            ("assemble", 2),
+        ]
+    );
+}
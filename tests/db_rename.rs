@@ -79,7 +79,7 @@ async fn rename_column_round_trip() {
     let mut new_ir = base_ir.clone();
     {
         let user_model = new_ir.models.get_mut("User").unwrap();
-        if let Some(mut field) = user_model.fields.remove("email") {
+        if let Some(mut field) = user_model.fields.shift_remove("email") {
             field.rename_from = Some("email".into());
             user_model.fields.insert("contact_email".into(), field);
         }
@@ -74,10 +74,10 @@ async fn unique_constraint_round_trip() {
     let old_ir = introspect_schema(&pg.db_uri, &db_map).await.unwrap();
 
     let mut new_ir = base_ir.clone();
-    if let Some(model) = new_ir.models.get_mut("Post") {
-        if let Some(uc) = model.unique_constraints.get_mut("post_title_author_unique") {
-            uc.fields = vec!["title".into()];
-        }
+    if let Some(model) = new_ir.models.get_mut("Post")
+        && let Some(uc) = model.unique_constraints.get_mut("post_title_author_unique")
+    {
+        uc.fields = vec!["title".into()];
     }
 
     let (up, down) = generate_migration(Some(&old_ir), &new_ir).unwrap();
@@ -138,10 +138,10 @@ async fn index_uniqueness_round_trip() {
     let old_ir = introspect_schema(&pg.db_uri, &db_map).await.unwrap();
 
     let mut new_ir = base_ir.clone();
-    if let Some(model) = new_ir.models.get_mut("User") {
-        if let Some(idx) = model.indexes.get_mut("user_email_unique") {
-            idx.unique = false;
-        }
+    if let Some(model) = new_ir.models.get_mut("User")
+        && let Some(idx) = model.indexes.get_mut("user_email_unique")
+    {
+        idx.unique = false;
     }
 
     let (up, down) = generate_migration(Some(&old_ir), &new_ir).unwrap();
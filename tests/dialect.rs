@@ -0,0 +1,156 @@
+use rustdbgen::{generate_initial_migration, generate_migration};
+
+mod common;
+use common::load_inline_schema;
+
+const SCHEMA: &str = r#"
+[meta]
+rust_case_style = "snake"
+db_case_style = "snake"
+default_derives = []
+db_backend = "sqlite"
+
+[enums.PetKind]
+variants = ["Cat", "Dog"]
+
+[models.Owner]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "INTEGER" }
+fields.name = { type = "String", db_type = "TEXT", nullable = false }
+
+[models.Pet]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "INTEGER" }
+fields.name = { type = "String", db_type = "TEXT", nullable = false }
+fields.kind = { type = "PetKind", db_type = "pet_kind", nullable = false }
+fields.legs = { type = "i32", db_type = "INTEGER", nullable = false, default = "4" }
+fields.owner_id = { type = "i64", db_type = "INTEGER", nullable = false }
+relations.owner = { on = "owner_id", references = { model = "Owner", field = "id" }, on_delete = "cascade" }
+unique_constraints.pet_name_owner_unique = { fields = ["name", "owner_id"] }
+exclusion_constraints.pet_no_overlap = { definition = "USING gist (name WITH =)" }
+indexes.pet_owner_idx = { fields = ["owner_id"] }
+"#;
+
+fn backend(schema: &str, name: &str) -> String {
+    schema.replace("db_backend = \"sqlite\"", &format!("db_backend = \"{}\"", name))
+}
+
+#[test]
+fn sqlite_declares_constraints_inline() {
+    let ir = load_inline_schema(SCHEMA);
+    let (up, _) = generate_initial_migration(&ir).unwrap();
+    assert!(up.contains("    CONSTRAINT pet_name_owner_unique UNIQUE (name, owner_id),\n"));
+    assert!(up.contains(
+        "    CONSTRAINT owner FOREIGN KEY (owner_id) REFERENCES owner (id) ON DELETE CASCADE\n);"
+    ));
+    assert!(up.contains("CREATE INDEX pet_owner_idx ON pet (owner_id);"));
+    assert!(!up.contains("ADD CONSTRAINT"));
+    assert!(!up.contains("EXCLUDE"));
+
+    let ir = load_inline_schema(&SCHEMA.replace("default = \"4\"", "default = \"abs(-4)\""));
+    let (up, _) = generate_initial_migration(&ir).unwrap();
+    assert!(up.contains("legs INTEGER NOT NULL DEFAULT (abs(-4))"));
+}
+
+#[test]
+fn sqlite_rebuilds_tables_it_cannot_alter() {
+    let old = load_inline_schema(SCHEMA);
+    let new = load_inline_schema(&SCHEMA.replace(
+        "fields.legs = { type = \"i32\", db_type = \"INTEGER\", nullable = false, default = \"4\" }",
+        "fields.leg_count = { type = \"i64\", db_type = \"BIGINT\", nullable = true, rename_from = \"legs\" }\n\
+         fields.nickname = { type = \"String\", db_type = \"TEXT\", nullable = true }",
+    ));
+    let (up, down) = generate_migration(Some(&old), &new).unwrap();
+    assert!(up.starts_with("-- rustdbgen:no-transaction\nPRAGMA foreign_keys = OFF;\nBEGIN;\n"));
+    assert!(up.ends_with("COMMIT;\nPRAGMA foreign_keys = ON;\n"));
+    assert!(up.contains("CREATE TABLE pet__new (\n"));
+    assert!(up.contains("    leg_count BIGINT,\n"));
+    assert!(up.contains(
+        "INSERT INTO pet__new (id, name, kind, leg_count, owner_id) SELECT id, name, kind, legs, owner_id FROM pet;"
+    ));
+    assert!(up.contains("DROP TABLE pet;\nALTER TABLE pet__new RENAME TO pet;\n"));
+    assert!(up.contains("CREATE INDEX pet_owner_idx ON pet (owner_id);"));
+    assert!(!up.contains("ALTER COLUMN"));
+    assert!(down.contains("    legs INTEGER NOT NULL DEFAULT 4,\n"));
+    assert!(down.contains("SELECT id, name, kind, leg_count, owner_id FROM pet;"));
+}
+
+#[test]
+fn sqlite_adds_nullable_columns_in_place() {
+    let old = load_inline_schema(SCHEMA);
+    let new = load_inline_schema(&SCHEMA.replace(
+        "[models.Owner]\n",
+        "[models.Owner]\nfields.email = { type = \"String\", db_type = \"TEXT\", nullable = true }\n",
+    ));
+    let (up, down) = generate_migration(Some(&old), &new).unwrap();
+    assert!(up.contains("ALTER TABLE owner ADD COLUMN email TEXT;"));
+    assert!(!up.contains("__new"));
+    assert!(!up.contains("PRAGMA"));
+    assert!(down.contains("ALTER TABLE owner DROP COLUMN email;"));
+}
+
+#[test]
+fn mysql_restates_columns_with_modify() {
+    let old = load_inline_schema(&backend(SCHEMA, "mysql"));
+    let (up, _) = generate_initial_migration(&old).unwrap();
+    assert!(up.contains("kind ENUM('cat', 'dog') NOT NULL"));
+    assert!(up.contains("ALTER TABLE pet ADD CONSTRAINT pet_name_owner_unique UNIQUE (name, owner_id);"));
+    assert!(!up.contains("CREATE TYPE"));
+    assert!(!up.contains("EXCLUDE"));
+
+    let new = load_inline_schema(&backend(
+        &SCHEMA.replace(
+            "db_type = \"INTEGER\", nullable = false, default = \"4\"",
+            "db_type = \"BIGINT\", nullable = true, default = \"2\"",
+        ),
+        "mysql",
+    ));
+    let (up, down) = generate_migration(Some(&old), &new).unwrap();
+    assert!(up.contains("ALTER TABLE pet MODIFY COLUMN legs BIGINT DEFAULT 2;"));
+    assert!(down.contains("ALTER TABLE pet MODIFY COLUMN legs INTEGER NOT NULL DEFAULT 4;"));
+    assert!(!up.contains("ALTER COLUMN"));
+}
+
+#[test]
+fn postgres_alters_columns_property_by_property() {
+    let old = load_inline_schema(&backend(SCHEMA, "postgres"));
+    let new = load_inline_schema(&backend(
+        &SCHEMA.replace("nullable = false, default = \"4\"", "nullable = true, default = \"4\""),
+        "postgres",
+    ));
+    let (up, _) = generate_migration(Some(&old), &new).unwrap();
+    assert!(up.contains("ALTER TABLE pet ALTER COLUMN legs DROP NOT NULL;"));
+    assert!(!up.contains("MODIFY COLUMN"));
+    assert!(!up.contains("ALTER COLUMN legs TYPE"));
+}
+
+#[test]
+fn postgres_builds_new_indexes_concurrently_when_asked() {
+    let old = load_inline_schema(&backend(SCHEMA, "postgres"));
+    let new = load_inline_schema(&backend(
+        &SCHEMA.replace(
+            "indexes.pet_owner_idx = { fields = [\"owner_id\"] }",
+            "indexes.pet_owner_idx = { fields = [\"owner_id\"] }\n\
+             indexes.pet_name_idx = { fields = [\"name\"] }\n\
+             options.concurrent_indexes = true",
+        ),
+        "postgres",
+    ));
+    let (up, down) = generate_migration(Some(&old), &new).unwrap();
+    assert_eq!(
+        up,
+        "-- rustdbgen:no-transaction\nCREATE INDEX CONCURRENTLY pet_name_idx ON pet (name);\n"
+    );
+    assert_eq!(down, "-- rustdbgen:no-transaction\nDROP INDEX CONCURRENTLY pet_name_idx;\n");
+
+    let plain = load_inline_schema(&backend(
+        &SCHEMA.replace(
+            "indexes.pet_owner_idx = { fields = [\"owner_id\"] }",
+            "indexes.pet_owner_idx = { fields = [\"owner_id\"] }\nindexes.pet_name_idx = { fields = [\"name\"] }",
+        ),
+        "postgres",
+    ));
+    let (up, _) = generate_migration(Some(&old), &plain).unwrap();
+    assert!(up.starts_with("BEGIN;\n"));
+    assert!(up.contains("CREATE INDEX pet_name_idx ON pet (name);"));
+}
@@ -0,0 +1,101 @@
+use rustdbgen::drift::{DriftCategory, DriftSeverity, detect_drift};
+use rustdbgen::{apply_migrations, connect_any_pool, generate_initial_migration};
+use std::fs;
+
+mod common;
+use common::load_inline_schema;
+
+const SCHEMA: &str = r#"
+[meta]
+rust_case_style = "snake"
+db_case_style = "snake"
+default_derives = []
+db_backend = "sqlite"
+
+[models.Owner]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "INTEGER" }
+fields.name = { type = "String", db_type = "TEXT", nullable = false }
+
+[models.Pet]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "INTEGER" }
+fields.name = { type = "String", db_type = "TEXT", nullable = false }
+fields.legs = { type = "i32", db_type = "INTEGER", nullable = false, default = "4" }
+fields.owner_id = { type = "i64", db_type = "INTEGER", nullable = false }
+relations.owner = { on = "owner_id", references = { model = "Owner", field = "id" } }
+unique_constraints.pet_name_owner_unique = { fields = ["name", "owner_id"] }
+check_constraints.pet_legs_check = { expression = "legs >= 0" }
+indexes.pet_owner_idx = { fields = ["owner_id"] }
+"#;
+
+#[tokio::test]
+async fn hand_edits_are_reported_by_category() {
+    let ir = load_inline_schema(SCHEMA);
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    let (up, down) = generate_initial_migration(&ir).unwrap();
+    fs::write(migrations.join("20240101000000_init.up.sql"), &up).unwrap();
+    fs::write(migrations.join("20240101000000_init.down.sql"), down).unwrap();
+    fs::write(dir.path().join("test.db"), "").unwrap();
+    let url = format!("sqlite://{}", dir.path().join("test.db").display());
+    let migrations = migrations.to_str().unwrap().to_string();
+    let pool = connect_any_pool(&url).await.unwrap();
+    apply_migrations(&pool, &migrations).await.unwrap();
+
+    assert!(detect_drift(&url, &ir, &migrations).await.unwrap().is_empty());
+
+    for sql in [
+        "CREATE INDEX pet_name_idx ON pet (name)",
+        "ALTER TABLE owner ADD COLUMN email TEXT DEFAULT 'none'",
+        "DROP INDEX pet_owner_idx",
+    ] {
+        sqlx::query(sql).execute(&pool).await.unwrap();
+    }
+    fs::write(format!("{}/20240101000000_init.up.sql", migrations), format!("{}-- edited\n", up)).unwrap();
+
+    let drift = detect_drift(&url, &ir, &migrations).await.unwrap();
+    let found: Vec<(DriftCategory, DriftSeverity, &str)> = drift
+        .iter()
+        .map(|d| (d.category, d.severity, d.description.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (DriftCategory::Column, DriftSeverity::Warning, "unexpected column owner.email"),
+            (DriftCategory::Index, DriftSeverity::Warning, "index pet_owner_idx on pet is missing"),
+            (DriftCategory::Index, DriftSeverity::Warning, "unexpected index pet_name_idx on pet"),
+            (
+                DriftCategory::History,
+                DriftSeverity::Error,
+                "migration 20240101000000_init.up.sql was edited after it was applied"
+            ),
+        ]
+    );
+    let json = serde_json::to_value(&drift[3]).unwrap();
+    assert_eq!(json["category"], "history");
+    assert_eq!(json["severity"], "error");
+}
+
+#[tokio::test]
+async fn changed_defaults_are_reported() {
+    let ir = load_inline_schema(SCHEMA);
+    let edited = load_inline_schema(&SCHEMA.replace("default = \"4\"", "default = \"(2 + 2)\""));
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    let (up, _) = generate_initial_migration(&edited).unwrap();
+    fs::write(migrations.join("20240101000000_init.up.sql"), &up).unwrap();
+    fs::write(dir.path().join("test.db"), "").unwrap();
+    let url = format!("sqlite://{}", dir.path().join("test.db").display());
+    let migrations = migrations.to_str().unwrap().to_string();
+    let pool = connect_any_pool(&url).await.unwrap();
+    apply_migrations(&pool, &migrations).await.unwrap();
+
+    assert!(detect_drift(&url, &edited, &migrations).await.unwrap().is_empty());
+    let drift = detect_drift(&url, &ir, &migrations).await.unwrap();
+    assert_eq!(drift.len(), 1);
+    assert_eq!(drift[0].category, DriftCategory::Default);
+    assert_eq!(drift[0].description, "default of pet.legs is 2 + 2 instead of 4");
+}
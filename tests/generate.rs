@@ -18,8 +18,8 @@ fn generate_includes_new_and_update_structs() {
 fn enums_are_generated() {
     let ir: SchemaIR = load_schema("schema.model.toml").unwrap();
     let code = generate_code(&ir);
-    assert!(code.contains("enum PostStatus"));
-    assert!(code.contains("Draft"));
+    assert!(code.contains("enum TransactionStatus"));
+    assert!(code.contains("Pending"));
 }
 
 #[test]
@@ -29,7 +29,7 @@ fn model_options_add_fields() {
     apply_model_options(&mut ir);
     assert!(ir.models["User"].fields.contains_key("created_at"));
     assert!(ir.models["User"].fields.contains_key("deleted_at"));
-    assert!(ir.models["Post"].fields.contains_key("updated_at"));
+    assert!(ir.models["Account"].fields.contains_key("updated_at"));
 }
 
 #[test]
@@ -51,9 +51,9 @@ fn type_aliases_expand() {
         })
         .collect();
     apply_type_aliases(&mut ir, &aliases);
-    assert_eq!(ir.models["User"].fields["email"].rust_type, "String");
+    assert_eq!(ir.models["User"].fields["profile_pic"].rust_type, "String");
     assert_eq!(
-        ir.models["User"].fields["email"].db_type.as_deref(),
+        ir.models["User"].fields["profile_pic"].db_type.as_deref(),
         Some("TEXT")
     );
 }
@@ -73,7 +73,7 @@ fn permissions_parse() {
     let ir: SchemaIR = load_schema("schema.model.toml").unwrap();
     let perms = &ir.models["User"].permissions;
     assert_eq!(perms.read, vec!["admin", "member"]);
-    assert_eq!(perms.update, vec!["admin"]);
+    assert_eq!(perms.update, vec!["admin", "member"]);
     assert_eq!(perms.delete, vec!["admin"]);
 }
 
@@ -83,7 +83,7 @@ fn routes_generate_constants() {
     let code = generate_code(&ir);
     assert!(code.contains("pub mod routes"));
     assert!(code.contains("pub const USER"));
-    assert!(code.contains("pub const POST"));
+    assert!(code.contains("pub const TRANSACTION"));
 }
 
 #[test]
@@ -91,7 +91,7 @@ fn route_permissions_parse() {
     let ir: SchemaIR = load_schema("schema.model.toml").unwrap();
     let route = &ir.routes["User"];
     assert_eq!(route.permissions.read, vec!["admin", "member"]);
-    assert_eq!(route.permissions.update, vec!["admin"]);
+    assert_eq!(route.permissions.update, vec!["admin", "member"]);
     assert_eq!(route.permissions.delete, vec!["admin"]);
 }
 
@@ -110,11 +110,11 @@ fn typescript_generation() {
 fn graphql_generation() {
     let ir: SchemaIR = load_schema("schema.model.toml").unwrap();
     let gql = rustdbgen::generate_graphql_schema(&ir);
-    assert!(gql.contains("type Post"));
-    assert!(gql.contains("author: User"));
+    assert!(gql.contains("type Transaction"));
+    assert!(gql.contains("user: User"));
     assert!(gql.contains("type Query"));
     assert!(gql.contains("createUser"));
-    assert!(gql.contains("updatePost"));
+    assert!(gql.contains("updateTransaction"));
 }
 
 #[test]
@@ -137,10 +137,20 @@ fn crud_sql_generation() {
     let ir: SchemaIR = load_schema("schema.model.toml").unwrap();
     let code = generate_code(&ir);
     assert!(code.contains("INSERT INTO user"));
-    assert!(code.contains("DELETE FROM user"));
+    assert!(code.contains("UPDATE user SET deleted_at"));
     assert!(code.contains("SELECT * FROM user WHERE id"));
 }
 
+#[test]
+fn db_schema_qualifies_generated_queries() {
+    let mut ir: SchemaIR = load_schema("schema.model.toml").unwrap();
+    ir.meta.db_schema = Some("billing".into());
+    let code = generate_code(&ir);
+    assert!(code.contains("INSERT INTO billing.user"));
+    assert!(code.contains("SELECT * FROM billing.user WHERE id"));
+    assert!(!code.contains("FROM user "));
+}
+
 #[test]
 fn auth_helpers_generated() {
     let ir: SchemaIR = load_schema("schema.model.toml").unwrap();
@@ -162,7 +172,7 @@ fn meta_backend_default() {
 #[test]
 fn auth_provider_enum_parses() {
     let ir: SchemaIR = load_schema("schema.model.toml").unwrap();
-    assert!(matches!(ir.meta.auth.provider, rustdbgen::ir::AuthProvider::None));
+    assert!(matches!(ir.meta.auth.provider, rustdbgen::ir::AuthProvider::Jwt));
     assert_eq!(ir.meta.auth.role_claim, "role");
 }
 
@@ -179,10 +189,11 @@ fn typed_error_enum_generated() {
     let ir: SchemaIR = load_schema("schema.model.toml").unwrap();
     let code = generate_code(&ir);
     assert!(code.contains("enum UserCreateError"));
-    assert!(code.contains("PostsFk"));
-    assert!(code.contains("enum PostCreateError"));
-    assert!(code.contains("PostTitleAuthorUnique"));
-    assert!(code.contains("AuthorFk"));
+    assert!(code.contains("UserEmailUnique"));
+    assert!(code.contains("enum TransactionCreateError"));
+    assert!(code.contains("enum AccountCreateError"));
+    assert!(code.contains("AccountNameUserUnique"));
+    assert!(code.contains("FromAccountFk"));
 }
 
 #[test]
@@ -192,3 +203,126 @@ fn pagination_list_generated() {
     assert!(code.contains("struct Pagination"));
     assert!(code.contains("pagination: Option<Pagination>"));
 }
+
+#[test]
+fn primary_keys_shape_handlers_and_routes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("schema.toml");
+    fs::write(
+        &path,
+        r#"
+[models.Org]
+primary_key = { fields = ["id"], generated = "identity" }
+fields.id = { type = "i64", db_type = "BIGINT" }
+fields.name = { type = "String", db_type = "TEXT" }
+
+[models.Membership]
+primary_key = ["org_id", "user_id"]
+fields.org_id = { type = "i64", db_type = "BIGINT" }
+fields.user_id = { type = "Uuid", db_type = "UUID" }
+fields.role = { type = "String", db_type = "TEXT" }
+"#,
+    )
+    .unwrap();
+    let ir = load_schema(path.to_str().unwrap()).unwrap();
+    let code = generate_code(&ir);
+
+    assert!(code.contains("pub type OrgKey = i64;"));
+    assert!(code.contains("pub async fn find<'c, E>(executor: E, id: i64)"));
+    assert!(code.contains("INSERT INTO org (name) VALUES ($1)"));
+    assert!(code.contains("\"/org/:id\""));
+    assert!(code.contains("Path(id): Path<i64>"));
+
+    assert!(code.contains("pub type MembershipKey = (i64, uuid::Uuid);"));
+    assert!(code.contains("executor: E, org_id: i64, user_id: uuid::Uuid)"));
+    assert!(code.contains("WHERE org_id = $1 AND user_id = $2"));
+    assert!(code.contains("\"/membership/:org_id/:user_id\""));
+    assert!(code.contains("Path((org_id, user_id)): Path<(i64, uuid::Uuid)>"));
+    assert!(code.contains("find_by_ids<'c, E>(executor: E, ids: &[MembershipKey])"));
+    assert!(!code.contains("pub org_id: Option<i64>"));
+}
+
+#[test]
+fn delete_handlers_map_restricting_foreign_keys() {
+    let ir: SchemaIR = load_schema("schema.model.toml").unwrap();
+    let code = generate_code(&ir);
+    assert!(code.contains("pub enum AccountDeleteError"));
+    assert!(code.contains("ReferencedByTransactionAccount"));
+    assert!(code.contains("if c == \"account\" { return Err(AccountDeleteError::ReferencedByTransactionAccount); }"));
+    assert!(code.contains("AccountDeleteError::ReferencedByTransactionAccount => StatusCode::CONFLICT"));
+    // Cascading and set-null relations never block a delete
+    assert!(!code.contains("ReferencedByAccountUser"));
+    assert!(!code.contains("ReferencedByTransactionCategory"));
+}
+
+#[test]
+fn many_to_many_helpers_routes_and_types() {
+    let ir: SchemaIR = load_schema("schema.model.toml").unwrap();
+    let code = generate_code(&ir);
+    assert!(code.contains("pub async fn attach_tags<'c, E>(&self, executor: E, tag_id: TagKey)"));
+    assert!(code.contains("pub async fn detach_tags<'c, E>(&self, executor: E, tag_id: TagKey)"));
+    assert!(code.contains("pub async fn find_tags<'c, E>(&self, executor: E, pagination: Option<Pagination>) -> Result<Vec<Tag>, sqlx::Error>"));
+    assert!(code.contains("INSERT INTO tag_transaction (transaction_id, tag_id) VALUES ($1, $2) ON CONFLICT DO NOTHING"));
+    assert!(code.contains("pub async fn attach_transactions<'c, E>(&self, executor: E, transaction_id: TransactionKey)"));
+
+    let router = rustdbgen::codegen::router::generate_router(&ir);
+    assert!(router.contains(".route(\"/transaction/:id/tags\", get(list_transaction_tags))"));
+    assert!(router.contains(".route(\"/transaction/:id/tags/:tag_id\", put(attach_transaction_tags))"));
+    assert!(router.contains(".route(\"/transaction/:id/tags/:tag_id\", delete(detach_transaction_tags))"));
+    assert!(router.contains("Path((id, tag_id)): Path<(uuid::Uuid, uuid::Uuid)>"));
+
+    let gql = rustdbgen::generate_graphql_schema(&ir);
+    assert!(gql.contains("    tags: [Tag!]!\n"));
+    assert!(gql.contains("    accounts: [Account!]!\n"));
+    assert!(gql.contains("    category: Category\n"));
+
+    let ts = rustdbgen::generate_typescript(&ir);
+    assert!(ts.contains("    tags?: Tag[];\n"));
+    assert!(ts.contains("    transactions?: Transaction[];\n"));
+}
+
+#[test]
+fn generated_server_embeds_migrations() {
+    let ir: SchemaIR = load_schema("schema.model.toml").unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir_all(&migrations).unwrap();
+    fs::write(
+        migrations.join("20240101000000_init.up.sql"),
+        "BEGIN;\nCREATE TABLE a (id BIGINT PRIMARY KEY);\nCREATE TABLE b (note TEXT DEFAULT 'x;y');\nCOMMIT;\n",
+    )
+    .unwrap();
+    fs::write(migrations.join("20240101000000_init.down.sql"), "DROP TABLE a;\n").unwrap();
+    fs::write(
+        migrations.join("20240102000000_index.up.sql"),
+        "-- rustdbgen:no-transaction\nCREATE INDEX CONCURRENTLY a_id ON a (id);\n",
+    )
+    .unwrap();
+    let out = dir.path().join("backend");
+    rustdbgen::generate_code_multi(&ir, &out, Some(&migrations)).unwrap();
+
+    let code = fs::read_to_string(out.join("src/generated/migrations.rs")).unwrap();
+    assert!(code.contains("name: \"20240101000000_init.up.sql\""));
+    assert!(!code.contains("init.down.sql"));
+    // the hash the CLI records for the file
+    assert!(code.contains("hash: \"3f106978392fcef08a51cde2baa92606cf32ff6fbf5903044e70eaec83fb2a0c\""));
+    assert!(code.contains("            \"CREATE TABLE a (id BIGINT PRIMARY KEY)\",\n"));
+    assert!(code.contains("            \"CREATE TABLE b (note TEXT DEFAULT 'x;y')\",\n"));
+    assert!(!code.contains("\"BEGIN\""));
+    assert!(code.contains("no_transaction: true"));
+    assert!(code.contains("    pool: &sqlx::PgPool,\n    mode: MigrationMode,\n    lock_timeout: std::time::Duration,\n"));
+    // the lock is polled until the timeout, as the CLI does
+    assert!(code.contains("SELECT pg_try_advisory_lock($1)"));
+    assert!(code.contains("if locked || Instant::now() >= deadline {"));
+    assert!(code.contains("waiting for the migration lock held by another process"));
+
+    let main = fs::read_to_string(out.join("src/generated/main.rs")).unwrap();
+    assert!(main.contains(
+        "run_migrations(&pool, config.database.migrations, config.database.migration_lock_timeout).await?;"
+    ));
+    let config = fs::read_to_string(out.join("src/generated/config.rs")).unwrap();
+    assert!(config.contains("std::env::var(\"MIGRATIONS\")"));
+    assert!(config.contains("std::env::var(\"MIGRATION_LOCK_TIMEOUT\")"));
+    let module = fs::read_to_string(out.join("src/generated/mod.rs")).unwrap();
+    assert!(module.contains("pub mod migrations;"));
+}
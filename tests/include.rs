@@ -4,7 +4,7 @@ use rustdbgen::load_schema;
 fn includes_merge_models() {
     let ir = load_schema("schema.model.toml").unwrap();
     assert!(ir.models.contains_key("User"));
-    assert!(ir.models.contains_key("Post"));
+    assert!(ir.models.contains_key("Transaction"));
 }
 
 #[test]
@@ -66,3 +66,47 @@ fn include_cycle_errors() {
     let res = load_schema(a.to_str().unwrap());
     assert!(res.is_err(), "expected cyclic include error");
 }
+
+#[test]
+fn split_schema_loads_back() {
+    use rustdbgen::{diff_schemas, write_split_schema};
+    use std::fs;
+    use tempfile::tempdir;
+
+    let ir = load_schema("schema.model.toml").unwrap();
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("schema.model.toml");
+    let written = write_split_schema(&ir, &root, &dir.path().join("models")).unwrap();
+    assert_eq!(written.len(), ir.models.len() + 1);
+
+    let root_text = fs::read_to_string(&root).unwrap();
+    assert!(root_text.contains("\"models/User.toml\""));
+    assert!(!root_text.contains("[models."));
+    let user_text = fs::read_to_string(dir.path().join("models/User.toml")).unwrap();
+    assert!(user_text.contains("[models.User"));
+    assert!(user_text.contains("[seed.User"));
+
+    let reloaded = load_schema(root.to_str().unwrap()).unwrap();
+    assert_eq!(
+        reloaded.models.keys().collect::<Vec<_>>(),
+        ir.models.keys().collect::<Vec<_>>()
+    );
+    assert_eq!(reloaded.seeds.len(), ir.seeds.len());
+    assert_eq!(reloaded.enums.len(), ir.enums.len());
+    assert!(diff_schemas(Some(&ir), &reloaded).unwrap().is_empty());
+}
+
+#[test]
+fn single_file_schema_uses_seed_tables() {
+    use rustdbgen::schema_to_toml;
+
+    let ir = load_schema("schema.model.toml").unwrap();
+    let text = schema_to_toml(&ir).unwrap();
+    assert!(text.contains("[seed.User"));
+    assert!(!text.contains("[seeds"));
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("schema.toml");
+    std::fs::write(&path, text).unwrap();
+    let reloaded = load_schema(path.to_str().unwrap()).unwrap();
+    assert_eq!(reloaded.seeds.len(), ir.seeds.len());
+}
@@ -1,12 +1,15 @@
 use rustdbgen::{
     apply_type_aliases,
-    ir::{FieldDef, FieldRef, Meta, ModelDef, RelationDef, SchemaIR, TypeAlias},
+    ir::{
+        FieldDef, FieldRef, KeyGeneration, ManyToManyDef, Meta, ModelDef, PrimaryKeyDef, ReferentialAction,
+        RelationDef, SchemaIR, TypeAlias,
+    },
     lint_schema, load_schema,
 };
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
 #[test]
-fn lint_passes_for_valid_schema() {
+fn example_schema_lints_clean_apart_from_one_sided_relations() {
     let mut ir: SchemaIR = load_schema("schema.model.toml").unwrap();
     let alias_text = std::fs::read_to_string("type_map.toml").unwrap();
     let val: toml::Value = toml::from_str(&alias_text).unwrap();
@@ -24,16 +27,17 @@ fn lint_passes_for_valid_schema() {
         })
         .collect();
     apply_type_aliases(&mut ir, &aliases);
-    let errors = lint_schema(&ir);
-    assert_eq!(
-        errors,
-        vec!["Exclusion constraint post_author_excl on model Post uses a raw definition that cannot be fully validated".to_string()]
-    );
+    // the example models only declare some of their has-many sides
+    let errors: Vec<String> = lint_schema(&ir)
+        .into_iter()
+        .filter(|e| !e.contains("is not reciprocated"))
+        .collect();
+    assert!(errors.is_empty(), "unexpected lint errors: {:?}", errors);
 }
 
 #[test]
 fn lint_fails_for_invalid_relation() {
-    let mut fields = HashMap::new();
+    let mut fields = IndexMap::new();
     fields.insert(
         "id".to_string(),
         FieldDef {
@@ -45,6 +49,8 @@ fn lint_fails_for_invalid_relation() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
     fields.insert(
@@ -58,44 +64,57 @@ fn lint_fails_for_invalid_relation() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut relations = HashMap::new();
+    let mut relations = IndexMap::new();
     relations.insert(
         "bad_rel".to_string(),
         RelationDef {
             on: "author_id".into(),
             references: FieldRef { model: "Missing".into(), field: "id".into() },
+            on_delete: Default::default(),
+            on_update: Default::default(),
+            deferrable: false,
+            initially_deferred: false,
         },
     );
-    let mut models = HashMap::new();
+    let mut models = IndexMap::new();
     models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
             fields,
-            indexes: HashMap::new(),
+            indexes: IndexMap::new(),
             relations,
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let ir = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+    let ir = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
             auth: Default::default(),
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models,
     };
     let errors = lint_schema(&ir);
@@ -103,7 +122,7 @@ fn lint_fails_for_invalid_relation() {
 }
 #[test]
 fn lint_fails_for_unreciprocated_relation() {
-    let mut post_fields = HashMap::new();
+    let mut post_fields = IndexMap::new();
     post_fields.insert(
         "id".to_string(),
         FieldDef {
@@ -115,6 +134,8 @@ fn lint_fails_for_unreciprocated_relation() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
     post_fields.insert(
@@ -128,29 +149,41 @@ fn lint_fails_for_unreciprocated_relation() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut post_rel = HashMap::new();
+    let mut post_rel = IndexMap::new();
     post_rel.insert(
         "author".to_string(),
         RelationDef {
             on: "user_id".into(),
             references: FieldRef { model: "User".into(), field: "id".into() },
+            on_delete: Default::default(),
+            on_update: Default::default(),
+            deferrable: false,
+            initially_deferred: false,
         },
     );
 
     let post_model = ModelDef { includes: Vec::new(), 
         fields: post_fields,
-        indexes: HashMap::new(),
+        indexes: IndexMap::new(),
         relations: post_rel,
-        unique_constraints: HashMap::new(),
-        check_constraints: HashMap::new(),
-        exclusion_constraints: HashMap::new(),
+        unique_constraints: IndexMap::new(),
+        check_constraints: IndexMap::new(),
+        exclusion_constraints: IndexMap::new(),
         permissions: Default::default(),
         options: Default::default(),
+        owned_by: None,
+        primary_key: None,
+        many_to_many: IndexMap::new(),
+        table: None,
+        dropped: false,
+        rename_from: None,
     };
 
-    let mut user_fields = HashMap::new();
+    let mut user_fields = IndexMap::new();
     user_fields.insert(
         "id".to_string(),
         FieldDef {
@@ -162,30 +195,38 @@ fn lint_fails_for_unreciprocated_relation() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
     let user_model = ModelDef { includes: Vec::new(), 
         fields: user_fields,
-        indexes: HashMap::new(),
-        relations: HashMap::new(),
-        unique_constraints: HashMap::new(),
-        check_constraints: HashMap::new(),
-        exclusion_constraints: HashMap::new(),
+        indexes: IndexMap::new(),
+        relations: IndexMap::new(),
+        unique_constraints: IndexMap::new(),
+        check_constraints: IndexMap::new(),
+        exclusion_constraints: IndexMap::new(),
         permissions: Default::default(),
         options: Default::default(),
+        owned_by: None,
+        primary_key: None,
+        many_to_many: IndexMap::new(),
+        table: None,
+        dropped: false,
+        rename_from: None,
     };
 
-    let mut models = HashMap::new();
+    let mut models = IndexMap::new();
     models.insert("Post".to_string(), post_model);
     models.insert("User".to_string(), user_model);
 
-    let ir = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+    let ir = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta::default(),
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models,
     };
 
@@ -198,7 +239,7 @@ fn lint_fails_for_unreciprocated_relation() {
 
 #[test]
 fn lint_fails_for_duplicate_routes() {
-    let mut routes = HashMap::new();
+    let mut routes = IndexMap::new();
     routes.insert(
         "user".to_string(),
         rustdbgen::ir::RouteDef {
@@ -218,14 +259,14 @@ fn lint_fails_for_duplicate_routes() {
         },
     );
 
-    let ir = SchemaIR { macros: HashMap::new(), 
+    let ir = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
         routes,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta::default(),
-        enums: HashMap::new(),
-        models: HashMap::new(),
+        enums: IndexMap::new(),
+        models: IndexMap::new(),
     };
 
     let errors = lint_schema(&ir);
@@ -237,7 +278,7 @@ fn lint_fails_for_duplicate_routes() {
 
 #[test]
 fn lint_fails_for_invalid_route_method() {
-    let mut routes = HashMap::new();
+    let mut routes = IndexMap::new();
     routes.insert(
         "bad".to_string(),
         rustdbgen::ir::RouteDef {
@@ -248,14 +289,14 @@ fn lint_fails_for_invalid_route_method() {
         },
     );
 
-    let ir = SchemaIR { macros: HashMap::new(), 
+    let ir = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
         routes,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta::default(),
-        enums: HashMap::new(),
-        models: HashMap::new(),
+        enums: IndexMap::new(),
+        models: IndexMap::new(),
     };
 
     let errors = lint_schema(&ir);
@@ -264,7 +305,7 @@ fn lint_fails_for_invalid_route_method() {
 
 #[test]
 fn lint_fails_for_bad_check_expression() {
-    let mut fields = HashMap::new();
+    let mut fields = IndexMap::new();
     fields.insert(
         "id".to_string(),
         FieldDef {
@@ -276,36 +317,44 @@ fn lint_fails_for_bad_check_expression() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut checks = HashMap::new();
+    let mut checks = IndexMap::new();
     checks.insert(
         "bad_check".to_string(),
         rustdbgen::ir::CheckConstraintDef {
             expression: "missing_field > 0".into(),
         },
     );
-    let mut models = HashMap::new();
+    let mut models = IndexMap::new();
     models.insert(
         "Thing".to_string(),
         ModelDef { includes: Vec::new(), 
             fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
             check_constraints: checks,
-            exclusion_constraints: HashMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let ir = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+    let ir = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta::default(),
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models,
     };
     let errors = lint_schema(&ir);
@@ -318,7 +367,7 @@ fn lint_fails_for_bad_check_expression() {
 
 #[test]
 fn lint_fails_for_unknown_role() {
-    let mut fields = HashMap::new();
+    let mut fields = IndexMap::new();
     fields.insert(
         "id".to_string(),
         FieldDef {
@@ -330,37 +379,45 @@ fn lint_fails_for_unknown_role() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut models = HashMap::new();
+    let mut models = IndexMap::new();
     models.insert(
         "Thing".to_string(),
         ModelDef { includes: Vec::new(), 
             fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: rustdbgen::ir::Permissions {
                 read: vec!["unknown".into()],
                 update: Vec::new(),
                 delete: Vec::new(),
             },
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let mut enums = HashMap::new();
+    let mut enums = IndexMap::new();
     enums.insert(
         "Role".to_string(),
         rustdbgen::ir::EnumDef {
             variants: vec!["admin".into()],
         },
     );
-    let ir = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+    let ir = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta::default(),
         enums,
@@ -372,7 +429,7 @@ fn lint_fails_for_unknown_role() {
 
 #[test]
 fn lint_allows_public_role() {
-    let mut fields = HashMap::new();
+    let mut fields = IndexMap::new();
     fields.insert(
         "id".to_string(),
         FieldDef {
@@ -384,35 +441,43 @@ fn lint_allows_public_role() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut models = HashMap::new();
+    let mut models = IndexMap::new();
     models.insert(
         "Thing".to_string(),
         ModelDef { includes: Vec::new(), 
             fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: rustdbgen::ir::Permissions {
                 read: vec!["viewer".into()],
                 update: Vec::new(),
                 delete: Vec::new(),
             },
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
     let mut meta = Meta::default();
     meta.auth.public_role = "viewer".into();
-    let ir = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+    let ir = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
         schema_version: "1.0".into(),
         meta,
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models,
     };
     let errors = lint_schema(&ir);
@@ -421,21 +486,21 @@ fn lint_allows_public_role() {
 
 #[test]
 fn lint_fails_for_seed_unknown_model() {
-    let mut seeds = HashMap::new();
+    let mut seeds = IndexMap::new();
     seeds.insert(
         "Missing".to_string(),
         rustdbgen::ir::SeedDef {
-            rows: vec![HashMap::new()],
+            rows: vec![IndexMap::new()],
         },
     );
-    let ir = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
-        plugins: HashMap::new(),
+    let ir = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
+        plugins: IndexMap::new(),
         seeds,
         schema_version: "1.0".into(),
         meta: Meta::default(),
-        enums: HashMap::new(),
-        models: HashMap::new(),
+        enums: IndexMap::new(),
+        models: IndexMap::new(),
     };
     let errors = lint_schema(&ir);
     assert!(errors.iter().any(|e| e.contains("unknown model")));
@@ -443,7 +508,7 @@ fn lint_fails_for_seed_unknown_model() {
 
 #[test]
 fn lint_fails_for_seed_unknown_field() {
-    let mut fields = HashMap::new();
+    let mut fields = IndexMap::new();
     fields.insert(
         "id".to_string(),
         FieldDef {
@@ -455,34 +520,42 @@ fn lint_fails_for_seed_unknown_field() {
             tags: Vec::new(),
             zod: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
     let model = ModelDef { includes: Vec::new(), 
         fields: fields.clone(),
-        indexes: HashMap::new(),
-        relations: HashMap::new(),
-        unique_constraints: HashMap::new(),
-        check_constraints: HashMap::new(),
-        exclusion_constraints: HashMap::new(),
+        indexes: IndexMap::new(),
+        relations: IndexMap::new(),
+        unique_constraints: IndexMap::new(),
+        check_constraints: IndexMap::new(),
+        exclusion_constraints: IndexMap::new(),
         permissions: Default::default(),
         options: Default::default(),
+        owned_by: None,
+        primary_key: None,
+        many_to_many: IndexMap::new(),
+        table: None,
+        dropped: false,
+        rename_from: None,
     };
-    let mut models = HashMap::new();
+    let mut models = IndexMap::new();
     models.insert("User".to_string(), model);
-    let mut row = HashMap::new();
+    let mut row = IndexMap::new();
     row.insert("bad_field".to_string(), toml::Value::String("val".into()));
-    let mut seeds = HashMap::new();
+    let mut seeds = IndexMap::new();
     seeds.insert(
         "User".to_string(),
         rustdbgen::ir::SeedDef { rows: vec![row] },
     );
-    let ir = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
-        plugins: HashMap::new(),
+    let ir = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
+        plugins: IndexMap::new(),
         seeds,
         schema_version: "1.0".into(),
         meta: Meta::default(),
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models,
     };
     let errors = lint_schema(&ir);
@@ -491,7 +564,7 @@ fn lint_fails_for_seed_unknown_field() {
 #[test]
 fn lint_warns_for_exclusion_definition() {
     use rustdbgen::ir::ExclusionConstraintDef;
-    let mut fields = HashMap::new();
+    let mut fields = IndexMap::new();
     fields.insert(
         "id".to_string(),
         FieldDef {
@@ -503,36 +576,44 @@ fn lint_warns_for_exclusion_definition() {
             tags: Vec::new(),
             zod: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut exs = HashMap::new();
+    let mut exs = IndexMap::new();
     exs.insert(
         "excl".to_string(),
         ExclusionConstraintDef {
             definition: "USING gist (id WITH =)".into(),
         },
     );
-    let mut models = HashMap::new();
+    let mut models = IndexMap::new();
     models.insert(
         "Thing".to_string(),
         ModelDef { includes: Vec::new(), 
             fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
             exclusion_constraints: exs,
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let ir = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+    let ir = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta::default(),
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models,
     };
     let errors = lint_schema(&ir);
@@ -541,3 +622,95 @@ fn lint_warns_for_exclusion_definition() {
         "expected exclusion constraint lint"
     );
 }
+
+#[test]
+fn primary_key_unknown_field_is_flagged() {
+    let mut fields = IndexMap::new();
+    fields.insert(
+        "code".to_string(),
+        FieldDef {
+            rust_type: "String".into(),
+            db_type: Some("TEXT".into()),
+            default: None,
+            nullable: false,
+            tags: Vec::new(),
+            zod: None,
+            rename_from: None,
+            storage: None,
+            column: None,
+            dropped: false,
+        },
+    );
+    let model = ModelDef {
+        fields,
+        primary_key: Some(PrimaryKeyDef {
+            fields: vec!["code".into(), "region".into()],
+            generated: KeyGeneration::Identity,
+        }),
+        ..Default::default()
+    };
+    let mut models = IndexMap::new();
+    models.insert("Country".to_string(), model);
+    let ir = SchemaIR {
+        macros: IndexMap::new(),
+        routes: IndexMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
+        dropped: Vec::new(),
+        schema_version: "1.0".into(),
+        meta: Meta::default(),
+        enums: IndexMap::new(),
+        models,
+    };
+
+    let errors = lint_schema(&ir);
+    assert!(errors.iter().any(|e| e.contains("Primary key on model Country references unknown field region")));
+    assert!(errors.iter().any(|e| e.contains("cannot be an identity key")));
+}
+
+#[test]
+fn set_null_on_required_field_is_flagged() {
+    let mut ir: SchemaIR = load_schema("schema.model.toml").unwrap();
+    let rel = ir
+        .models
+        .get_mut("Transaction")
+        .unwrap()
+        .relations
+        .get_mut("account")
+        .unwrap();
+    rel.on_delete = ReferentialAction::SetNull;
+    let errors = lint_schema(&ir);
+    assert!(errors.iter().any(|e| e.contains(
+        "Relation account on model Transaction uses set_null but field account_id is not nullable"
+    )));
+}
+
+#[test]
+fn many_to_many_problems_are_flagged() {
+    let mut ir: SchemaIR = load_schema("schema.model.toml").unwrap();
+    let user = ir.models.get_mut("User").unwrap();
+    user.many_to_many.insert(
+        "labels".into(),
+        ManyToManyDef { model: "Label".into(), through: None, source_column: None, target_column: None },
+    );
+    user.many_to_many.insert(
+        "friends".into(),
+        ManyToManyDef { model: "User".into(), through: None, source_column: None, target_column: None },
+    );
+    let errors = lint_schema(&ir);
+    assert!(errors.iter().any(|e| e.contains(
+        "Many-to-many relation labels on model User references unknown model Label"
+    )));
+    assert!(errors.iter().any(|e| e.contains(
+        "Many-to-many relation friends on model User uses column user_id for both sides"
+    )));
+}
+
+#[test]
+fn db_schema_on_sqlite_is_flagged() {
+    let mut ir: SchemaIR = load_schema("schema.model.toml").unwrap();
+    ir.meta.db_schema = Some("billing".into());
+    assert!(!lint_schema(&ir).iter().any(|e| e.contains("db_schema")));
+    ir.meta.db_backend = rustdbgen::ir::DatabaseBackend::Sqlite;
+    assert!(lint_schema(&ir).iter().any(|e| e == "meta.db_schema is not supported on SQLite"));
+}
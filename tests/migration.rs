@@ -1,17 +1,17 @@
 use rustdbgen::{
-    apply_type_aliases, generate_initial_migration,
-    ir::{SchemaIR, TypeAlias},
+    apply_type_aliases, generate_initial_migration, generate_migration,
+    ir::{self, KeyGeneration, PrimaryKeyDef, ReferentialAction, SchemaIR, TypeAlias},
     load_schema,
 };
 use std::fs;
 
-#[test]
-fn initial_migration_contains_create_table() {
-    let mut ir: SchemaIR = load_schema("schema.model.toml").unwrap();
+mod common;
+use common::load_inline_schema;
+
+fn load_aliases() -> std::collections::HashMap<String, TypeAlias> {
     let alias_text = fs::read_to_string("type_map.toml").unwrap();
     let val: toml::Value = toml::from_str(&alias_text).unwrap();
-    let aliases: std::collections::HashMap<String, TypeAlias> = val
-        .as_table()
+    val.as_table()
         .unwrap()
         .iter()
         .filter_map(|(k, v)| {
@@ -22,8 +22,13 @@ fn initial_migration_contains_create_table() {
                 .ok()
                 .map(|a| (k.clone(), a))
         })
-        .collect();
-    apply_type_aliases(&mut ir, &aliases);
+        .collect()
+}
+
+#[test]
+fn initial_migration_contains_create_table() {
+    let mut ir: SchemaIR = load_schema("schema.model.toml").unwrap();
+    apply_type_aliases(&mut ir, &load_aliases());
     let (up, down) = generate_initial_migration(&ir).unwrap();
     assert!(up.starts_with("BEGIN;"));
     assert!(up.trim_end().ends_with("COMMIT;"));
@@ -31,8 +36,274 @@ fn initial_migration_contains_create_table() {
     assert!(down.trim_end().ends_with("COMMIT;"));
     assert!(up.contains("CREATE TABLE user"));
     assert!(down.contains("DROP TABLE user"));
-    assert!(up.contains("CREATE TYPE poststatus"));
-    assert!(up.contains("ADD CONSTRAINT post_title_author_unique UNIQUE"));
-    assert!(up.contains("ADD CONSTRAINT post_title_length CHECK"));
-    assert!(up.contains("ADD CONSTRAINT post_author_excl EXCLUDE"));
+    assert!(up.contains("CREATE TYPE transaction_status"));
+    assert!(up.contains("ADD CONSTRAINT account_name_user_unique UNIQUE"));
+    assert!(up.contains("ADD CONSTRAINT account_name_length CHECK"));
+    assert!(up.contains("FOREIGN KEY (user_id)"));
+}
+
+#[test]
+fn initial_migration_follows_declaration_order() {
+    let mut ir: SchemaIR = load_schema("schema.model.toml").unwrap();
+    apply_type_aliases(&mut ir, &load_aliases());
+    let (up, down) = generate_initial_migration(&ir).unwrap();
+    assert_eq!((up.clone(), down), generate_initial_migration(&ir).unwrap());
+
+    // models keep the include order, columns keep the TOML order
+    let user = up.find("CREATE TABLE user (").unwrap();
+    let account = up.find("CREATE TABLE account (").unwrap();
+    let budget = up.find("CREATE TABLE budget (").unwrap();
+    assert!(user < account && account < budget);
+    let table = &up[user..account];
+    let cols: Vec<_> = table
+        .lines()
+        .skip(1)
+        .filter_map(|l| l.split_whitespace().next())
+        .take(4)
+        .collect();
+    assert_eq!(cols, ["id", "email", "first_name", "last_name"]);
+
+    // enums keep their declaration order too
+    let account_type = up.find("CREATE TYPE account_type").unwrap();
+    let role = up.find("CREATE TYPE role").unwrap();
+    assert!(account_type < role);
+}
+
+#[test]
+fn primary_key_declarations_drive_create_table() {
+    let ir = load_inline_schema(
+        r#"
+[models.Org]
+primary_key = { fields = ["id"], generated = "identity" }
+fields.id = { type = "i64", db_type = "BIGINT" }
+fields.name = { type = "String", db_type = "TEXT" }
+
+[models.Country]
+primary_key = "code"
+fields.code = { type = "String", db_type = "CHAR(2)" }
+
+[models.Membership]
+primary_key = ["org_id", "user_id"]
+fields.org_id = { type = "i64", db_type = "BIGINT" }
+fields.user_id = { type = "Uuid", db_type = "UUID" }
+"#,
+    );
+    let (up, _) = generate_initial_migration(&ir).unwrap();
+    assert!(up.contains("id BIGINT NOT NULL GENERATED BY DEFAULT AS IDENTITY"));
+    assert!(up.contains("PRIMARY KEY (id)"));
+    assert!(up.contains("PRIMARY KEY (code)"));
+    assert!(up.contains("PRIMARY KEY (org_id, user_id)"));
+}
+
+#[test]
+fn primary_key_change_is_diffed() {
+    let old = load_inline_schema(
+        r#"
+[models.Membership]
+fields.id = { type = "Uuid", db_type = "UUID PRIMARY KEY" }
+fields.org_id = { type = "i64", db_type = "BIGINT" }
+fields.user_id = { type = "Uuid", db_type = "UUID" }
+"#,
+    );
+    let mut new = old.clone();
+    let model = new.models.get_mut("Membership").unwrap();
+    model.fields.get_mut("id").unwrap().db_type = Some("UUID".into());
+    model.primary_key = Some(PrimaryKeyDef {
+        fields: vec!["org_id".into(), "user_id".into()],
+        generated: KeyGeneration::None,
+    });
+
+    let (up, down) = generate_migration(Some(&old), &new).unwrap();
+    assert!(up.contains("ALTER TABLE membership DROP CONSTRAINT membership_pkey;"));
+    assert!(up.contains("ALTER TABLE membership ADD PRIMARY KEY (org_id, user_id);"));
+    assert!(down.contains("ALTER TABLE membership ADD PRIMARY KEY (id);"));
+}
+
+#[test]
+fn relations_carry_referential_actions() {
+    let mut ir: SchemaIR = load_schema("schema.model.toml").unwrap();
+    apply_type_aliases(&mut ir, &load_aliases());
+    let (up, _) = generate_initial_migration(&ir).unwrap();
+    assert!(up.contains(
+        "ALTER TABLE account ADD CONSTRAINT user FOREIGN KEY (user_id) REFERENCES user (id) ON DELETE CASCADE;"
+    ));
+    assert!(up.contains("FOREIGN KEY (category_id) REFERENCES category (id) ON DELETE SET NULL;"));
+
+    let mut new = ir.clone();
+    let rel = new
+        .models
+        .get_mut("Transaction")
+        .unwrap()
+        .relations
+        .get_mut("account")
+        .unwrap();
+    rel.on_delete = ReferentialAction::Restrict;
+    rel.on_update = ReferentialAction::Cascade;
+    rel.deferrable = true;
+    rel.initially_deferred = true;
+
+    let (up, down) = generate_migration(Some(&ir), &new).unwrap();
+    assert!(up.contains("ALTER TABLE transaction DROP CONSTRAINT account;"));
+    assert!(up.contains(
+        "REFERENCES account (id) ON DELETE RESTRICT ON UPDATE CASCADE DEFERRABLE INITIALLY DEFERRED;"
+    ));
+    assert!(down.contains("FOREIGN KEY (account_id) REFERENCES account (id);"));
+}
+
+#[test]
+fn many_to_many_creates_one_join_table() {
+    let mut ir: SchemaIR = load_schema("schema.model.toml").unwrap();
+    apply_type_aliases(&mut ir, &load_aliases());
+    let (up, down) = generate_initial_migration(&ir).unwrap();
+    // Transaction.tags and Tag.transactions share the same table
+    assert_eq!(up.matches("CREATE TABLE tag_transaction (").count(), 1);
+    assert!(up.contains("    transaction_id UUID NOT NULL,\n    tag_id UUID NOT NULL,"));
+    assert!(up.contains("PRIMARY KEY (transaction_id, tag_id)"));
+    assert!(up.contains(
+        "FOREIGN KEY (tag_id) REFERENCES tag (id) ON DELETE CASCADE;"
+    ));
+    // the join table goes before the tables it references
+    assert!(down.find("DROP TABLE tag_transaction;").unwrap() < down.find("DROP TABLE tag;").unwrap());
+
+    // adding and removing a relation creates and drops its table
+    let mut old = ir.clone();
+    old.models.get_mut("Transaction").unwrap().many_to_many.clear();
+    old.models.get_mut("Tag").unwrap().many_to_many.clear();
+    let (up, down) = generate_migration(Some(&old), &ir).unwrap();
+    assert!(up.contains("CREATE TABLE tag_transaction ("));
+    assert!(down.contains("DROP TABLE tag_transaction;"));
+    let (up, _) = generate_migration(Some(&ir), &old).unwrap();
+    assert!(up.contains("DROP TABLE tag_transaction;"));
+
+    // an introspected join table is the same table, not a removed model
+    let mut introspected = old.clone();
+    introspected
+        .models
+        .insert("tag_transaction".into(), Default::default());
+    let (up, _) = generate_migration(Some(&introspected), &ir).unwrap();
+    assert!(!up.contains("tag_transaction"));
+}
+
+const STATUS_SCHEMA: &str = r#"
+[enums.Status]
+variants = ["Open", "Closed", "Archived"]
+
+[models.Ticket]
+fields.id = { type = "Uuid", db_type = "UUID PRIMARY KEY" }
+fields.status = { type = "Status", db_type = "status", default = "'open'" }
+"#;
+
+#[test]
+fn enum_additions_alter_the_type_in_place() {
+    let old = load_inline_schema(STATUS_SCHEMA);
+    let mut new = old.clone();
+    new.enums.get_mut("Status").unwrap().variants =
+        vec!["Draft".into(), "Open".into(), "Pending".into(), "Closed".into(), "Archived".into(), "Spam".into()];
+
+    let (up, down) = generate_migration(Some(&old), &new).unwrap();
+    assert!(!up.contains("DROP TYPE"));
+    assert!(up.contains("ALTER TYPE status ADD VALUE 'draft' BEFORE 'open';"));
+    assert!(up.contains("ALTER TYPE status ADD VALUE 'pending' AFTER 'open';"));
+    assert!(up.contains("ALTER TYPE status ADD VALUE 'spam';"));
+    // values cannot be dropped from a Postgres enum, so down swaps the type back
+    assert!(down.contains("ALTER TYPE status RENAME TO status_old;"));
+    assert!(down.contains("CREATE TYPE status AS ENUM ('open', 'closed', 'archived');"));
+    assert!(down.contains("ALTER TABLE ticket ALTER COLUMN status TYPE status USING status::text::status;"));
+    assert!(down.contains("DROP TYPE status_old;"));
+}
+
+#[test]
+fn enum_variant_rename_from_renames_the_value() {
+    let old = load_inline_schema(STATUS_SCHEMA);
+    let new = load_inline_schema(&STATUS_SCHEMA.replace(
+        r#""Closed""#,
+        r#"{ name = "Resolved", rename_from = "Closed" }"#,
+    ));
+
+    let (up, down) = generate_migration(Some(&old), &new).unwrap();
+    assert!(up.contains("ALTER TYPE status RENAME VALUE 'closed' TO 'resolved';"));
+    assert!(!up.contains("RENAME TO status_old"));
+    assert!(down.contains("ALTER TYPE status RENAME VALUE 'resolved' TO 'closed';"));
+    assert!(!down.contains("RENAME TO status_old"));
+}
+
+#[test]
+fn enum_removal_swaps_the_type() {
+    let old = load_inline_schema(STATUS_SCHEMA);
+    let mut new = old.clone();
+    new.enums.get_mut("Status").unwrap().variants = vec!["Open".into(), "Closed".into()];
+
+    let (up, down) = generate_migration(Some(&old), &new).unwrap();
+    let expected = "ALTER TYPE status RENAME TO status_old;
+CREATE TYPE status AS ENUM ('open', 'closed');
+ALTER TABLE ticket ALTER COLUMN status DROP DEFAULT;
+ALTER TABLE ticket ALTER COLUMN status TYPE status USING status::text::status;
+ALTER TABLE ticket ALTER COLUMN status SET DEFAULT 'open';
+DROP TYPE status_old;
+";
+    assert!(up.contains(expected), "{}", up);
+    assert!(down.contains("CREATE TYPE status AS ENUM ('open', 'closed', 'archived');"));
+}
+
+#[test]
+fn mysql_enums_are_inline_columns() {
+    let mysql = STATUS_SCHEMA.replace("[enums.Status]", "[meta]\nrust_case_style = \"camel\"\ndb_case_style = \"snake\"\ndefault_derives = []\ndb_backend = \"mysql\"\n\n[enums.Status]");
+    let old = load_inline_schema(&mysql);
+    let (up, _) = generate_initial_migration(&old).unwrap();
+    assert!(!up.contains("CREATE TYPE"));
+    assert!(up.contains("status ENUM('open', 'closed', 'archived') NOT NULL DEFAULT 'open'"));
+
+    let new = load_inline_schema(&mysql.replace(
+        r#""Closed""#,
+        r#"{ name = "Resolved", rename_from = "Closed" }"#,
+    ));
+    let (up, down) = generate_migration(Some(&old), &new).unwrap();
+    let expected = "ALTER TABLE ticket MODIFY COLUMN status ENUM('open', 'closed', 'archived', 'resolved') NOT NULL DEFAULT 'open';
+UPDATE ticket SET status = 'resolved' WHERE status = 'closed';
+ALTER TABLE ticket MODIFY COLUMN status ENUM('open', 'resolved', 'archived') NOT NULL DEFAULT 'open';
+";
+    assert!(up.contains(expected), "{}", up);
+    assert!(down.contains("UPDATE ticket SET status = 'closed' WHERE status = 'resolved';"));
+}
+
+#[test]
+fn db_schema_qualifies_tables_and_types() {
+    let mut ir: SchemaIR = load_schema("schema.model.toml").unwrap();
+    apply_type_aliases(&mut ir, &load_aliases());
+    ir.meta.db_schema = Some("billing".into());
+    let (up, down) = generate_initial_migration(&ir).unwrap();
+    assert!(up.starts_with("BEGIN;\nCREATE SCHEMA IF NOT EXISTS billing;\n"), "{}", up);
+    assert!(up.contains("CREATE TYPE billing.role AS ENUM"));
+    assert!(up.contains("CREATE TABLE billing.account ("));
+    assert!(up.contains(
+        "ALTER TABLE billing.account ADD CONSTRAINT user FOREIGN KEY (user_id) REFERENCES billing.user (id) ON DELETE CASCADE;"
+    ));
+    // join tables live next to the tables they link, their objects keep bare names
+    assert!(up.contains("CREATE TABLE billing.tag_transaction ("));
+    assert!(up.contains("ADD CONSTRAINT tag_transaction_tag_id_fkey FOREIGN KEY (tag_id) REFERENCES billing.tag (id)"));
+    assert!(down.contains("DROP TABLE billing.tag_transaction;"));
+    assert!(!down.contains("DROP SCHEMA"));
+
+    let mut old = load_inline_schema(STATUS_SCHEMA);
+    old.meta.db_schema = Some("billing".into());
+    let mut new = old.clone();
+    new.enums.get_mut("Status").unwrap().variants = vec!["Open".into(), "Closed".into()];
+    let (up, _) = generate_migration(Some(&old), &new).unwrap();
+    assert!(up.contains("ALTER TYPE billing.status RENAME TO status_old;"), "{}", up);
+    assert!(up.contains("ALTER TABLE billing.ticket ALTER COLUMN status TYPE billing.status"));
+    assert!(up.contains("DROP TYPE billing.status_old;"));
+    assert!(!up.contains("CREATE SCHEMA"));
+
+    // renamed tables stay in their schema
+    let mut renamed = old.clone();
+    let ticket = renamed.models.shift_remove("Ticket").unwrap();
+    renamed.models.insert("Issue".into(), ir::ModelDef { rename_from: Some("Ticket".into()), ..ticket });
+    let (up, down) = generate_migration(Some(&old), &renamed).unwrap();
+    assert!(up.contains("ALTER TABLE billing.ticket RENAME TO issue;"), "{}", up);
+    assert!(down.contains("ALTER TABLE billing.issue RENAME TO ticket;"));
+
+    // moving to a schema creates it
+    let (up, _) = generate_migration(Some(&load_inline_schema(STATUS_SCHEMA)), &old).unwrap();
+    assert!(up.contains("CREATE SCHEMA IF NOT EXISTS billing;"));
+    assert!(up.contains("CREATE TABLE billing.ticket ("));
 }
@@ -5,13 +5,13 @@ use rustdbgen::{
         RelationDef, SchemaIR, UniqueConstraintDef,
     },
 };
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
 #[test]
 fn diff_migration_adds_column() {
     // old schema with only id
-    let mut old_models = HashMap::new();
-    let mut user_fields = HashMap::new();
+    let mut old_models = IndexMap::new();
+    let mut user_fields = IndexMap::new();
     user_fields.insert(
         "id".to_string(),
         FieldDef {
@@ -23,36 +23,45 @@ fn diff_migration_adds_column() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
     old_models.insert(
         "User".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: user_fields.clone(),
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     // new schema with id and name
@@ -68,37 +77,46 @@ fn diff_migration_adds_column() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut new_models = HashMap::new();
+    let mut new_models = IndexMap::new();
     new_models.insert(
         "User".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: new_fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new_ir = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new_ir = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new_ir).unwrap();
@@ -109,7 +127,7 @@ fn diff_migration_adds_column() {
 #[test]
 fn diff_migration_changes_column_type() {
     // old schema with name TEXT
-    let mut old_fields = HashMap::new();
+    let mut old_fields = IndexMap::new();
     old_fields.insert(
         "name".to_string(),
         FieldDef {
@@ -121,41 +139,50 @@ fn diff_migration_changes_column_type() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut old_models = HashMap::new();
+    let mut old_models = IndexMap::new();
     old_models.insert(
         "User".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: old_fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     // new schema with name VARCHAR(255)
-    let mut new_fields = HashMap::new();
+    let mut new_fields = IndexMap::new();
     new_fields.insert(
         "name".to_string(),
         FieldDef {
@@ -167,37 +194,46 @@ fn diff_migration_changes_column_type() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut new_models = HashMap::new();
+    let mut new_models = IndexMap::new();
     new_models.insert(
         "User".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: new_fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new).unwrap();
@@ -208,7 +244,7 @@ fn diff_migration_changes_column_type() {
 #[test]
 fn diff_migration_changes_column_default() {
     // old schema with default 1
-    let mut old_fields = HashMap::new();
+    let mut old_fields = IndexMap::new();
     old_fields.insert(
         "count".to_string(),
         FieldDef {
@@ -220,41 +256,50 @@ fn diff_migration_changes_column_default() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut old_models = HashMap::new();
+    let mut old_models = IndexMap::new();
     old_models.insert(
         "Item".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: old_fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     // new schema with default 2
-    let mut new_fields = HashMap::new();
+    let mut new_fields = IndexMap::new();
     new_fields.insert(
         "count".to_string(),
         FieldDef {
@@ -266,37 +311,46 @@ fn diff_migration_changes_column_default() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut new_models = HashMap::new();
+    let mut new_models = IndexMap::new();
     new_models.insert(
         "Item".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: new_fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new).unwrap();
@@ -307,7 +361,7 @@ fn diff_migration_changes_column_default() {
 #[test]
 fn diff_migration_changes_column_nullability() {
     // old schema nullable = false
-    let mut old_fields = HashMap::new();
+    let mut old_fields = IndexMap::new();
     old_fields.insert(
         "title".to_string(),
         FieldDef {
@@ -319,41 +373,50 @@ fn diff_migration_changes_column_nullability() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut old_models = HashMap::new();
+    let mut old_models = IndexMap::new();
     old_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: old_fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     // new schema nullable = true
-    let mut new_fields = HashMap::new();
+    let mut new_fields = IndexMap::new();
     new_fields.insert(
         "title".to_string(),
         FieldDef {
@@ -365,37 +428,46 @@ fn diff_migration_changes_column_nullability() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut new_models = HashMap::new();
+    let mut new_models = IndexMap::new();
     new_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: new_fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new).unwrap();
@@ -405,7 +477,7 @@ fn diff_migration_changes_column_nullability() {
 
 #[test]
 fn diff_migration_adds_index() {
-    let mut old_fields = HashMap::new();
+    let mut old_fields = IndexMap::new();
     old_fields.insert(
         "author_id".to_string(),
         FieldDef {
@@ -417,42 +489,51 @@ fn diff_migration_adds_index() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut old_models = HashMap::new();
+    let mut old_models = IndexMap::new();
     old_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: old_fields.clone(),
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     // new schema with index
-    let mut new_models = HashMap::new();
-    let mut indexes = HashMap::new();
+    let mut new_models = IndexMap::new();
+    let mut indexes = IndexMap::new();
     indexes.insert(
         "post_author_idx".to_string(),
         IndexDef {
@@ -465,29 +546,36 @@ fn diff_migration_adds_index() {
         ModelDef { includes: Vec::new(), 
             fields: old_fields,
             indexes,
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new).unwrap();
@@ -497,7 +585,7 @@ fn diff_migration_adds_index() {
 
 #[test]
 fn diff_migration_changes_index_uniqueness() {
-    let mut fields = HashMap::new();
+    let mut fields = IndexMap::new();
     fields.insert(
         "email".to_string(),
         FieldDef {
@@ -509,11 +597,13 @@ fn diff_migration_changes_index_uniqueness() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
 
     // old model with non-unique index
-    let mut old_indexes = HashMap::new();
+    let mut old_indexes = IndexMap::new();
     old_indexes.insert(
         "user_email_idx".to_string(),
         IndexDef {
@@ -521,39 +611,46 @@ fn diff_migration_changes_index_uniqueness() {
             unique: false,
         },
     );
-    let mut old_models = HashMap::new();
+    let mut old_models = IndexMap::new();
     old_models.insert(
         "User".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: fields.clone(),
             indexes: old_indexes,
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     // new model with unique index
-    let mut new_indexes = HashMap::new();
+    let mut new_indexes = IndexMap::new();
     new_indexes.insert(
         "user_email_idx".to_string(),
         IndexDef {
@@ -561,35 +658,42 @@ fn diff_migration_changes_index_uniqueness() {
             unique: true,
         },
     );
-    let mut new_models = HashMap::new();
+    let mut new_models = IndexMap::new();
     new_models.insert(
         "User".to_string(),
         ModelDef { includes: Vec::new(), 
             fields,
             indexes: new_indexes,
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new).unwrap();
@@ -601,7 +705,7 @@ fn diff_migration_changes_index_uniqueness() {
 
 #[test]
 fn diff_migration_adds_unique_constraint() {
-    let mut fields = HashMap::new();
+    let mut fields = IndexMap::new();
     fields.insert(
         "title".to_string(),
         FieldDef {
@@ -613,6 +717,8 @@ fn diff_migration_adds_unique_constraint() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
     fields.insert(
@@ -626,42 +732,51 @@ fn diff_migration_adds_unique_constraint() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
 
-    let mut old_models = HashMap::new();
+    let mut old_models = IndexMap::new();
     old_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: fields.clone(),
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
-    let mut new_models = HashMap::new();
-    let mut ucs = HashMap::new();
+    let mut new_models = IndexMap::new();
+    let mut ucs = IndexMap::new();
     ucs.insert(
         "post_title_author_unique".to_string(),
         UniqueConstraintDef {
@@ -671,31 +786,38 @@ fn diff_migration_adds_unique_constraint() {
     new_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
-            fields: fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
+            fields,
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
             unique_constraints: ucs,
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new).unwrap();
@@ -705,7 +827,7 @@ fn diff_migration_adds_unique_constraint() {
 
 #[test]
 fn diff_migration_changes_unique_constraint_fields() {
-    let mut fields = HashMap::new();
+    let mut fields = IndexMap::new();
     fields.insert(
         "title".to_string(),
         FieldDef {
@@ -717,6 +839,8 @@ fn diff_migration_changes_unique_constraint_fields() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
     fields.insert(
@@ -730,83 +854,99 @@ fn diff_migration_changes_unique_constraint_fields() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
 
-    let mut old_uc = HashMap::new();
+    let mut old_uc = IndexMap::new();
     old_uc.insert(
         "post_uc".to_string(),
         UniqueConstraintDef {
             fields: vec!["title".into(), "author_id".into()],
         },
     );
-    let mut old_models = HashMap::new();
+    let mut old_models = IndexMap::new();
     old_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: fields.clone(),
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
             unique_constraints: old_uc,
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
-    let mut new_uc = HashMap::new();
+    let mut new_uc = IndexMap::new();
     new_uc.insert(
         "post_uc".to_string(),
         UniqueConstraintDef {
             fields: vec!["title".into()],
         },
     );
-    let mut new_models = HashMap::new();
+    let mut new_models = IndexMap::new();
     new_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
-            fields: fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
+            fields,
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
             unique_constraints: new_uc,
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new).unwrap();
@@ -817,7 +957,7 @@ fn diff_migration_changes_unique_constraint_fields() {
 
 #[test]
 fn diff_migration_adds_foreign_key() {
-    let mut old_fields = HashMap::new();
+    let mut old_fields = IndexMap::new();
     old_fields.insert(
         "author_id".to_string(),
         FieldDef {
@@ -829,77 +969,97 @@ fn diff_migration_adds_foreign_key() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut old_models = HashMap::new();
+    let mut old_models = IndexMap::new();
     old_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: old_fields.clone(),
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     // new schema with relation
-    let mut new_models = HashMap::new();
-    let mut relations = HashMap::new();
+    let mut new_models = IndexMap::new();
+    let mut relations = IndexMap::new();
     relations.insert(
         "post_author_fk".to_string(),
         RelationDef {
             on: "author_id".into(),
             references: FieldRef { model: "User".into(), field: "id".into() },
+            on_delete: Default::default(),
+            on_update: Default::default(),
+            deferrable: false,
+            initially_deferred: false,
         },
     );
     new_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: old_fields,
-            indexes: HashMap::new(),
+            indexes: IndexMap::new(),
             relations,
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new).unwrap();
@@ -909,7 +1069,7 @@ fn diff_migration_adds_foreign_key() {
 
 #[test]
 fn diff_migration_changes_foreign_key() {
-    let mut fields = HashMap::new();
+    let mut fields = IndexMap::new();
     fields.insert(
         "author_id".to_string(),
         FieldDef {
@@ -921,25 +1081,31 @@ fn diff_migration_changes_foreign_key() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
 
     // old schema with relation to User
-    let mut relations_old = HashMap::new();
+    let mut relations_old = IndexMap::new();
     relations_old.insert(
         "post_author_fk".to_string(),
         RelationDef {
             on: "author_id".into(),
             references: FieldRef { model: "User".into(), field: "id".into() },
+            on_delete: Default::default(),
+            on_update: Default::default(),
+            deferrable: false,
+            initially_deferred: false,
         },
     );
 
-    let mut old_models = HashMap::new();
+    let mut old_models = IndexMap::new();
     old_models.insert(
         "User".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: {
-                let mut f = HashMap::new();
+                let mut f = IndexMap::new();
                 f.insert(
                     "id".to_string(),
                     FieldDef {
@@ -951,24 +1117,32 @@ fn diff_migration_changes_foreign_key() {
                         zod: None,
                         rename_from: None,
                         storage: None,
+                        column: None,
+                        dropped: false,
                     },
                 );
                 f
             },
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
     old_models.insert(
         "Account".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: {
-                let mut f = HashMap::new();
+                let mut f = IndexMap::new();
                 f.insert(
                     "id".to_string(),
                     FieldDef {
@@ -980,65 +1154,84 @@ fn diff_migration_changes_foreign_key() {
                         zod: None,
                         rename_from: None,
                         storage: None,
+                        column: None,
+                        dropped: false,
                     },
                 );
                 f
             },
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
     old_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: fields.clone(),
-            indexes: HashMap::new(),
+            indexes: IndexMap::new(),
             relations: relations_old,
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     // new schema with relation to Account
-    let mut relations_new = HashMap::new();
+    let mut relations_new = IndexMap::new();
     relations_new.insert(
         "post_author_fk".to_string(),
         RelationDef {
             on: "author_id".into(),
             references: FieldRef { model: "Account".into(), field: "id".into() },
+            on_delete: Default::default(),
+            on_update: Default::default(),
+            deferrable: false,
+            initially_deferred: false,
         },
     );
 
-    let mut new_models = HashMap::new();
+    let mut new_models = IndexMap::new();
     new_models.insert(
         "User".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: {
-                let mut f = HashMap::new();
+                let mut f = IndexMap::new();
                 f.insert(
                     "id".to_string(),
                     FieldDef {
@@ -1050,24 +1243,32 @@ fn diff_migration_changes_foreign_key() {
                         zod: None,
                         rename_from: None,
                         storage: None,
+                        column: None,
+                        dropped: false,
                     },
                 );
                 f
             },
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
     new_models.insert(
         "Account".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: {
-                let mut f = HashMap::new();
+                let mut f = IndexMap::new();
                 f.insert(
                     "id".to_string(),
                     FieldDef {
@@ -1079,58 +1280,73 @@ fn diff_migration_changes_foreign_key() {
                         zod: None,
                         rename_from: None,
                         storage: None,
+                        column: None,
+                        dropped: false,
                     },
                 );
                 f
             },
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
     new_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
-            fields: fields,
-            indexes: HashMap::new(),
+            fields,
+            indexes: IndexMap::new(),
             relations: relations_new,
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new).unwrap();
     assert!(up.contains("DROP CONSTRAINT post_author_fk"));
-    assert!(up.contains("REFERENCES account.id"));
-    assert!(down.contains("REFERENCES user.id"));
+    assert!(up.contains("REFERENCES account (id)"));
+    assert!(down.contains("REFERENCES user (id)"));
 }
 
 #[test]
 fn diff_migration_renames_column() {
-    let mut old_fields = HashMap::new();
+    let mut old_fields = IndexMap::new();
     old_fields.insert(
         "username".to_string(),
         FieldDef {
@@ -1142,24 +1358,32 @@ fn diff_migration_renames_column() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut old_models = HashMap::new();
+    let mut old_models = IndexMap::new();
     old_models.insert(
         "User".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: old_fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
@@ -1168,14 +1392,15 @@ fn diff_migration_renames_column() {
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
             observability_provider: None,
+            db_schema: None,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
-    let mut new_fields = HashMap::new();
+    let mut new_fields = IndexMap::new();
     new_fields.insert(
         "name".to_string(),
         FieldDef {
@@ -1187,24 +1412,32 @@ fn diff_migration_renames_column() {
             tags: Vec::new(),
             zod: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
-    let mut new_models = HashMap::new();
+    let mut new_models = IndexMap::new();
     new_models.insert(
         "User".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: new_fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
@@ -1213,11 +1446,12 @@ fn diff_migration_renames_column() {
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
             observability_provider: None,
+            db_schema: None,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new).unwrap();
@@ -1227,7 +1461,7 @@ fn diff_migration_renames_column() {
 
 #[test]
 fn diff_migration_adds_check_constraint() {
-    let mut fields = HashMap::new();
+    let mut fields = IndexMap::new();
     fields.insert(
         "title".to_string(),
         FieldDef {
@@ -1239,42 +1473,51 @@ fn diff_migration_adds_check_constraint() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
 
-    let mut old_models = HashMap::new();
+    let mut old_models = IndexMap::new();
     old_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: fields.clone(),
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
-    let mut new_models = HashMap::new();
-    let mut checks = HashMap::new();
+    let mut new_models = IndexMap::new();
+    let mut checks = IndexMap::new();
     checks.insert(
         "post_title_len".to_string(),
         CheckConstraintDef {
@@ -1284,31 +1527,38 @@ fn diff_migration_adds_check_constraint() {
     new_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
-            fields: fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
+            fields,
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
             check_constraints: checks,
-            exclusion_constraints: HashMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new).unwrap();
@@ -1318,7 +1568,7 @@ fn diff_migration_adds_check_constraint() {
 
 #[test]
 fn diff_migration_changes_check_constraint() {
-    let mut fields = HashMap::new();
+    let mut fields = IndexMap::new();
     fields.insert(
         "title".to_string(),
         FieldDef {
@@ -1330,83 +1580,99 @@ fn diff_migration_changes_check_constraint() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
 
-    let mut old_checks = HashMap::new();
+    let mut old_checks = IndexMap::new();
     old_checks.insert(
         "post_title_len".to_string(),
         CheckConstraintDef {
             expression: "char_length(title) > 0".into(),
         },
     );
-    let mut old_models = HashMap::new();
+    let mut old_models = IndexMap::new();
     old_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: fields.clone(),
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
             check_constraints: old_checks,
-            exclusion_constraints: HashMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
-    let mut new_checks = HashMap::new();
+    let mut new_checks = IndexMap::new();
     new_checks.insert(
         "post_title_len".to_string(),
         CheckConstraintDef {
             expression: "char_length(title) > 3".into(),
         },
     );
-    let mut new_models = HashMap::new();
+    let mut new_models = IndexMap::new();
     new_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
-            fields: fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
+            fields,
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
             check_constraints: new_checks,
-            exclusion_constraints: HashMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new).unwrap();
@@ -1417,7 +1683,7 @@ fn diff_migration_changes_check_constraint() {
 
 #[test]
 fn diff_migration_adds_exclusion_constraint() {
-    let mut fields = HashMap::new();
+    let mut fields = IndexMap::new();
     fields.insert(
         "author_id".to_string(),
         FieldDef {
@@ -1429,42 +1695,51 @@ fn diff_migration_adds_exclusion_constraint() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
 
-    let mut old_models = HashMap::new();
+    let mut old_models = IndexMap::new();
     old_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: fields.clone(),
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
-            exclusion_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
+            exclusion_constraints: IndexMap::new(),
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
-    let mut new_models = HashMap::new();
-    let mut excls = HashMap::new();
+    let mut new_models = IndexMap::new();
+    let mut excls = IndexMap::new();
     excls.insert(
         "post_excl".to_string(),
         ExclusionConstraintDef {
@@ -1474,31 +1749,38 @@ fn diff_migration_adds_exclusion_constraint() {
     new_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
-            fields: fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
+            fields,
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
             exclusion_constraints: excls,
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new).unwrap();
@@ -1508,7 +1790,7 @@ fn diff_migration_adds_exclusion_constraint() {
 
 #[test]
 fn diff_migration_changes_exclusion_constraint() {
-    let mut fields = HashMap::new();
+    let mut fields = IndexMap::new();
     fields.insert(
         "author_id".to_string(),
         FieldDef {
@@ -1520,83 +1802,99 @@ fn diff_migration_changes_exclusion_constraint() {
             zod: None,
             rename_from: None,
             storage: None,
+            column: None,
+            dropped: false,
         },
     );
 
-    let mut old_excls = HashMap::new();
+    let mut old_excls = IndexMap::new();
     old_excls.insert(
         "post_excl".to_string(),
         ExclusionConstraintDef {
             definition: "USING gist (author_id WITH =)".into(),
         },
     );
-    let mut old_models = HashMap::new();
+    let mut old_models = IndexMap::new();
     old_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
             fields: fields.clone(),
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
             exclusion_constraints: old_excls,
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let old = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let old = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: old_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
-    let mut new_excls = HashMap::new();
+    let mut new_excls = IndexMap::new();
     new_excls.insert(
         "post_excl".to_string(),
         ExclusionConstraintDef {
             definition: "USING gist (author_id WITH <>)".into(),
         },
     );
-    let mut new_models = HashMap::new();
+    let mut new_models = IndexMap::new();
     new_models.insert(
         "Post".to_string(),
         ModelDef { includes: Vec::new(), 
-            fields: fields,
-            indexes: HashMap::new(),
-            relations: HashMap::new(),
-            unique_constraints: HashMap::new(),
-            check_constraints: HashMap::new(),
+            fields,
+            indexes: IndexMap::new(),
+            relations: IndexMap::new(),
+            unique_constraints: IndexMap::new(),
+            check_constraints: IndexMap::new(),
             exclusion_constraints: new_excls,
             permissions: Default::default(),
             options: Default::default(),
+            owned_by: None,
+            primary_key: None,
+            many_to_many: IndexMap::new(),
+            table: None,
+            dropped: false,
+            rename_from: None,
         },
     );
-    let new = SchemaIR { macros: HashMap::new(), 
-        routes: HashMap::new(),
+    let new = SchemaIR { macros: IndexMap::new(), dropped: Vec::new(), 
+        routes: IndexMap::new(),
         schema_version: "1.0".into(),
         meta: Meta {
             auth: Default::default(),
             observability_provider: None,
+            db_schema: None,
             rust_case_style: "camel".into(),
             db_case_style: "snake".into(),
             default_derives: vec![],
             db_backend: rustdbgen::ir::DatabaseBackend::Postgres,
         },
-        enums: HashMap::new(),
+        enums: IndexMap::new(),
         models: new_models,
-        plugins: HashMap::new(),
-        seeds: HashMap::new(),
+        plugins: IndexMap::new(),
+        seeds: IndexMap::new(),
     };
 
     let (up, down) = generate_migration(Some(&old), &new).unwrap();
@@ -0,0 +1,488 @@
+use rustdbgen::{
+    MigrateOptions, MigrationState, RepairAction, RolledBackMigration, RollbackTarget, apply_migrations,
+    apply_migrations_with_options, baseline_migrations, connect_any_pool, migration_status,
+    introspect_schema, prune_squashed_migrations, redo_migration, repair_migrations, rollback_migrations,
+    squash_migrations,
+};
+use sqlx::AnyPool;
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+
+const MIGRATIONS: &[(&str, &str)] = &[
+    ("20240101000000_init.up.sql", "BEGIN;\nCREATE TABLE budget (id INTEGER PRIMARY KEY);\nCOMMIT;\n"),
+    ("20240101000000_init.down.sql", "BEGIN;\nDROP TABLE budget;\nCOMMIT;\n"),
+    ("20240102000000_notes.up.sql", "ALTER TABLE budget ADD COLUMN notes TEXT;\n"),
+    ("20240102000000_notes.down.sql", "ALTER TABLE budget DROP COLUMN notes;\n"),
+    ("20240103000000_tag.up.sql", "CREATE TABLE tag (id INTEGER PRIMARY KEY);\n"),
+    ("20240103000000_tag.down.sql", "DROP TABLE tag;\n"),
+];
+
+async fn setup() -> (TempDir, AnyPool, String) {
+    let (dir, migrations) = migrations_dir();
+    let pool = connect(&dir).await;
+    (dir, pool, migrations)
+}
+
+fn migrations_dir() -> (TempDir, String) {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    for (name, sql) in MIGRATIONS {
+        fs::write(migrations.join(name), sql).unwrap();
+    }
+    fs::write(dir.path().join("test.db"), "").unwrap();
+    let migrations = migrations.to_str().unwrap().to_string();
+    (dir, migrations)
+}
+
+async fn connect(dir: &TempDir) -> AnyPool {
+    connect_any_pool(&format!("sqlite://{}", dir.path().join("test.db").display()))
+        .await
+        .unwrap()
+}
+
+fn files(dir: &str) -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+        .collect();
+    files.sort();
+    files
+}
+
+async fn tables(pool: &AnyPool) -> Vec<String> {
+    sqlx::query_as::<_, (String,)>(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE '\\_\\_%' ESCAPE '\\' ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|(n,)| n)
+    .collect()
+}
+
+#[tokio::test]
+async fn status_reports_every_state() {
+    let (_dir, pool, migrations) = setup().await;
+    fs::remove_file(format!("{}/20240103000000_tag.up.sql", migrations)).unwrap();
+    apply_migrations(&pool, &migrations).await.unwrap();
+    assert_eq!(tables(&pool).await, vec!["budget"]);
+
+    fs::write(format!("{}/20240104000000_later.up.sql", migrations), "SELECT 1;").unwrap();
+    fs::write(
+        format!("{}/20240102000000_notes.up.sql", migrations),
+        "ALTER TABLE budget ADD COLUMN notes VARCHAR(10);\n",
+    )
+    .unwrap();
+    sqlx::query(
+        "INSERT INTO __rustdbgen_migrations (name, hash, applied_at, execution_time_ms, success) \
+         VALUES ('20231231000000_gone.up.sql', 'x', '2023-12-31T00:00:00Z', 3, 1)",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let status = migration_status(&pool, &migrations).await.unwrap();
+    let states: Vec<(&str, MigrationState)> = status.iter().map(|s| (s.name.as_str(), s.state)).collect();
+    assert_eq!(
+        states,
+        vec![
+            ("20240101000000_init.up.sql", MigrationState::Applied),
+            ("20240102000000_notes.up.sql", MigrationState::Modified),
+            ("20240104000000_later.up.sql", MigrationState::Pending),
+            ("20231231000000_gone.up.sql", MigrationState::Orphaned),
+        ]
+    );
+    assert!(status[0].applied_at.is_some());
+    assert!(status[0].execution_time_ms.is_some());
+    assert!(status[2].applied_at.is_none());
+    assert_eq!(status[3].execution_time_ms, Some(3));
+}
+
+#[tokio::test]
+async fn down_rolls_back_newest_first() {
+    let (_dir, pool, migrations) = setup().await;
+    apply_migrations(&pool, &migrations).await.unwrap();
+    assert_eq!(tables(&pool).await, vec!["budget", "tag"]);
+
+    let options = MigrateOptions::default();
+    let rolled_back = rollback_migrations(&pool, &migrations, RollbackTarget::Steps(1), &options).await.unwrap();
+    assert_eq!(names(&rolled_back), vec!["20240103000000_tag.up.sql"]);
+    assert_eq!(tables(&pool).await, vec!["budget"]);
+
+    let target = RollbackTarget::To("20240101000000_init".into());
+    let rolled_back = rollback_migrations(&pool, &migrations, target, &options).await.unwrap();
+    assert_eq!(names(&rolled_back), vec!["20240102000000_notes.up.sql"]);
+    let status = migration_status(&pool, &migrations).await.unwrap();
+    assert_eq!(status[0].state, MigrationState::Applied);
+    assert_eq!(status[1].state, MigrationState::Pending);
+
+    let target = RollbackTarget::To("20240103000000_tag".into());
+    assert!(rollback_migrations(&pool, &migrations, target, &options).await.is_err());
+
+    // applying again picks the rolled back migrations up
+    apply_migrations(&pool, &migrations).await.unwrap();
+    assert_eq!(tables(&pool).await, vec!["budget", "tag"]);
+}
+
+fn names(rolled_back: &[RolledBackMigration]) -> Vec<&str> {
+    rolled_back.iter().map(|m| m.name.as_str()).collect()
+}
+
+#[tokio::test]
+async fn data_migrations_roll_back_with_their_down_file_or_without_one() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    for (name, sql) in &MIGRATIONS[..2] {
+        fs::write(migrations.join(name), sql).unwrap();
+    }
+    fs::write(migrations.join("20240105000000_seed.data.sql"), "INSERT INTO budget (id) VALUES (1);\n").unwrap();
+    fs::write(dir.path().join("test.db"), "").unwrap();
+    let pool = connect(&dir).await;
+    let migrations = migrations.to_str().unwrap().to_string();
+    let options = MigrateOptions::default();
+    let rows = |pool: AnyPool| async move {
+        sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM budget").fetch_one(&pool).await.unwrap().0
+    };
+
+    // without a down file the data stays and the row leaves the history
+    apply_migrations(&pool, &migrations).await.unwrap();
+    let rolled_back = rollback_migrations(&pool, &migrations, RollbackTarget::Steps(1), &options).await.unwrap();
+    assert_eq!(names(&rolled_back), vec!["20240105000000_seed.data.sql"]);
+    assert!(!rolled_back[0].reverted);
+    assert_eq!(rows(pool.clone()).await, 1);
+    let status = migration_status(&pool, &migrations).await.unwrap();
+    assert_eq!(status[1].state, MigrationState::Pending);
+
+    // rolling back past it reaches the schema migration
+    sqlx::query("DELETE FROM budget").execute(&pool).await.unwrap();
+    apply_migrations(&pool, &migrations).await.unwrap();
+    let rolled_back = rollback_migrations(&pool, &migrations, RollbackTarget::Steps(2), &options).await.unwrap();
+    assert_eq!(
+        names(&rolled_back),
+        vec!["20240105000000_seed.data.sql", "20240101000000_init.up.sql"]
+    );
+    assert!(rolled_back[1].reverted);
+    assert!(tables(&pool).await.is_empty());
+
+    // a .data.down.sql undoes the data changes
+    fs::write(
+        format!("{}/20240105000000_seed.data.down.sql", migrations),
+        "DELETE FROM budget WHERE id = 1;\n",
+    )
+    .unwrap();
+    apply_migrations(&pool, &migrations).await.unwrap();
+    let rolled_back = rollback_migrations(&pool, &migrations, RollbackTarget::Steps(1), &options).await.unwrap();
+    assert!(rolled_back[0].reverted);
+    assert_eq!(rows(pool.clone()).await, 0);
+}
+
+#[tokio::test]
+async fn failed_down_keeps_history() {
+    let (_dir, pool, migrations) = setup().await;
+    apply_migrations(&pool, &migrations).await.unwrap();
+    fs::write(format!("{}/20240103000000_tag.down.sql", migrations), "DROP TABLE missing;").unwrap();
+    let options = MigrateOptions::default();
+    assert!(rollback_migrations(&pool, &migrations, RollbackTarget::Steps(1), &options).await.is_err());
+    let status = migration_status(&pool, &migrations).await.unwrap();
+    assert!(status.iter().all(|s| s.state == MigrationState::Applied));
+}
+
+#[tokio::test]
+async fn redo_reapplies_the_last_migration() {
+    let (_dir, pool, migrations) = setup().await;
+    apply_migrations(&pool, &migrations).await.unwrap();
+    sqlx::query("INSERT INTO tag (id) VALUES (1)").execute(&pool).await.unwrap();
+
+    let redone = redo_migration(&pool, &migrations, &MigrateOptions::default()).await.unwrap();
+    assert_eq!(redone.as_deref(), Some("20240103000000_tag.up.sql"));
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tag").fetch_one(&pool).await.unwrap();
+    assert_eq!(count, 0);
+    let status = migration_status(&pool, &migrations).await.unwrap();
+    assert!(status.iter().all(|s| s.state == MigrationState::Applied));
+}
+
+#[tokio::test]
+async fn concurrent_applies_run_each_migration_once() {
+    let (dir, migrations) = migrations_dir();
+    fs::write(
+        format!("{}/20240104000000_slow.up.sql", migrations),
+        "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 200000) \
+         SELECT COUNT(*) FROM n;\n",
+    )
+    .unwrap();
+    let first = connect(&dir).await;
+    let second = connect(&dir).await;
+    let (a, b) = tokio::join!(
+        apply_migrations(&first, &migrations),
+        apply_migrations(&second, &migrations)
+    );
+    a.unwrap();
+    b.unwrap();
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM __rustdbgen_migrations")
+        .fetch_one(&first)
+        .await
+        .unwrap();
+    assert_eq!(count, 4);
+    let (held,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM __rustdbgen_migrations_lock")
+        .fetch_one(&first)
+        .await
+        .unwrap();
+    assert_eq!(held, 0);
+}
+
+#[tokio::test]
+async fn held_lock_times_out() {
+    let (_dir, pool, migrations) = setup().await;
+    apply_migrations(&pool, &migrations).await.unwrap();
+    sqlx::query("INSERT INTO __rustdbgen_migrations_lock (id, locked_at) VALUES (1, 'now')")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let options = MigrateOptions {
+        lock_timeout: Duration::from_millis(300),
+    };
+    let err = apply_migrations_with_options(&pool, &migrations, &options).await.unwrap_err();
+    assert!(err.to_string().contains("waiting for the migration lock"));
+    assert!(rollback_migrations(&pool, &migrations, RollbackTarget::Steps(1), &options).await.is_err());
+    assert_eq!(tables(&pool).await, vec!["budget", "tag"]);
+}
+
+#[tokio::test]
+async fn no_transaction_files_run_statement_by_statement() {
+    let (_dir, pool, migrations) = setup().await;
+    fs::write(
+        format!("{}/20240104000000_loose.up.sql", migrations),
+        "-- rustdbgen:no-transaction\nCREATE TABLE loose (id INTEGER);\nINSERT INTO missing VALUES (1);\n",
+    )
+    .unwrap();
+    let err = apply_migrations(&pool, &migrations).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("statement 2 (line 3) failed"));
+    assert_eq!(tables(&pool).await, vec!["budget", "loose", "tag"]);
+    let status = migration_status(&pool, &migrations).await.unwrap();
+    assert_eq!(status.last().unwrap().state, MigrationState::Failed);
+
+    fs::write(
+        format!("{}/20240104000000_loose.up.sql", migrations),
+        "-- rustdbgen:no-transaction\nBEGIN;\nINSERT INTO loose VALUES (1);\nCOMMIT;\n",
+    )
+    .unwrap();
+    let err = apply_migrations(&pool, &migrations).await.unwrap_err();
+    assert!(err.to_string().contains("previous attempt of 20240104000000_loose.up.sql failed"));
+    assert!(err.to_string().contains("migrate repair"));
+
+    let options = MigrateOptions::default();
+    let repaired = repair_migrations(&pool, &migrations, Some("20240104000000_loose"), &options)
+        .await
+        .unwrap();
+    assert_eq!(repaired.len(), 1);
+    assert_eq!(repaired[0].action, RepairAction::ClearedFailure);
+    apply_migrations(&pool, &migrations).await.unwrap();
+    let status = migration_status(&pool, &migrations).await.unwrap();
+    assert_eq!(status.last().unwrap().state, MigrationState::Applied);
+}
+
+#[tokio::test]
+async fn failed_attempts_are_recorded_and_retried() {
+    let (_dir, pool, migrations) = setup().await;
+    fs::write(
+        format!("{}/20240103000000_tag.up.sql", migrations),
+        "CREATE TABLE tag (id INTEGER PRIMARY KEY);\n\nINSERT INTO missing VALUES (1);\n",
+    )
+    .unwrap();
+    assert!(apply_migrations(&pool, &migrations).await.is_err());
+    assert_eq!(tables(&pool).await, vec!["budget"]);
+
+    let status = migration_status(&pool, &migrations).await.unwrap();
+    let failed = status.last().unwrap();
+    assert_eq!(failed.state, MigrationState::Failed);
+    assert!(failed.error.as_deref().unwrap().contains("statement 2 (line 3) failed"));
+    assert!(failed.error.as_deref().unwrap().contains("no such table: missing"));
+    assert_eq!(failed.failed_statement.as_deref(), Some("INSERT INTO missing VALUES (1)"));
+    assert!(failed.execution_time_ms.is_some());
+
+    fs::write(
+        format!("{}/20240103000000_tag.up.sql", migrations),
+        "CREATE TABLE tag (id INTEGER PRIMARY KEY);\n",
+    )
+    .unwrap();
+    apply_migrations(&pool, &migrations).await.unwrap();
+    assert_eq!(tables(&pool).await, vec!["budget", "tag"]);
+    let status = migration_status(&pool, &migrations).await.unwrap();
+    assert!(status.iter().all(|s| s.state == MigrationState::Applied && s.error.is_none()));
+}
+
+#[tokio::test]
+async fn repair_rehashes_edited_migrations() {
+    let (_dir, pool, migrations) = setup().await;
+    apply_migrations(&pool, &migrations).await.unwrap();
+    fs::write(
+        format!("{}/20240102000000_notes.up.sql", migrations),
+        "ALTER TABLE budget ADD COLUMN notes TEXT; -- reviewed\n",
+    )
+    .unwrap();
+    assert!(apply_migrations(&pool, &migrations).await.is_err());
+
+    let repaired = repair_migrations(&pool, &migrations, None, &MigrateOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(repaired.len(), 1);
+    assert_eq!(repaired[0].name, "20240102000000_notes.up.sql");
+    assert_eq!(repaired[0].action, RepairAction::Rehashed);
+    apply_migrations(&pool, &migrations).await.unwrap();
+    let status = migration_status(&pool, &migrations).await.unwrap();
+    assert!(status.iter().all(|s| s.state == MigrationState::Applied));
+}
+
+#[tokio::test]
+async fn baseline_records_the_existing_schema_as_applied() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("test.db"), "").unwrap();
+    let pool = connect(&dir).await;
+    sqlx::query("CREATE TABLE budget (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let migrations = dir.path().join("migrations");
+    let migrations = migrations.to_str().unwrap();
+    let options = MigrateOptions::default();
+
+    let name = baseline_migrations(&pool, migrations, &Default::default(), &Default::default(), &options)
+        .await
+        .unwrap();
+    assert!(name.ends_with("_baseline.up.sql"));
+    let up = fs::read_to_string(format!("{}/{}", migrations, name)).unwrap();
+    assert!(up.contains("CREATE TABLE budget ("));
+    assert!(!up.contains("__rustdbgen"));
+    let snapshot = fs::read_to_string(format!("{}/schema.json", migrations)).unwrap();
+    assert!(snapshot.contains("\"budget\""));
+
+    let status = migration_status(&pool, migrations).await.unwrap();
+    assert_eq!(status.len(), 1);
+    assert_eq!(status[0].state, MigrationState::Applied);
+    apply_migrations(&pool, migrations).await.unwrap();
+    assert_eq!(tables(&pool).await, vec!["budget"]);
+
+    let err = baseline_migrations(&pool, migrations, &Default::default(), &Default::default(), &options)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("already has a migration history"));
+}
+
+#[tokio::test]
+async fn squash_replaces_applied_migrations() {
+    let (dir, pool, migrations) = setup().await;
+    let options = MigrateOptions::default();
+    apply_migrations(&pool, &migrations).await.unwrap();
+    rollback_migrations(&pool, &migrations, RollbackTarget::Steps(1), &options)
+        .await
+        .unwrap();
+    let url = format!("sqlite://{}", dir.path().join("test.db").display());
+    let mut snapshot = introspect_schema(&url, &Default::default()).await.unwrap();
+    snapshot.models.retain(|name, _| !name.starts_with("__rustdbgen"));
+    fs::write(
+        format!("{}/20240102000000_notes.schema.json", migrations),
+        serde_json::to_string(&snapshot).unwrap(),
+    )
+    .unwrap();
+    apply_migrations(&pool, &migrations).await.unwrap();
+
+    let squashed = squash_migrations(&migrations, "20240102000000_notes").unwrap();
+    assert_eq!(squashed.name, "20240102000000_notes_squashed.up.sql");
+    assert_eq!(
+        squashed.replaces,
+        vec!["20240101000000_init.up.sql", "20240102000000_notes.up.sql"]
+    );
+    assert_eq!(
+        files(&migrations),
+        vec![
+            "20240101000000_init.down.sql",
+            "20240101000000_init.up.sql",
+            "20240102000000_notes.down.sql",
+            "20240102000000_notes.schema.json",
+            "20240102000000_notes.up.sql",
+            "20240102000000_notes_squashed.down.sql",
+            "20240102000000_notes_squashed.schema.json",
+            "20240102000000_notes_squashed.up.sql",
+            "20240103000000_tag.down.sql",
+            "20240103000000_tag.up.sql",
+        ]
+    );
+    let up = fs::read_to_string(format!("{}/{}", migrations, squashed.name)).unwrap();
+    assert!(up.starts_with("-- rustdbgen:squashes 20240101000000_init.up.sql\n"));
+    assert!(up.contains("notes"));
+
+    // already migrated: the squash is recorded in place of the originals
+    apply_migrations(&pool, &migrations).await.unwrap();
+    let status = migration_status(&pool, &migrations).await.unwrap();
+    let states: Vec<(&str, MigrationState)> = status.iter().map(|s| (s.name.as_str(), s.state)).collect();
+    assert_eq!(
+        states,
+        vec![
+            ("20240101000000_init.up.sql", MigrationState::Squashed),
+            ("20240102000000_notes.up.sql", MigrationState::Squashed),
+            ("20240102000000_notes_squashed.up.sql", MigrationState::Applied),
+            ("20240103000000_tag.up.sql", MigrationState::Applied),
+        ]
+    );
+    assert_eq!(status[2].replaces, squashed.replaces);
+
+    // a fresh database runs the squashed migration
+    let fresh_path = dir.path().join("fresh.db");
+    fs::write(&fresh_path, "").unwrap();
+    let fresh = connect_any_pool(&format!("sqlite://{}", fresh_path.display()))
+        .await
+        .unwrap();
+    apply_migrations(&fresh, &migrations).await.unwrap();
+    assert_eq!(tables(&fresh).await, vec!["budget", "tag"]);
+    let status = migration_status(&fresh, &migrations).await.unwrap();
+    assert_eq!(status[0].state, MigrationState::Squashed);
+    assert_eq!(status[2].state, MigrationState::Applied);
+
+    // a database part way through catches up from the original files
+    let partial_path = dir.path().join("partial.db");
+    fs::write(&partial_path, "").unwrap();
+    let partial = connect_any_pool(&format!("sqlite://{}", partial_path.display()))
+        .await
+        .unwrap();
+    let first = dir.path().join("first");
+    fs::create_dir(&first).unwrap();
+    fs::copy(
+        format!("{}/20240101000000_init.up.sql", migrations),
+        first.join("20240101000000_init.up.sql"),
+    )
+    .unwrap();
+    let first = first.to_str().unwrap();
+    apply_migrations(&partial, first).await.unwrap();
+    apply_migrations(&partial, &migrations).await.unwrap();
+    let status = migration_status(&partial, &migrations).await.unwrap();
+    let states: Vec<MigrationState> = status.iter().map(|s| s.state).collect();
+    assert_eq!(
+        states,
+        vec![
+            MigrationState::Squashed,
+            MigrationState::Squashed,
+            MigrationState::Applied,
+            MigrationState::Applied,
+        ]
+    );
+
+    // once pruned, only the squash is left and a partial database is refused
+    assert_eq!(prune_squashed_migrations(&migrations).unwrap(), squashed.replaces);
+    assert_eq!(files(&migrations).len(), 5);
+    assert!(prune_squashed_migrations(&migrations).unwrap().is_empty());
+    let late_path = dir.path().join("late.db");
+    fs::write(&late_path, "").unwrap();
+    let late = connect_any_pool(&format!("sqlite://{}", late_path.display()))
+        .await
+        .unwrap();
+    apply_migrations(&late, first).await.unwrap();
+    let err = apply_migrations(&late, &migrations).await.unwrap_err();
+    assert!(err.to_string().contains("applied only some"));
+
+    let err = squash_migrations(&migrations, "20240102000000_notes_squashed").unwrap_err();
+    assert!(err.to_string().contains("nothing to squash"));
+}
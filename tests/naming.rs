@@ -0,0 +1,174 @@
+use rustdbgen::{
+    diff_schemas, generate_code, generate_graphql_schema, generate_initial_migration, generate_migration,
+    generate_typescript, ir::SchemaIR, lint_schema, load_schema, naming, SchemaOperation,
+};
+use std::fs;
+
+mod common;
+use common::load_inline_schema;
+
+const SCHEMA: &str = r#"
+[meta]
+rust_case_style = "camel"
+db_case_style = "snake"
+default_derives = ["Debug", "Clone", "Serialize", "Deserialize"]
+
+[enums.EntryKind]
+variants = ["Income", "Expense"]
+
+[models.RecurringTransaction]
+fields.id = { type = "Uuid", db_type = "UUID PRIMARY KEY" }
+fields.next_run_at = { type = "DateTime<Utc>", db_type = "TIMESTAMPTZ", nullable = false }
+fields.kind = { type = "EntryKind", db_type = "entry_kind", nullable = false }
+fields.amount = { type = "Decimal", db_type = "DECIMAL(15,2)", nullable = false, column = "amount_cents" }
+indexes.recurring_next_run_idx = { fields = ["next_run_at"] }
+indexes.recurring_amount_idx = { fields = ["amount"] }
+"#;
+
+#[test]
+fn case_conversion() {
+    assert_eq!(naming::words("HTTPRequestLog"), ["http", "request", "log"]);
+    assert_eq!(naming::convert("RecurringTransaction", "snake"), "recurring_transaction");
+    assert_eq!(naming::convert("next_run_at", "camel"), "nextRunAt");
+    assert_eq!(naming::convert("next_run_at", "pascal"), "NextRunAt");
+    assert_eq!(naming::convert("nextRunAt", "kebab"), "next-run-at");
+    assert_eq!(naming::convert("nextRunAt", "screaming_snake"), "NEXT_RUN_AT");
+    assert_eq!(naming::convert("RecurringTransaction", "lower"), "recurringtransaction");
+    assert_eq!(naming::convert("RecurringTransaction", "preserve"), "RecurringTransaction");
+}
+
+#[test]
+fn migrations_use_db_case_style_and_overrides() {
+    let ir = load_inline_schema(SCHEMA);
+    let (up, down) = generate_initial_migration(&ir).unwrap();
+    assert!(up.contains("CREATE TYPE entry_kind AS ENUM"));
+    assert!(up.contains("CREATE TABLE recurring_transaction ("));
+    assert!(up.contains("amount_cents DECIMAL(15,2)"));
+    assert!(up.contains("ON recurring_transaction (next_run_at)"));
+    assert!(up.contains("ON recurring_transaction (amount_cents)"));
+    assert!(down.contains("DROP TABLE recurring_transaction;"));
+
+    let ir = load_inline_schema(&SCHEMA.replace(
+        "[models.RecurringTransaction]",
+        "[models.RecurringTransaction]\ntable = \"schedules\"",
+    ));
+    let (up, _) = generate_initial_migration(&ir).unwrap();
+    assert!(up.contains("CREATE TABLE schedules ("));
+}
+
+#[test]
+fn changed_overrides_rename_tables_and_columns() {
+    let old = load_inline_schema(SCHEMA);
+    let new = load_inline_schema(
+        &SCHEMA
+            .replace("[models.RecurringTransaction]", "[models.RecurringTransaction]\ntable = \"schedules\"")
+            .replace("column = \"amount_cents\"", "column = \"amount_minor\""),
+    );
+    let (up, down) = generate_migration(Some(&old), &new).unwrap();
+    assert!(up.contains("ALTER TABLE recurring_transaction RENAME TO schedules;"));
+    assert!(up.contains("ALTER TABLE schedules RENAME COLUMN amount_cents TO amount_minor;"));
+    assert!(!up.contains("DROP TABLE"));
+    assert!(!up.contains("ADD COLUMN"));
+    // down renames the column back before the table
+    let column = down.find("RENAME COLUMN amount_minor TO amount_cents").unwrap();
+    let table = down.find("ALTER TABLE schedules RENAME TO recurring_transaction;").unwrap();
+    assert!(column < table);
+}
+
+/// `ir` as the checked-in initial migration created it, before case styles
+/// applied: tables and enum types named by lowercasing, as an introspected
+/// database reports them.
+fn lowercase_names(ir: &SchemaIR) -> SchemaIR {
+    let mut old = ir.clone();
+    old.meta.db_case_style = "preserve".into();
+    old.enums = ir.enums.iter().map(|(name, en)| (name.to_lowercase(), en.clone())).collect();
+    old.models = ir
+        .models
+        .iter()
+        .map(|(name, model)| {
+            let mut model = model.clone();
+            for field in model.fields.values_mut() {
+                for enum_name in ir.enums.keys() {
+                    field.rust_type = field.rust_type.replace(enum_name.as_str(), &enum_name.to_lowercase());
+                }
+            }
+            for rel in model.relations.values_mut() {
+                rel.references.model = rel.references.model.to_lowercase();
+            }
+            for m2m in model.many_to_many.values_mut() {
+                m2m.model = m2m.model.to_lowercase();
+            }
+            (name.to_lowercase(), model)
+        })
+        .collect();
+    old
+}
+
+#[test]
+fn lowercase_names_from_the_initial_migration_are_renamed() {
+    let initial = fs::read_to_string("migrations/20250705082223_finance_app_initial.up.sql").unwrap();
+    assert!(initial.contains("CREATE TABLE recurringtransaction ("));
+    assert!(initial.contains("CREATE TYPE accounttype AS ENUM"));
+
+    let new = load_schema("schema.model.toml").unwrap();
+    let old = lowercase_names(&new);
+    for line in initial.lines() {
+        if let Some(table) = line.strip_prefix("CREATE TABLE ").and_then(|l| l.strip_suffix(" (")) {
+            assert!(old.models.contains_key(table), "no model for table {}", table);
+        }
+        if let Some(name) = line.strip_prefix("CREATE TYPE ").and_then(|l| l.split(' ').next()) {
+            assert!(old.enums.contains_key(name), "no enum for type {}", name);
+        }
+    }
+
+    let diff = diff_schemas(Some(&old), &new).unwrap();
+    assert!(!diff.operations.iter().any(|op| matches!(
+        op,
+        SchemaOperation::CreateTable { .. }
+            | SchemaOperation::DropTable { .. }
+            | SchemaOperation::CreateEnum { .. }
+            | SchemaOperation::DropEnum { .. }
+    )));
+    let (up, down) = diff.to_sql().unwrap();
+    assert!(up.contains("ALTER TABLE recurringtransaction RENAME TO recurring_transaction;"));
+    assert!(up.contains("ALTER TYPE accounttype RENAME TO account_type;"));
+    assert!(up.contains("ALTER TYPE recurringfrequency RENAME TO recurring_frequency;"));
+    assert!(!up.contains("ALTER TYPE role RENAME"));
+    assert!(down.contains("ALTER TABLE recurring_transaction RENAME TO recurringtransaction;"));
+    assert!(down.contains("ALTER TYPE account_type RENAME TO accounttype;"));
+}
+
+#[test]
+fn generated_code_maps_names() {
+    let ir = load_inline_schema(SCHEMA);
+    let code = generate_code(&ir);
+    assert!(code.contains("#[serde(rename_all = \"camelCase\")]\npub struct RecurringTransaction {"));
+    assert!(code.contains("#[serde(rename_all = \"camelCase\")]\npub struct RecurringTransactionNew {"));
+    assert!(code.contains("    #[sqlx(rename = \"amount_cents\")]\n    pub amount: Decimal,"));
+    assert!(code.contains("#[sqlx(type_name = \"entry_kind\""));
+    assert!(code.contains("INSERT INTO recurring_transaction (id, next_run_at, kind, amount_cents)"));
+    assert!(code.contains("SELECT * FROM recurring_transaction WHERE id = $1"));
+
+    let ts = generate_typescript(&ir);
+    assert!(ts.contains("    nextRunAt: DateTime<Utc>;"));
+    assert!(ts.contains("    nextRunAt: z.any(),"));
+    let gql = generate_graphql_schema(&ir);
+    assert!(gql.contains("    nextRunAt: DateTime<Utc>!"));
+
+    // snake JSON needs no rename at all
+    let ir = load_inline_schema(&SCHEMA.replace("rust_case_style = \"camel\"", "rust_case_style = \"snake\""));
+    assert!(!generate_code(&ir).contains("rename_all = \"camelCase\""));
+    assert!(generate_typescript(&ir).contains("    next_run_at: DateTime<Utc>;"));
+}
+
+#[test]
+fn naming_problems_are_flagged() {
+    let ir = load_inline_schema(
+        &SCHEMA
+            .replace("db_case_style = \"snake\"", "db_case_style = \"sausage\"")
+            .replace("column = \"amount_cents\"", "column = \"kind\""),
+    );
+    let errors = lint_schema(&ir);
+    assert!(errors.iter().any(|e| e.contains("meta.db_case_style sausage")));
+    assert!(errors.iter().any(|e| e.contains("both map to column kind")));
+}
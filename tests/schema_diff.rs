@@ -0,0 +1,290 @@
+use rustdbgen::{SchemaOperation, diff_schemas, generate_migration, ir::SchemaIR};
+
+mod common;
+use common::load_inline_schema;
+
+const SCHEMA: &str = r#"
+[meta]
+rust_case_style = "snake"
+db_case_style = "snake"
+default_derives = []
+
+[enums.Period]
+variants = ["Monthly", "Yearly"]
+
+[models.Budget]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "BIGINT" }
+fields.name = { type = "String", db_type = "VARCHAR(100)", nullable = false }
+fields.period = { type = "Period", db_type = "period", nullable = false }
+
+[models.Category]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "BIGINT" }
+"#;
+
+fn changed_schema() -> SchemaIR {
+    load_inline_schema(
+        &SCHEMA
+            .replace("variants = [\"Monthly\", \"Yearly\"]", "variants = [\"Weekly\", \"Monthly\"]")
+            .replace("VARCHAR(100)", "VARCHAR(200)")
+            .replace(
+                "fields.period = { type = \"Period\", db_type = \"period\", nullable = false }",
+                "fields.period = { type = \"Period\", db_type = \"period\", nullable = false }\n\
+                 fields.category_id = { type = \"i64\", db_type = \"BIGINT\", nullable = true }\n\
+                 relations.category = { on = \"category_id\", references = \"Category.id\" }",
+            ),
+    )
+}
+
+#[test]
+fn diff_lists_typed_operations() {
+    let old = load_inline_schema(SCHEMA);
+    let new = changed_schema();
+    let diff = diff_schemas(Some(&old), &new).unwrap();
+    let ops = &diff.operations;
+    assert!(ops.iter().any(|op| matches!(op,
+        SchemaOperation::AddEnumVariant { name, variant } if name == "period" && variant == "weekly")));
+    assert!(ops.iter().any(|op| matches!(op,
+        SchemaOperation::DropEnumVariant { name, variant } if name == "period" && variant == "yearly")));
+    assert!(ops.iter().any(|op| matches!(op,
+        SchemaOperation::AlterColumnType { table, column, from, to }
+            if table == "budget" && column == "name" && from == "VARCHAR(100)" && to == "VARCHAR(200)")));
+    assert!(ops.iter().any(|op| matches!(op,
+        SchemaOperation::AddColumn { table, column, .. } if table == "budget" && column == "category_id")));
+    assert!(ops.iter().any(|op| matches!(op,
+        SchemaOperation::AddRelation { table, relation, .. }
+            if table == "budget" && relation.references.model == "category")));
+
+    let json = serde_json::to_value(&diff).unwrap();
+    assert_eq!(json["backend"], "postgres");
+    assert!(json["operations"].as_array().unwrap().iter().any(|op| op["op"] == "add_relation"));
+
+    assert_eq!(diff.to_sql().unwrap(), generate_migration(Some(&old), &new).unwrap());
+    assert!(diff_schemas(Some(&old), &old).unwrap().is_empty());
+}
+
+#[test]
+fn down_migration_undoes_operations_newest_first() {
+    let old = load_inline_schema(SCHEMA);
+    let new = changed_schema();
+    let (up, down) = diff_schemas(Some(&old), &new).unwrap().to_sql().unwrap();
+    let position = |sql: &str, stmt: &str| sql.find(stmt).unwrap_or_else(|| panic!("{} not in\n{}", stmt, sql));
+    assert!(
+        position(&up, "ADD COLUMN category_id") < position(&up, "ADD CONSTRAINT category FOREIGN KEY")
+    );
+    assert!(position(&down, "DROP CONSTRAINT category") < position(&down, "DROP COLUMN category_id"));
+    // the enum changed first, so its type is swapped back last
+    assert!(position(&down, "DROP COLUMN category_id") < position(&down, "RENAME TO period_old"));
+}
+
+#[test]
+fn initial_diff_creates_everything() {
+    let new = load_inline_schema(SCHEMA);
+    let diff = diff_schemas(None, &new).unwrap();
+    let created: Vec<String> = diff
+        .operations
+        .iter()
+        .map(|op| match op {
+            SchemaOperation::CreateEnum { name, .. } => format!("enum {}", name),
+            SchemaOperation::CreateTable { table, .. } => format!("table {}", table),
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect();
+    assert_eq!(created, vec!["enum period", "table budget", "table category"]);
+    assert_eq!(diff.to_sql().unwrap(), generate_migration(None, &new).unwrap());
+}
+
+const PEOPLE: &str = r#"
+[meta]
+rust_case_style = "snake"
+db_case_style = "snake"
+default_derives = []
+
+[enums.Mood]
+variants = ["Happy", "Sad"]
+
+[models.Owner]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "BIGINT" }
+
+[models.Person]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "BIGINT" }
+fields.mood = { type = "Mood", db_type = "mood", nullable = false }
+fields.owner_id = { type = "i64", db_type = "BIGINT", nullable = true }
+relations.person_owner_fkey = { on = "owner_id", references = "Owner.id" }
+"#;
+
+fn position(sql: &str, stmt: &str) -> usize {
+    sql.find(stmt).unwrap_or_else(|| panic!("{} not in\n{}", stmt, sql))
+}
+
+#[test]
+fn dropped_enum_goes_after_the_table_using_it() {
+    let old = load_inline_schema(PEOPLE);
+    let new = load_inline_schema(
+        r#"
+[meta]
+rust_case_style = "snake"
+db_case_style = "snake"
+default_derives = []
+
+[models.Owner]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "BIGINT" }
+"#,
+    );
+    let (up, down) = generate_migration(Some(&old), &new).unwrap();
+    assert!(position(&up, "DROP TABLE person;") < position(&up, "DROP TYPE mood;"));
+    assert!(position(&down, "CREATE TYPE mood") < position(&down, "CREATE TABLE person"));
+    // the foreign key is restored once both tables exist again
+    assert!(position(&down, "CREATE TABLE person") < position(&down, "ADD CONSTRAINT person_owner_fkey"));
+}
+
+#[test]
+fn foreign_keys_go_before_the_table_they_reference() {
+    let old = load_inline_schema(PEOPLE);
+    let new = load_inline_schema(
+        &PEOPLE
+            .replace("[models.Owner]\nprimary_key = \"id\"\nfields.id = { type = \"i64\", db_type = \"BIGINT\" }\n", "")
+            .replace("relations.person_owner_fkey = { on = \"owner_id\", references = \"Owner.id\" }\n", ""),
+    );
+    let (up, down) = generate_migration(Some(&old), &new).unwrap();
+    assert!(position(&up, "DROP CONSTRAINT person_owner_fkey") < position(&up, "DROP TABLE owner;"));
+    assert!(position(&down, "CREATE TABLE owner") < position(&down, "ADD CONSTRAINT person_owner_fkey"));
+}
+
+#[test]
+fn down_migration_reverses_the_operations_exactly() {
+    let old = load_inline_schema(PEOPLE);
+    let new = load_inline_schema(SCHEMA);
+    let diff = diff_schemas(Some(&old), &new).unwrap();
+    let reversed = diff_schemas(Some(&new), &old).unwrap();
+    let (up, down) = diff.to_sql().unwrap();
+    let (reversed_up, _) = reversed.to_sql().unwrap();
+    // the enum type exists before the table using it, in both directions
+    assert!(position(&up, "CREATE TYPE period") < position(&up, "CREATE TABLE budget"));
+    assert!(position(&down, "CREATE TYPE mood") < position(&down, "CREATE TABLE person"));
+    assert!(position(&reversed_up, "CREATE TYPE mood") < position(&reversed_up, "CREATE TABLE person"));
+    assert!(position(&down, "DROP TABLE budget;") < position(&down, "DROP TYPE period;"));
+}
+
+#[test]
+fn created_table_gets_its_indexes_constraints_and_keys() {
+    let old = load_inline_schema(SCHEMA);
+    let new = load_inline_schema(&format!(
+        "{}\n{}",
+        SCHEMA,
+        r#"
+[models.Owner]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "BIGINT" }
+fields.name = { type = "String", db_type = "TEXT", nullable = false }
+fields.category_id = { type = "i64", db_type = "BIGINT", nullable = true }
+fields.pet_id = { type = "i64", db_type = "BIGINT", nullable = true }
+indexes.owner_name_idx = { fields = ["name"] }
+unique_constraints.owner_category_unique = { fields = ["category_id", "name"] }
+check_constraints.owner_name_check = { expression = "name <> ''" }
+relations.owner_category_fkey = { on = "category_id", references = "Category.id", on_delete = "cascade", deferrable = true }
+relations.owner_pet_fkey = { on = "pet_id", references = "Pet.id" }
+
+[models.Pet]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "BIGINT" }
+"#
+    ));
+    let diff = diff_schemas(Some(&old), &new).unwrap();
+    assert!(diff.operations.iter().any(|op| matches!(op,
+        SchemaOperation::AddIndex { table, name, .. } if table == "owner" && name == "owner_name_idx")));
+    let (up, down) = diff.to_sql().unwrap();
+    assert!(up.contains("CREATE INDEX owner_name_idx ON owner (name);"));
+    assert!(up.contains("ADD CONSTRAINT owner_category_unique UNIQUE (category_id, name)"));
+    assert!(up.contains("ADD CONSTRAINT owner_name_check CHECK (name <> '')"));
+    assert!(up.contains("ON DELETE CASCADE DEFERRABLE"));
+    // keys are added once every table in the diff exists
+    assert!(position(&up, "CREATE TABLE pet") < position(&up, "ADD CONSTRAINT owner_pet_fkey"));
+    assert!(position(&down, "DROP CONSTRAINT owner_pet_fkey") < position(&down, "DROP TABLE pet;"));
+}
+
+const ITEM: &str = r#"
+[meta]
+rust_case_style = "snake"
+db_case_style = "snake"
+default_derives = []
+
+[models.Item]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "BIGINT" }
+fields.code = { type = "String", db_type = "VARCHAR(20)", nullable = false }
+fields.legacy = { type = "String", db_type = "TEXT", nullable = true }
+indexes.item_legacy_idx = { fields = ["legacy"] }
+"#;
+
+fn moved_key(backend: &str) -> (String, String) {
+    let old = load_inline_schema(&ITEM.replace("[meta]", &format!("[meta]\ndb_backend = \"{}\"", backend)));
+    let new = load_inline_schema(
+        &ITEM
+            .replace("[meta]", &format!("[meta]\ndb_backend = \"{}\"", backend))
+            .replace("primary_key = \"id\"", "primary_key = \"code\"")
+            .replace("fields.id = { type = \"i64\", db_type = \"BIGINT\" }\n", "")
+            .replace("fields.legacy = { type = \"String\", db_type = \"TEXT\", nullable = true }\n", "")
+            .replace("indexes.item_legacy_idx = { fields = [\"legacy\"] }\n", "")
+            .replace("VARCHAR(20)", "VARCHAR(40)"),
+    );
+    diff_schemas(Some(&old), &new).unwrap().to_sql().unwrap()
+}
+
+#[test]
+fn primary_key_moves_around_the_columns_it_covers() {
+    let (up, down) = moved_key("postgres");
+    // the key and index are dropped before their columns, and the new key
+    // is added once its column is altered
+    assert!(position(&up, "DROP CONSTRAINT item_pkey;") < position(&up, "DROP COLUMN id;"));
+    assert!(position(&up, "DROP INDEX item_legacy_idx;") < position(&up, "DROP COLUMN legacy;"));
+    assert!(position(&up, "ALTER COLUMN code TYPE VARCHAR(40)") < position(&up, "ADD PRIMARY KEY (code);"));
+    assert!(position(&down, "DROP CONSTRAINT item_pkey;") < position(&down, "ALTER COLUMN code TYPE VARCHAR(20)"));
+    assert!(position(&down, "ADD COLUMN id") < position(&down, "ADD PRIMARY KEY (id);"));
+    assert!(position(&down, "ADD COLUMN legacy") < position(&down, "CREATE INDEX item_legacy_idx"));
+
+    let (up, down) = moved_key("mysql");
+    assert!(position(&up, "DROP PRIMARY KEY;") < position(&up, "DROP COLUMN id;"));
+    assert!(position(&up, "MODIFY COLUMN code") < position(&up, "ADD PRIMARY KEY (code);"));
+    assert!(position(&down, "DROP PRIMARY KEY;") < position(&down, "MODIFY COLUMN code"));
+    assert!(position(&down, "ADD COLUMN id") < position(&down, "ADD PRIMARY KEY (id);"));
+}
+
+#[test]
+fn foreign_keys_follow_the_keys_they_point_at() {
+    let old = load_inline_schema(&format!(
+        "{}\n{}",
+        ITEM,
+        r#"
+[models.Tag]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "BIGINT" }
+fields.item_id = { type = "i64", db_type = "BIGINT", nullable = true }
+relations.tag_item_fkey = { on = "item_id", references = "Item.id" }
+"#
+    ));
+    let new = load_inline_schema(&format!(
+        "{}\n{}",
+        ITEM.replace("primary_key = \"id\"", "primary_key = \"code\"")
+            .replace("fields.id = { type = \"i64\", db_type = \"BIGINT\" }\n", ""),
+        r#"
+[models.Tag]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "BIGINT" }
+fields.item_code = { type = "String", db_type = "VARCHAR(20)", nullable = true }
+relations.tag_item_code_fkey = { on = "item_code", references = "Item.code" }
+"#
+    ));
+    let (up, down) = diff_schemas(Some(&old), &new).unwrap().to_sql().unwrap();
+    // the foreign key on another table goes before the key it points at
+    assert!(position(&up, "DROP CONSTRAINT tag_item_fkey") < position(&up, "DROP CONSTRAINT item_pkey;"));
+    assert!(position(&up, "DROP CONSTRAINT item_pkey;") < position(&up, "DROP COLUMN id;"));
+    assert!(position(&up, "ADD COLUMN item_code") < position(&up, "ADD PRIMARY KEY (code);"));
+    assert!(position(&up, "ADD PRIMARY KEY (code);") < position(&up, "ADD CONSTRAINT tag_item_code_fkey"));
+    assert!(position(&down, "DROP CONSTRAINT tag_item_code_fkey") < position(&down, "DROP CONSTRAINT item_pkey;"));
+    assert!(position(&down, "ADD PRIMARY KEY (id);") < position(&down, "ADD CONSTRAINT tag_item_fkey"));
+}
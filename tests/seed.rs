@@ -6,5 +6,8 @@ fn seed_sql_contains_inserts() {
     let sql = generate_seed_sql(&ir);
     println!("{}", sql);
     assert!(sql.contains("INSERT INTO user"));
-    assert!(sql.contains("admin@example.com"));
+    assert!(sql.contains("admin@finance.com"));
+    // columns follow the order they are written in the seed rows
+    assert!(sql.contains("INSERT INTO user (id, email, role)"));
+    assert!(sql.find("INSERT INTO user").unwrap() < sql.find("INSERT INTO category").unwrap());
 }
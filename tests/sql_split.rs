@@ -0,0 +1,110 @@
+use rustdbgen::ir::DatabaseBackend;
+use rustdbgen::sql_split::split_statements;
+use rustdbgen::{apply_migrations, connect_any_pool};
+use std::fs;
+
+fn split(sql: &str, backend: DatabaseBackend) -> Vec<(String, usize)> {
+    split_statements(sql, &backend)
+        .unwrap()
+        .into_iter()
+        .map(|s| (s.sql, s.line))
+        .collect()
+}
+
+#[test]
+fn semicolons_in_literals_and_comments_do_not_split() {
+    let sql = "INSERT INTO note (body) VALUES ('a; b', 'it''s; fine');\n\
+               -- a comment; with a semicolon\n\
+               /* block; comment */ SELECT \"odd;name\" FROM note;\n";
+    assert_eq!(
+        split(sql, DatabaseBackend::Postgres),
+        vec![
+            ("INSERT INTO note (body) VALUES ('a; b', 'it''s; fine')".to_string(), 1),
+            ("SELECT \"odd;name\" FROM note".to_string(), 3),
+        ]
+    );
+}
+
+#[test]
+fn postgres_dollar_quoted_bodies_stay_whole() {
+    let sql = r#"CREATE FUNCTION touch() RETURNS trigger AS $$
+BEGIN
+    NEW.updated_at = now();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $body$ BEGIN PERFORM 1; END $body$;
+SELECT $1, E'a\'; b' FROM t;
+/* outer /* nested; */ still comment; */ SELECT 2;
+"#;
+    let statements = split(sql, DatabaseBackend::Postgres);
+    assert_eq!(statements.len(), 4);
+    assert!(statements[0].0.ends_with("$$ LANGUAGE plpgsql"));
+    assert_eq!(statements[1], ("DO $body$ BEGIN PERFORM 1; END $body$".to_string(), 8));
+    assert_eq!(statements[2], ("SELECT $1, E'a\\'; b' FROM t".to_string(), 9));
+    assert_eq!(statements[3], ("SELECT 2".to_string(), 10));
+}
+
+#[test]
+fn mysql_delimiters_and_comments() {
+    let sql = "DELIMITER //\n\
+               CREATE PROCEDURE p() BEGIN SELECT 1; SELECT 'x\\'; y'; END//\n\
+               DELIMITER ;\n\
+               # hash comment; here\n\
+               SELECT `a;b` FROM t;\n";
+    assert_eq!(
+        split(sql, DatabaseBackend::Mysql),
+        vec![
+            ("CREATE PROCEDURE p() BEGIN SELECT 1; SELECT 'x\\'; y'; END".to_string(), 2),
+            ("SELECT `a;b` FROM t".to_string(), 5),
+        ]
+    );
+}
+
+#[test]
+fn sqlite_trigger_bodies_stay_whole() {
+    let sql = "CREATE TRIGGER stamp AFTER UPDATE ON note BEGIN\n\
+               UPDATE note SET kind = CASE WHEN NEW.id > 0 THEN 'a' ELSE 'b' END;\n\
+               UPDATE note SET seen = 1;\n\
+               END;\n\
+               BEGIN;\nSELECT 1;\nCOMMIT;\n";
+    let statements = split(sql, DatabaseBackend::Sqlite);
+    assert_eq!(statements.len(), 4);
+    assert!(statements[0].0.ends_with("UPDATE note SET seen = 1;\nEND"));
+    assert_eq!(statements[1], ("BEGIN".to_string(), 5));
+}
+
+#[test]
+fn unterminated_literals_are_reported() {
+    let err = split_statements("SELECT 1;\nSELECT 'oops;\n", &DatabaseBackend::Postgres).unwrap_err();
+    assert_eq!(err.to_string(), "unterminated string literal starting on line 2");
+    let err = split_statements("SELECT $$ body", &DatabaseBackend::Postgres).unwrap_err();
+    assert!(err.to_string().contains("dollar-quoted"));
+}
+
+#[tokio::test]
+async fn failures_name_the_statement_and_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let migrations = dir.path().join("migrations");
+    fs::create_dir(&migrations).unwrap();
+    fs::write(
+        migrations.join("1_note.up.sql"),
+        "CREATE TABLE note (id INTEGER PRIMARY KEY, body TEXT, seen INTEGER);\n\
+         CREATE TRIGGER mark AFTER INSERT ON note BEGIN\n  UPDATE note SET seen = 1 WHERE id = NEW.id;\nEND;\n\
+         INSERT INTO note (body) VALUES ('first; second');\n",
+    )
+    .unwrap();
+    fs::write(migrations.join("2_broken.up.sql"), "SELECT 1;\n\nSELECT * FROM missing;\n").unwrap();
+    let db = dir.path().join("test.db");
+    fs::write(&db, "").unwrap();
+    let pool = connect_any_pool(&format!("sqlite://{}", db.display())).await.unwrap();
+
+    let err = apply_migrations(&pool, migrations.to_str().unwrap()).await.unwrap_err();
+    assert_eq!(err.to_string(), "2_broken.up.sql: statement 2 (line 3) failed");
+
+    let (body, seen): (String, i64) =
+        sqlx::query_as("SELECT body, seen FROM note").fetch_one(&pool).await.unwrap();
+    assert_eq!(body, "first; second");
+    assert_eq!(seen, 1);
+}
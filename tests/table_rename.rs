@@ -0,0 +1,123 @@
+use rustdbgen::changes::{ChangeRisk, classify_changes};
+use rustdbgen::{diff_schemas, generate_migration, lint_schema, lint_schema_changes};
+
+mod common;
+use common::load_inline_schema;
+
+const SCHEMA: &str = r#"
+[meta]
+rust_case_style = "snake"
+db_case_style = "snake"
+default_derives = []
+
+[models.Category]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "BIGINT" }
+
+[models.Tag]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "BIGINT" }
+
+[models.Budget]
+primary_key = { fields = ["id"], generated = "identity" }
+fields.id = { type = "i64", db_type = "BIGINT" }
+fields.name = { type = "String", db_type = "TEXT", nullable = false }
+fields.amount = { type = "i64", db_type = "BIGINT", nullable = false }
+fields.category_id = { type = "i64", db_type = "BIGINT", nullable = false }
+indexes.budget_name_idx = { fields = ["name"] }
+check_constraints.budget_amount_positive = { expression = "amount > 0" }
+relations.budget_category_fkey = { on = "category_id", references = { model = "Category", field = "id" } }
+many_to_many.tags = { model = "Tag" }
+"#;
+
+fn renamed(schema: &str) -> String {
+    schema
+        .replace("[models.Budget]", "[models.Plan]\nrename_from = \"Budget\"")
+        .replace("budget_name_idx", "plan_name_idx")
+        .replace("budget_amount_positive", "plan_amount_positive")
+        .replace("budget_category_fkey", "plan_category_fkey")
+}
+
+#[test]
+fn renamed_model_renames_table_and_its_objects() {
+    let old = load_inline_schema(SCHEMA);
+    let new = load_inline_schema(&renamed(SCHEMA));
+    let (up, down) = generate_migration(Some(&old), &new).unwrap();
+
+    assert!(up.contains("ALTER TABLE budget RENAME TO plan;"));
+    assert!(up.contains("ALTER TABLE plan RENAME CONSTRAINT budget_pkey TO plan_pkey;"));
+    assert!(up.contains("ALTER SEQUENCE budget_id_seq RENAME TO plan_id_seq;"));
+    assert!(up.contains("ALTER INDEX budget_name_idx RENAME TO plan_name_idx;"));
+    assert!(up.contains("ALTER TABLE plan RENAME CONSTRAINT budget_amount_positive TO plan_amount_positive;"));
+    assert!(up.contains("ALTER TABLE plan RENAME CONSTRAINT budget_category_fkey TO plan_category_fkey;"));
+    assert!(up.contains("ALTER TABLE budget_tag RENAME TO plan_tag;"));
+    assert!(up.contains("ALTER TABLE plan_tag RENAME COLUMN budget_id TO plan_id;"));
+    assert!(!up.contains("DROP TABLE"));
+    assert!(!up.contains("CREATE TABLE"));
+    assert!(!up.contains("DROP INDEX"));
+    assert!(!up.contains("ADD CONSTRAINT"));
+
+    // down renames the objects back before the table itself
+    let index = down.find("ALTER INDEX plan_name_idx RENAME TO budget_name_idx;").unwrap();
+    let table = down.find("ALTER TABLE plan RENAME TO budget;").unwrap();
+    assert!(index < table);
+    assert!(down.contains("ALTER TABLE plan_tag RENAME COLUMN plan_id TO budget_id;"));
+    assert!(!down.contains("DROP TABLE"));
+
+    let changes = classify_changes(&diff_schemas(Some(&old), &new).unwrap());
+    let rename = changes.iter().find(|c| c.description == "rename table budget to plan").unwrap();
+    assert_eq!(rename.risk, ChangeRisk::NeedsReview);
+    assert!(!changes.iter().any(|c| c.risk == ChangeRisk::Destructive));
+}
+
+#[test]
+fn renamed_model_fields_are_diffed_against_the_old_model() {
+    let old = load_inline_schema(SCHEMA);
+    let new = load_inline_schema(&renamed(SCHEMA).replace(
+        "fields.name = { type = \"String\", db_type = \"TEXT\", nullable = false }",
+        "fields.title = { type = \"String\", db_type = \"TEXT\", nullable = true, rename_from = \"name\" }\n\
+         fields.notes = { type = \"String\", db_type = \"TEXT\", nullable = true }",
+    ).replace("fields = [\"name\"]", "fields = [\"title\"]"));
+    let (up, _) = generate_migration(Some(&old), &new).unwrap();
+    let table = up.find("ALTER TABLE budget RENAME TO plan;").unwrap();
+    let column = up.find("ALTER TABLE plan RENAME COLUMN name TO title;").unwrap();
+    assert!(table < column);
+    assert!(up.contains("ALTER TABLE plan ALTER COLUMN title DROP NOT NULL;"));
+    assert!(up.contains("ALTER TABLE plan ADD COLUMN notes TEXT;"));
+    assert!(up.contains("ALTER INDEX budget_name_idx RENAME TO plan_name_idx;"));
+    assert!(!up.contains("DROP COLUMN"));
+}
+
+#[test]
+fn other_backends_rename_what_they_can() {
+    let mysql = |s: &str| s.replace("default_derives = []", "default_derives = []\ndb_backend = \"mysql\"");
+    let old = load_inline_schema(&mysql(SCHEMA));
+    let new = load_inline_schema(&mysql(&renamed(SCHEMA)));
+    let (up, _) = generate_migration(Some(&old), &new).unwrap();
+    assert!(up.contains("ALTER TABLE budget RENAME TO plan;"));
+    assert!(up.contains("ALTER TABLE plan RENAME INDEX budget_name_idx TO plan_name_idx;"));
+    assert!(!up.contains("ALTER SEQUENCE"));
+    assert!(!up.contains("RENAME CONSTRAINT"));
+    // MySQL cannot rename foreign keys, so it recreates them
+    assert!(up.contains("ALTER TABLE plan DROP FOREIGN KEY budget_category_fkey;"));
+    assert!(!up.contains("DROP TABLE budget;"));
+}
+
+#[test]
+fn unmarked_renames_are_flagged() {
+    let old = load_inline_schema(SCHEMA);
+    let new = load_inline_schema(&renamed(SCHEMA).replace("rename_from = \"Budget\"", ""));
+    let warnings = lint_schema_changes(&old, &new);
+    assert!(warnings.iter().any(|w| w.contains("Model Plan has the same columns as removed model Budget")));
+    let (up, _) = generate_migration(Some(&old), &new).unwrap();
+    assert!(up.contains("DROP TABLE budget;"));
+
+    let new = load_inline_schema(&renamed(SCHEMA));
+    assert!(lint_schema_changes(&old, &new).is_empty());
+
+    let both = load_inline_schema(&format!(
+        "{}\n[models.Budget]\nfields.id = {{ type = \"i64\", db_type = \"BIGINT PRIMARY KEY\" }}\n",
+        renamed(SCHEMA)
+    ));
+    assert!(lint_schema(&both).iter().any(|e| e.contains("Model Plan renames from Budget, which is still declared")));
+}
@@ -0,0 +1,98 @@
+use rustdbgen::verify::verify_migrations_in_shadow;
+use rustdbgen::{generate_initial_migration, generate_migration, ir::SchemaIR};
+use std::fs;
+use tempfile::TempDir;
+
+mod common;
+use common::load_inline_schema;
+
+const SCHEMA: &str = r#"
+[meta]
+rust_case_style = "snake"
+db_case_style = "snake"
+default_derives = []
+db_backend = "sqlite"
+
+[models.Owner]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "INTEGER" }
+fields.name = { type = "String", db_type = "TEXT", nullable = false }
+
+[models.Pet]
+primary_key = "id"
+fields.id = { type = "i64", db_type = "INTEGER" }
+fields.name = { type = "String", db_type = "TEXT", nullable = false }
+fields.owner_id = { type = "i64", db_type = "INTEGER", nullable = false }
+relations.owner = { on = "owner_id", references = { model = "Owner", field = "id" } }
+unique_constraints.pet_name_owner_unique = { fields = ["name", "owner_id"] }
+indexes.pet_owner_idx = { fields = ["owner_id"] }
+"#;
+
+const NICKNAMES: &str = "indexes.pet_owner_idx = { fields = [\"owner_id\"] }\n\
+    fields.nickname = { type = \"String\", db_type = \"TEXT\", nullable = true }\n\
+    indexes.pet_nickname_idx = { fields = [\"nickname\"] }";
+
+/// Migrations for SCHEMA followed by one adding `Pet.nickname`, and the final schema.
+fn migrations() -> (TempDir, String, SchemaIR) {
+    let first = load_inline_schema(SCHEMA);
+    let second = load_inline_schema(
+        &SCHEMA.replace("indexes.pet_owner_idx = { fields = [\"owner_id\"] }", NICKNAMES),
+    );
+    let dir = tempfile::tempdir().unwrap();
+    let (up, down) = generate_initial_migration(&first).unwrap();
+    fs::write(dir.path().join("20240101000000_init.up.sql"), up).unwrap();
+    fs::write(dir.path().join("20240101000000_init.down.sql"), down).unwrap();
+    let (up, down) = generate_migration(Some(&first), &second).unwrap();
+    fs::write(dir.path().join("20240102000000_nickname.up.sql"), up).unwrap();
+    fs::write(dir.path().join("20240102000000_nickname.down.sql"), down).unwrap();
+    let path = dir.path().to_str().unwrap().to_string();
+    (dir, path, second)
+}
+
+#[tokio::test]
+async fn generated_migrations_verify() {
+    let (_dir, path, ir) = migrations();
+    let report = verify_migrations_in_shadow(&ir, &path, None).await.unwrap();
+    assert!(report.passed(), "{:#?}", report.problems);
+    assert_eq!(
+        report.applied,
+        vec!["20240101000000_init.up.sql", "20240102000000_nickname.up.sql"]
+    );
+    assert_eq!(
+        report.rolled_back,
+        vec!["20240102000000_nickname.up.sql", "20240101000000_init.up.sql"]
+    );
+}
+
+#[tokio::test]
+async fn incomplete_down_and_stale_schema_are_reported() {
+    let (dir, path, _) = migrations();
+    fs::write(
+        dir.path().join("20240102000000_nickname.down.sql"),
+        "DROP INDEX pet_nickname_idx;\n",
+    )
+    .unwrap();
+    // the schema moved on without a migration for it
+    let ir = load_inline_schema(&SCHEMA.replace(
+        "nullable = false }\n\n[models.Pet]",
+        "nullable = true }\n\n[models.Pet]",
+    ));
+    let report = verify_migrations_in_shadow(&ir, &path, None).await.unwrap();
+    assert!(!report.passed());
+    assert!(report.problems.contains(&"after all migrations: column owner.name is NOT NULL".to_string()));
+    assert!(report.problems.contains(&"after all migrations: unexpected column pet.nickname".to_string()));
+    assert!(report.problems.contains(
+        &"rolling back 20240102000000_nickname.up.sql leaves a different schema: unexpected column pet.nickname"
+            .to_string()
+    ));
+}
+
+#[tokio::test]
+async fn failing_down_is_a_problem() {
+    let (dir, path, ir) = migrations();
+    fs::write(dir.path().join("20240101000000_init.down.sql"), "DROP TABLE missing;\n").unwrap();
+    let report = verify_migrations_in_shadow(&ir, &path, None).await.unwrap();
+    assert_eq!(report.rolled_back, vec!["20240102000000_nickname.up.sql"]);
+    assert_eq!(report.problems.len(), 1);
+    assert!(report.problems[0].starts_with("rolling back 20240101000000_init.up.sql failed"));
+}